@@ -3,29 +3,40 @@ use bevy::prelude::*;
 use bevy::text::TextSettings;
 use bevy_common_assets::json::JsonAssetPlugin;
 use bevy_embedded_assets::EmbeddedAssetPlugin;
+use bevy_mod_picking::prelude::{DebugPickingPlugin, DefaultHighlightingPlugin};
+use bevy_mod_picking::DefaultPickingPlugins;
 use bevy_tweening::{component_animator_system, TweeningPlugin};
 use bevy_ui_navigation::DefaultNavigationPlugins;
 
 use crate::modes::battle::battlemode::BattleModePlugins;
+use crate::modes::battle::bulletpattern::RawBulletPatterns;
+use crate::modes::battle::enemydatabase::{RawEnemyDatabase, RawFormations};
+use crate::modes::battle::skilldatabase::RawSkillDatabase;
 use crate::modes::dungeon::dungeonmode::DungeonModePlugins;
-use crate::modes::dungeon::model::grid::RawDungeonData;
+use crate::modes::dungeon::generate::GeneratedDungeon;
+use crate::modes::dungeon::model::grid::RawDungeonFloors;
+use crate::modes::dungeon::model::tileset::TilesetManifest;
+use crate::modes::gameover::gameovermode::GameOverPlugin;
 use crate::modes::mode_state::GameModeState;
 use crate::modes::pause::pausemode::PauseModePlugins;
+use crate::modes::sharedassets::characterdatabase::RawCharacterDatabase;
+use crate::modes::sharedassets::itemdatabase::RawItemDatabase;
 use crate::modes::sharedassets::shared::SharedAssetsPlugin;
-use crate::utils::utilresources::WindowScaleFactor;
+use crate::utils::utilresources::{GameRng, WindowScaleFactor};
 use crate::utils::utilsystems::{resize_sprite_system, resize_text_system, update_scale_factor};
 
 mod modes;
 mod utils;
 
 fn main() {
-    App::new()
-        .insert_resource(Msaa::Off)
+    let mut app = App::new();
+    app.insert_resource(Msaa::Off)
         .insert_resource(TextSettings {
             allow_dynamic_font_size: true,
             ..default()
         })
         .insert_resource(WindowScaleFactor(2.0))
+        .init_resource::<GameRng>()
         .add_state::<GameModeState>()
         .add_plugins((
             DefaultPlugins
@@ -41,8 +52,19 @@ fn main() {
                 })
                 .add_before::<AssetPlugin, _>(EmbeddedAssetPlugin),
             TweeningPlugin,
-            JsonAssetPlugin::<RawDungeonData>::new(&["dungeon.json"]),
+            JsonAssetPlugin::<RawDungeonFloors>::new(&["dungeon.json"]),
+            JsonAssetPlugin::<TilesetManifest>::new(&["tileset.json"]),
+            JsonAssetPlugin::<RawItemDatabase>::new(&["items.json"]),
+            JsonAssetPlugin::<RawBulletPatterns>::new(&["patterns.json"]),
+            JsonAssetPlugin::<RawEnemyDatabase>::new(&["enemies.json"]),
+            JsonAssetPlugin::<RawFormations>::new(&["formations.json"]),
+            JsonAssetPlugin::<RawSkillDatabase>::new(&["skills.json"]),
+            JsonAssetPlugin::<RawCharacterDatabase>::new(&["characters.json"]),
             DefaultNavigationPlugins,
+            DefaultPickingPlugins
+                .build()
+                .disable::<DebugPickingPlugin>()
+                .disable::<DefaultHighlightingPlugin>(),
             LogDiagnosticsPlugin::default(),
             FrameTimeDiagnosticsPlugin,
             // WorldInspectorPlugin::new(),
@@ -59,6 +81,10 @@ fn main() {
         .add_plugins(DungeonModePlugins)
         .add_plugins(BattleModePlugins)
         .add_plugins(PauseModePlugins)
-        .add_plugins(SharedAssetsPlugin)
-        .run();
+        .add_plugins(GameOverPlugin)
+        .add_plugins(SharedAssetsPlugin);
+    if let Some(generated_dungeon) = GeneratedDungeon::from_args(std::env::args()) {
+        app.insert_resource(generated_dungeon);
+    }
+    app.run();
 }
@@ -0,0 +1,85 @@
+use bevy::utils::HashMap;
+use serde::Deserialize;
+
+/// What a skill or attack hits with. Plain attacks are `Physical`.
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Element {
+    Physical,
+    Fire,
+    Ice,
+    Elec,
+    Force,
+    Light,
+    Dark,
+    /// Goes through every affinity.
+    Almighty,
+}
+
+/// How a combatant takes hits of one element.
+#[derive(Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Affinity {
+    #[default]
+    Normal,
+    /// Takes more damage, and the attacker gets to go again.
+    Weak,
+    /// Takes half the damage.
+    Resist,
+    /// Takes nothing.
+    Null,
+    /// Sends the damage back at the attacker.
+    Reflect,
+    /// Heals by the damage instead.
+    Drain,
+}
+
+impl Affinity {
+    /// Whether the hit gets through to the target at all, statuses included.
+    pub fn lands(self) -> bool {
+        matches!(self, Affinity::Normal | Affinity::Weak | Affinity::Resist)
+    }
+}
+
+/// A combatant's affinity for each element. Elements that aren't listed are `Affinity::Normal`.
+///
+/// ```json
+/// { "Fire": "Weak", "Ice": "Drain" }
+/// ```
+#[derive(Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+#[serde(transparent)]
+pub struct Affinities(pub HashMap<Element, Affinity>);
+
+impl Affinities {
+    pub fn get(&self, element: Element) -> Affinity {
+        if element == Element::Almighty {
+            return Affinity::Normal;
+        }
+        self.0.get(&element).copied().unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::modes::battle::affinity::{Affinities, Affinity, Element};
+
+    #[test]
+    fn should_parse_affinities() {
+        let affinities: Affinities =
+            serde_json::from_str(r#"{ "Fire": "Weak", "Ice": "Drain", "Almighty": "Null" }"#)
+                .unwrap();
+        assert_eq!(affinities.get(Element::Fire), Affinity::Weak);
+        assert_eq!(affinities.get(Element::Ice), Affinity::Drain);
+        assert_eq!(affinities.get(Element::Elec), Affinity::Normal);
+        // nothing stands up to almighty
+        assert_eq!(affinities.get(Element::Almighty), Affinity::Normal);
+    }
+
+    #[test]
+    fn only_some_affinities_let_hits_land() {
+        assert!(Affinity::Normal.lands());
+        assert!(Affinity::Weak.lands());
+        assert!(Affinity::Resist.lands());
+        assert!(!Affinity::Null.lands());
+        assert!(!Affinity::Reflect.lands());
+        assert!(!Affinity::Drain.lands());
+    }
+}
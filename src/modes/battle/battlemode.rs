@@ -6,7 +6,20 @@ use bevy_asset_loader::prelude::{LoadingState, LoadingStateAppExt};
 
 use crate::modes::battle::backgroundtiles::{BackgroundTilePlugin, UnvacuumTween};
 use crate::modes::battle::battlemoderesources::{BattleModeAssets, BattleModeAtlases};
+use crate::modes::battle::bulletpattern::{BulletPatternAssets, BulletPatterns};
+use crate::modes::battle::combatant::{Combatant, Side};
+use crate::modes::battle::dodge::DodgePlugin;
+use crate::modes::battle::encounter::BattleRequest;
+use crate::modes::battle::enemydatabase::{EnemyAssets, EnemyDatabase, EnemyId};
+use crate::modes::battle::outcome::BattleOutcomePlugin;
+use crate::modes::battle::skilldatabase::SkillDatabase;
+use crate::modes::battle::turns::{
+    BattleAction, BattleCommand, BattleState, BattleTurnPlugin, TurnQueue,
+};
+use crate::modes::inventory::Inventory;
 use crate::modes::mode_state::GameModeState;
+use crate::modes::party::Party;
+use crate::modes::sharedassets::itemdatabase::ItemDatabase;
 use crate::utils::utilsystems::cleanup_system;
 
 pub struct BattleMode;
@@ -19,6 +32,10 @@ pub struct BattleModeEntity;
 #[derive(Component)]
 pub struct BattleModeCamera;
 
+/// Which `Party` member a party combatant is.
+#[derive(Component)]
+pub struct PartySlot(pub usize);
+
 impl Plugin for BattleMode {
     fn build(&self, app: &mut App) {
         app.add_loading_state(
@@ -27,13 +44,27 @@ impl Plugin for BattleMode {
         )
         .add_collection_to_loading_state::<_, BattleModeAssets>(GameModeState::LoadingBattle)
         .init_resource_after_loading_state::<_, BattleModeAtlases>(GameModeState::LoadingBattle)
+        .add_collection_to_loading_state::<_, BulletPatternAssets>(GameModeState::LoadingBattle)
+        .init_resource_after_loading_state::<_, BulletPatterns>(GameModeState::LoadingBattle)
+        .add_collection_to_loading_state::<_, EnemyAssets>(GameModeState::LoadingBattle)
+        .init_resource_after_loading_state::<_, EnemyDatabase>(GameModeState::LoadingBattle)
         .add_systems(
             OnExit(GameModeState::LoadingBattle),
-            BattleMode::spawn_camera,
+            (
+                BattleMode::spawn_camera,
+                BattleMode::spawn_formation,
+                BattleMode::spawn_party,
+            ),
+        )
+        .add_systems(OnEnter(BattleState::Won), BattleMode::store_party)
+        .add_systems(OnEnter(BattleState::Fled), BattleMode::store_party)
+        .add_systems(
+            Update,
+            BattleMode::hide_defeated_enemies.run_if(in_state(GameModeState::InBattle)),
         )
         .add_systems(
             Update,
-            (BattleMode::update).run_if(in_state(GameModeState::InBattle)),
+            BattleMode::choose_action_from_keys.run_if(in_state(BattleState::ChoosingAction)),
         )
         .add_systems(
             Update,
@@ -54,19 +85,147 @@ impl PluginGroup for BattleModePlugins {
         PluginGroupBuilder::start::<Self>()
             .add(BattleMode)
             .add(BackgroundTilePlugin)
+            .add(BattleTurnPlugin)
+            .add(DodgePlugin)
+            .add(BattleOutcomePlugin)
     }
 }
 
 impl BattleMode {
-    fn update(
-        keyboard_input: Res<Input<KeyCode>>,
-        mut next_state: ResMut<NextState<GameModeState>>,
+    fn spawn_formation(
+        mut commands: Commands,
+        battle_request: Option<Res<BattleRequest>>,
+        enemy_database: Res<EnemyDatabase>,
     ) {
-        if keyboard_input.just_pressed(KeyCode::Semicolon) {
-            next_state.set(GameModeState::ExitingBattle);
+        let Some(battle_request) = battle_request else {
+            warn!("battle started without a request");
+            return;
+        };
+        info!("battle against {} is starting", battle_request.formation);
+        for (id, combatant, position) in
+            enemy_database.formation_combatants(&battle_request.formation)
+        {
+            let scale = enemy_database
+                .get(&id)
+                .map_or(1.0, |info| info.sprite.scale);
+            commands.spawn((
+                BattleModeEntity,
+                SpriteSheetBundle {
+                    texture_atlas: enemy_database.atlas(&id),
+                    transform: Transform::from_translation(position.extend(0.5))
+                        .with_scale(Vec3::splat(scale)),
+                    ..default()
+                },
+                combatant,
+                id,
+            ));
         }
     }
 
+    fn spawn_party(mut commands: Commands, party: Res<Party>, item_database: Res<ItemDatabase>) {
+        for (i, member) in party.members().iter().enumerate() {
+            commands.spawn((
+                BattleModeEntity,
+                member.combatant(&item_database),
+                PartySlot(i),
+            ));
+        }
+    }
+
+    /// HP and MP carry over to the dungeon after a win or a getaway. A lost battle leaves the
+    /// party the way it was, so a retry starts from the same spot.
+    fn store_party(mut party: ResMut<Party>, combatant_query: Query<(&Combatant, &PartySlot)>) {
+        for (combatant, slot) in combatant_query.iter() {
+            if let Some(member) = party.members_mut().get_mut(slot.0) {
+                member.hp = combatant.hp;
+                member.mp = combatant.mp;
+            }
+        }
+    }
+
+    #[allow(clippy::type_complexity)]
+    fn hide_defeated_enemies(
+        mut enemy_query: Query<(&Combatant, &mut Visibility), (With<EnemyId>, Changed<Combatant>)>,
+    ) {
+        for (combatant, mut visibility) in enemy_query.iter_mut() {
+            if !combatant.is_alive() {
+                *visibility = Visibility::Hidden;
+            }
+        }
+    }
+
+    /// Z attacks the first enemy still standing, V uses the first skill there's enough MP for on
+    /// it or on whoever's turn it is, B uses the first item that works in battle on whoever's
+    /// turn it is, X defends and C runs away.
+    fn choose_action_from_keys(
+        keyboard_input: Res<Input<KeyCode>>,
+        combatant_query: Query<(Entity, &Combatant)>,
+        turn_queue: Res<TurnQueue>,
+        (inventory, item_database, skill_database): (
+            Res<Inventory>,
+            Res<ItemDatabase>,
+            Res<SkillDatabase>,
+        ),
+        mut command_writer: EventWriter<BattleCommand>,
+    ) {
+        let first_enemy = combatant_query
+            .iter()
+            .find(|(_, combatant)| combatant.side == Side::Enemy && combatant.is_alive())
+            .map(|(entity, _)| entity);
+        let action = if keyboard_input.just_pressed(KeyCode::Z) {
+            let Some(target) = first_enemy else {
+                return;
+            };
+            BattleAction::Attack { target }
+        } else if keyboard_input.just_pressed(KeyCode::V) {
+            let Some((actor, combatant)) = turn_queue
+                .current()
+                .and_then(|actor| combatant_query.get(actor).ok())
+            else {
+                return;
+            };
+            let Some((skill, info)) = combatant.skills.iter().find_map(|skill| {
+                let info = skill_database.get(skill)?;
+                (info.mp_cost <= combatant.mp).then_some((skill, info))
+            }) else {
+                return;
+            };
+            let target = if info.targeting.side(combatant.side) == combatant.side {
+                Some(actor)
+            } else {
+                first_enemy
+            };
+            let Some(target) = target else {
+                return;
+            };
+            BattleAction::Skill {
+                skill: skill.clone(),
+                target,
+            }
+        } else if keyboard_input.just_pressed(KeyCode::B) {
+            let (Some(target), Some(stack)) = (
+                turn_queue.current(),
+                inventory
+                    .stacks()
+                    .iter()
+                    .find(|stack| item_database.usable_in_battle(&stack.item)),
+            ) else {
+                return;
+            };
+            BattleAction::Item {
+                item: stack.item.clone(),
+                target,
+            }
+        } else if keyboard_input.just_pressed(KeyCode::X) {
+            BattleAction::Defend
+        } else if keyboard_input.just_pressed(KeyCode::C) {
+            BattleAction::Flee
+        } else {
+            return;
+        };
+        command_writer.send(BattleCommand(action));
+    }
+
     fn spawn_camera(mut commands: Commands) {
         commands.spawn((
             BattleModeCamera,
@@ -84,3 +243,40 @@ impl BattleMode {
         ));
     }
 }
+
+#[cfg(test)]
+mod test {
+    use bevy::prelude::*;
+
+    use crate::modes::battle::battlemode::{BattleMode, PartySlot};
+    use crate::modes::battle::combatant::Combatant;
+    use crate::modes::party::test_helpers::test_party;
+    use crate::modes::party::Party;
+    use crate::modes::sharedassets::itemdatabase::test_helpers::test_item_database;
+
+    #[test]
+    fn party_goes_into_battle_and_comes_back_out() {
+        let mut world = World::new();
+        world.insert_resource(test_party());
+        world.insert_resource(test_item_database());
+        world.resource_mut::<Party>().members_mut()[0].hp = 45;
+        Schedule::default()
+            .add_systems(BattleMode::spawn_party)
+            .run(&mut world);
+
+        let mut combatant_query = world.query::<(&mut Combatant, &PartySlot)>();
+        let (mut hero, slot) = combatant_query.single_mut(&mut world);
+        assert_eq!(slot.0, 0);
+        assert_eq!((hero.hp, hero.max_hp), (45, 60));
+        // Maxwell's 5 defense is on top of the hero's own 8
+        assert_eq!(hero.stats.defense, 13);
+        hero.take_damage(20);
+        hero.mp -= 4;
+
+        Schedule::default()
+            .add_systems(BattleMode::store_party)
+            .run(&mut world);
+        let hero = &world.resource::<Party>().members()[0];
+        assert_eq!((hero.hp, hero.mp), (25, 16));
+    }
+}
@@ -0,0 +1,567 @@
+use bevy::math::Vec2;
+use bevy::prelude::{warn, AssetServer, Assets, FromWorld, Handle, Resource, World};
+use bevy::reflect::{TypePath, TypeUuid};
+use bevy::utils::HashMap;
+use bevy_asset_loader::prelude::AssetCollection;
+use serde::Deserialize;
+
+/// Patterns run at this many frames a second no matter how fast the game is drawing, so a
+/// pattern always plays out the same way.
+pub const PATTERN_FPS: u32 = 60;
+pub const PATTERN_TIMESTEP: f32 = 1.0 / PATTERN_FPS as f32;
+
+/// The shortest `Emitter::interval` a pattern is allowed, a bit under two shots a frame. Much
+/// faster and an emitter without a shot limit floods the arena with thousands of bullets a frame.
+pub const MIN_INTERVAL: f32 = 0.01;
+
+/// Turns seconds into pattern frames.
+pub fn frames(secs: f32) -> u32 {
+    (secs * PATTERN_FPS as f32).round() as u32
+}
+
+/// What a bullet looks like and how big it is for hits.
+#[derive(Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct BulletSprite {
+    /// Path to an image. Without one the bullet is a plain square in `color`.
+    pub path: Option<String>,
+    pub color: [f32; 3],
+    pub radius: f32,
+}
+
+impl Default for BulletSprite {
+    fn default() -> Self {
+        BulletSprite {
+            path: None,
+            color: [1.0, 0.3, 0.5],
+            radius: 4.0,
+        }
+    }
+}
+
+/// Something that fires bullets. Angles are in degrees, 0 is straight down and they go
+/// anticlockwise.
+#[derive(Deserialize, Clone, Debug, PartialEq)]
+pub struct Emitter {
+    /// Where the bullets come from, from the middle of the arena.
+    pub position: [f32; 2],
+    /// Seconds before the first shot.
+    #[serde(default)]
+    pub start: f32,
+    /// Seconds between shots. Has to be at least `MIN_INTERVAL`, but can be less than a frame,
+    /// in which case more than one shot goes off on the same frame.
+    pub interval: f32,
+    /// How many times it fires. Without a limit it keeps going until the dodging is over.
+    #[serde(default)]
+    pub shots: Option<u32>,
+    /// Bullets fired at once.
+    #[serde(default = "default_bullets")]
+    pub bullets: u32,
+    /// Which way the first bullet of the first shot goes.
+    #[serde(default)]
+    pub angle: f32,
+    /// The arc the bullets of one shot are spread over. 360 makes a full ring.
+    #[serde(default)]
+    pub spread: f32,
+    /// Added to `angle` after every shot, which turns rings into spirals.
+    #[serde(default)]
+    pub rotation: f32,
+    /// Pixels per second.
+    pub speed: f32,
+    /// Pixels per second per second, negative slows the bullets down. They never go backwards.
+    #[serde(default)]
+    pub acceleration: f32,
+    #[serde(default)]
+    pub sprite: BulletSprite,
+}
+
+fn default_bullets() -> u32 {
+    1
+}
+
+impl Emitter {
+    /// The directions the bullets of shot number `shot` go in.
+    pub fn directions(&self, shot: u32) -> Vec<Vec2> {
+        let first = self.angle + self.rotation * shot as f32;
+        // a full ring would put the last bullet on top of the first
+        let gaps = if self.spread >= 360.0 {
+            self.bullets
+        } else {
+            self.bullets.saturating_sub(1).max(1)
+        };
+        let start = if self.spread >= 360.0 {
+            first
+        } else {
+            first - self.spread / 2.0
+        };
+        (0..self.bullets)
+            .map(|i| {
+                let angle = if self.bullets == 1 {
+                    first
+                } else {
+                    start + self.spread * i as f32 / gaps as f32
+                }
+                .to_radians();
+                Vec2::new(angle.sin(), -angle.cos())
+            })
+            .collect()
+    }
+
+    /// Whether shot number `shot` is due by `frame`. Shots that are due keep going off on the
+    /// same frame until the emitter catches up.
+    fn fires_on(&self, frame: u32, shot: u32) -> bool {
+        self.shots.is_none_or(|shots| shot < shots)
+            && frames(self.start + self.interval * shot as f32) <= frame
+    }
+}
+
+/// A set of emitters that fire together while the player dodges.
+#[derive(Deserialize, Clone, Debug, PartialEq)]
+pub struct BulletPattern {
+    pub emitters: Vec<Emitter>,
+}
+
+impl BulletPattern {
+    /// Indices of the emitters with an `interval` under `MIN_INTERVAL`. Those would fire every
+    /// shot they have on the same frame, or never stop if they don't have a limit.
+    pub fn invalid_emitters(&self) -> Vec<usize> {
+        self.emitters
+            .iter()
+            .enumerate()
+            .filter(|(_, emitter)| emitter.interval.is_nan() || emitter.interval < MIN_INTERVAL)
+            .map(|(index, _)| index)
+            .collect()
+    }
+}
+
+/// Every bullet pattern by name, from `patterns.patterns.json`.
+///
+/// ```json
+/// {
+///     "patterns": {
+///         "spiral": {
+///             "emitters": [
+///                 {
+///                     "position": [0.0, 50.0],
+///                     "interval": 0.1,
+///                     "bullets": 4,
+///                     "spread": 360.0,
+///                     "rotation": 7.5,
+///                     "speed": 60.0,
+///                     "acceleration": 20.0,
+///                     "sprite": { "path": "img/bullets/star.png", "radius": 3.0 }
+///                 }
+///             ]
+///         }
+///     }
+/// }
+/// ```
+#[derive(Deserialize, TypePath, TypeUuid)]
+#[uuid = "5f0d9b7e-8c21-4e6a-b3f4-2a91c7d05e18"]
+pub struct RawBulletPatterns {
+    pub patterns: HashMap<String, BulletPattern>,
+}
+
+#[derive(AssetCollection, Resource)]
+pub struct BulletPatternAssets {
+    #[asset(path = "battle/patterns.patterns.json")]
+    pub patterns: Handle<RawBulletPatterns>,
+}
+
+/// `RawBulletPatterns`, taken out of the assets once they've loaded.
+#[derive(Resource, Debug)]
+pub struct BulletPatterns(pub HashMap<String, BulletPattern>);
+
+impl BulletPatterns {
+    /// Leaves out every pattern with an invalid emitter, so they never get picked.
+    pub fn new(raw_bullet_patterns: &RawBulletPatterns) -> Self {
+        let patterns = raw_bullet_patterns
+            .patterns
+            .iter()
+            .filter(|(name, pattern)| {
+                let invalid_emitters = pattern.invalid_emitters();
+                if !invalid_emitters.is_empty() {
+                    warn!(
+                        "leaving out bullet pattern {}, emitters {:?} fire faster than every {}s",
+                        name, invalid_emitters, MIN_INTERVAL
+                    );
+                }
+                invalid_emitters.is_empty()
+            })
+            .map(|(name, pattern)| (name.clone(), pattern.clone()))
+            .collect();
+        BulletPatterns(patterns)
+    }
+}
+
+impl FromWorld for BulletPatterns {
+    fn from_world(world: &mut World) -> Self {
+        let pattern_assets = world
+            .get_resource::<BulletPatternAssets>()
+            .expect("failed to get bullet pattern handles");
+        let raw_bullet_patterns = world
+            .get_resource::<Assets<RawBulletPatterns>>()
+            .expect("failed to get bullet patterns")
+            .get(&pattern_assets.patterns)
+            .expect("failed to get bullet patterns out of assets");
+        BulletPatterns::new(raw_bullet_patterns)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct BulletState {
+    /// Unique for the whole run of a pattern, so bullets can be told apart after others are
+    /// removed.
+    pub id: u32,
+    /// Which of the pattern's emitters fired it.
+    pub emitter: usize,
+    pub position: Vec2,
+    pub direction: Vec2,
+    pub speed: f32,
+}
+
+/// Plays out a pattern one fixed frame at a time. Nothing in here depends on the frame rate or
+/// on anything random, so the same pattern always ends up in the same place on the same frame.
+#[derive(Clone, Debug)]
+pub struct PatternRuntime {
+    pattern: BulletPattern,
+    /// Half the width and height of the area bullets stay in. They're removed once they leave.
+    bounds: Vec2,
+    frame: u32,
+    shots_fired: Vec<u32>,
+    next_id: u32,
+    bullets: Vec<BulletState>,
+}
+
+impl PatternRuntime {
+    pub fn new(pattern: BulletPattern, bounds: Vec2) -> Self {
+        PatternRuntime {
+            shots_fired: vec![0; pattern.emitters.len()],
+            pattern,
+            bounds,
+            frame: 0,
+            next_id: 0,
+            bullets: vec![],
+        }
+    }
+
+    pub fn bullets(&self) -> &[BulletState] {
+        &self.bullets
+    }
+
+    pub fn sprite(&self, emitter: usize) -> &BulletSprite {
+        &self.pattern.emitters[emitter].sprite
+    }
+
+    /// Moves every bullet along, drops the ones that left the bounds, then fires whatever is due
+    /// this frame. Freshly fired bullets start on their emitter and move from the next frame.
+    pub fn step(&mut self) {
+        for bullet in self.bullets.iter_mut() {
+            let acceleration = self.pattern.emitters[bullet.emitter].acceleration;
+            bullet.speed = (bullet.speed + acceleration * PATTERN_TIMESTEP).max(0.0);
+            bullet.position += bullet.direction * bullet.speed * PATTERN_TIMESTEP;
+        }
+        let bounds = self.bounds;
+        self.bullets
+            .retain(|bullet| bullet.position.abs().cmple(bounds).all());
+
+        for (index, emitter) in self.pattern.emitters.iter().enumerate() {
+            while emitter.fires_on(self.frame, self.shots_fired[index]) {
+                let shot = self.shots_fired[index];
+                for direction in emitter.directions(shot) {
+                    self.bullets.push(BulletState {
+                        id: self.next_id,
+                        emitter: index,
+                        position: Vec2::from(emitter.position),
+                        direction,
+                        speed: emitter.speed,
+                    });
+                    self.next_id += 1;
+                }
+                self.shots_fired[index] += 1;
+            }
+        }
+        self.frame += 1;
+    }
+
+    /// Takes out every bullet `hit` says yes to and returns how many there were.
+    pub fn remove_hits(&mut self, mut hit: impl FnMut(&BulletState) -> bool) -> usize {
+        let before = self.bullets.len();
+        self.bullets.retain(|bullet| !hit(bullet));
+        before - self.bullets.len()
+    }
+}
+
+#[cfg(test)]
+pub mod test_helpers {
+    use bevy::math::Vec2;
+
+    use crate::modes::battle::bulletpattern::{
+        BulletPattern, BulletSprite, BulletState, Emitter, PatternRuntime,
+    };
+
+    /// A runtime that already has `bullets` out, with big enough bounds that they never leave.
+    /// They all belong to one emitter that never fires.
+    pub fn with_bullets(bullets: Vec<BulletState>) -> PatternRuntime {
+        let emitter = Emitter {
+            position: [0.0; 2],
+            start: 0.0,
+            interval: 1.0,
+            shots: Some(0),
+            bullets: 1,
+            angle: 0.0,
+            spread: 0.0,
+            rotation: 0.0,
+            speed: 0.0,
+            acceleration: 0.0,
+            sprite: BulletSprite::default(),
+        };
+        let mut runtime = PatternRuntime::new(
+            BulletPattern {
+                emitters: vec![emitter],
+            },
+            Vec2::splat(10000.0),
+        );
+        runtime.next_id = bullets
+            .iter()
+            .map(|bullet| bullet.id + 1)
+            .max()
+            .unwrap_or(0);
+        runtime.bullets = bullets;
+        runtime
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use bevy::math::Vec2;
+
+    use crate::modes::battle::bulletpattern::{
+        frames, BulletPattern, BulletPatterns, Emitter, PatternRuntime, RawBulletPatterns,
+        MIN_INTERVAL,
+    };
+
+    fn emitter(json: &str) -> Emitter {
+        serde_json::from_str(json).unwrap()
+    }
+
+    fn assert_close(actual: Vec2, expected: Vec2) {
+        assert!(
+            actual.distance(expected) < 0.001,
+            "{actual} isn't close to {expected}"
+        );
+    }
+
+    #[test]
+    fn should_parse_bullet_patterns() {
+        let raw_bullet_patterns: RawBulletPatterns = serde_json::from_str(
+            r#"{
+                "patterns": {
+                    "aimed": {
+                        "emitters": [
+                            { "position": [0.0, 80.0], "interval": 0.5, "speed": 100.0 }
+                        ]
+                    },
+                    "spiral": {
+                        "emitters": [
+                            {
+                                "position": [0.0, 50.0],
+                                "start": 1.0,
+                                "interval": 0.1,
+                                "shots": 30,
+                                "bullets": 4,
+                                "spread": 360.0,
+                                "rotation": 7.5,
+                                "speed": 60.0,
+                                "acceleration": 20.0,
+                                "sprite": { "path": "img/bullets/star.png", "radius": 3.0 }
+                            }
+                        ]
+                    }
+                }
+            }"#,
+        )
+        .unwrap();
+
+        let aimed = &raw_bullet_patterns.patterns["aimed"].emitters[0];
+        assert_eq!(aimed.bullets, 1);
+        assert_eq!(aimed.shots, None);
+        assert_eq!(aimed.acceleration, 0.0);
+        assert_eq!(aimed.sprite.radius, 4.0);
+
+        let spiral = &raw_bullet_patterns.patterns["spiral"].emitters[0];
+        assert_eq!(spiral.shots, Some(30));
+        assert_eq!(spiral.rotation, 7.5);
+        assert_eq!(spiral.sprite.path.as_deref(), Some("img/bullets/star.png"));
+        assert_eq!(spiral.sprite.radius, 3.0);
+        assert_eq!(spiral.sprite.color, [1.0, 0.3, 0.5]);
+    }
+
+    #[test]
+    fn spread_and_rotation_aim_the_bullets() {
+        let ring = emitter(
+            r#"{ "position": [0, 0], "interval": 1, "bullets": 4, "spread": 360, "rotation": 90, "speed": 1 }"#,
+        );
+        let directions = ring.directions(0);
+        assert_close(directions[0], Vec2::new(0.0, -1.0));
+        assert_close(directions[1], Vec2::new(1.0, 0.0));
+        assert_close(directions[2], Vec2::new(0.0, 1.0));
+        assert_close(directions[3], Vec2::new(-1.0, 0.0));
+        assert_close(ring.directions(1)[0], Vec2::new(1.0, 0.0));
+
+        let fan = emitter(
+            r#"{ "position": [0, 0], "interval": 1, "bullets": 3, "spread": 90, "speed": 1 }"#,
+        );
+        let directions = fan.directions(0);
+        assert_close(directions[0], Vec2::new(-1.0, -1.0).normalize());
+        assert_close(directions[1], Vec2::new(0.0, -1.0));
+        assert_close(directions[2], Vec2::new(1.0, -1.0).normalize());
+    }
+
+    #[test]
+    fn emitters_fire_on_time() {
+        let pattern = BulletPattern {
+            emitters: vec![emitter(
+                r#"{ "position": [0, 0], "start": 0.5, "interval": 0.25, "shots": 3, "bullets": 2, "spread": 360, "speed": 10 }"#,
+            )],
+        };
+        let mut runtime = PatternRuntime::new(pattern, Vec2::splat(1000.0));
+        let mut fired_on = vec![];
+        for frame in 0..frames(3.0) {
+            let before = runtime.bullets().len();
+            runtime.step();
+            if runtime.bullets().len() > before {
+                fired_on.push(frame);
+            }
+        }
+        assert_eq!(fired_on, [30, 45, 60]);
+        assert_eq!(runtime.bullets().len(), 6);
+        let ids: Vec<_> = runtime.bullets().iter().map(|bullet| bullet.id).collect();
+        assert_eq!(ids, [0, 1, 2, 3, 4, 5]);
+    }
+
+    #[test]
+    fn emitters_can_fire_faster_than_the_frame_rate() {
+        // a shot every 0.6 frames
+        let pattern = BulletPattern {
+            emitters: vec![emitter(
+                r#"{ "position": [0, 0], "interval": 0.01, "speed": 0 }"#,
+            )],
+        };
+        let mut runtime = PatternRuntime::new(pattern, Vec2::splat(1000.0));
+        let mut shots_per_frame = vec![];
+        for _ in 0..frames(1.0) {
+            let before = runtime.bullets().len();
+            runtime.step();
+            shots_per_frame.push(runtime.bullets().len() - before);
+        }
+        assert!(shots_per_frame.iter().all(|shots| (1..=2).contains(shots)));
+        assert_eq!(runtime.bullets().len(), 100);
+
+        let capped = BulletPattern {
+            emitters: vec![emitter(
+                r#"{ "position": [0, 0], "interval": 0.001, "shots": 5, "speed": 0 }"#,
+            )],
+        };
+        let mut runtime = PatternRuntime::new(capped, Vec2::splat(1000.0));
+        runtime.step();
+        assert_eq!(runtime.bullets().len(), 5);
+        runtime.step();
+        assert_eq!(runtime.bullets().len(), 5);
+    }
+
+    #[test]
+    fn patterns_need_a_positive_interval() {
+        let raw_bullet_patterns: RawBulletPatterns = serde_json::from_str(
+            r#"{
+                "patterns": {
+                    "fine": {
+                        "emitters": [{ "position": [0, 0], "interval": 0.5, "speed": 1 }]
+                    },
+                    "stuck": {
+                        "emitters": [
+                            { "position": [0, 0], "interval": 0.5, "speed": 1 },
+                            { "position": [0, 0], "interval": 0, "speed": 1 },
+                            { "position": [0, 0], "interval": -1, "speed": 1 }
+                        ]
+                    }
+                }
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(
+            raw_bullet_patterns.patterns["stuck"].invalid_emitters(),
+            [1, 2]
+        );
+        let bullet_patterns = BulletPatterns::new(&raw_bullet_patterns);
+        assert!(bullet_patterns.0.contains_key("fine"));
+        assert!(!bullet_patterns.0.contains_key("stuck"));
+    }
+
+    #[test]
+    fn patterns_cant_fire_faster_than_the_minimum_interval() {
+        let pattern = |interval| BulletPattern {
+            emitters: vec![Emitter {
+                interval,
+                ..emitter(r#"{ "position": [0, 0], "interval": 1, "speed": 1 }"#)
+            }],
+        };
+        assert!(pattern(MIN_INTERVAL).invalid_emitters().is_empty());
+        assert_eq!(pattern(0.000001).invalid_emitters(), [0]);
+        assert_eq!(pattern(f32::NAN).invalid_emitters(), [0]);
+    }
+
+    #[test]
+    fn bullets_move_and_accelerate_frame_by_frame() {
+        let pattern = BulletPattern {
+            emitters: vec![emitter(
+                r#"{ "position": [0, 10], "interval": 10, "speed": 60, "acceleration": 60 }"#,
+            )],
+        };
+        let mut runtime = PatternRuntime::new(pattern, Vec2::splat(100.0));
+        runtime.step();
+        assert_eq!(runtime.bullets()[0].position, Vec2::new(0.0, 10.0));
+        runtime.step();
+        // one frame of 61 px/s, straight down
+        assert_close(
+            runtime.bullets()[0].position,
+            Vec2::new(0.0, 10.0 - 61.0 / 60.0),
+        );
+        for _ in 0..frames(1.0) - 1 {
+            runtime.step();
+        }
+        assert!((runtime.bullets()[0].speed - 120.0).abs() < 0.01);
+
+        // off the bottom of the bounds after a couple of seconds
+        for _ in 0..frames(2.0) {
+            runtime.step();
+        }
+        assert!(runtime.bullets().is_empty());
+    }
+
+    #[test]
+    fn replays_are_identical() {
+        let pattern = BulletPattern {
+            emitters: vec![
+                emitter(
+                    r#"{ "position": [0, 50], "interval": 0.1, "bullets": 5, "spread": 360, "rotation": 7.5, "speed": 40, "acceleration": 15 }"#,
+                ),
+                emitter(
+                    r#"{ "position": [-80, 0], "start": 0.3, "interval": 0.7, "bullets": 3, "angle": -90, "spread": 30, "speed": 90, "acceleration": -30 }"#,
+                ),
+            ],
+        };
+        let mut first = PatternRuntime::new(pattern.clone(), Vec2::new(160.0, 100.0));
+        let mut second = PatternRuntime::new(pattern, Vec2::new(160.0, 100.0));
+        for _ in 0..frames(4.0) {
+            first.step();
+            second.step();
+            assert_eq!(first.bullets(), second.bullets());
+        }
+        assert!(!first.bullets().is_empty());
+
+        let removed = first.remove_hits(|bullet| bullet.emitter == 1);
+        assert!(removed > 0);
+        assert!(first.bullets().iter().all(|bullet| bullet.emitter == 0));
+    }
+}
@@ -0,0 +1,136 @@
+use std::ops::Add;
+
+use bevy::prelude::Component;
+use serde::Deserialize;
+
+use crate::modes::battle::affinity::Affinities;
+use crate::modes::battle::skilldatabase::SkillId;
+
+#[derive(Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Stats {
+    pub attack: u32,
+    pub defense: u32,
+    pub magic: u32,
+    /// Faster combatants act earlier in each round.
+    pub speed: u32,
+}
+
+impl Add for Stats {
+    type Output = Stats;
+
+    fn add(self, other: Stats) -> Stats {
+        Stats {
+            attack: self.attack + other.attack,
+            defense: self.defense + other.defense,
+            magic: self.magic + other.magic,
+            speed: self.speed + other.speed,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Side {
+    Party,
+    Enemy,
+}
+
+impl Side {
+    pub fn opponent(self) -> Self {
+        match self {
+            Side::Party => Side::Enemy,
+            Side::Enemy => Side::Party,
+        }
+    }
+}
+
+/// Something lingering on a combatant between turns. Only one can be on them at a time.
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StatusEffect {
+    /// Loses some HP at the start of every turn.
+    Poison,
+    /// Loses their next turn.
+    Stun,
+}
+
+/// Anyone taking part in a battle, on either side.
+#[derive(Component, Clone, Debug, PartialEq)]
+pub struct Combatant {
+    pub name: String,
+    pub side: Side,
+    pub hp: u32,
+    pub max_hp: u32,
+    pub mp: u32,
+    pub max_mp: u32,
+    pub stats: Stats,
+    /// Set by the defend action, and cleared again when the combatant's next turn comes around.
+    pub defending: bool,
+    pub affinities: Affinities,
+    pub skills: Vec<SkillId>,
+    pub status: Option<StatusEffect>,
+}
+
+impl Combatant {
+    /// Starts out with full HP and MP.
+    pub fn new(name: &str, side: Side, max_hp: u32, max_mp: u32, stats: Stats) -> Self {
+        Combatant {
+            name: name.to_string(),
+            side,
+            hp: max_hp,
+            max_hp,
+            mp: max_mp,
+            max_mp,
+            stats,
+            defending: false,
+            affinities: Affinities::default(),
+            skills: Vec::new(),
+            status: None,
+        }
+    }
+
+    pub fn with_affinities(mut self, affinities: Affinities) -> Self {
+        self.affinities = affinities;
+        self
+    }
+
+    pub fn with_skills(mut self, skills: Vec<SkillId>) -> Self {
+        self.skills = skills;
+        self
+    }
+
+    pub fn is_alive(&self) -> bool {
+        self.hp > 0
+    }
+
+    /// Returns how much HP was actually lost.
+    pub fn take_damage(&mut self, amount: u32) -> u32 {
+        let lost = amount.min(self.hp);
+        self.hp -= lost;
+        lost
+    }
+
+    /// Returns how much HP was actually gained. The dead can't be healed.
+    pub fn heal(&mut self, amount: u32) -> u32 {
+        if !self.is_alive() {
+            return 0;
+        }
+        let gained = amount.min(self.max_hp - self.hp);
+        self.hp += gained;
+        gained
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::modes::battle::combatant::{Combatant, Side, Stats};
+
+    #[test]
+    fn hp_stays_in_range() {
+        let mut combatant = Combatant::new("Slime", Side::Enemy, 10, 0, Stats::default());
+        assert_eq!(combatant.heal(5), 0);
+        assert_eq!(combatant.take_damage(4), 4);
+        assert_eq!(combatant.heal(5), 4);
+        assert_eq!(combatant.take_damage(15), 10);
+        assert!(!combatant.is_alive());
+        assert_eq!(combatant.heal(5), 0);
+    }
+}
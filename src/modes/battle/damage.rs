@@ -0,0 +1,290 @@
+use crate::modes::battle::affinity::{Affinities, Affinity, Element};
+use crate::modes::battle::combatant::Stats;
+
+/// Damage done by a plain attack before stats come into it.
+pub const ATTACK_POWER: u32 = 20;
+
+/// Damage from a hit with `power` behind it. Hits always do at least 1 damage, and defending
+/// halves it.
+pub fn hit_damage(power: u32, attack: u32, defense: u32, defending: bool) -> u32 {
+    let damage = (power * attack / (attack + defense).max(1)).max(1);
+    if defending {
+        (damage / 2).max(1)
+    } else {
+        damage
+    }
+}
+
+/// The stat a hit goes by: attack for physical hits, magic for everything else.
+pub fn offense(element: Element, stats: &Stats) -> u32 {
+    match element {
+        Element::Physical => stats.attack,
+        _ => stats.magic,
+    }
+}
+
+/// What a hit ends up doing once the target's affinity is taken into account.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HitOutcome {
+    /// The target takes `amount`. `weak` is set if it hit a weakness.
+    Damage {
+        amount: u32,
+        weak: bool,
+    },
+    Nulled,
+    /// The attacker takes `amount` instead.
+    Reflected {
+        amount: u32,
+    },
+    /// The target heals by `amount` instead.
+    Drained {
+        amount: u32,
+    },
+}
+
+/// Weaknesses take half as much again, at least 1 more. Resisting halves it, down to 1.
+pub fn apply_affinity(damage: u32, affinity: Affinity) -> HitOutcome {
+    match affinity {
+        Affinity::Normal => HitOutcome::Damage {
+            amount: damage,
+            weak: false,
+        },
+        Affinity::Weak => HitOutcome::Damage {
+            amount: (damage * 3 / 2).max(damage + 1),
+            weak: true,
+        },
+        Affinity::Resist => HitOutcome::Damage {
+            amount: (damage / 2).max(1),
+            weak: false,
+        },
+        Affinity::Null => HitOutcome::Nulled,
+        Affinity::Reflect => HitOutcome::Reflected { amount: damage },
+        Affinity::Drain => HitOutcome::Drained { amount: damage },
+    }
+}
+
+/// A whole hit of `element` with `power` behind it, from `attacker` on `target`.
+pub fn elemental_hit(
+    power: u32,
+    element: Element,
+    attacker: &Stats,
+    target: &Stats,
+    defending: bool,
+    affinities: &Affinities,
+) -> HitOutcome {
+    let damage = hit_damage(power, offense(element, attacker), target.defense, defending);
+    apply_affinity(damage, affinities.get(element))
+}
+
+/// HP a healing skill gives back. Magic adds a bit on top of the skill's power.
+pub fn heal_amount(power: u32, magic: u32) -> u32 {
+    power + magic / 2
+}
+
+/// Poison takes an eighth of max HP at the start of every turn, and at least 1.
+pub fn poison_damage(max_hp: u32) -> u32 {
+    (max_hp / 8).max(1)
+}
+
+/// Whether a status with `chance` of sticking does, `roll` being between 0 and 1. Hits that
+/// don't land never leave a status.
+pub fn status_lands(chance: f32, roll: f32, affinity: Affinity) -> bool {
+    affinity.lands() && roll < chance
+}
+
+/// Hitting a weakness earns another go straight away, unless anything in the same action was
+/// nulled, reflected or drained. An extra turn can't earn another one.
+pub fn earns_extra_turn(outcomes: &[HitOutcome], on_extra_turn: bool) -> bool {
+    if on_extra_turn {
+        return false;
+    }
+    let hit_weakness = outcomes
+        .iter()
+        .any(|outcome| matches!(outcome, HitOutcome::Damage { weak: true, .. }));
+    let blocked = outcomes
+        .iter()
+        .any(|outcome| !matches!(outcome, HitOutcome::Damage { .. }));
+    hit_weakness && !blocked
+}
+
+#[cfg(test)]
+mod test {
+    use bevy::utils::HashMap;
+
+    use crate::modes::battle::affinity::{Affinities, Affinity, Element};
+    use crate::modes::battle::combatant::Stats;
+    use crate::modes::battle::damage::{
+        apply_affinity, earns_extra_turn, elemental_hit, heal_amount, hit_damage, offense,
+        poison_damage, status_lands, HitOutcome,
+    };
+
+    fn stats(attack: u32, defense: u32, magic: u32) -> Stats {
+        Stats {
+            attack,
+            defense,
+            magic,
+            speed: 0,
+        }
+    }
+
+    fn damage(amount: u32) -> HitOutcome {
+        HitOutcome::Damage {
+            amount,
+            weak: false,
+        }
+    }
+
+    fn weak(amount: u32) -> HitOutcome {
+        HitOutcome::Damage { amount, weak: true }
+    }
+
+    #[test]
+    fn damage_goes_by_attack_and_defense() {
+        assert_eq!(hit_damage(20, 10, 10, false), 10);
+        assert_eq!(hit_damage(20, 10, 30, false), 5);
+        assert_eq!(hit_damage(20, 30, 10, false), 15);
+        assert_eq!(hit_damage(40, 10, 10, false), 20);
+    }
+
+    #[test]
+    fn defending_halves_damage() {
+        assert_eq!(hit_damage(20, 10, 10, true), 5);
+        assert_eq!(hit_damage(20, 10, 0, true), 10);
+    }
+
+    #[test]
+    fn hits_always_do_something() {
+        assert_eq!(hit_damage(20, 1, 100, true), 1);
+        assert_eq!(hit_damage(20, 0, 0, false), 1);
+        assert_eq!(hit_damage(0, 50, 0, false), 1);
+    }
+
+    #[test]
+    fn physical_hits_use_attack_and_the_rest_use_magic() {
+        let stats = stats(7, 0, 3);
+        assert_eq!(offense(Element::Physical, &stats), 7);
+        assert_eq!(offense(Element::Fire, &stats), 3);
+        assert_eq!(offense(Element::Almighty, &stats), 3);
+    }
+
+    #[test]
+    fn normal_affinity_changes_nothing() {
+        assert_eq!(apply_affinity(10, Affinity::Normal), damage(10));
+        assert_eq!(apply_affinity(0, Affinity::Normal), damage(0));
+    }
+
+    #[test]
+    fn weaknesses_take_half_again() {
+        assert_eq!(apply_affinity(10, Affinity::Weak), weak(15));
+        assert_eq!(apply_affinity(11, Affinity::Weak), weak(16));
+        // always at least a little more
+        assert_eq!(apply_affinity(1, Affinity::Weak), weak(2));
+    }
+
+    #[test]
+    fn resisting_halves_damage_down_to_1() {
+        assert_eq!(apply_affinity(10, Affinity::Resist), damage(5));
+        assert_eq!(apply_affinity(11, Affinity::Resist), damage(5));
+        assert_eq!(apply_affinity(1, Affinity::Resist), damage(1));
+    }
+
+    #[test]
+    fn null_reflect_and_drain_keep_the_target_safe() {
+        assert_eq!(apply_affinity(10, Affinity::Null), HitOutcome::Nulled);
+        assert_eq!(
+            apply_affinity(10, Affinity::Reflect),
+            HitOutcome::Reflected { amount: 10 }
+        );
+        assert_eq!(
+            apply_affinity(10, Affinity::Drain),
+            HitOutcome::Drained { amount: 10 }
+        );
+    }
+
+    #[test]
+    fn elemental_hits_put_it_all_together() {
+        let caster = stats(1, 0, 10);
+        let target = stats(100, 10, 0);
+        let affinities = Affinities(HashMap::from_iter([
+            (Element::Fire, Affinity::Weak),
+            (Element::Ice, Affinity::Null),
+            (Element::Physical, Affinity::Resist),
+        ]));
+        // 30 power, 10 magic against 10 defense
+        assert_eq!(
+            elemental_hit(30, Element::Elec, &caster, &target, false, &affinities),
+            damage(15)
+        );
+        assert_eq!(
+            elemental_hit(30, Element::Fire, &caster, &target, false, &affinities),
+            weak(22)
+        );
+        assert_eq!(
+            elemental_hit(30, Element::Fire, &caster, &target, true, &affinities),
+            weak(10)
+        );
+        assert_eq!(
+            elemental_hit(30, Element::Ice, &caster, &target, false, &affinities),
+            HitOutcome::Nulled
+        );
+        // physical goes by the caster's 1 attack
+        assert_eq!(
+            elemental_hit(30, Element::Physical, &caster, &target, false, &affinities),
+            damage(1)
+        );
+        assert_eq!(
+            elemental_hit(30, Element::Almighty, &caster, &target, false, &affinities),
+            damage(15)
+        );
+    }
+
+    #[test]
+    fn healing_adds_half_the_magic() {
+        assert_eq!(heal_amount(40, 0), 40);
+        assert_eq!(heal_amount(40, 11), 45);
+    }
+
+    #[test]
+    fn poison_takes_an_eighth() {
+        assert_eq!(poison_damage(80), 10);
+        assert_eq!(poison_damage(60), 7);
+        assert_eq!(poison_damage(3), 1);
+    }
+
+    #[test]
+    fn statuses_stick_by_chance_if_the_hit_lands() {
+        assert!(status_lands(0.5, 0.2, Affinity::Normal));
+        assert!(!status_lands(0.5, 0.7, Affinity::Normal));
+        assert!(status_lands(1.0, 0.99, Affinity::Resist));
+        assert!(!status_lands(0.0, 0.0, Affinity::Weak));
+        assert!(!status_lands(1.0, 0.0, Affinity::Null));
+        assert!(!status_lands(1.0, 0.0, Affinity::Reflect));
+        assert!(!status_lands(1.0, 0.0, Affinity::Drain));
+    }
+
+    #[test]
+    fn weaknesses_earn_an_extra_turn() {
+        assert!(earns_extra_turn(&[weak(10)], false));
+        assert!(earns_extra_turn(&[damage(3), weak(10), damage(5)], false));
+        assert!(!earns_extra_turn(&[damage(10)], false));
+        assert!(!earns_extra_turn(&[], false));
+    }
+
+    #[test]
+    fn blocked_hits_lose_the_extra_turn() {
+        assert!(!earns_extra_turn(&[weak(10), HitOutcome::Nulled], false));
+        assert!(!earns_extra_turn(
+            &[weak(10), HitOutcome::Reflected { amount: 3 }],
+            false
+        ));
+        assert!(!earns_extra_turn(
+            &[HitOutcome::Drained { amount: 3 }, weak(10)],
+            false
+        ));
+    }
+
+    #[test]
+    fn extra_turns_dont_chain() {
+        assert!(!earns_extra_turn(&[weak(10)], true));
+    }
+}
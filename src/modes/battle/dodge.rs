@@ -0,0 +1,553 @@
+use bevy::prelude::*;
+use bevy::utils::{HashMap, HashSet};
+
+use crate::modes::battle::affinity::Element;
+use crate::modes::battle::battlemode::BattleModeEntity;
+use crate::modes::battle::bulletpattern::{
+    frames, BulletPattern, BulletPatterns, PatternRuntime, PATTERN_TIMESTEP,
+};
+use crate::modes::battle::combatant::Combatant;
+use crate::modes::battle::damage::{earns_extra_turn, HitOutcome};
+use crate::modes::battle::enemydatabase::{EnemyDatabase, EnemyId};
+use crate::modes::battle::turns::{finish_turn, hit, BattleState, TurnQueue};
+use crate::utils::utilresources::GameRng;
+use crate::utils::utilsystems::cleanup_system;
+
+/// Half the width and height of the box the hitbox and bullets stay in, centred on the battle
+/// camera.
+const ARENA_HALF_SIZE: Vec2 = Vec2::new(160.0, 100.0);
+const HITBOX_RADIUS: f32 = 3.0;
+const HITBOX_SPEED: f32 = 150.0; // px per second
+/// Power behind each physical bullet. Lower than a plain attack since a few of them usually land.
+const BULLET_POWER: u32 = 8;
+
+pub struct DodgePlugin;
+
+impl Plugin for DodgePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<DodgeSettings>()
+            .add_systems(OnEnter(BattleState::Dodging), start_dodging)
+            .add_systems(
+                Update,
+                (move_hitbox, run_pattern, show_bullets, stop_dodging)
+                    .chain()
+                    .run_if(in_state(BattleState::Dodging)),
+            )
+            .add_systems(OnExit(BattleState::Dodging), cleanup_system::<DodgeEntity>);
+    }
+}
+
+/// Having this around makes enemy attacks go through the dodge phase.
+#[derive(Resource, Debug)]
+pub struct DodgeSettings {
+    /// How long the player has to dodge for each enemy attack.
+    pub duration_secs: f32,
+}
+
+impl Default for DodgeSettings {
+    fn default() -> Self {
+        DodgeSettings { duration_secs: 4.0 }
+    }
+}
+
+/// The enemy attack being dodged, and the party member who takes the hits.
+#[derive(Resource, Clone, Copy, Debug)]
+pub struct DodgeAttack {
+    pub attacker: Entity,
+    pub target: Entity,
+}
+
+impl DodgeAttack {
+    /// Whether the attacker or the target isn't standing any more. Bullets can be reflected, so
+    /// it isn't always the target.
+    fn someone_down(&self, is_alive: impl Fn(Entity) -> bool) -> bool {
+        ![self.attacker, self.target].into_iter().all(is_alive)
+    }
+}
+
+/// The pattern being dodged. It's stepped at a fixed rate however long the game's frames take,
+/// `unstepped_secs` is the time that's passed but isn't enough for another step yet.
+#[derive(Resource, Debug)]
+pub struct DodgePhase {
+    pub runtime: PatternRuntime,
+    pub frames_left: u32,
+    pub unstepped_secs: f32,
+    /// What every bullet that hit did, to tell whether the attacker earned an extra turn.
+    pub outcomes: Vec<HitOutcome>,
+}
+
+/// Everything that only lasts for one dodge phase. These are `BattleModeEntity`s as well so they
+/// go away with the rest of the battle.
+#[derive(Component)]
+pub struct DodgeEntity;
+
+/// What the player moves around. Only its small middle counts for hits.
+#[derive(Component)]
+pub struct DodgeHitbox;
+
+/// Shows the pattern runtime's bullet with this id.
+#[derive(Component, Debug)]
+pub struct Bullet(pub u32);
+
+/// Whether a bullet at `bullet` touches the hitbox at `hitbox`.
+pub fn is_hit(hitbox: Vec2, bullet: Vec2, bullet_radius: f32) -> bool {
+    hitbox.distance(bullet) < HITBOX_RADIUS + bullet_radius
+}
+
+/// One of the attacker's patterns picked at random. Attackers without any of their own use any
+/// pattern there is.
+pub fn pick_pattern(
+    bullet_patterns: &BulletPatterns,
+    attacker_patterns: &[String],
+    rng: &mut GameRng,
+) -> BulletPattern {
+    for name in attacker_patterns {
+        if !bullet_patterns.0.contains_key(name) {
+            warn!("there's no bullet pattern called {}", name);
+        }
+    }
+    let mut names: Vec<_> = attacker_patterns
+        .iter()
+        .filter(|name| bullet_patterns.0.contains_key(*name))
+        .collect();
+    if names.is_empty() {
+        names = bullet_patterns.0.keys().collect();
+    }
+    // sorted so the same seed always picks the same one
+    names.sort();
+    if names.is_empty() {
+        warn!("there are no bullet patterns, nothing to dodge");
+        return BulletPattern { emitters: vec![] };
+    }
+    let name = names[rng.with(|rng| rng.usize(0..names.len()))];
+    bullet_patterns.0[name].clone()
+}
+
+fn start_dodging(
+    mut commands: Commands,
+    dodge_settings: Res<DodgeSettings>,
+    (bullet_patterns, enemy_database): (Res<BulletPatterns>, Res<EnemyDatabase>),
+    dodge_attack: Res<DodgeAttack>,
+    enemy_query: Query<&EnemyId>,
+    mut rng: ResMut<GameRng>,
+) {
+    let attacker_patterns = enemy_query
+        .get(dodge_attack.attacker)
+        .ok()
+        .and_then(|id| enemy_database.get(id))
+        .map_or(&[][..], |info| &info.patterns);
+    let pattern = pick_pattern(&bullet_patterns, attacker_patterns, &mut rng);
+    commands.insert_resource(DodgePhase {
+        runtime: PatternRuntime::new(pattern, ARENA_HALF_SIZE),
+        frames_left: frames(dodge_settings.duration_secs),
+        unstepped_secs: 0.0,
+        outcomes: vec![],
+    });
+    commands.spawn((
+        DodgeEntity,
+        BattleModeEntity,
+        SpriteBundle {
+            sprite: Sprite {
+                color: Color::rgba(0.0, 0.0, 0.0, 0.7),
+                custom_size: Some(ARENA_HALF_SIZE * 2.0),
+                ..default()
+            },
+            transform: Transform::from_xyz(0.0, 0.0, 1.0),
+            ..default()
+        },
+    ));
+    commands.spawn((
+        DodgeEntity,
+        BattleModeEntity,
+        DodgeHitbox,
+        SpriteBundle {
+            sprite: Sprite {
+                color: Color::WHITE,
+                custom_size: Some(Vec2::splat(HITBOX_RADIUS * 2.0)),
+                ..default()
+            },
+            transform: Transform::from_xyz(0.0, -ARENA_HALF_SIZE.y / 2.0, 3.0),
+            ..default()
+        },
+    ));
+}
+
+fn move_hitbox(
+    keyboard_input: Res<Input<KeyCode>>,
+    time: Res<Time>,
+    mut hitbox_query: Query<&mut Transform, With<DodgeHitbox>>,
+) {
+    let pressed = |keys: [KeyCode; 2]| keyboard_input.any_pressed(keys) as i32 as f32;
+    let direction = Vec2::new(
+        pressed([KeyCode::Right, KeyCode::D]) - pressed([KeyCode::Left, KeyCode::A]),
+        pressed([KeyCode::Up, KeyCode::W]) - pressed([KeyCode::Down, KeyCode::S]),
+    )
+    .normalize_or_zero();
+    for mut transform in hitbox_query.iter_mut() {
+        let position =
+            transform.translation.truncate() + direction * HITBOX_SPEED * time.delta_seconds();
+        let position = position.clamp(-ARENA_HALF_SIZE, ARENA_HALF_SIZE);
+        transform.translation = position.extend(transform.translation.z);
+    }
+}
+
+/// Steps the pattern as many times as the time since the last frame allows, checking for hits
+/// after every step so they don't depend on the frame rate either. Every bullet is a physical hit
+/// from the attacker, so affinities work the same as for a plain attack.
+fn run_pattern(
+    time: Res<Time>,
+    mut dodge_phase: ResMut<DodgePhase>,
+    dodge_attack: Res<DodgeAttack>,
+    hitbox_query: Query<&Transform, With<DodgeHitbox>>,
+    mut combatant_query: Query<(Entity, &mut Combatant)>,
+) {
+    let Ok(hitbox) = hitbox_query.get_single() else {
+        return;
+    };
+    let hitbox = hitbox.translation.truncate();
+    let Ok((_, attacker)) = combatant_query.get(dodge_attack.attacker) else {
+        return;
+    };
+    let attacker = attacker.clone();
+
+    let dodge_phase = dodge_phase.as_mut();
+    dodge_phase.unstepped_secs += time.delta_seconds();
+    while dodge_phase.unstepped_secs >= PATTERN_TIMESTEP && dodge_phase.frames_left > 0 {
+        dodge_phase.unstepped_secs -= PATTERN_TIMESTEP;
+        dodge_phase.frames_left -= 1;
+        dodge_phase.runtime.step();
+
+        let runtime = &dodge_phase.runtime;
+        let hit_ids: HashSet<_> = runtime
+            .bullets()
+            .iter()
+            .filter(|bullet| {
+                is_hit(
+                    hitbox,
+                    bullet.position,
+                    runtime.sprite(bullet.emitter).radius,
+                )
+            })
+            .map(|bullet| bullet.id)
+            .collect();
+        dodge_phase
+            .runtime
+            .remove_hits(|bullet| hit_ids.contains(&bullet.id));
+        for _ in 0..hit_ids.len() {
+            let outcome = hit(
+                &mut combatant_query,
+                (dodge_attack.attacker, &attacker),
+                dodge_attack.target,
+                BULLET_POWER,
+                Element::Physical,
+            );
+            dodge_phase.outcomes.extend(outcome);
+        }
+        let someone_down = dodge_attack.someone_down(|entity| {
+            combatant_query
+                .get(entity)
+                .is_ok_and(|(_, combatant)| combatant.is_alive())
+        });
+        if someone_down {
+            return;
+        }
+    }
+}
+
+/// Keeps one sprite for every bullet the pattern runtime has.
+fn show_bullets(
+    mut commands: Commands,
+    dodge_phase: Res<DodgePhase>,
+    asset_server: Res<AssetServer>,
+    mut bullet_query: Query<(Entity, &Bullet, &mut Transform)>,
+) {
+    let runtime = &dodge_phase.runtime;
+    let mut bullets: HashMap<_, _> = runtime
+        .bullets()
+        .iter()
+        .map(|bullet| (bullet.id, bullet))
+        .collect();
+    for (entity, Bullet(id), mut transform) in bullet_query.iter_mut() {
+        match bullets.remove(id) {
+            Some(bullet) => {
+                transform.translation = bullet.position.extend(transform.translation.z);
+            }
+            None => commands.entity(entity).despawn_recursive(),
+        }
+    }
+    for (id, bullet) in bullets {
+        let sprite = runtime.sprite(bullet.emitter);
+        let [r, g, b] = sprite.color;
+        commands.spawn((
+            DodgeEntity,
+            BattleModeEntity,
+            Bullet(id),
+            SpriteBundle {
+                sprite: Sprite {
+                    color: if sprite.path.is_some() {
+                        Color::WHITE
+                    } else {
+                        Color::rgb(r, g, b)
+                    },
+                    custom_size: Some(Vec2::splat(sprite.radius * 2.0)),
+                    ..default()
+                },
+                texture: sprite
+                    .path
+                    .as_ref()
+                    .map(|path| asset_server.load(path))
+                    .unwrap_or_default(),
+                transform: Transform::from_translation(bullet.position.extend(2.0)),
+                ..default()
+            },
+        ));
+    }
+}
+
+/// The phase is over once time runs out, or early if the party member being attacked or the
+/// attacker goes down. Hitting a weakness earns the attacker an extra turn, the same as a plain
+/// attack would.
+fn stop_dodging(
+    mut commands: Commands,
+    dodge_attack: Res<DodgeAttack>,
+    dodge_phase: Res<DodgePhase>,
+    combatant_query: Query<(Entity, &Combatant)>,
+    mut turn_queue: ResMut<TurnQueue>,
+    mut next_battle_state: ResMut<NextState<BattleState>>,
+) {
+    let someone_down = dodge_attack.someone_down(|entity| {
+        combatant_query
+            .get(entity)
+            .is_ok_and(|(_, combatant)| combatant.is_alive())
+    });
+    if dodge_phase.frames_left > 0 && !someone_down {
+        return;
+    }
+    commands.remove_resource::<DodgeAttack>();
+    commands.remove_resource::<DodgePhase>();
+    if earns_extra_turn(&dodge_phase.outcomes, turn_queue.is_extra_turn()) {
+        turn_queue.grant_extra_turn();
+    }
+    let combatants: Vec<_> = combatant_query.iter().collect();
+    finish_turn(&combatants, &mut turn_queue, &mut next_battle_state);
+}
+
+#[cfg(test)]
+mod test {
+    use bevy::prelude::*;
+
+    use crate::modes::battle::affinity::{Affinities, Affinity, Element};
+    use crate::modes::battle::bulletpattern::test_helpers::with_bullets;
+    use crate::modes::battle::bulletpattern::{
+        BulletPattern, BulletPatterns, BulletSprite, BulletState, Emitter, PATTERN_TIMESTEP,
+    };
+    use crate::modes::battle::combatant::{Combatant, Side, Stats};
+    use crate::modes::battle::dodge::{
+        is_hit, pick_pattern, Bullet, DodgeEntity, DodgeHitbox, DodgePhase, DodgePlugin,
+    };
+    use crate::modes::battle::enemydatabase::test_helpers::test_enemy_database;
+    use crate::modes::battle::skilldatabase::test_helpers::test_skill_database;
+    use crate::modes::battle::turns::{BattleState, BattleTurnPlugin, TurnQueue};
+    use crate::modes::inventory::Inventory;
+    use crate::modes::mode_state::GameModeState;
+    use crate::modes::sharedassets::itemdatabase::test_helpers::test_item_database;
+    use crate::utils::utilresources::GameRng;
+
+    #[test]
+    fn bullets_hit_when_they_touch() {
+        assert!(is_hit(Vec2::ZERO, Vec2::new(6.0, 0.0), 4.0));
+        assert!(!is_hit(Vec2::ZERO, Vec2::new(7.5, 0.0), 4.0));
+        assert!(is_hit(Vec2::ZERO, Vec2::new(7.5, 0.0), 5.0));
+        assert!(!is_hit(Vec2::new(10.0, 10.0), Vec2::ZERO, 4.0));
+    }
+
+    #[test]
+    fn enemies_use_their_own_patterns() {
+        let pattern = |speed| BulletPattern {
+            emitters: vec![Emitter {
+                position: [0.0; 2],
+                start: 0.0,
+                interval: 1.0,
+                shots: None,
+                bullets: 1,
+                angle: 0.0,
+                spread: 0.0,
+                rotation: 0.0,
+                speed,
+                acceleration: 0.0,
+                sprite: BulletSprite::default(),
+            }],
+        };
+        let bullet_patterns = BulletPatterns(
+            [
+                ("rain".to_string(), pattern(1.0)),
+                ("spiral".to_string(), pattern(2.0)),
+            ]
+            .into_iter()
+            .collect(),
+        );
+        let mut rng = GameRng(3);
+        for _ in 0..10 {
+            let picked = pick_pattern(&bullet_patterns, &["rain".to_string()], &mut rng);
+            assert_eq!(picked, pattern(1.0));
+        }
+        // unknown names are skipped, and with nothing left any pattern will do
+        let picked = pick_pattern(
+            &bullet_patterns,
+            &["hail".to_string(), "spiral".to_string()],
+            &mut rng,
+        );
+        assert_eq!(picked, pattern(2.0));
+        let picked = pick_pattern(&bullet_patterns, &["hail".to_string()], &mut rng);
+        assert!(picked == pattern(1.0) || picked == pattern(2.0));
+        let picked = pick_pattern(&BulletPatterns(Default::default()), &[], &mut rng);
+        assert!(picked.emitters.is_empty());
+    }
+
+    /// Swaps the pattern for one with a single bullet sitting still at `position`, and lets one
+    /// step happen on the next update.
+    fn place_bullet(app: &mut App, position: Vec2) {
+        let mut dodge_phase = app.world.resource_mut::<DodgePhase>();
+        dodge_phase.runtime = with_bullets(vec![BulletState {
+            id: 7,
+            emitter: 0,
+            position,
+            direction: Vec2::ZERO,
+            speed: 0.0,
+        }]);
+        dodge_phase.unstepped_secs = PATTERN_TIMESTEP;
+    }
+
+    fn bullet_count(app: &mut App) -> usize {
+        app.world.query::<&Bullet>().iter(&app.world).count()
+    }
+
+    /// A slime attacks a hero with `affinities` and 50 HP, and the battle goes as far as the
+    /// dodge phase. Gives back the hero, the slime and where the hitbox starts.
+    fn start_dodging(affinities: Affinities) -> (App, Entity, Entity, Vec2) {
+        let mut app = App::new();
+        app.add_plugins((TaskPoolPlugin::default(), AssetPlugin::default()))
+            .add_state::<GameModeState>()
+            .add_plugins((BattleTurnPlugin, DodgePlugin))
+            .insert_resource(GameRng(1))
+            .insert_resource(test_item_database())
+            .insert_resource(test_enemy_database())
+            .insert_resource(test_skill_database())
+            .insert_resource(BulletPatterns(
+                [("nothing".to_string(), BulletPattern { emitters: vec![] })]
+                    .into_iter()
+                    .collect(),
+            ))
+            .init_resource::<Inventory>()
+            .init_resource::<Input<KeyCode>>()
+            .init_resource::<Time>();
+        let stats = |speed| Stats {
+            attack: 10,
+            defense: 10,
+            magic: 0,
+            speed,
+        };
+        let hero = app
+            .world
+            .spawn(Combatant::new("Hero", Side::Party, 50, 0, stats(1)).with_affinities(affinities))
+            .id();
+        let slime = app
+            .world
+            .spawn(Combatant::new("Slime", Side::Enemy, 50, 0, stats(10)))
+            .id();
+        app.world
+            .resource_mut::<NextState<GameModeState>>()
+            .set(GameModeState::InBattle);
+        for _ in 0..4 {
+            app.update();
+        }
+        assert_eq!(
+            app.world.resource::<State<BattleState>>().get(),
+            &BattleState::Dodging
+        );
+        // the attack itself doesn't do anything, only the bullets do
+        assert_eq!(app.world.get::<Combatant>(hero).unwrap().hp, 50);
+
+        let hitbox = app
+            .world
+            .query_filtered::<&Transform, With<DodgeHitbox>>()
+            .single(&app.world)
+            .translation
+            .truncate();
+        (app, hero, slime, hitbox)
+    }
+
+    fn hp(app: &App, entity: Entity) -> u32 {
+        app.world.get::<Combatant>(entity).unwrap().hp
+    }
+
+    fn end_dodging(app: &mut App) {
+        app.world.resource_mut::<DodgePhase>().frames_left = 0;
+        app.update();
+        app.update();
+    }
+
+    #[test]
+    fn enemy_attacks_are_dodged() {
+        let (mut app, hero, _, hitbox) = start_dodging(Affinities::default());
+        place_bullet(&mut app, hitbox + Vec2::new(50.0, 0.0));
+        app.update();
+        app.update();
+        assert_eq!(hp(&app, hero), 50);
+        assert_eq!(bullet_count(&mut app), 1);
+
+        place_bullet(&mut app, hitbox);
+        app.update();
+        app.update();
+        // power 8, half of it gets through
+        assert_eq!(hp(&app, hero), 46);
+        assert_eq!(bullet_count(&mut app), 0);
+
+        end_dodging(&mut app);
+        assert_eq!(
+            app.world.resource::<State<BattleState>>().get(),
+            &BattleState::ChoosingAction
+        );
+        assert_eq!(app.world.resource::<TurnQueue>().current(), Some(hero));
+        assert_eq!(
+            app.world.query::<&DodgeEntity>().iter(&app.world).count(),
+            0
+        );
+    }
+
+    #[test]
+    fn bullets_go_by_physical_affinity() {
+        let affinities =
+            |affinity| Affinities([(Element::Physical, affinity)].into_iter().collect());
+
+        let (mut app, hero, _, hitbox) = start_dodging(affinities(Affinity::Null));
+        place_bullet(&mut app, hitbox);
+        app.update();
+        app.update();
+        assert_eq!(hp(&app, hero), 50);
+        assert_eq!(bullet_count(&mut app), 0);
+
+        let (mut app, hero, slime, hitbox) = start_dodging(affinities(Affinity::Reflect));
+        place_bullet(&mut app, hitbox);
+        app.update();
+        app.update();
+        assert_eq!(hp(&app, hero), 50);
+        // the slime's own 10 attack against its 10 defense
+        assert_eq!(hp(&app, slime), 46);
+    }
+
+    #[test]
+    fn hitting_a_weakness_earns_the_attacker_another_go() {
+        let weak = Affinities([(Element::Physical, Affinity::Weak)].into_iter().collect());
+        let (mut app, hero, slime, hitbox) = start_dodging(weak);
+        place_bullet(&mut app, hitbox);
+        app.update();
+        app.update();
+        assert_eq!(hp(&app, hero), 44);
+
+        end_dodging(&mut app);
+        let turn_queue = app.world.resource::<TurnQueue>();
+        assert_eq!(turn_queue.current(), Some(slime));
+        assert!(turn_queue.is_extra_turn());
+    }
+}
@@ -0,0 +1,37 @@
+use std::fmt::{Display, Formatter};
+
+use bevy::prelude::Resource;
+use serde::Deserialize;
+
+/// Id of a group of enemies that get fought together.
+#[derive(Deserialize, Clone, PartialEq, Eq, Hash, Debug)]
+#[serde(transparent)]
+pub struct FormationId(pub String);
+
+impl Display for FormationId {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// What started a battle.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BattleInitiator {
+    RandomEncounter,
+    /// The FOE at `index` in floor `floor`'s `foes`.
+    Foe {
+        floor: usize,
+        index: usize,
+    },
+}
+
+/// The battle that's about to start. The dungeon inserts it right before entering
+/// `GameModeState::LoadingBattle`, and it stays around until the battle's `BattleOutcome` has been
+/// dealt with.
+#[derive(Resource, Clone, Debug, PartialEq)]
+pub struct BattleRequest {
+    pub formation: FormationId,
+    /// The tileset of the floor the battle started on.
+    pub terrain: Option<String>,
+    pub initiator: BattleInitiator,
+}
@@ -0,0 +1,439 @@
+use std::fmt::{Display, Formatter};
+
+use bevy::math::Vec2;
+use bevy::prelude::{
+    warn, AssetServer, Assets, Component, FromWorld, Handle, Image, Resource, TextureAtlas, World,
+};
+use bevy::reflect::{TypePath, TypeUuid};
+use bevy::utils::HashMap;
+use bevy_asset_loader::prelude::AssetCollection;
+use serde::Deserialize;
+
+use crate::modes::battle::affinity::Affinities;
+use crate::modes::battle::combatant::{Combatant, Side, Stats};
+use crate::modes::battle::encounter::FormationId;
+use crate::modes::battle::skilldatabase::SkillId;
+use crate::modes::dungeon::model::items::ItemId;
+
+/// Id of an enemy in the `EnemyDatabase`. Enemies in battle carry theirs as a component.
+#[derive(Component, Deserialize, Clone, PartialEq, Eq, Hash, Debug)]
+#[serde(transparent)]
+pub struct EnemyId(pub String);
+
+impl Display for EnemyId {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// The frames of an enemy's sprite, laid out in a grid. `path` is under `img/enemies`.
+#[derive(Deserialize, Clone, Debug)]
+pub struct EnemySpriteSheet {
+    pub path: String,
+    pub frame_size: [f32; 2],
+    #[serde(default = "default_frame_count")]
+    pub columns: usize,
+    #[serde(default = "default_frame_count")]
+    pub rows: usize,
+    #[serde(default = "default_scale")]
+    pub scale: f32,
+}
+
+fn default_frame_count() -> usize {
+    1
+}
+
+fn default_scale() -> f32 {
+    1.0
+}
+
+/// An item an enemy might leave behind, `chance` being between 0 and 1.
+#[derive(Deserialize, Clone, Debug, PartialEq)]
+pub struct EnemyDrop {
+    pub item: ItemId,
+    pub chance: f32,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct EnemyInfo {
+    pub name: String,
+    pub max_hp: u32,
+    #[serde(default)]
+    pub max_mp: u32,
+    pub stats: Stats,
+    pub sprite: EnemySpriteSheet,
+    /// Experience the party gets for beating it.
+    #[serde(default)]
+    pub xp: u32,
+    /// Skills it might use instead of attacking, by id in the `SkillDatabase`.
+    #[serde(default)]
+    pub skills: Vec<SkillId>,
+    /// Names of the bullet patterns it attacks with, one is picked for every attack.
+    #[serde(default)]
+    pub patterns: Vec<String>,
+    #[serde(default)]
+    pub drops: Vec<EnemyDrop>,
+    #[serde(default)]
+    pub affinities: Affinities,
+}
+
+impl EnemyInfo {
+    pub fn combatant(&self) -> Combatant {
+        Combatant::new(
+            &self.name,
+            Side::Enemy,
+            self.max_hp,
+            self.max_mp,
+            self.stats,
+        )
+        .with_affinities(self.affinities.clone())
+        .with_skills(self.skills.clone())
+    }
+}
+
+/// One enemy in a formation. `position` is from the middle of the battle camera.
+#[derive(Deserialize, Clone, Debug)]
+pub struct FormationSlot {
+    pub enemy: EnemyId,
+    pub position: [f32; 2],
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct Formation {
+    pub enemies: Vec<FormationSlot>,
+}
+
+/// Every enemy by id, from `enemies.enemies.json`.
+///
+/// ```json
+/// {
+///     "enemies": {
+///         "Slime": {
+///             "name": "Slime",
+///             "max_hp": 40,
+///             "stats": { "attack": 8, "defense": 6, "magic": 0, "speed": 8 },
+///             "sprite": { "path": "img/enemies/slime.png", "frame_size": [64, 64], "columns": 4 },
+///             "xp": 12,
+///             "patterns": ["spiral"],
+///             "skills": ["Venom"],
+///             "drops": [{ "item": "Tonic", "chance": 0.25 }],
+///             "affinities": { "Fire": "Weak", "Ice": "Resist" }
+///         }
+///     }
+/// }
+/// ```
+#[derive(Deserialize, TypePath, TypeUuid)]
+#[uuid = "c4a7e2d1-6b3f-4f08-9d5e-71b2a8c9e034"]
+pub struct RawEnemyDatabase {
+    pub enemies: HashMap<EnemyId, EnemyInfo>,
+}
+
+/// Every formation by id, from `formations.formations.json`. Dungeons use the ids in their
+/// encounter tables and FOEs.
+///
+/// ```json
+/// {
+///     "formations": {
+///         "slimes": {
+///             "enemies": [
+///                 { "enemy": "Slime", "position": [-80, 40] },
+///                 { "enemy": "Slime", "position": [80, 40] }
+///             ]
+///         }
+///     }
+/// }
+/// ```
+#[derive(Deserialize, TypePath, TypeUuid)]
+#[uuid = "0e6b93f5-2d47-4a1c-8f3e-b59d4c7a1260"]
+pub struct RawFormations {
+    pub formations: HashMap<FormationId, Formation>,
+}
+
+#[derive(AssetCollection, Resource)]
+pub struct EnemyAssets {
+    #[asset(path = "battle/enemies.enemies.json")]
+    pub enemies: Handle<RawEnemyDatabase>,
+    #[asset(path = "battle/formations.formations.json")]
+    pub formations: Handle<RawFormations>,
+    #[asset(path = "img/enemies", collection(typed, mapped))]
+    pub sprite_sheets: HashMap<String, Handle<Image>>,
+}
+
+/// `RawEnemyDatabase` and `RawFormations` plus a texture atlas for every enemy's sprite sheet.
+#[derive(Resource)]
+pub struct EnemyDatabase {
+    enemies: HashMap<EnemyId, EnemyInfo>,
+    formations: HashMap<FormationId, Formation>,
+    atlases: HashMap<EnemyId, Handle<TextureAtlas>>,
+}
+
+impl EnemyDatabase {
+    /// Doesn't make any atlases, `atlas` gives the default handle for everything.
+    pub fn new(raw_enemy_database: &RawEnemyDatabase, raw_formations: &RawFormations) -> Self {
+        EnemyDatabase {
+            enemies: raw_enemy_database.enemies.clone(),
+            formations: raw_formations.formations.clone(),
+            atlases: HashMap::new(),
+        }
+    }
+
+    pub fn get(&self, id: &EnemyId) -> Option<&EnemyInfo> {
+        self.enemies.get(id)
+    }
+
+    pub fn atlas(&self, id: &EnemyId) -> Handle<TextureAtlas> {
+        self.atlases.get(id).cloned().unwrap_or_default()
+    }
+
+    /// The enemies in a formation, ready to be spawned. When the same enemy shows up more than
+    /// once they're told apart with a letter after their name. Enemies that aren't in the
+    /// database are left out.
+    pub fn formation_combatants(&self, id: &FormationId) -> Vec<(EnemyId, Combatant, Vec2)> {
+        let Some(formation) = self.formations.get(id) else {
+            warn!("there's no formation called {}", id);
+            return vec![];
+        };
+        let known: Vec<_> = formation
+            .enemies
+            .iter()
+            .filter_map(|slot| match self.get(&slot.enemy) {
+                Some(info) => Some((slot, info)),
+                None => {
+                    warn!("formation {} has unknown enemy {}", id, slot.enemy);
+                    None
+                }
+            })
+            .collect();
+        let mut seen: HashMap<&EnemyId, u8> = HashMap::new();
+        known
+            .iter()
+            .map(|(slot, info)| {
+                let mut combatant = info.combatant();
+                let total = known.iter().filter(|(s, _)| s.enemy == slot.enemy).count();
+                let count = seen.entry(&slot.enemy).or_insert(0);
+                if total > 1 {
+                    combatant.name = format!("{} {}", combatant.name, (b'A' + *count) as char);
+                }
+                *count += 1;
+                (slot.enemy.clone(), combatant, Vec2::from(slot.position))
+            })
+            .collect()
+    }
+}
+
+impl FromWorld for EnemyDatabase {
+    fn from_world(world: &mut World) -> Self {
+        let cell = world.cell();
+        let enemy_assets = cell
+            .get_resource::<EnemyAssets>()
+            .expect("failed to get enemy asset handles");
+        let raw_enemy_databases = cell
+            .get_resource::<Assets<RawEnemyDatabase>>()
+            .expect("failed to get enemy databases");
+        let raw_enemy_database = raw_enemy_databases
+            .get(&enemy_assets.enemies)
+            .expect("failed to get enemy database out of assets");
+        let raw_formations = cell
+            .get_resource::<Assets<RawFormations>>()
+            .expect("failed to get formations");
+        let raw_formations = raw_formations
+            .get(&enemy_assets.formations)
+            .expect("failed to get formations out of assets");
+        let mut texture_atlases = cell
+            .get_resource_mut::<Assets<TextureAtlas>>()
+            .expect("failed to get texture atlases");
+
+        let mut enemy_database = EnemyDatabase::new(raw_enemy_database, raw_formations);
+        for (id, info) in raw_enemy_database.enemies.iter() {
+            let Some(sprite_sheet) = enemy_assets.sprite_sheets.get(&info.sprite.path) else {
+                warn!("sprite sheet {} for {} wasn't loaded", info.sprite.path, id);
+                continue;
+            };
+            let atlas = TextureAtlas::from_grid(
+                sprite_sheet.clone(),
+                Vec2::from(info.sprite.frame_size),
+                info.sprite.columns,
+                info.sprite.rows,
+                None,
+                None,
+            );
+            enemy_database
+                .atlases
+                .insert(id.clone(), texture_atlases.add(atlas));
+        }
+        enemy_database
+    }
+}
+
+#[cfg(test)]
+pub mod test_helpers {
+    use bevy::utils::HashMap;
+
+    use crate::modes::battle::affinity::Affinities;
+    use crate::modes::battle::combatant::Stats;
+    use crate::modes::battle::encounter::FormationId;
+    use crate::modes::battle::enemydatabase::{
+        EnemyDatabase, EnemyId, EnemyInfo, EnemySpriteSheet, Formation, FormationSlot,
+        RawEnemyDatabase, RawFormations,
+    };
+
+    /// Slimes (40 HP, speed 8) attack with the "rain" pattern, the formation "slimes" has two of
+    /// them.
+    pub fn test_enemy_database() -> EnemyDatabase {
+        let slime = EnemyId("Slime".to_string());
+        let info = EnemyInfo {
+            name: "Slime".to_string(),
+            max_hp: 40,
+            max_mp: 0,
+            stats: Stats {
+                attack: 8,
+                defense: 6,
+                magic: 0,
+                speed: 8,
+            },
+            sprite: EnemySpriteSheet {
+                path: String::new(),
+                frame_size: [64.0; 2],
+                columns: 1,
+                rows: 1,
+                scale: 1.0,
+            },
+            xp: 12,
+            skills: vec![],
+            patterns: vec!["rain".to_string()],
+            drops: vec![],
+            affinities: Affinities::default(),
+        };
+        let slot = |x| FormationSlot {
+            enemy: slime.clone(),
+            position: [x, 40.0],
+        };
+        EnemyDatabase::new(
+            &RawEnemyDatabase {
+                enemies: HashMap::from_iter([(slime.clone(), info)]),
+            },
+            &RawFormations {
+                formations: HashMap::from_iter([(
+                    FormationId("slimes".to_string()),
+                    Formation {
+                        enemies: vec![slot(-80.0), slot(80.0)],
+                    },
+                )]),
+            },
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use bevy::math::Vec2;
+
+    use crate::modes::battle::affinity::{Affinity, Element};
+    use crate::modes::battle::combatant::Side;
+    use crate::modes::battle::encounter::FormationId;
+    use crate::modes::battle::enemydatabase::{
+        EnemyDatabase, EnemyDrop, EnemyId, RawEnemyDatabase, RawFormations,
+    };
+    use crate::modes::battle::skilldatabase::SkillId;
+    use crate::modes::dungeon::model::items::ItemId;
+
+    fn parse() -> EnemyDatabase {
+        let raw_enemy_database: RawEnemyDatabase = serde_json::from_str(
+            r#"{
+                "enemies": {
+                    "Slime": {
+                        "name": "Slime",
+                        "max_hp": 40,
+                        "stats": { "attack": 8, "defense": 6, "magic": 0, "speed": 8 },
+                        "sprite": { "path": "img/enemies/slime.png", "frame_size": [64, 64], "columns": 4 },
+                        "xp": 12,
+                        "patterns": ["spiral"],
+                        "skills": ["Venom"],
+                        "drops": [{ "item": "Tonic", "chance": 0.25 }],
+                        "affinities": { "Fire": "Weak", "Ice": "Resist" }
+                    },
+                    "Bat": {
+                        "name": "Bat",
+                        "max_hp": 25,
+                        "max_mp": 10,
+                        "stats": { "attack": 10, "defense": 3, "magic": 5, "speed": 15 },
+                        "sprite": { "path": "img/enemies/bat.png", "frame_size": [32, 32], "scale": 2.0 }
+                    }
+                }
+            }"#,
+        )
+        .unwrap();
+        let raw_formations: RawFormations = serde_json::from_str(
+            r#"{
+                "formations": {
+                    "slimes": {
+                        "enemies": [
+                            { "enemy": "Slime", "position": [-80, 40] },
+                            { "enemy": "Bat", "position": [0, 60] },
+                            { "enemy": "Slime", "position": [80, 40] }
+                        ]
+                    },
+                    "ghosts": {
+                        "enemies": [
+                            { "enemy": "Ghost", "position": [0, 40] },
+                            { "enemy": "Bat", "position": [0, 60] }
+                        ]
+                    }
+                }
+            }"#,
+        )
+        .unwrap();
+        EnemyDatabase::new(&raw_enemy_database, &raw_formations)
+    }
+
+    #[test]
+    fn should_parse_enemies() {
+        let enemy_database = parse();
+        let slime = enemy_database.get(&EnemyId("Slime".to_string())).unwrap();
+        assert_eq!(slime.sprite.columns, 4);
+        assert_eq!(slime.sprite.rows, 1);
+        assert_eq!(slime.patterns, ["spiral"]);
+        assert_eq!(
+            slime.drops,
+            [EnemyDrop {
+                item: ItemId::new("Tonic"),
+                chance: 0.25
+            }]
+        );
+        let combatant = slime.combatant();
+        assert_eq!(combatant.skills, [SkillId("Venom".to_string())]);
+        assert_eq!(combatant.affinities.get(Element::Fire), Affinity::Weak);
+        assert_eq!(combatant.affinities.get(Element::Ice), Affinity::Resist);
+
+        let bat = enemy_database.get(&EnemyId("Bat".to_string())).unwrap();
+        assert_eq!(bat.xp, 0);
+        assert_eq!(bat.sprite.scale, 2.0);
+        assert!(bat.skills.is_empty());
+        assert_eq!(bat.affinities.get(Element::Fire), Affinity::Normal);
+        let combatant = bat.combatant();
+        assert_eq!(combatant.side, Side::Enemy);
+        assert_eq!((combatant.hp, combatant.mp), (25, 10));
+        assert_eq!(combatant.stats.speed, 15);
+    }
+
+    #[test]
+    fn formations_name_their_enemies() {
+        let enemy_database = parse();
+        let slimes = enemy_database.formation_combatants(&FormationId("slimes".to_string()));
+        let names: Vec<_> = slimes
+            .iter()
+            .map(|(_, combatant, _)| combatant.name.as_str())
+            .collect();
+        assert_eq!(names, ["Slime A", "Bat", "Slime B"]);
+        assert_eq!(slimes[2].2, Vec2::new(80.0, 40.0));
+
+        // the ghost isn't in the database, the bat still shows up
+        let ghosts = enemy_database.formation_combatants(&FormationId("ghosts".to_string()));
+        assert_eq!(ghosts.len(), 1);
+        assert_eq!(ghosts[0].0, EnemyId("Bat".to_string()));
+
+        assert!(enemy_database
+            .formation_combatants(&FormationId("nobody".to_string()))
+            .is_empty());
+    }
+}
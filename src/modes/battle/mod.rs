@@ -1,3 +1,13 @@
+pub mod affinity;
 pub mod backgroundtiles;
 pub mod battlemode;
 pub mod battlemoderesources;
+pub mod bulletpattern;
+pub mod combatant;
+pub mod damage;
+pub mod dodge;
+pub mod encounter;
+pub mod enemydatabase;
+pub mod outcome;
+pub mod skilldatabase;
+pub mod turns;
@@ -0,0 +1,228 @@
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+
+use crate::modes::battle::combatant::{Combatant, Side};
+use crate::modes::battle::enemydatabase::{EnemyDatabase, EnemyDrop, EnemyId};
+use crate::modes::battle::turns::BattleState;
+use crate::modes::dungeon::model::items::ItemId;
+use crate::modes::mode_state::GameModeState;
+use crate::utils::utilresources::GameRng;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BattleResult {
+    Victory,
+    Defeat,
+    Fled,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct HpChange {
+    pub name: String,
+    pub before: u32,
+    pub after: u32,
+}
+
+/// How the battle went. Written as soon as it's over, and dealt with by the dungeon on the way
+/// back to `GameModeState::InDungeon`.
+#[derive(Resource, Clone, Debug, PartialEq)]
+pub struct BattleOutcome {
+    pub result: BattleResult,
+    /// Only won battles give anything.
+    pub xp: u32,
+    pub drops: Vec<ItemId>,
+    /// One for every party member.
+    pub hp_changes: Vec<HpChange>,
+}
+
+/// The party's HP when the battle started, by entity.
+#[derive(Resource, Default)]
+struct StartingHp(HashMap<Entity, u32>);
+
+pub struct BattleOutcomePlugin;
+
+impl Plugin for BattleOutcomePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<StartingHp>()
+            .add_systems(OnEnter(GameModeState::InBattle), record_starting_hp)
+            .add_systems(OnEnter(BattleState::Won), write_outcome)
+            .add_systems(OnEnter(BattleState::Lost), write_outcome)
+            .add_systems(OnEnter(BattleState::Fled), write_outcome);
+    }
+}
+
+/// Rolls each of an enemy's drops on its own, so one enemy can drop several things.
+pub fn roll_drops(drops: &[EnemyDrop], rng: &mut fastrand::Rng) -> Vec<ItemId> {
+    drops
+        .iter()
+        .filter(|drop| rng.f32() < drop.chance)
+        .map(|drop| drop.item.clone())
+        .collect()
+}
+
+fn record_starting_hp(
+    mut starting_hp: ResMut<StartingHp>,
+    combatant_query: Query<(Entity, &Combatant)>,
+) {
+    starting_hp.0 = combatant_query
+        .iter()
+        .filter(|(_, combatant)| combatant.side == Side::Party)
+        .map(|(entity, combatant)| (entity, combatant.hp))
+        .collect();
+}
+
+fn write_outcome(
+    mut commands: Commands,
+    battle_state: Res<State<BattleState>>,
+    starting_hp: Res<StartingHp>,
+    combatant_query: Query<(Entity, &Combatant, Option<&EnemyId>)>,
+    enemy_database: Res<EnemyDatabase>,
+    mut rng: ResMut<GameRng>,
+) {
+    let result = match battle_state.get() {
+        BattleState::Won => BattleResult::Victory,
+        BattleState::Lost => BattleResult::Defeat,
+        BattleState::Fled => BattleResult::Fled,
+        _ => return,
+    };
+
+    let (mut xp, mut drops) = (0, vec![]);
+    if result == BattleResult::Victory {
+        for (_, combatant, id) in combatant_query.iter() {
+            let Some(info) = id.and_then(|id| enemy_database.get(id)) else {
+                continue;
+            };
+            if !combatant.is_alive() {
+                xp += info.xp;
+                drops.extend(rng.with(|rng| roll_drops(&info.drops, rng)));
+            }
+        }
+    }
+    let hp_changes = combatant_query
+        .iter()
+        .filter(|(_, combatant, _)| combatant.side == Side::Party)
+        .map(|(entity, combatant, _)| HpChange {
+            name: combatant.name.clone(),
+            before: starting_hp.0.get(&entity).copied().unwrap_or(combatant.hp),
+            after: combatant.hp,
+        })
+        .collect();
+
+    info!("battle over: {:?}, {} XP, drops {:?}", result, xp, drops);
+    commands.insert_resource(BattleOutcome {
+        result,
+        xp,
+        drops,
+        hp_changes,
+    });
+}
+
+#[cfg(test)]
+mod test {
+    use bevy::prelude::*;
+
+    use crate::modes::battle::combatant::{Combatant, Side, Stats};
+    use crate::modes::battle::enemydatabase::test_helpers::test_enemy_database;
+    use crate::modes::battle::enemydatabase::{EnemyDrop, EnemyId};
+    use crate::modes::battle::outcome::{
+        roll_drops, BattleOutcome, BattleOutcomePlugin, BattleResult, HpChange,
+    };
+    use crate::modes::battle::skilldatabase::test_helpers::test_skill_database;
+    use crate::modes::battle::turns::{BattleAction, BattleCommand, BattleState, BattleTurnPlugin};
+    use crate::modes::dungeon::model::items::ItemId;
+    use crate::modes::inventory::Inventory;
+    use crate::modes::mode_state::GameModeState;
+    use crate::modes::sharedassets::itemdatabase::test_helpers::test_item_database;
+    use crate::utils::utilresources::GameRng;
+
+    #[test]
+    fn drops_are_rolled_one_by_one() {
+        let drops = [
+            EnemyDrop {
+                item: ItemId::new("Tonic"),
+                chance: 1.0,
+            },
+            EnemyDrop {
+                item: ItemId::new("Key"),
+                chance: 0.0,
+            },
+            EnemyDrop {
+                item: ItemId::new("Tonic"),
+                chance: 1.0,
+            },
+        ];
+        let mut rng = fastrand::Rng::with_seed(0);
+        assert_eq!(
+            roll_drops(&drops, &mut rng),
+            [ItemId::new("Tonic"), ItemId::new("Tonic")]
+        );
+        assert!(roll_drops(&[], &mut rng).is_empty());
+    }
+
+    fn run_battle(hero_speed: u32, action: impl Fn(Entity) -> BattleAction) -> BattleOutcome {
+        let mut app = App::new();
+        app.add_state::<GameModeState>()
+            .add_plugins((BattleTurnPlugin, BattleOutcomePlugin))
+            .insert_resource(GameRng(0))
+            .insert_resource(test_item_database())
+            .insert_resource(test_enemy_database())
+            .insert_resource(test_skill_database())
+            .init_resource::<Inventory>();
+        let stats = Stats {
+            attack: 50,
+            defense: 20,
+            magic: 0,
+            speed: hero_speed,
+        };
+        app.world
+            .spawn(Combatant::new("Hero", Side::Party, 100, 0, stats));
+        let slime = app
+            .world
+            .spawn((
+                test_enemy_database()
+                    .get(&EnemyId("Slime".to_string()))
+                    .unwrap()
+                    .combatant(),
+                EnemyId("Slime".to_string()),
+            ))
+            .id();
+        app.world
+            .resource_mut::<NextState<GameModeState>>()
+            .set(GameModeState::InBattle);
+        for _ in 0..50 {
+            app.world.send_event(BattleCommand(action(slime)));
+            app.update();
+            if let Some(outcome) = app.world.get_resource::<BattleOutcome>() {
+                return outcome.clone();
+            }
+        }
+        panic!(
+            "the battle never ended, it's {:?}",
+            app.world.resource::<State<BattleState>>().get()
+        );
+    }
+
+    #[test]
+    fn winning_gives_xp() {
+        let outcome = run_battle(1, |slime| BattleAction::Attack { target: slime });
+        assert_eq!(outcome.result, BattleResult::Victory);
+        assert_eq!(outcome.xp, 12);
+        assert!(outcome.drops.is_empty());
+        // the slime is faster and got a hit in each of the three rounds
+        assert_eq!(
+            outcome.hp_changes,
+            [HpChange {
+                name: "Hero".to_string(),
+                before: 100,
+                after: 100 - 3 * 5,
+            }]
+        );
+    }
+
+    #[test]
+    fn running_away_gives_nothing() {
+        let outcome = run_battle(100, |_| BattleAction::Flee);
+        assert_eq!(outcome.result, BattleResult::Fled);
+        assert_eq!(outcome.xp, 0);
+        assert_eq!(outcome.hp_changes[0].after, 100);
+    }
+}
@@ -0,0 +1,219 @@
+use std::fmt::{Display, Formatter};
+
+use bevy::prelude::{AssetServer, Assets, FromWorld, Handle, Resource, World};
+use bevy::reflect::{TypePath, TypeUuid};
+use bevy::utils::HashMap;
+use bevy_asset_loader::prelude::AssetCollection;
+use serde::Deserialize;
+
+use crate::modes::battle::affinity::Element;
+use crate::modes::battle::combatant::{Side, StatusEffect};
+
+/// Id of a skill in the `SkillDatabase`. Characters and enemies list the skills they know by id.
+#[derive(Deserialize, Clone, PartialEq, Eq, Hash, Debug)]
+#[serde(transparent)]
+pub struct SkillId(pub String);
+
+impl Display for SkillId {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// Who a skill goes to, seen from whoever uses it.
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Targeting {
+    OneEnemy,
+    AllEnemies,
+    OneAlly,
+    AllAllies,
+}
+
+impl Targeting {
+    /// The side the skill goes to when someone on `user` uses it.
+    pub fn side(self, user: Side) -> Side {
+        match self {
+            Targeting::OneEnemy | Targeting::AllEnemies => user.opponent(),
+            Targeting::OneAlly | Targeting::AllAllies => user,
+        }
+    }
+
+    pub fn hits_all(self) -> bool {
+        matches!(self, Targeting::AllEnemies | Targeting::AllAllies)
+    }
+}
+
+/// A status a skill might leave on whoever it hits, `chance` being between 0 and 1.
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq)]
+pub struct StatusInfliction {
+    pub effect: StatusEffect,
+    pub chance: f32,
+}
+
+/// Skills aimed at enemies do damage, skills aimed at allies heal by their power.
+#[derive(Deserialize, Clone, Debug)]
+pub struct SkillInfo {
+    pub name: String,
+    #[serde(default)]
+    pub mp_cost: u32,
+    pub element: Element,
+    #[serde(default)]
+    pub power: u32,
+    pub targeting: Targeting,
+    #[serde(default)]
+    pub status: Option<StatusInfliction>,
+}
+
+/// Every skill by id, from `skills.skills.json`.
+///
+/// ```json
+/// {
+///     "skills": {
+///         "Zap": {
+///             "name": "Zap",
+///             "mp_cost": 4,
+///             "element": "Elec",
+///             "power": 30,
+///             "targeting": "OneEnemy",
+///             "status": { "effect": "Stun", "chance": 0.2 }
+///         },
+///         "Mend": { "name": "Mend", "mp_cost": 5, "element": "Light", "power": 40, "targeting": "OneAlly" }
+///     }
+/// }
+/// ```
+#[derive(Deserialize, TypePath, TypeUuid)]
+#[uuid = "0d6f3a92-4e8b-4b71-a5c3-8f2e6d1b9a07"]
+pub struct RawSkillDatabase {
+    pub skills: HashMap<SkillId, SkillInfo>,
+}
+
+#[derive(AssetCollection, Resource)]
+pub struct SkillAssets {
+    #[asset(path = "battle/skills.skills.json")]
+    pub database: Handle<RawSkillDatabase>,
+}
+
+#[derive(Resource)]
+pub struct SkillDatabase {
+    skills: HashMap<SkillId, SkillInfo>,
+}
+
+impl SkillDatabase {
+    pub fn new(raw_skill_database: &RawSkillDatabase) -> Self {
+        SkillDatabase {
+            skills: raw_skill_database.skills.clone(),
+        }
+    }
+
+    pub fn get(&self, id: &SkillId) -> Option<&SkillInfo> {
+        self.skills.get(id)
+    }
+}
+
+impl FromWorld for SkillDatabase {
+    fn from_world(world: &mut World) -> Self {
+        let skill_assets = world
+            .get_resource::<SkillAssets>()
+            .expect("failed to get skill asset handles");
+        let raw_skill_databases = world
+            .get_resource::<Assets<RawSkillDatabase>>()
+            .expect("failed to get skill databases");
+        let raw_skill_database = raw_skill_databases
+            .get(&skill_assets.database)
+            .expect("failed to get skill database out of assets");
+        SkillDatabase::new(raw_skill_database)
+    }
+}
+
+#[cfg(test)]
+pub mod test_helpers {
+    use bevy::utils::HashMap;
+
+    use crate::modes::battle::affinity::Element;
+    use crate::modes::battle::combatant::StatusEffect;
+    use crate::modes::battle::skilldatabase::{
+        RawSkillDatabase, SkillDatabase, SkillId, SkillInfo, StatusInfliction, Targeting,
+    };
+
+    /// Zap is a 4 MP elec spell with 30 power, Blaze hits every enemy with fire for 20 and 6 MP,
+    /// Mend heals an ally by 40 for 5 MP, and Venom always poisons for 3 MP.
+    pub fn test_skill_database() -> SkillDatabase {
+        let skill = |name: &str, mp_cost, element, power, targeting| {
+            let info = SkillInfo {
+                name: name.to_string(),
+                mp_cost,
+                element,
+                power,
+                targeting,
+                status: None,
+            };
+            (SkillId(name.to_string()), info)
+        };
+        let (venom, mut venom_info) = skill("Venom", 3, Element::Dark, 5, Targeting::OneEnemy);
+        venom_info.status = Some(StatusInfliction {
+            effect: StatusEffect::Poison,
+            chance: 1.0,
+        });
+        SkillDatabase::new(&RawSkillDatabase {
+            skills: HashMap::from_iter([
+                skill("Zap", 4, Element::Elec, 30, Targeting::OneEnemy),
+                skill("Blaze", 6, Element::Fire, 20, Targeting::AllEnemies),
+                skill("Mend", 5, Element::Light, 40, Targeting::OneAlly),
+                (venom, venom_info),
+            ]),
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::modes::battle::affinity::Element;
+    use crate::modes::battle::combatant::{Side, StatusEffect};
+    use crate::modes::battle::skilldatabase::{
+        RawSkillDatabase, SkillDatabase, SkillId, StatusInfliction, Targeting,
+    };
+
+    #[test]
+    fn should_parse_skill_database() {
+        let raw_skill_database: RawSkillDatabase = serde_json::from_str(
+            r#"{
+                "skills": {
+                    "Zap": {
+                        "name": "Zap",
+                        "mp_cost": 4,
+                        "element": "Elec",
+                        "power": 30,
+                        "targeting": "OneEnemy",
+                        "status": { "effect": "Stun", "chance": 0.2 }
+                    },
+                    "Bash": { "name": "Bash", "element": "Physical", "power": 40, "targeting": "OneEnemy" }
+                }
+            }"#,
+        )
+        .unwrap();
+        let skill_database = SkillDatabase::new(&raw_skill_database);
+        let zap = skill_database.get(&SkillId("Zap".to_string())).unwrap();
+        assert_eq!(zap.mp_cost, 4);
+        assert_eq!(zap.element, Element::Elec);
+        assert_eq!(
+            zap.status,
+            Some(StatusInfliction {
+                effect: StatusEffect::Stun,
+                chance: 0.2
+            })
+        );
+        let bash = skill_database.get(&SkillId("Bash".to_string())).unwrap();
+        assert_eq!(bash.mp_cost, 0);
+        assert_eq!(bash.status, None);
+        assert!(skill_database.get(&SkillId("Nope".to_string())).is_none());
+    }
+
+    #[test]
+    fn targeting_is_seen_from_the_user() {
+        assert_eq!(Targeting::OneEnemy.side(Side::Party), Side::Enemy);
+        assert_eq!(Targeting::AllEnemies.side(Side::Enemy), Side::Party);
+        assert_eq!(Targeting::OneAlly.side(Side::Enemy), Side::Enemy);
+        assert!(Targeting::AllAllies.hits_all());
+        assert!(!Targeting::OneAlly.hits_all());
+    }
+}
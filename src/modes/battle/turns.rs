@@ -0,0 +1,1064 @@
+use std::cmp::Reverse;
+use std::collections::VecDeque;
+
+use bevy::prelude::*;
+
+use crate::modes::battle::affinity::Element;
+use crate::modes::battle::combatant::{Combatant, Side, StatusEffect};
+use crate::modes::battle::damage::{
+    earns_extra_turn, elemental_hit, heal_amount, poison_damage, status_lands, HitOutcome,
+    ATTACK_POWER,
+};
+use crate::modes::battle::dodge::{DodgeAttack, DodgeSettings};
+use crate::modes::battle::skilldatabase::{SkillDatabase, SkillId};
+use crate::modes::dungeon::model::items::ItemId;
+use crate::modes::inventory::Inventory;
+use crate::modes::mode_state::GameModeState;
+use crate::modes::sharedassets::itemdatabase::ItemDatabase;
+use crate::utils::utilresources::GameRng;
+
+/// Where the battle is at. It's `Inactive` whenever the game isn't in `GameModeState::InBattle`.
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq, Hash, States)]
+pub enum BattleState {
+    #[default]
+    Inactive,
+    /// Waiting for whoever's turn it is to pick an action. Enemies pick straight away.
+    ChoosingAction,
+    /// Carrying out the action that was picked.
+    Resolving,
+    /// An enemy is attacking and the player dodges its bullets for the party member it went for.
+    /// This only happens with the `DodgePlugin`, without it enemy attacks land straight away.
+    Dodging,
+    Won,
+    Lost,
+    Fled,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum BattleAction {
+    Attack {
+        target: Entity,
+    },
+    /// Skills that hit everyone on a side still need a `target` on that side.
+    Skill {
+        skill: SkillId,
+        target: Entity,
+    },
+    Item {
+        item: ItemId,
+        target: Entity,
+    },
+    Defend,
+    Flee,
+}
+
+/// Picks the action for the party member whose turn it is. Commands that can't be carried out,
+/// like attacking someone who's already down, are ignored.
+#[derive(Event, Clone, Debug)]
+pub struct BattleCommand(pub BattleAction);
+
+/// The action `BattleState::Resolving` is about to carry out.
+#[derive(Resource, Clone, Debug)]
+pub struct PendingAction {
+    pub actor: Entity,
+    pub action: BattleAction,
+}
+
+/// Who acts when. Every round everyone still standing gets one turn, fastest first, plus an
+/// extra one for hitting a weakness.
+#[derive(Resource, Default, Debug)]
+pub struct TurnQueue {
+    upcoming: VecDeque<Entity>,
+    current: Option<Entity>,
+    round: u32,
+    extra_turn_earned: bool,
+    on_extra_turn: bool,
+}
+
+impl TurnQueue {
+    /// Whose turn it is.
+    pub fn current(&self) -> Option<Entity> {
+        self.current
+    }
+
+    pub fn round(&self) -> u32 {
+        self.round
+    }
+
+    /// Whoever's turn it is goes again once it's over.
+    pub fn grant_extra_turn(&mut self) {
+        self.extra_turn_earned = true;
+    }
+
+    pub fn is_extra_turn(&self) -> bool {
+        self.on_extra_turn
+    }
+
+    /// Moves on to the next combatant who's still standing, starting a new round once everyone
+    /// has had their turn. An earned extra turn keeps it with the current combatant instead, as
+    /// long as they're still standing.
+    pub fn advance(&mut self, combatants: &[(Entity, &Combatant)]) -> Option<Entity> {
+        let is_alive = |entity| {
+            combatants
+                .iter()
+                .any(|(other, combatant)| *other == entity && combatant.is_alive())
+        };
+        self.on_extra_turn =
+            std::mem::take(&mut self.extra_turn_earned) && self.current.is_some_and(is_alive);
+        if self.on_extra_turn {
+            return self.current;
+        }
+        self.current = None;
+        // the second pass only finds nobody if nobody's left
+        for _ in 0..2 {
+            if let Some(next) =
+                std::iter::from_fn(|| self.upcoming.pop_front()).find(|e| is_alive(*e))
+            {
+                self.current = Some(next);
+                break;
+            }
+            self.round += 1;
+            self.upcoming = turn_order(combatants.iter().copied());
+        }
+        self.current
+    }
+}
+
+/// Everyone still standing, fastest first. Ties go to the party, then to whoever was spawned
+/// first.
+pub fn turn_order<'a>(
+    combatants: impl IntoIterator<Item = (Entity, &'a Combatant)>,
+) -> VecDeque<Entity> {
+    let mut alive: Vec<_> = combatants
+        .into_iter()
+        .filter(|(_, combatant)| combatant.is_alive())
+        .collect();
+    alive.sort_by_key(|(entity, combatant)| {
+        (
+            Reverse(combatant.stats.speed),
+            combatant.side != Side::Party,
+            *entity,
+        )
+    });
+    alive.into_iter().map(|(entity, _)| entity).collect()
+}
+
+/// How the battle ended, or `None` if both sides are still standing.
+pub fn battle_result<'a>(
+    combatants: impl IntoIterator<Item = &'a Combatant>,
+) -> Option<BattleState> {
+    let (mut party_alive, mut enemies_alive) = (false, false);
+    for combatant in combatants
+        .into_iter()
+        .filter(|combatant| combatant.is_alive())
+    {
+        match combatant.side {
+            Side::Party => party_alive = true,
+            Side::Enemy => enemies_alive = true,
+        }
+    }
+    if !enemies_alive {
+        Some(BattleState::Won)
+    } else if !party_alive {
+        Some(BattleState::Lost)
+    } else {
+        None
+    }
+}
+
+/// Ends the current turn. The battle is over if either side is down, otherwise it's the next
+/// combatant's turn.
+pub fn finish_turn(
+    combatants: &[(Entity, &Combatant)],
+    turn_queue: &mut TurnQueue,
+    next_battle_state: &mut NextState<BattleState>,
+) {
+    if let Some(result) = battle_result(combatants.iter().map(|(_, combatant)| *combatant)) {
+        next_battle_state.set(result);
+        return;
+    }
+    turn_queue.advance(combatants);
+    next_battle_state.set(BattleState::ChoosingAction);
+}
+
+/// The chance of getting away, going by how fast the one running is compared to the fastest
+/// enemy.
+pub fn flee_chance(speed: u32, enemy_speed: u32) -> f32 {
+    (0.5 + 0.05 * (speed as f32 - enemy_speed as f32)).clamp(0.1, 0.95)
+}
+
+pub struct BattleTurnPlugin;
+
+impl Plugin for BattleTurnPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_state::<BattleState>()
+            .init_resource::<TurnQueue>()
+            .add_event::<BattleCommand>()
+            .add_systems(OnEnter(GameModeState::InBattle), start_battle)
+            .add_systems(OnExit(GameModeState::InBattle), end_battle)
+            .add_systems(OnEnter(BattleState::ChoosingAction), begin_turn)
+            .add_systems(
+                Update,
+                read_battle_commands.run_if(in_state(BattleState::ChoosingAction)),
+            )
+            .add_systems(OnEnter(BattleState::Resolving), resolve_action)
+            .add_systems(OnEnter(BattleState::Won), leave_battle)
+            .add_systems(OnEnter(BattleState::Fled), leave_battle);
+    }
+}
+
+fn start_battle(
+    mut turn_queue: ResMut<TurnQueue>,
+    combatant_query: Query<(Entity, &Combatant)>,
+    mut next_battle_state: ResMut<NextState<BattleState>>,
+) {
+    let combatants: Vec<_> = combatant_query.iter().collect();
+    *turn_queue = TurnQueue::default();
+    turn_queue.advance(&combatants);
+    next_battle_state.set(
+        battle_result(combatants.iter().map(|(_, combatant)| *combatant))
+            .unwrap_or(BattleState::ChoosingAction),
+    );
+}
+
+fn end_battle(mut next_battle_state: ResMut<NextState<BattleState>>) {
+    next_battle_state.set(BattleState::Inactive);
+}
+
+// losing is handled by GameOverPlugin, everything else goes straight back to the dungeon
+fn leave_battle(mut next_state: ResMut<NextState<GameModeState>>) {
+    next_state.set(GameModeState::ExitingBattle);
+}
+
+/// Statuses take effect as a turn starts, except on extra turns. A stunned combatant loses the
+/// turn, which goes straight to `BattleState::Resolving` with nothing to carry out.
+///
+/// Enemies decide what to do as soon as their turn starts. Half the time they use one of their
+/// skills if they can afford any, otherwise they just hit someone at random.
+fn begin_turn(
+    mut commands: Commands,
+    turn_queue: Res<TurnQueue>,
+    mut combatant_query: Query<(Entity, &mut Combatant)>,
+    (mut rng, skill_database): (ResMut<GameRng>, Res<SkillDatabase>),
+    mut next_battle_state: ResMut<NextState<BattleState>>,
+) {
+    let Some(actor) = turn_queue.current() else {
+        return;
+    };
+    let Ok((_, mut combatant)) = combatant_query.get_mut(actor) else {
+        return;
+    };
+    combatant.defending = false;
+    info!("round {}, {}'s turn", turn_queue.round(), combatant.name);
+    if !turn_queue.is_extra_turn() {
+        match combatant.status {
+            Some(StatusEffect::Stun) => {
+                combatant.status = None;
+                info!("{} is stunned", combatant.name);
+                next_battle_state.set(BattleState::Resolving);
+                return;
+            }
+            Some(StatusEffect::Poison) => {
+                let damage = poison_damage(combatant.max_hp);
+                let lost = combatant.take_damage(damage);
+                info!("{} lost {} to poison", combatant.name, lost);
+                if !combatant.is_alive() {
+                    next_battle_state.set(BattleState::Resolving);
+                    return;
+                }
+            }
+            None => {}
+        }
+    }
+    if combatant.side != Side::Enemy {
+        return;
+    }
+
+    let usable_skills: Vec<_> = combatant
+        .skills
+        .iter()
+        .filter_map(|id| Some((id, skill_database.get(id)?)))
+        .filter(|(_, info)| info.mp_cost <= combatant.mp)
+        .collect();
+    let skill = if !usable_skills.is_empty() && rng.with(|rng| rng.bool()) {
+        let (id, info) = usable_skills[rng.with(|rng| rng.usize(0..usable_skills.len()))];
+        Some((id.clone(), info.targeting.side(combatant.side)))
+    } else {
+        None
+    };
+    let target_side = skill.as_ref().map_or(Side::Party, |(_, side)| *side);
+    let targets: Vec<_> = combatant_query
+        .iter()
+        .filter(|(_, combatant)| combatant.side == target_side && combatant.is_alive())
+        .map(|(entity, _)| entity)
+        .collect();
+    if targets.is_empty() {
+        return;
+    }
+    let target = targets[rng.with(|rng| rng.usize(0..targets.len()))];
+    let action = match skill {
+        Some((skill, _)) => BattleAction::Skill { skill, target },
+        None => BattleAction::Attack { target },
+    };
+    commands.insert_resource(PendingAction { actor, action });
+    next_battle_state.set(BattleState::Resolving);
+}
+
+/// Why `actor` can't carry out `action` right now, if it can't.
+fn check_action(
+    actor: &Combatant,
+    action: &BattleAction,
+    combatant_query: &Query<&Combatant>,
+    inventory: &Inventory,
+    (item_database, skill_database): (&ItemDatabase, &SkillDatabase),
+) -> Result<(), &'static str> {
+    let target = match action {
+        BattleAction::Attack { target }
+        | BattleAction::Skill { target, .. }
+        | BattleAction::Item { target, .. } => Some(*target),
+        BattleAction::Defend | BattleAction::Flee => None,
+    };
+    let target = match target.map(|target| combatant_query.get(target)) {
+        Some(Ok(target)) if target.is_alive() => Some(target),
+        Some(_) => return Err("the target isn't standing"),
+        None => None,
+    };
+    match action {
+        BattleAction::Skill { skill, .. } => {
+            let Some(info) = skill_database.get(skill) else {
+                return Err("there's no such skill");
+            };
+            if !actor.skills.contains(skill) {
+                Err("the skill hasn't been learned")
+            } else if actor.mp < info.mp_cost {
+                Err("not enough MP")
+            } else if target.is_some_and(|target| target.side != info.targeting.side(actor.side)) {
+                Err("the skill doesn't go to that side")
+            } else {
+                Ok(())
+            }
+        }
+        BattleAction::Item { item, .. } if !item_database.usable_in_battle(item) => {
+            Err("the item can't be used in battle")
+        }
+        BattleAction::Item { item, .. } if inventory.count(item) == 0 => {
+            Err("there aren't any left")
+        }
+        _ => Ok(()),
+    }
+}
+
+fn read_battle_commands(
+    mut commands: Commands,
+    mut command_reader: EventReader<BattleCommand>,
+    turn_queue: Res<TurnQueue>,
+    combatant_query: Query<&Combatant>,
+    (inventory, item_database, skill_database): (
+        Res<Inventory>,
+        Res<ItemDatabase>,
+        Res<SkillDatabase>,
+    ),
+    mut next_battle_state: ResMut<NextState<BattleState>>,
+) {
+    let Some((actor, combatant)) = turn_queue
+        .current()
+        .and_then(|actor| Some((actor, combatant_query.get(actor).ok()?)))
+    else {
+        return;
+    };
+    for BattleCommand(action) in command_reader.iter() {
+        if combatant.side != Side::Party {
+            continue;
+        }
+        if let Err(reason) = check_action(
+            combatant,
+            action,
+            &combatant_query,
+            &inventory,
+            (&item_database, &skill_database),
+        ) {
+            warn!("{} can't do {:?}: {}", combatant.name, action, reason);
+            continue;
+        }
+        commands.insert_resource(PendingAction {
+            actor,
+            action: action.clone(),
+        });
+        next_battle_state.set(BattleState::Resolving);
+        return;
+    }
+}
+
+/// Hits `target` with `element`, going by the target's affinity for it. Reflected damage goes to
+/// `actor` instead.
+pub fn hit(
+    combatant_query: &mut Query<(Entity, &mut Combatant)>,
+    (actor, attacker): (Entity, &Combatant),
+    target: Entity,
+    power: u32,
+    element: Element,
+) -> Option<HitOutcome> {
+    let Ok((_, mut target)) = combatant_query.get_mut(target) else {
+        return None;
+    };
+    let outcome = elemental_hit(
+        power,
+        element,
+        &attacker.stats,
+        &target.stats,
+        target.defending,
+        &target.affinities,
+    );
+    match outcome {
+        HitOutcome::Damage { amount, weak } => {
+            let lost = target.take_damage(amount);
+            info!("{} hit {} for {}", attacker.name, target.name, lost);
+            if weak {
+                info!("that's {}'s weakness", target.name);
+            }
+        }
+        HitOutcome::Nulled => info!("{} took nothing from {}", target.name, attacker.name),
+        HitOutcome::Drained { amount } => {
+            let healed = target.heal(amount);
+            info!("{} drained {} HP from the hit", target.name, healed);
+        }
+        HitOutcome::Reflected { amount } => {
+            info!("{} sent the hit back", target.name);
+            if let Ok((_, mut attacker)) = combatant_query.get_mut(actor) {
+                let lost = attacker.take_damage(amount);
+                info!("{} took {}", attacker.name, lost);
+            }
+        }
+    }
+    Some(outcome)
+}
+
+/// Carries out the `PendingAction`. Without one the turn was lost, so it just ends.
+fn resolve_action(
+    mut commands: Commands,
+    pending_action: Option<Res<PendingAction>>,
+    mut turn_queue: ResMut<TurnQueue>,
+    mut combatant_query: Query<(Entity, &mut Combatant)>,
+    (mut inventory, item_database, skill_database): (
+        ResMut<Inventory>,
+        Res<ItemDatabase>,
+        Res<SkillDatabase>,
+    ),
+    (mut rng, dodge_settings): (ResMut<GameRng>, Option<Res<DodgeSettings>>),
+    mut next_battle_state: ResMut<NextState<BattleState>>,
+) {
+    let Some(PendingAction { actor, action }) = pending_action.map(|pending| pending.clone())
+    else {
+        let combatants: Vec<_> = combatant_query.iter().collect();
+        finish_turn(&combatants, &mut turn_queue, &mut next_battle_state);
+        return;
+    };
+    commands.remove_resource::<PendingAction>();
+    let Ok((_, combatant)) = combatant_query.get(actor) else {
+        return;
+    };
+    let combatant = combatant.clone();
+
+    match action {
+        BattleAction::Attack { target }
+            if dodge_settings.is_some() && combatant.side == Side::Enemy =>
+        {
+            commands.insert_resource(DodgeAttack {
+                attacker: actor,
+                target,
+            });
+            next_battle_state.set(BattleState::Dodging);
+            return;
+        }
+        BattleAction::Attack { target } => {
+            let outcome = hit(
+                &mut combatant_query,
+                (actor, &combatant),
+                target,
+                ATTACK_POWER,
+                Element::Physical,
+            );
+            if earns_extra_turn(outcome.as_slice(), turn_queue.is_extra_turn()) {
+                turn_queue.grant_extra_turn();
+            }
+        }
+        BattleAction::Skill { skill, target } => {
+            let Some(info) = skill_database.get(&skill) else {
+                return;
+            };
+            if let Ok((_, mut caster)) = combatant_query.get_mut(actor) {
+                caster.mp = caster.mp.saturating_sub(info.mp_cost);
+            }
+            info!("{} used {}", combatant.name, info.name);
+            let side = info.targeting.side(combatant.side);
+            let targets: Vec<_> = if info.targeting.hits_all() {
+                combatant_query
+                    .iter()
+                    .filter(|(_, other)| other.side == side && other.is_alive())
+                    .map(|(entity, _)| entity)
+                    .collect()
+            } else {
+                vec![target]
+            };
+            if side == combatant.side {
+                let amount = heal_amount(info.power, combatant.stats.magic);
+                for target in targets {
+                    if let Ok((_, mut target)) = combatant_query.get_mut(target) {
+                        let healed = target.heal(amount);
+                        info!("{} got {} HP back", target.name, healed);
+                    }
+                }
+            } else {
+                let mut outcomes = Vec::new();
+                for target in targets {
+                    let Some(outcome) = hit(
+                        &mut combatant_query,
+                        (actor, &combatant),
+                        target,
+                        info.power,
+                        info.element,
+                    ) else {
+                        continue;
+                    };
+                    outcomes.push(outcome);
+                    let (Some(status), Ok((_, mut target))) =
+                        (info.status, combatant_query.get_mut(target))
+                    else {
+                        continue;
+                    };
+                    let roll = rng.with(|rng| rng.f32());
+                    let affinity = target.affinities.get(info.element);
+                    if target.is_alive() && status_lands(status.chance, roll, affinity) {
+                        target.status = Some(status.effect);
+                        info!("{} is now {:?}", target.name, status.effect);
+                    }
+                }
+                if earns_extra_turn(&outcomes, turn_queue.is_extra_turn()) {
+                    turn_queue.grant_extra_turn();
+                }
+            }
+        }
+        BattleAction::Item { item, target } => {
+            if inventory.remove(&item) {
+                if let Ok((_, mut target)) = combatant_query.get_mut(target) {
+                    let healed = target.heal(item_database.heal(&item));
+                    info!(
+                        "{} used {} on {}, +{} HP",
+                        combatant.name, item, target.name, healed
+                    );
+                }
+            }
+        }
+        BattleAction::Defend => {
+            if let Ok((_, mut defender)) = combatant_query.get_mut(actor) {
+                defender.defending = true;
+            }
+        }
+        BattleAction::Flee => {
+            let enemy_speed = combatant_query
+                .iter()
+                .filter(|(_, other)| other.side == combatant.side.opponent() && other.is_alive())
+                .map(|(_, other)| other.stats.speed)
+                .max()
+                .unwrap_or(0);
+            let chance = flee_chance(combatant.stats.speed, enemy_speed);
+            if rng.with(|rng| rng.f32()) < chance {
+                info!("{} got away", combatant.name);
+                next_battle_state.set(BattleState::Fled);
+                return;
+            }
+            info!("{} couldn't get away", combatant.name);
+        }
+    }
+
+    let combatants: Vec<_> = combatant_query.iter().collect();
+    finish_turn(&combatants, &mut turn_queue, &mut next_battle_state);
+}
+
+#[cfg(test)]
+mod test {
+    use bevy::prelude::*;
+
+    use crate::modes::battle::affinity::{Affinities, Affinity, Element};
+    use crate::modes::battle::combatant::{Combatant, Side, Stats, StatusEffect};
+    use crate::modes::battle::damage::{heal_amount, hit_damage, poison_damage, ATTACK_POWER};
+    use crate::modes::battle::skilldatabase::test_helpers::test_skill_database;
+    use crate::modes::battle::skilldatabase::SkillId;
+    use crate::modes::battle::turns::{
+        flee_chance, turn_order, BattleAction, BattleCommand, BattleState, BattleTurnPlugin,
+        TurnQueue,
+    };
+    use crate::modes::dungeon::model::items::ItemId;
+    use crate::modes::inventory::Inventory;
+    use crate::modes::mode_state::GameModeState;
+    use crate::modes::sharedassets::itemdatabase::test_helpers::test_item_database;
+    use crate::utils::utilresources::GameRng;
+
+    fn stats(attack: u32, defense: u32, speed: u32) -> Stats {
+        Stats {
+            attack,
+            defense,
+            magic: attack,
+            speed,
+        }
+    }
+
+    fn setup() -> App {
+        let mut app = App::new();
+        app.add_state::<GameModeState>()
+            .add_plugins(BattleTurnPlugin)
+            .insert_resource(GameRng(1))
+            .insert_resource(test_item_database())
+            .insert_resource(test_skill_database())
+            .init_resource::<Inventory>();
+        app
+    }
+
+    fn start(app: &mut App) {
+        app.world
+            .resource_mut::<NextState<GameModeState>>()
+            .set(GameModeState::InBattle);
+        app.update();
+        app.update();
+    }
+
+    fn battle_state(app: &App) -> BattleState {
+        *app.world.resource::<State<BattleState>>().get()
+    }
+
+    fn current_actor(app: &App) -> Option<Entity> {
+        app.world.resource::<TurnQueue>().current()
+    }
+
+    /// Runs frames until it's `actor`'s turn to choose or the battle ends, and returns the state
+    /// it stopped in.
+    fn wait_for_turn(app: &mut App, actor: Entity) -> BattleState {
+        for _ in 0..50 {
+            let state = battle_state(app);
+            if state == BattleState::ChoosingAction && current_actor(app) == Some(actor) {
+                return state;
+            }
+            if matches!(
+                state,
+                BattleState::Won | BattleState::Lost | BattleState::Fled
+            ) {
+                return state;
+            }
+            app.update();
+        }
+        panic!("{:?} never got a turn", actor);
+    }
+
+    fn command(app: &mut App, action: BattleAction) {
+        app.world.send_event(BattleCommand(action));
+        app.update();
+        app.update();
+    }
+
+    fn hp(app: &App, entity: Entity) -> u32 {
+        app.world.get::<Combatant>(entity).unwrap().hp
+    }
+
+    fn skill(skill: &str, target: Entity) -> BattleAction {
+        BattleAction::Skill {
+            skill: SkillId(skill.to_string()),
+            target,
+        }
+    }
+
+    fn skills(names: &[&str]) -> Vec<SkillId> {
+        names.iter().map(|name| SkillId(name.to_string())).collect()
+    }
+
+    fn affinities(affinities: &[(Element, Affinity)]) -> Affinities {
+        Affinities(affinities.iter().copied().collect())
+    }
+
+    #[test]
+    fn faster_combatants_act_first() {
+        let mut world = World::new();
+        let slow = Combatant::new("Slow", Side::Party, 10, 0, stats(1, 1, 2));
+        let fast = Combatant::new("Fast", Side::Enemy, 10, 0, stats(1, 1, 9));
+        let tied = Combatant::new("Tied", Side::Party, 10, 0, stats(1, 1, 9));
+        let mut dead = Combatant::new("Dead", Side::Enemy, 10, 0, stats(1, 1, 20));
+        dead.hp = 0;
+        let entities: Vec<_> = (0..4).map(|_| world.spawn_empty().id()).collect();
+        let order = turn_order([
+            (entities[0], &slow),
+            (entities[1], &fast),
+            (entities[2], &tied),
+            (entities[3], &dead),
+        ]);
+        assert_eq!(order, [entities[2], entities[1], entities[0]]);
+
+        assert_eq!(flee_chance(10, 10), 0.5);
+        assert!(flee_chance(100, 0) > flee_chance(12, 10));
+        assert!(flee_chance(0, 100) > 0.0);
+    }
+
+    #[test]
+    fn party_wins_by_attacking() {
+        let mut app = setup();
+        let hero = app
+            .world
+            .spawn(Combatant::new(
+                "Hero",
+                Side::Party,
+                100,
+                0,
+                stats(20, 5, 10),
+            ))
+            .id();
+        let slime = app
+            .world
+            .spawn(Combatant::new("Slime", Side::Enemy, 30, 0, stats(5, 5, 5)))
+            .id();
+        start(&mut app);
+        assert_eq!(current_actor(&app), Some(hero));
+
+        let mut turns = 0;
+        while wait_for_turn(&mut app, hero) != BattleState::Won {
+            command(&mut app, BattleAction::Attack { target: slime });
+            turns += 1;
+            assert!(turns < 10, "the battle never ended");
+        }
+        // 30 HP, 16 a hit
+        assert_eq!(turns, 2);
+        assert_eq!(hp(&app, slime), 0);
+        assert!(hp(&app, hero) < 100);
+        assert_eq!(app.world.resource::<TurnQueue>().round(), 2);
+
+        app.update();
+        assert_eq!(
+            app.world.resource::<State<GameModeState>>().get(),
+            &GameModeState::ExitingBattle
+        );
+        app.update();
+        assert_eq!(battle_state(&app), BattleState::Inactive);
+    }
+
+    #[test]
+    fn party_can_lose() {
+        let mut app = setup();
+        let hero = app
+            .world
+            .spawn(Combatant::new("Hero", Side::Party, 30, 0, stats(1, 0, 1)))
+            .id();
+        app.world.spawn(Combatant::new(
+            "Ogre",
+            Side::Enemy,
+            500,
+            0,
+            stats(50, 50, 20),
+        ));
+        start(&mut app);
+        // the ogre goes first and hits for 20, then for 10 while the hero defends
+        assert_eq!(wait_for_turn(&mut app, hero), BattleState::ChoosingAction);
+        assert_eq!(hp(&app, hero), 10);
+        command(&mut app, BattleAction::Defend);
+        assert_eq!(wait_for_turn(&mut app, hero), BattleState::Lost);
+        assert_eq!(hp(&app, hero), 0);
+    }
+
+    #[test]
+    fn defending_halves_damage_until_next_turn() {
+        let mut app = setup();
+        let hero = app
+            .world
+            .spawn(Combatant::new(
+                "Hero",
+                Side::Party,
+                200,
+                0,
+                stats(1, 10, 10),
+            ))
+            .id();
+        let slime = app
+            .world
+            .spawn(Combatant::new(
+                "Slime",
+                Side::Enemy,
+                200,
+                0,
+                stats(30, 1, 5),
+            ))
+            .id();
+        start(&mut app);
+
+        wait_for_turn(&mut app, hero);
+        command(&mut app, BattleAction::Defend);
+        wait_for_turn(&mut app, hero);
+        assert!(!app.world.get::<Combatant>(hero).unwrap().defending);
+        let defended = hit_damage(ATTACK_POWER, 30, 10, true);
+        assert_eq!(hp(&app, hero), 200 - defended);
+
+        command(&mut app, BattleAction::Attack { target: slime });
+        wait_for_turn(&mut app, hero);
+        let undefended = hit_damage(ATTACK_POWER, 30, 10, false);
+        assert_eq!(hp(&app, hero), 200 - defended - undefended);
+        assert!(defended < undefended);
+    }
+
+    #[test]
+    fn items_and_skills_need_something_to_spend() {
+        let mut app = setup();
+        let hero = app
+            .world
+            .spawn(
+                Combatant::new("Hero", Side::Party, 100, 5, stats(5, 100, 10))
+                    .with_skills(skills(&["Zap", "Blaze"])),
+            )
+            .id();
+        let slime = app
+            .world
+            .spawn(Combatant::new(
+                "Slime",
+                Side::Enemy,
+                100,
+                0,
+                stats(100, 5, 5),
+            ))
+            .id();
+        let tonic = ItemId::new("Tonic");
+        let item_database = test_item_database();
+        app.world
+            .resource_mut::<Inventory>()
+            .add(&tonic, None, &item_database)
+            .unwrap();
+        app.world.get_mut::<Combatant>(hero).unwrap().hp = 50;
+        start(&mut app);
+        wait_for_turn(&mut app, hero);
+
+        // not usable in battle, none in the inventory, too expensive, not learned, wrong side,
+        // not a skill at all
+        for action in [
+            BattleAction::Item {
+                item: ItemId::new("Key"),
+                target: hero,
+            },
+            BattleAction::Item {
+                item: ItemId::new("Maxwell"),
+                target: hero,
+            },
+            skill("Blaze", slime),
+            skill("Mend", hero),
+            skill("Zap", hero),
+            skill("Nope", slime),
+        ] {
+            command(&mut app, action);
+            assert_eq!(battle_state(&app), BattleState::ChoosingAction);
+            assert_eq!(current_actor(&app), Some(hero));
+        }
+
+        command(
+            &mut app,
+            BattleAction::Item {
+                item: tonic.clone(),
+                target: hero,
+            },
+        );
+        assert_eq!(app.world.resource::<Inventory>().count(&tonic), 0);
+        wait_for_turn(&mut app, hero);
+        let enemy_hit = hit_damage(ATTACK_POWER, 100, 100, false);
+        assert_eq!(hp(&app, hero), 80 - enemy_hit);
+
+        command(&mut app, skill("Zap", slime));
+        assert_eq!(app.world.get::<Combatant>(hero).unwrap().mp, 1);
+        assert_eq!(hp(&app, slime), 100 - hit_damage(30, 5, 5, false));
+    }
+
+    #[test]
+    fn hitting_a_weakness_earns_one_extra_turn() {
+        let mut app = setup();
+        let hero = app
+            .world
+            .spawn(
+                Combatant::new("Hero", Side::Party, 100, 20, stats(10, 100, 10))
+                    .with_skills(skills(&["Zap"])),
+            )
+            .id();
+        let slime = app
+            .world
+            .spawn(
+                Combatant::new("Slime", Side::Enemy, 200, 0, stats(1, 10, 5))
+                    .with_affinities(affinities(&[(Element::Elec, Affinity::Weak)])),
+            )
+            .id();
+        start(&mut app);
+        wait_for_turn(&mut app, hero);
+
+        command(&mut app, skill("Zap", slime));
+        // 15 damage, half again for the weakness
+        assert_eq!(hp(&app, slime), 200 - 22);
+        assert_eq!(current_actor(&app), Some(hero));
+        assert!(app.world.resource::<TurnQueue>().is_extra_turn());
+        assert_eq!(app.world.resource::<TurnQueue>().round(), 1);
+        wait_for_turn(&mut app, hero);
+
+        // the extra turn doesn't earn another one, so the slime gets its go
+        command(&mut app, skill("Zap", slime));
+        assert_eq!(hp(&app, slime), 200 - 44);
+        wait_for_turn(&mut app, hero);
+        assert_eq!(app.world.resource::<TurnQueue>().round(), 2);
+        assert!(!app.world.resource::<TurnQueue>().is_extra_turn());
+        assert_eq!(
+            hp(&app, hero),
+            100 - hit_damage(ATTACK_POWER, 1, 100, false)
+        );
+    }
+
+    #[test]
+    fn reflected_and_drained_hits_go_the_wrong_way() {
+        let mut app = setup();
+        let hero = app
+            .world
+            .spawn(
+                Combatant::new("Hero", Side::Party, 100, 30, stats(10, 10, 10))
+                    .with_skills(skills(&["Zap", "Blaze", "Mend"])),
+            )
+            .id();
+        let slime = app
+            .world
+            .spawn(
+                Combatant::new("Slime", Side::Enemy, 100, 0, stats(0, 10, 5)).with_affinities(
+                    affinities(&[
+                        (Element::Elec, Affinity::Reflect),
+                        (Element::Fire, Affinity::Drain),
+                        (Element::Physical, Affinity::Null),
+                    ]),
+                ),
+            )
+            .id();
+        app.world.get_mut::<Combatant>(slime).unwrap().hp = 50;
+        start(&mut app);
+        let enemy_hit = hit_damage(ATTACK_POWER, 0, 10, false);
+
+        wait_for_turn(&mut app, hero);
+        command(&mut app, skill("Zap", slime));
+        wait_for_turn(&mut app, hero);
+        assert_eq!(hp(&app, slime), 50);
+        let zapped = hit_damage(30, 10, 10, false);
+        assert_eq!(hp(&app, hero), 100 - zapped - enemy_hit);
+
+        command(&mut app, skill("Blaze", slime));
+        wait_for_turn(&mut app, hero);
+        assert_eq!(hp(&app, slime), 50 + hit_damage(20, 10, 10, false));
+
+        command(&mut app, BattleAction::Attack { target: slime });
+        wait_for_turn(&mut app, hero);
+        assert_eq!(hp(&app, slime), 60);
+        assert_eq!(hp(&app, hero), 100 - zapped - 3 * enemy_hit);
+
+        command(&mut app, skill("Mend", hero));
+        assert_eq!(app.world.get::<Combatant>(hero).unwrap().mp, 30 - 4 - 6 - 5);
+        wait_for_turn(&mut app, hero);
+        let mended = (100 - zapped - 3 * enemy_hit + heal_amount(40, 10)).min(100);
+        assert_eq!(hp(&app, hero), mended - enemy_hit);
+    }
+
+    #[test]
+    fn poison_wears_down_and_stun_skips_a_turn() {
+        let mut app = setup();
+        let hero = app
+            .world
+            .spawn(
+                Combatant::new("Hero", Side::Party, 100, 10, stats(10, 100, 10))
+                    .with_skills(skills(&["Venom"])),
+            )
+            .id();
+        let slime = app
+            .world
+            .spawn(Combatant::new(
+                "Slime",
+                Side::Enemy,
+                80,
+                0,
+                stats(10, 1000, 5),
+            ))
+            .id();
+        start(&mut app);
+        let enemy_hit = hit_damage(ATTACK_POWER, 10, 100, false);
+
+        wait_for_turn(&mut app, hero);
+        command(&mut app, skill("Venom", slime));
+        wait_for_turn(&mut app, hero);
+        let slime_combatant = app.world.get::<Combatant>(slime).unwrap();
+        assert_eq!(slime_combatant.status, Some(StatusEffect::Poison));
+        assert_eq!(hp(&app, slime), 80 - 1 - poison_damage(80));
+        assert_eq!(hp(&app, hero), 100 - enemy_hit);
+
+        app.world.get_mut::<Combatant>(slime).unwrap().status = Some(StatusEffect::Stun);
+        command(&mut app, BattleAction::Defend);
+        wait_for_turn(&mut app, hero);
+        assert_eq!(hp(&app, hero), 100 - enemy_hit);
+        assert_eq!(app.world.get::<Combatant>(slime).unwrap().status, None);
+        assert_eq!(app.world.resource::<TurnQueue>().round(), 3);
+    }
+
+    #[test]
+    fn enemies_use_their_skills() {
+        let mut app = setup();
+        let hero = app
+            .world
+            .spawn(Combatant::new(
+                "Hero",
+                Side::Party,
+                1000,
+                0,
+                stats(1, 100, 10),
+            ))
+            .id();
+        let snake = app
+            .world
+            .spawn(
+                Combatant::new("Snake", Side::Enemy, 1000, 3, stats(1, 100, 5))
+                    .with_skills(skills(&["Venom"])),
+            )
+            .id();
+        start(&mut app);
+
+        for _ in 0..20 {
+            wait_for_turn(&mut app, hero);
+            if app.world.get::<Combatant>(hero).unwrap().status.is_some() {
+                break;
+            }
+            command(&mut app, BattleAction::Defend);
+        }
+        assert_eq!(
+            app.world.get::<Combatant>(hero).unwrap().status,
+            Some(StatusEffect::Poison)
+        );
+        // only enough MP for the one
+        assert_eq!(app.world.get::<Combatant>(snake).unwrap().mp, 0);
+    }
+
+    #[test]
+    fn fast_party_can_flee() {
+        let mut app = setup();
+        let hero = app
+            .world
+            .spawn(Combatant::new(
+                "Hero",
+                Side::Party,
+                100,
+                0,
+                stats(1, 100, 100),
+            ))
+            .id();
+        app.world
+            .spawn(Combatant::new("Snail", Side::Enemy, 100, 0, stats(1, 1, 0)));
+        start(&mut app);
+
+        let mut tries = 0;
+        while wait_for_turn(&mut app, hero) != BattleState::Fled {
+            command(&mut app, BattleAction::Flee);
+            tries += 1;
+            assert!(tries < 5, "never got away");
+        }
+        app.update();
+        assert_eq!(
+            app.world.resource::<State<GameModeState>>().get(),
+            &GameModeState::ExitingBattle
+        );
+    }
+}
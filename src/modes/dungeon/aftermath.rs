@@ -0,0 +1,204 @@
+use bevy::prelude::*;
+
+use crate::modes::battle::encounter::{BattleInitiator, BattleRequest};
+use crate::modes::battle::outcome::{BattleOutcome, BattleResult};
+use crate::modes::dungeon::dungeonmode::CurrentFloor;
+use crate::modes::dungeon::foes::{DefeatedFoes, Foe};
+use crate::modes::inventory::Inventory;
+use crate::modes::mode_state::GameModeState;
+use crate::modes::party::Party;
+use crate::modes::sharedassets::characterdatabase::CharacterDatabase;
+use crate::modes::sharedassets::itemdatabase::ItemDatabase;
+
+/// How many steps a FOE waits after the player ran away from it.
+const FLED_FOE_WAIT_STEPS: u32 = 3;
+
+/// Deals with the `BattleOutcome` once the battle's gone and the player is back in the dungeon.
+pub struct AftermathPlugin;
+
+impl Plugin for AftermathPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(OnExit(GameModeState::ExitingBattle), apply_battle_outcome);
+    }
+}
+
+/// Beaten FOEs are gone for good and ones the player ran from wait a few steps. Everyone still
+/// standing after a win gets the XP, and drops go in the inventory if there's room. The player
+/// stays where they were when the battle started.
+pub fn apply_battle_outcome(
+    mut commands: Commands,
+    battle_outcome: Option<Res<BattleOutcome>>,
+    battle_request: Option<Res<BattleRequest>>,
+    (current_floor, mut defeated_foes): (Res<CurrentFloor>, ResMut<DefeatedFoes>),
+    mut foe_query: Query<(Entity, &mut Foe)>,
+    (mut inventory, item_database): (ResMut<Inventory>, Res<ItemDatabase>),
+    (mut party, character_database): (ResMut<Party>, Res<CharacterDatabase>),
+) {
+    commands.remove_resource::<BattleOutcome>();
+    commands.remove_resource::<BattleRequest>();
+    let Some(battle_outcome) = battle_outcome else {
+        warn!("back from a battle without an outcome");
+        return;
+    };
+
+    let mut foe = None;
+    if let Some(BattleInitiator::Foe { floor, index }) = battle_request.map(|r| r.initiator) {
+        if battle_outcome.result == BattleResult::Victory {
+            defeated_foes.0.insert((floor, index));
+        }
+        if floor == current_floor.0 {
+            foe = foe_query.iter_mut().find(|(_, foe)| foe.index == index);
+        }
+    }
+
+    match battle_outcome.result {
+        BattleResult::Victory => {
+            if let Some((entity, _)) = foe {
+                commands.entity(entity).despawn_recursive();
+            }
+            info!("the party got {} XP", battle_outcome.xp);
+            for member in party.members_mut().iter_mut().filter(|m| m.is_alive()) {
+                let Some(info) = character_database.get(&member.character) else {
+                    continue;
+                };
+                if member.gain_xp(battle_outcome.xp, info) > 0 {
+                    info!("{} is now level {}", member.name, member.level);
+                }
+            }
+            for item in battle_outcome.drops.iter() {
+                match inventory.add(item, None, &item_database) {
+                    Ok(()) => info!("picked up {}", item_database.name(item)),
+                    Err(_) => info!("no room for {}", item_database.name(item)),
+                }
+            }
+        }
+        BattleResult::Fled => {
+            if let Some((_, mut foe)) = foe {
+                foe.wait_steps = FLED_FOE_WAIT_STEPS;
+            }
+        }
+        // lost battles end up in GameModeState::GameOver instead of coming back here
+        BattleResult::Defeat => warn!("back in the dungeon after losing a battle"),
+    }
+    for hp_change in battle_outcome.hp_changes.iter() {
+        info!(
+            "{} went from {} to {} HP",
+            hp_change.name, hp_change.before, hp_change.after
+        );
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use bevy::prelude::*;
+
+    use crate::modes::battle::encounter::{BattleInitiator, BattleRequest, FormationId};
+    use crate::modes::battle::outcome::{BattleOutcome, BattleResult};
+    use crate::modes::dungeon::aftermath::AftermathPlugin;
+    use crate::modes::dungeon::dungeonmode::CurrentFloor;
+    use crate::modes::dungeon::foes::{DefeatedFoes, Foe};
+    use crate::modes::dungeon::model::cell::{GridDirection, GridPosition};
+    use crate::modes::dungeon::model::foe::FoeBehavior;
+    use crate::modes::dungeon::model::items::ItemId;
+    use crate::modes::inventory::Inventory;
+    use crate::modes::mode_state::GameModeState;
+    use crate::modes::party::test_helpers::test_party;
+    use crate::modes::party::Party;
+    use crate::modes::sharedassets::characterdatabase::test_helpers::test_character_database;
+    use crate::modes::sharedassets::itemdatabase::test_helpers::test_item_database;
+
+    /// Comes back from a battle against the first FOE of floor 1, with the player standing on it.
+    fn come_back(result: BattleResult, drops: Vec<ItemId>) -> (App, Entity, Entity) {
+        let mut app = App::new();
+        app.add_state::<GameModeState>()
+            .add_plugins(AftermathPlugin)
+            .insert_resource(CurrentFloor(1))
+            .insert_resource(test_item_database())
+            .init_resource::<DefeatedFoes>()
+            .init_resource::<Inventory>()
+            .insert_resource(test_character_database())
+            .insert_resource(test_party())
+            .insert_resource(BattleRequest {
+                formation: FormationId("slimes".to_string()),
+                terrain: None,
+                initiator: BattleInitiator::Foe { floor: 1, index: 0 },
+            })
+            .insert_resource(BattleOutcome {
+                result,
+                xp: 60,
+                drops,
+                hp_changes: vec![],
+            });
+        let position = GridPosition { row: 2, col: 3 };
+        let player = app.world.spawn((position, GridDirection::Left)).id();
+        let foe = app
+            .world
+            .spawn((
+                Foe {
+                    index: 0,
+                    formation: FormationId("slimes".to_string()),
+                    behavior: FoeBehavior::Wander,
+                    patrol_index: 0,
+                    wait_steps: 0,
+                },
+                position,
+            ))
+            .id();
+        app.world
+            .resource_mut::<NextState<GameModeState>>()
+            .set(GameModeState::ExitingBattle);
+        app.update();
+        app.world
+            .resource_mut::<NextState<GameModeState>>()
+            .set(GameModeState::InDungeon);
+        app.update();
+        (app, player, foe)
+    }
+
+    #[test]
+    fn beaten_foes_are_gone_and_drops_are_kept() {
+        let (app, player, foe) = come_back(
+            BattleResult::Victory,
+            vec![
+                ItemId::new("Tonic"),
+                ItemId::new("Polaroid"),
+                ItemId::new("Maxwell"),
+                ItemId::new("Key"),
+            ],
+        );
+        assert!(app.world.get_entity(foe).is_none());
+        assert!(app.world.resource::<DefeatedFoes>().0.contains(&(1, 0)));
+        let inventory = app.world.resource::<Inventory>();
+        assert_eq!(inventory.count(&ItemId::new("Tonic")), 1);
+        assert_eq!(inventory.count(&ItemId::new("Maxwell")), 1);
+        // three slots
+        assert_eq!(inventory.count(&ItemId::new("Key")), 0);
+        let hero = &app.world.resource::<Party>().members()[0];
+        assert_eq!((hero.xp, hero.level), (60, 2));
+
+        assert!(app.world.get_resource::<BattleOutcome>().is_none());
+        assert!(app.world.get_resource::<BattleRequest>().is_none());
+        assert_eq!(
+            app.world.get::<GridPosition>(player),
+            Some(&GridPosition { row: 2, col: 3 })
+        );
+        assert_eq!(
+            app.world.get::<GridDirection>(player),
+            Some(&GridDirection::Left)
+        );
+    }
+
+    #[test]
+    fn foes_wait_after_the_player_runs() {
+        let (app, _, foe) = come_back(BattleResult::Fled, vec![ItemId::new("Tonic")]);
+        assert_eq!(app.world.get::<Foe>(foe).unwrap().wait_steps, 3);
+        assert!(app.world.resource::<DefeatedFoes>().0.is_empty());
+        assert_eq!(app.world.resource::<Party>().members()[0].xp, 0);
+        assert_eq!(
+            app.world
+                .resource::<Inventory>()
+                .count(&ItemId::new("Tonic")),
+            0
+        );
+    }
+}
@@ -0,0 +1,498 @@
+use std::time::Duration;
+
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use bevy_tweening::lens::TransformPositionLens;
+use bevy_tweening::{Animator, EaseMethod, Tween, TweenCompleted};
+
+use crate::modes::dungeon::dungeoninput::{DungeonAction, ReadDungeonInput};
+use crate::modes::dungeon::dungeonplayer::{
+    try_move_player, DungeonPlayer, DungeonPlayerMovementState,
+};
+use crate::modes::dungeon::model::cell::{GridDirection, GridPosition};
+use crate::modes::dungeon::model::grid::DungeonTileLookup;
+use crate::modes::dungeon::model::tile::TileType;
+use crate::modes::inventory::Inventory;
+use crate::modes::mode_state::GameModeState;
+use crate::modes::sharedassets::itemdatabase::ItemDatabase;
+
+const DOOR_ANIMATION_DURATION: f32 = 0.3;
+/// `TweenCompleted::user_data` for a door that's done opening or closing.
+const DOOR_TWEEN_COMPLETED: u64 = 0xD002;
+/// Open doors slide up into the ceiling.
+const DOOR_OPEN_OFFSET: Vec3 = Vec3::Y;
+
+/// Identifies the edge a door is on. Both cells next to a door have a face for it, so this always
+/// uses the `Right` or `Back` side of the edge to make both faces agree.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct DoorId {
+    pub floor: usize,
+    pub position: GridPosition,
+    pub direction: GridDirection,
+}
+
+impl DoorId {
+    pub fn new(floor: usize, position: GridPosition, direction: GridDirection) -> Self {
+        let neighbor = match direction {
+            GridDirection::Left | GridDirection::Forward => position.try_translated(direction),
+            _ => None,
+        };
+        match neighbor {
+            Some(neighbor) => DoorId {
+                floor,
+                position: neighbor,
+                direction: direction.get_inverse_direction(),
+            },
+            None => DoorId {
+                floor,
+                position,
+                direction,
+            },
+        }
+    }
+}
+
+/// Unlocked doors never get locked again, so once the player has touched a door it's one of these.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DoorState {
+    Closed,
+    Open,
+}
+
+impl DoorState {
+    pub fn tile_type(self) -> TileType {
+        match self {
+            DoorState::Closed => TileType::Door,
+            DoorState::Open => TileType::OpenDoor,
+        }
+    }
+}
+
+/// Goes on both of the face tiles that make up a door.
+#[derive(Component, Clone, Debug)]
+pub struct Door {
+    pub id: DoorId,
+    /// Which face of its cell this tile is.
+    face: GridDirection,
+    /// The key that unlocks it, if it's locked.
+    pub key: Option<String>,
+}
+
+impl Door {
+    pub fn new(
+        floor: usize,
+        position: GridPosition,
+        face: GridDirection,
+        key: Option<String>,
+    ) -> Self {
+        Door {
+            id: DoorId::new(floor, position, face),
+            face,
+            key,
+        }
+    }
+
+    /// Where this face's tile sits when the door is in `state`.
+    fn translation(&self, state: DoorState) -> Vec3 {
+        let closed = GridDirection::get_tile_transform(self.face).translation;
+        match state {
+            DoorState::Open => closed + DOOR_OPEN_OFFSET,
+            DoorState::Closed => closed,
+        }
+    }
+}
+
+/// Every door that's been opened or closed, on every floor. Doors that aren't in here are still
+/// the way the dungeon data placed them. Nothing ever clears this, so doors stay the way the player
+/// left them through battles and floor changes.
+#[derive(Resource, Default)]
+pub struct DoorStates(pub HashMap<DoorId, DoorState>);
+
+pub struct DoorsPlugin;
+
+impl Plugin for DoorsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<DoorStates>().add_systems(
+            Update,
+            (
+                restore_doors,
+                use_doors
+                    .after(restore_doors)
+                    .run_if(in_state(GameModeState::InDungeon)),
+                finish_moving_doors,
+            )
+                .after(ReadDungeonInput)
+                .before(try_move_player),
+        );
+    }
+}
+
+/// Puts freshly spawned doors back the way the player left them.
+fn restore_doors(
+    door_states: Res<DoorStates>,
+    mut door_query: Query<(&Door, &mut TileType, &mut Transform), Added<Door>>,
+) {
+    for (door, mut tile_type, mut transform) in door_query.iter_mut() {
+        if let Some(state) = door_states.0.get(&door.id) {
+            *tile_type = state.tile_type();
+            transform.translation = door.translation(*state);
+        }
+    }
+}
+
+/// Opens or closes the door the player is facing.
+fn use_doors(
+    mut commands: Commands,
+    mut action_reader: EventReader<DungeonAction>,
+    dungeon_tile_lookup: Res<DungeonTileLookup>,
+    (inventory, item_database): (Res<Inventory>, Res<ItemDatabase>),
+    mut door_states: ResMut<DoorStates>,
+    player_query: Query<
+        (&GridPosition, &GridDirection, &DungeonPlayerMovementState),
+        With<DungeonPlayer>,
+    >,
+    mut door_query: Query<(Entity, &Door, &mut TileType, &Transform)>,
+) {
+    if !action_reader
+        .iter()
+        .any(|action| *action == DungeonAction::Interact)
+    {
+        return;
+    }
+    let Ok((grid_pos, grid_direction, movement_state)) = player_query.get_single() else {
+        return;
+    };
+    if *movement_state != DungeonPlayerMovementState::Stationary {
+        return;
+    }
+    let Some((_, door, tile_type, _)) = dungeon_tile_lookup
+        .try_get_tile(*grid_pos, *grid_direction)
+        .and_then(|tile_entity| door_query.get(tile_entity).ok())
+    else {
+        return;
+    };
+
+    let next_state = match *tile_type {
+        TileType::LockedDoor if inventory.can_unlock(door.key.as_deref(), &item_database) => {
+            DoorState::Open
+        }
+        TileType::LockedDoor => return,
+        TileType::OpenDoor => DoorState::Closed,
+        _ => DoorState::Open,
+    };
+    let id = door.id;
+    door_states.0.insert(id, next_state);
+
+    for (entity, door, mut tile_type, transform) in door_query.iter_mut() {
+        if door.id != id {
+            continue;
+        }
+        // a closing door is in the way straight away, an opening one only once it's out of it
+        if next_state == DoorState::Closed {
+            *tile_type = next_state.tile_type();
+        }
+        commands.entity(entity).insert(Animator::new(
+            Tween::new(
+                EaseMethod::Linear,
+                Duration::from_secs_f32(DOOR_ANIMATION_DURATION),
+                TransformPositionLens {
+                    start: transform.translation,
+                    end: door.translation(next_state),
+                },
+            )
+            .with_completed_event(DOOR_TWEEN_COMPLETED),
+        ));
+    }
+}
+
+/// Lets the player through a door once it's finished sliding open.
+fn finish_moving_doors(
+    mut tween_completed_reader: EventReader<TweenCompleted>,
+    door_states: Res<DoorStates>,
+    mut door_query: Query<(&Door, &mut TileType)>,
+) {
+    for event in tween_completed_reader.iter() {
+        if event.user_data != DOOR_TWEEN_COMPLETED {
+            continue;
+        }
+        let Ok((door, mut tile_type)) = door_query.get_mut(event.entity) else {
+            continue;
+        };
+        if let Some(state) = door_states.0.get(&door.id) {
+            *tile_type = state.tile_type();
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use bevy::prelude::*;
+    use bevy_tweening::{Animator, TweenCompleted};
+
+    use crate::modes::dungeon::doors::{
+        Door, DoorId, DoorState, DoorStates, DoorsPlugin, DOOR_OPEN_OFFSET, DOOR_TWEEN_COMPLETED,
+    };
+    use crate::modes::dungeon::dungeoninput::test_helpers::setup_keyboard_movement;
+    use crate::modes::dungeon::dungeonmode::test_helpers::setup_test_dungeon_floors;
+    use crate::modes::dungeon::dungeonmode::DungeonMode;
+    use crate::modes::dungeon::dungeonplayer::DungeonPlayerMovementState;
+    use crate::modes::dungeon::model::cell::test_helpers::setup_test_tile_preset_map;
+    use crate::modes::dungeon::model::cell::{GridDirection, GridPosition};
+    use crate::modes::dungeon::model::edge::{EdgeKind, RawEdgeData};
+    use crate::modes::dungeon::model::grid::test_helpers::setup_dungeon_tile_lookup;
+    use crate::modes::dungeon::model::grid::{DungeonTileLookup, RawDungeonData, RawDungeonFloors};
+    use crate::modes::dungeon::model::items::ItemId;
+    use crate::modes::dungeon::model::tile::TileType;
+    use crate::modes::dungeon::stairs::{FloorChange, StairsPlugin};
+    use crate::modes::inventory::Inventory;
+    use crate::modes::mode_state::GameModeState;
+    use crate::modes::sharedassets::itemdatabase::test_helpers::test_item_database;
+
+    /// Two cells with a door of `kind` between them, and the player on the left facing it.
+    fn setup(kind: EdgeKind, key: Option<&str>) -> App {
+        let data = RawDungeonData {
+            dungeon_grid: vec![vec![1, 1]],
+            player_start_position: [0, 0],
+            player_start_direction: GridDirection::Right,
+            edges: vec![RawEdgeData {
+                position: [0, 1],
+                direction: GridDirection::Left,
+                kind,
+                key: key.map(str::to_string),
+            }],
+            ..Default::default()
+        };
+        let mut app = App::new();
+        setup_test_tile_preset_map(&mut app);
+        setup_dungeon_tile_lookup(&mut app);
+        setup_test_dungeon_floors(
+            &mut app,
+            RawDungeonFloors {
+                floors: vec![data],
+                ..Default::default()
+            },
+        );
+        app.add_state::<GameModeState>()
+            .add_event::<TweenCompleted>()
+            .insert_resource(Input::<KeyCode>::default())
+            .init_resource::<Inventory>()
+            .add_plugins((StairsPlugin, DoorsPlugin))
+            .add_systems(
+                Startup,
+                (
+                    DungeonMode::initialize_preset_map,
+                    (DungeonMode::spawn_grid, DungeonMode::setup_player)
+                        .after(DungeonMode::initialize_preset_map),
+                ),
+            );
+        setup_keyboard_movement(&mut app);
+        app.world
+            .resource_mut::<NextState<GameModeState>>()
+            .set(GameModeState::InDungeon);
+        app.update();
+        app
+    }
+
+    fn press(app: &mut App, key: KeyCode) {
+        app.world.resource_mut::<Input<KeyCode>>().press(key);
+        app.update();
+        let mut input = app.world.resource_mut::<Input<KeyCode>>();
+        input.release(key);
+        input.clear();
+    }
+
+    fn add_key(app: &mut App, key: &str) {
+        let item_database = test_item_database();
+        app.world
+            .resource_mut::<Inventory>()
+            .add(&ItemId::new("Key"), Some(key), &item_database)
+            .unwrap();
+    }
+
+    /// The door's face on each side, left cell first.
+    fn door_faces(app: &App) -> [Entity; 2] {
+        let lookup = app.world.resource::<DungeonTileLookup>();
+        [
+            lookup
+                .try_get_tile(GridPosition { row: 0, col: 0 }, GridDirection::Right)
+                .unwrap(),
+            lookup
+                .try_get_tile(GridPosition { row: 0, col: 1 }, GridDirection::Left)
+                .unwrap(),
+        ]
+    }
+
+    /// The tweening plugin isn't running, so this pretends both faces finished moving.
+    fn finish_moving(app: &mut App) {
+        for face in door_faces(app) {
+            app.world.send_event(TweenCompleted {
+                entity: face,
+                user_data: DOOR_TWEEN_COMPLETED,
+            });
+        }
+        app.update();
+    }
+
+    fn door_tile_types(app: &App) -> [TileType; 2] {
+        door_faces(app).map(|face| app.world.get::<TileType>(face).unwrap().clone())
+    }
+
+    fn movement_state(app: &mut App) -> DungeonPlayerMovementState {
+        *app.world
+            .query::<&DungeonPlayerMovementState>()
+            .single(&app.world)
+    }
+
+    #[test]
+    fn door_ids_match_on_both_sides() {
+        let left = GridPosition { row: 2, col: 2 };
+        let right = GridPosition { row: 2, col: 3 };
+        assert_eq!(
+            DoorId::new(0, left, GridDirection::Right),
+            DoorId::new(0, right, GridDirection::Left)
+        );
+        let below = GridPosition { row: 3, col: 2 };
+        assert_eq!(
+            DoorId::new(1, left, GridDirection::Back),
+            DoorId::new(1, below, GridDirection::Forward)
+        );
+        assert_ne!(
+            DoorId::new(0, left, GridDirection::Right),
+            DoorId::new(1, right, GridDirection::Left)
+        );
+    }
+
+    #[test]
+    fn should_open_and_close_doors() {
+        let mut app = setup(EdgeKind::Door, None);
+        assert_eq!(door_tile_types(&app), [TileType::Door, TileType::Door]);
+
+        press(&mut app, KeyCode::Z);
+        // still in the way until it's done opening
+        assert_eq!(door_tile_types(&app), [TileType::Door, TileType::Door]);
+        for face in door_faces(&app) {
+            assert!(app.world.get::<Animator<Transform>>(face).is_some());
+        }
+        finish_moving(&mut app);
+        assert_eq!(
+            door_tile_types(&app),
+            [TileType::OpenDoor, TileType::OpenDoor]
+        );
+        let door_id = app.world.get::<Door>(door_faces(&app)[0]).unwrap().id;
+        assert_eq!(
+            app.world.resource::<DoorStates>().0.get(&door_id),
+            Some(&DoorState::Open)
+        );
+
+        press(&mut app, KeyCode::Z);
+        assert_eq!(door_tile_types(&app), [TileType::Door, TileType::Door]);
+    }
+
+    #[test]
+    fn should_walk_through_open_doors() {
+        let mut app = setup(EdgeKind::Door, None);
+        app.world
+            .resource_mut::<Input<KeyCode>>()
+            .press(KeyCode::Up);
+        app.update();
+        assert_eq!(
+            movement_state(&mut app),
+            DungeonPlayerMovementState::Colliding
+        );
+
+        for finished_opening in [false, true] {
+            let mut app = setup(EdgeKind::Door, None);
+            press(&mut app, KeyCode::Z);
+            if finished_opening {
+                finish_moving(&mut app);
+            }
+            app.world
+                .resource_mut::<Input<KeyCode>>()
+                .press(KeyCode::Up);
+            app.update();
+            let expected = if finished_opening {
+                DungeonPlayerMovementState::Walking
+            } else {
+                DungeonPlayerMovementState::Colliding
+            };
+            assert_eq!(movement_state(&mut app), expected);
+        }
+    }
+
+    #[test]
+    fn should_need_the_right_key() {
+        let mut app = setup(EdgeKind::LockedDoor, Some("red"));
+        press(&mut app, KeyCode::Z);
+        assert_eq!(
+            door_tile_types(&app),
+            [TileType::LockedDoor, TileType::LockedDoor]
+        );
+
+        add_key(&mut app, "blue");
+        press(&mut app, KeyCode::Z);
+        assert_eq!(
+            door_tile_types(&app),
+            [TileType::LockedDoor, TileType::LockedDoor]
+        );
+
+        add_key(&mut app, "red");
+        press(&mut app, KeyCode::Z);
+        finish_moving(&mut app);
+        assert_eq!(
+            door_tile_types(&app),
+            [TileType::OpenDoor, TileType::OpenDoor]
+        );
+
+        // once it's unlocked it's just a normal door
+        press(&mut app, KeyCode::Z);
+        assert_eq!(door_tile_types(&app), [TileType::Door, TileType::Door]);
+    }
+
+    #[test]
+    fn should_open_keyless_locks_with_any_key() {
+        let mut app = setup(EdgeKind::LockedDoor, None);
+        press(&mut app, KeyCode::Z);
+        assert_eq!(
+            door_tile_types(&app),
+            [TileType::LockedDoor, TileType::LockedDoor]
+        );
+
+        add_key(&mut app, "red");
+        press(&mut app, KeyCode::Z);
+        finish_moving(&mut app);
+        assert_eq!(
+            door_tile_types(&app),
+            [TileType::OpenDoor, TileType::OpenDoor]
+        );
+    }
+
+    #[test]
+    fn should_keep_doors_open_after_respawning() {
+        let mut app = setup(EdgeKind::Door, None);
+        press(&mut app, KeyCode::Z);
+        let closed_translations =
+            door_faces(&app).map(|face| app.world.get::<Transform>(face).unwrap().translation);
+
+        // respawn the same floor, like coming back to it
+        app.world.insert_resource(FloorChange {
+            floor: 0,
+            grid_position: GridPosition { row: 0, col: 0 },
+            direction: GridDirection::Right,
+        });
+        app.world
+            .resource_mut::<NextState<GameModeState>>()
+            .set(GameModeState::ChangingFloor);
+        app.update();
+
+        assert_eq!(
+            door_tile_types(&app),
+            [TileType::OpenDoor, TileType::OpenDoor]
+        );
+        for (face, closed) in door_faces(&app).into_iter().zip(closed_translations) {
+            assert_eq!(
+                app.world.get::<Transform>(face).unwrap().translation,
+                closed + DOOR_OPEN_OFFSET
+            );
+        }
+    }
+}
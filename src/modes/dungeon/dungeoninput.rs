@@ -0,0 +1,365 @@
+use bevy::prelude::*;
+use bevy_mod_picking::prelude::{Click, PickableBundle, Pointer, PointerButton, RaycastPickTarget};
+
+use crate::modes::dungeon::dungeonplayer::DungeonPlayer;
+use crate::modes::dungeon::model::cell::{GridDirection, GridPosition};
+use crate::modes::dungeon::model::grid::DungeonTileLookup;
+use crate::modes::dungeon::model::items::DungeonItem;
+use crate::modes::mode_state::GameModeState;
+
+/// Something the player wants to do in the dungeon. Keyboard and mouse input both get turned into
+/// these, so the player does the exact same thing whichever one they use.
+#[derive(Event, Clone, Copy, PartialEq, Debug)]
+pub enum DungeonAction {
+    /// Walk (or run) one cell. `direction` is relative to the way the player is facing, so it's
+    /// `Forward`, `Back`, `Left` or `Right`.
+    Move { direction: GridDirection, run: bool },
+    /// Turn to the `Left` or `Right`.
+    Turn(GridDirection),
+    /// Use whatever's on the wall in front of the player. Only doors do anything with it so far,
+    /// decals have no text to read and secret walls are walked through, not searched for.
+    Interact,
+}
+
+/// The systems that send `DungeonAction`s. Anything reading them should run after this.
+#[derive(SystemSet, Clone, PartialEq, Eq, Hash, Debug)]
+pub struct ReadDungeonInput;
+
+/// Something in the dungeon the player clicked on.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum ClickTarget {
+    Tile {
+        grid_position: GridPosition,
+        direction: GridDirection,
+    },
+    Item(GridPosition),
+}
+
+/// What clicking on `target` does for a player standing on `player_position` facing `facing`.
+/// Clicking the wall in front interacts with it, and clicking the floor of (or an item on) a
+/// neighbouring cell steps onto it. Anything else is out of reach.
+pub fn click_action(
+    player_position: GridPosition,
+    facing: GridDirection,
+    target: ClickTarget,
+) -> Option<DungeonAction> {
+    let ahead = player_position.try_translated(facing);
+    match target {
+        ClickTarget::Tile {
+            grid_position,
+            direction,
+        } if grid_position == player_position && direction == facing => {
+            Some(DungeonAction::Interact)
+        }
+        // the other side of the same wall, which is what gets hit if ours has been moved away
+        ClickTarget::Tile {
+            grid_position,
+            direction,
+        } if Some(grid_position) == ahead && direction == facing.get_inverse_direction() => {
+            Some(DungeonAction::Interact)
+        }
+        ClickTarget::Tile {
+            grid_position,
+            direction: GridDirection::Bottom,
+        }
+        | ClickTarget::Item(grid_position) => GridDirection::HORIZONTAL
+            .into_iter()
+            .find(|direction| player_position.try_translated(*direction) == Some(grid_position))
+            .map(|direction| DungeonAction::Move {
+                direction: direction.absolute_to_relative(facing),
+                run: false,
+            }),
+        _ => None,
+    }
+}
+
+pub fn read_keyboard_actions(
+    keyboard_input: Res<Input<KeyCode>>,
+    mut action_writer: EventWriter<DungeonAction>,
+) {
+    let run = keyboard_input.pressed(KeyCode::ShiftLeft);
+    let step = |direction| DungeonAction::Move { direction, run };
+    // up and down win over left and right. with shift held left and right step sideways
+    // instead of turning
+    let movement = if keyboard_input.pressed(KeyCode::Up) {
+        Some(step(GridDirection::Forward))
+    } else if keyboard_input.pressed(KeyCode::Down) {
+        Some(step(GridDirection::Back))
+    } else if keyboard_input.pressed(KeyCode::Left) {
+        Some(if run {
+            step(GridDirection::Left)
+        } else {
+            DungeonAction::Turn(GridDirection::Left)
+        })
+    } else if keyboard_input.pressed(KeyCode::Right) {
+        Some(if run {
+            step(GridDirection::Right)
+        } else {
+            DungeonAction::Turn(GridDirection::Right)
+        })
+    } else {
+        None
+    };
+    if let Some(movement) = movement {
+        action_writer.send(movement);
+    }
+    if keyboard_input.just_pressed(KeyCode::Z) {
+        action_writer.send(DungeonAction::Interact);
+    }
+}
+
+fn read_mouse_actions(
+    mut click_reader: EventReader<Pointer<Click>>,
+    mut action_writer: EventWriter<DungeonAction>,
+    dungeon_tile_lookup: Res<DungeonTileLookup>,
+    player_query: Query<(&GridPosition, &GridDirection), With<DungeonPlayer>>,
+    item_query: Query<&GridPosition, With<DungeonItem>>,
+    parent_query: Query<&Parent>,
+) {
+    let Ok((player_position, facing)) = player_query.get_single() else {
+        return;
+    };
+    for click in click_reader.iter() {
+        if click.button != PointerButton::Primary {
+            continue;
+        }
+        // item models are scenes, so the click lands on one of the meshes inside them
+        let item_position = std::iter::once(click.target)
+            .chain(parent_query.iter_ancestors(click.target))
+            .find_map(|entity| item_query.get(entity).ok());
+        let target = match item_position {
+            Some(grid_position) => ClickTarget::Item(*grid_position),
+            None => {
+                let Some((grid_position, direction)) = dungeon_tile_lookup.find_tile(click.target)
+                else {
+                    continue;
+                };
+                ClickTarget::Tile {
+                    grid_position,
+                    direction,
+                }
+            }
+        };
+        if let Some(action) = click_action(*player_position, *facing, target) {
+            action_writer.send(action);
+        }
+    }
+}
+
+/// The meshes in an item's scene only exist once the scene has spawned, and they need to be
+/// raycast targets for the item to be clickable.
+fn make_item_meshes_pickable(
+    mut commands: Commands,
+    mesh_query: Query<Entity, Added<Handle<Mesh>>>,
+    parent_query: Query<&Parent>,
+    item_query: Query<(), With<DungeonItem>>,
+) {
+    for entity in mesh_query.iter() {
+        if parent_query
+            .iter_ancestors(entity)
+            .any(|ancestor| item_query.contains(ancestor))
+        {
+            commands
+                .entity(entity)
+                .insert((PickableBundle::default(), RaycastPickTarget::default()));
+        }
+    }
+}
+
+pub struct DungeonInputPlugin;
+
+impl Plugin for DungeonInputPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<DungeonAction>().add_systems(
+            Update,
+            (
+                (read_keyboard_actions, read_mouse_actions).in_set(ReadDungeonInput),
+                make_item_meshes_pickable,
+            )
+                .run_if(in_state(GameModeState::InDungeon)),
+        );
+    }
+}
+
+#[cfg(test)]
+pub mod test_helpers {
+    use bevy::prelude::*;
+
+    use crate::modes::dungeon::dungeoninput::{
+        read_keyboard_actions, DungeonAction, ReadDungeonInput,
+    };
+    use crate::modes::dungeon::dungeonplayer::{try_move_player, PlayerMoveCompleted};
+
+    /// Keyboard input driving `try_move_player`, the same way the dungeon plugins set it up.
+    pub fn setup_keyboard_movement(app: &mut App) {
+        app.add_event::<DungeonAction>()
+            .add_event::<PlayerMoveCompleted>()
+            .add_systems(
+                Update,
+                (
+                    read_keyboard_actions.in_set(ReadDungeonInput),
+                    try_move_player.after(ReadDungeonInput),
+                ),
+            );
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use bevy::prelude::*;
+    use bevy_tweening::Animator;
+
+    use crate::modes::dungeon::dungeoninput::test_helpers::setup_keyboard_movement;
+    use crate::modes::dungeon::dungeoninput::{click_action, ClickTarget, DungeonAction};
+    use crate::modes::dungeon::dungeonmode::test_helpers::setup_test_dungeon_assets;
+    use crate::modes::dungeon::dungeonmode::DungeonMode;
+    use crate::modes::dungeon::dungeonplayer::DungeonPlayer;
+    use crate::modes::dungeon::model::cell::test_helpers::setup_test_tile_preset_map;
+    use crate::modes::dungeon::model::cell::{GridDirection, GridPosition};
+    use crate::modes::dungeon::model::grid::test_helpers::setup_dungeon_tile_lookup;
+    use crate::modes::dungeon::model::grid::RawDungeonData;
+    use crate::modes::mode_state::GameModeState;
+
+    const PLAYER: GridPosition = GridPosition { row: 1, col: 1 };
+
+    fn click(target: ClickTarget) -> Option<DungeonAction> {
+        click_action(PLAYER, GridDirection::Right, target)
+    }
+
+    fn tile(row: usize, col: usize, direction: GridDirection) -> ClickTarget {
+        ClickTarget::Tile {
+            grid_position: GridPosition { row, col },
+            direction,
+        }
+    }
+
+    #[test]
+    fn clicking_the_wall_in_front_interacts() {
+        assert_eq!(
+            click(tile(1, 1, GridDirection::Right)),
+            Some(DungeonAction::Interact)
+        );
+        assert_eq!(
+            click(tile(1, 2, GridDirection::Left)),
+            Some(DungeonAction::Interact)
+        );
+        // only the one we're facing
+        assert_eq!(click(tile(1, 1, GridDirection::Forward)), None);
+        assert_eq!(click(tile(1, 1, GridDirection::Top)), None);
+    }
+
+    #[test]
+    fn clicking_a_neighbouring_cell_steps_onto_it() {
+        let step = |direction| {
+            Some(DungeonAction::Move {
+                direction,
+                run: false,
+            })
+        };
+        assert_eq!(
+            click(tile(1, 2, GridDirection::Bottom)),
+            step(GridDirection::Forward)
+        );
+        assert_eq!(
+            click(tile(0, 1, GridDirection::Bottom)),
+            step(GridDirection::Left)
+        );
+        assert_eq!(
+            click(ClickTarget::Item(GridPosition { row: 1, col: 0 })),
+            step(GridDirection::Back)
+        );
+        assert_eq!(
+            click(ClickTarget::Item(GridPosition { row: 2, col: 1 })),
+            step(GridDirection::Right)
+        );
+    }
+
+    #[test]
+    fn far_away_things_are_out_of_reach() {
+        assert_eq!(click(tile(1, 1, GridDirection::Bottom)), None);
+        assert_eq!(click(tile(1, 3, GridDirection::Bottom)), None);
+        assert_eq!(click(tile(0, 2, GridDirection::Bottom)), None);
+        assert_eq!(
+            click(ClickTarget::Item(GridPosition { row: 3, col: 1 })),
+            None
+        );
+        assert_eq!(click(tile(1, 2, GridDirection::Right)), None);
+    }
+
+    fn setup() -> App {
+        let data = RawDungeonData {
+            dungeon_grid: vec![vec![1, 1, 1], vec![1, 1, 1]],
+            player_start_position: [0, 0],
+            player_start_direction: GridDirection::Back,
+            ..Default::default()
+        };
+        let mut app = App::new();
+        setup_test_tile_preset_map(&mut app);
+        setup_dungeon_tile_lookup(&mut app);
+        setup_test_dungeon_assets(&mut app, data);
+        app.add_state::<GameModeState>()
+            .insert_resource(Input::<KeyCode>::default())
+            .add_systems(
+                Startup,
+                (
+                    DungeonMode::initialize_preset_map,
+                    (DungeonMode::spawn_grid, DungeonMode::setup_player)
+                        .after(DungeonMode::initialize_preset_map),
+                ),
+            );
+        setup_keyboard_movement(&mut app);
+        app.update();
+        app
+    }
+
+    fn finish_moving(app: &mut App) -> (GridPosition, GridDirection) {
+        app.world
+            .query_filtered::<&mut Animator<Transform>, With<DungeonPlayer>>()
+            .single_mut(&mut app.world)
+            .tweenable_mut()
+            .set_elapsed(Duration::from_secs(1));
+        app.update();
+        let (grid_position, direction) = app
+            .world
+            .query_filtered::<(&GridPosition, &GridDirection), With<DungeonPlayer>>()
+            .single(&app.world);
+        (*grid_position, *direction)
+    }
+
+    #[test]
+    fn clicks_and_keys_do_the_same_thing() {
+        let mut keyboard_app = setup();
+        keyboard_app
+            .world
+            .resource_mut::<Input<KeyCode>>()
+            .press(KeyCode::Up);
+        keyboard_app.update();
+        keyboard_app
+            .world
+            .resource_mut::<Input<KeyCode>>()
+            .release(KeyCode::Up);
+
+        let mut mouse_app = setup();
+        let action = click_action(
+            GridPosition { row: 0, col: 0 },
+            GridDirection::Back,
+            tile(1, 0, GridDirection::Bottom),
+        )
+        .unwrap();
+        assert_eq!(
+            action,
+            DungeonAction::Move {
+                direction: GridDirection::Forward,
+                run: false
+            }
+        );
+        mouse_app.world.send_event(action);
+        mouse_app.update();
+
+        let expected = (GridPosition { row: 1, col: 0 }, GridDirection::Back);
+        assert_eq!(finish_moving(&mut keyboard_app), expected);
+        assert_eq!(finish_moving(&mut mouse_app), expected);
+    }
+}
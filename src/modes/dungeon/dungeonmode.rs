@@ -6,28 +6,45 @@ use bevy::asset::{Assets, Handle};
 use bevy::math::Vec3;
 use bevy::pbr::StandardMaterial;
 use bevy::prelude::{
-    default, AssetServer, Camera3dBundle, Commands, IntoSystemConfigs, OnExit,
-    PerspectiveProjection, Plugin, Projection, Res, ResMut, Resource, Scene, Transform,
+    apply_deferred, default, error, not, resource_exists, AssetServer, BuildChildren,
+    Camera3dBundle, Commands, Component, IntoSystemConfigs, NextState, OnEnter, OnExit,
+    PerspectiveProjection, Plugin, Projection, Res, ResMut, Resource,
 };
 use bevy_asset_loader::asset_collection::AssetCollection;
 use bevy_asset_loader::loading_state::{LoadingState, LoadingStateAppExt};
+use bevy_mod_picking::prelude::RaycastPickCamera;
 use bevy_tweening::lens::TransformPositionLens;
 use bevy_tweening::{Animator, AnimatorState, EaseMethod, Tween};
 
+use crate::modes::dungeon::aftermath::AftermathPlugin;
+use crate::modes::dungeon::doors::{Door, DoorsPlugin};
+use crate::modes::dungeon::dungeoninput::DungeonInputPlugin;
 use crate::modes::dungeon::dungeonplayer::{
-    DungeonPlayer, DungeonPlayerBundle, DungeonPlayerMovementState, DungeonPlayerPlugin,
-    SpeedMultiplier,
+    player_transform, DungeonPlayer, DungeonPlayerBundle, DungeonPlayerMovementState,
+    DungeonPlayerPlugin, MoveDirection, SpeedMultiplier,
 };
+use crate::modes::dungeon::encounters::EncounterPlugin;
+use crate::modes::dungeon::floortiles::FloorTilesPlugin;
+use crate::modes::dungeon::foes::{spawn_foes, FoePlugin};
+use crate::modes::dungeon::generate::use_generated_dungeon;
 use crate::modes::dungeon::model::cell::{
-    spawn_dungeon_cell, DungeonCell, GridPosType, GridPosition, TileBundle, TileBundlePreset,
+    spawn_dungeon_cell, DungeonCell, GridDirection, GridPosition, TileBundle, TileBundlePreset,
     TileBundlePresetMap,
 };
-use crate::modes::dungeon::model::grid::{DungeonTileLookup, RawDungeonData};
+use crate::modes::dungeon::model::edge::EdgeKind;
+use crate::modes::dungeon::model::floor::FloorKind;
+use crate::modes::dungeon::model::grid::{DungeonTileLookup, RawDungeonData, RawDungeonFloors};
 use crate::modes::dungeon::model::items::DungeonItem;
-use crate::modes::dungeon::model::tile::{
-    PurpleTileAssets, PurpleTileTextureMap, Tile, TileTexture,
-};
+use crate::modes::dungeon::model::tile::{Tile, TileTexture};
+use crate::modes::dungeon::model::tileset::{TilesetAssets, TilesetTextureMap};
+use crate::modes::dungeon::model::validation::DungeonValidationError;
+use crate::modes::dungeon::pickup::{CollectedItems, ItemPickupPlugin};
+use crate::modes::dungeon::stairs::StairsPlugin;
 use crate::modes::mode_state::GameModeState;
+use crate::modes::sharedassets::itemdatabase::ItemDatabase;
+use crate::modes::sharedassets::shared::{
+    spawn_validation_report, CharacterValidationReport, FontAssets,
+};
 
 pub struct DungeonMode;
 
@@ -36,199 +53,107 @@ pub struct DungeonModePlugins;
 #[derive(Resource, AssetCollection)]
 pub struct DungeonAssets {
     #[asset(path = "dungeon_data/test.dungeon.json")]
-    pub raw_dungeon_data: Handle<RawDungeonData>,
-    #[asset(path = "model/polaroid.gltf#Scene0")]
-    pub polaroid: Handle<Scene>,
-    #[asset(path = "model/key.gltf#Scene0")]
-    pub key: Handle<Scene>,
-    #[asset(path = "model/maxwell_the_cat_dingus.glb#Scene0")]
-    pub maxwell: Handle<Scene>,
+    pub raw_dungeon_data: Handle<RawDungeonFloors>,
 }
 
+impl DungeonAssets {
+    /// The data for the floor the player is currently on.
+    pub fn current_floor<'a>(
+        &self,
+        raw_dungeon_floors: &'a Assets<RawDungeonFloors>,
+        current_floor: &CurrentFloor,
+    ) -> &'a RawDungeonData {
+        raw_dungeon_floors
+            .get(&self.raw_dungeon_data)
+            .expect("failed to get raw dungeon data out of assets")
+            .floor(current_floor.0)
+    }
+}
+
+/// Only exists if the loaded dungeon failed validation. Nothing gets spawned while it's around.
+#[derive(Resource)]
+pub struct DungeonValidationReport(pub Vec<DungeonValidationError>);
+
+/// Index into `RawDungeonFloors::floors` for the floor that's currently spawned.
+#[derive(Resource, Default)]
+pub struct CurrentFloor(pub usize);
+
+/// Everything that belongs to the current floor (cells, items) and gets despawned when the
+/// player leaves it.
+#[derive(Component)]
+pub struct DungeonFloorEntity;
+
 impl DungeonMode {
+    pub fn validate_dungeon_data(
+        mut commands: Commands,
+        raw_dungeon_floors: Res<Assets<RawDungeonFloors>>,
+        dungeon_assets: Res<DungeonAssets>,
+        tile_texture_map: Res<TilesetTextureMap>,
+        item_database: Res<ItemDatabase>,
+        mut next_state: ResMut<NextState<GameModeState>>,
+    ) {
+        let data = raw_dungeon_floors
+            .get(&dungeon_assets.raw_dungeon_data)
+            .expect("failed to get raw dungeon data out of assets");
+        let mut errors = data.validate().err().unwrap_or_default();
+        if let Err(texture_errors) = data.validate_textures(&tile_texture_map) {
+            errors.extend(texture_errors);
+        }
+        if let Err(item_errors) = data.validate_items(&item_database) {
+            errors.extend(item_errors);
+        }
+        if errors.is_empty() {
+            commands.insert_resource(CurrentFloor(data.start_floor));
+            return;
+        }
+        for e in errors.iter() {
+            error!("invalid dungeon data: {}", e);
+        }
+        commands.insert_resource(DungeonValidationReport(errors));
+        next_state.set(GameModeState::InvalidDungeon);
+    }
+
+    fn show_validation_report(
+        mut commands: Commands,
+        report: Res<DungeonValidationReport>,
+        font_assets: Res<FontAssets>,
+    ) {
+        spawn_validation_report(
+            &mut commands,
+            &font_assets,
+            "Failed to load dungeon:",
+            &report.0,
+        );
+    }
+
     pub fn initialize_preset_map(
-        tile_texture_map: ResMut<PurpleTileTextureMap>,
+        tile_texture_map: Res<TilesetTextureMap>,
         mut tile_bundle_preset_map: ResMut<TileBundlePresetMap>,
     ) {
-        let wall_tile = tile_texture_map.0.get(&TileTexture::Wall).unwrap();
-        let floor_tile = tile_texture_map.0.get(&TileTexture::Floor).unwrap();
-        let ceiling_tile = tile_texture_map.0.get(&TileTexture::Ceiling).unwrap();
-        //region AUUGGGHHH
-        let open = TileBundle::new(
-            Tile::new_empty(),
-            Tile::new_empty(),
-            Tile::new_empty(),
-            Tile::new_empty(),
-            ceiling_tile.clone(),
-            floor_tile.clone(),
-        );
-        let empty = TileBundle::new(
-            Tile::new_empty(),
-            Tile::new_empty(),
-            Tile::new_empty(),
-            Tile::new_empty(),
-            Tile::new_empty(),
-            Tile::new_empty(),
-        );
-        let closed = TileBundle::new(
-            wall_tile.clone(),
-            wall_tile.clone(),
-            wall_tile.clone(),
-            wall_tile.clone(),
-            ceiling_tile.clone(),
-            floor_tile.clone(),
-        );
-        let north_wall = TileBundle::new(
-            Tile::new_empty(),
-            wall_tile.clone(),
-            Tile::new_empty(),
-            Tile::new_empty(),
-            ceiling_tile.clone(),
-            floor_tile.clone(),
-        );
-        let east_wall = TileBundle::new(
-            Tile::new_empty(),
-            Tile::new_empty(),
-            wall_tile.clone(),
-            Tile::new_empty(),
-            ceiling_tile.clone(),
-            floor_tile.clone(),
-        );
-        let south_wall = TileBundle::new(
-            Tile::new_empty(),
-            Tile::new_empty(),
-            Tile::new_empty(),
-            wall_tile.clone(),
-            ceiling_tile.clone(),
-            floor_tile.clone(),
-        );
-        let west_wall = TileBundle::new(
-            wall_tile.clone(),
-            Tile::new_empty(),
-            Tile::new_empty(),
-            Tile::new_empty(),
-            ceiling_tile.clone(),
-            floor_tile.clone(),
-        );
-        let north_west_corner = TileBundle::new(
-            wall_tile.clone(),
-            wall_tile.clone(),
-            Tile::new_empty(),
-            Tile::new_empty(),
-            ceiling_tile.clone(),
-            floor_tile.clone(),
-        );
-        let north_east_corner = TileBundle::new(
-            Tile::new_empty(),
-            wall_tile.clone(),
-            wall_tile.clone(),
-            Tile::new_empty(),
-            ceiling_tile.clone(),
-            floor_tile.clone(),
-        );
-        let south_east_corner = TileBundle::new(
-            Tile::new_empty(),
-            Tile::new_empty(),
-            wall_tile.clone(),
-            wall_tile.clone(),
-            ceiling_tile.clone(),
-            floor_tile.clone(),
-        );
-        let south_west_corner = TileBundle::new(
-            wall_tile.clone(),
-            Tile::new_empty(),
-            Tile::new_empty(),
-            wall_tile.clone(),
-            ceiling_tile.clone(),
-            floor_tile.clone(),
-        );
-        let north_south_hallway = TileBundle::new(
-            wall_tile.clone(),
-            Tile::new_empty(),
-            wall_tile.clone(),
-            Tile::new_empty(),
-            ceiling_tile.clone(),
-            floor_tile.clone(),
-        );
-        let east_west_hallway = TileBundle::new(
-            Tile::new_empty(),
-            wall_tile.clone(),
-            Tile::new_empty(),
-            wall_tile.clone(),
-            ceiling_tile.clone(),
-            floor_tile.clone(),
-        );
-        let north_hallway_end = TileBundle::new(
-            wall_tile.clone(),
-            wall_tile.clone(),
-            wall_tile.clone(),
-            Tile::new_empty(),
-            ceiling_tile.clone(),
-            floor_tile.clone(),
-        );
-        let east_hallway_end = TileBundle::new(
-            Tile::new_empty(),
-            wall_tile.clone(),
-            wall_tile.clone(),
-            wall_tile.clone(),
-            ceiling_tile.clone(),
-            floor_tile.clone(),
-        );
-        let south_hallway_end = TileBundle::new(
-            wall_tile.clone(),
-            Tile::new_empty(),
-            wall_tile.clone(),
-            wall_tile.clone(),
-            ceiling_tile.clone(),
-            floor_tile.clone(),
-        );
-        let west_hallway_end = TileBundle::new(
-            wall_tile.clone(),
-            wall_tile.clone(),
-            Tile::new_empty(),
-            wall_tile.clone(),
-            ceiling_tile.clone(),
-            floor_tile.clone(),
-        );
-        let map = &mut tile_bundle_preset_map.0;
-        map.insert(TileBundlePreset::Open, open);
-        map.insert(TileBundlePreset::Empty, empty);
-        map.insert(TileBundlePreset::Closed, closed);
-        map.insert(TileBundlePreset::ForwardWall, north_wall);
-        map.insert(TileBundlePreset::RightWall, east_wall);
-        map.insert(TileBundlePreset::BackWall, south_wall);
-        map.insert(TileBundlePreset::LeftWall, west_wall);
-        map.insert(TileBundlePreset::ForwardLeftCorner, north_west_corner);
-        map.insert(TileBundlePreset::ForwardRightCorner, north_east_corner);
-        map.insert(TileBundlePreset::BackRightCorner, south_east_corner);
-        map.insert(TileBundlePreset::BackLeftCorner, south_west_corner);
-        map.insert(TileBundlePreset::ForwardBackHallway, north_south_hallway);
-        map.insert(TileBundlePreset::LeftRightHallway, east_west_hallway);
-        map.insert(TileBundlePreset::ForwardHallwayEnd, north_hallway_end);
-        map.insert(TileBundlePreset::RightHallwayEnd, east_hallway_end);
-        map.insert(TileBundlePreset::BackHallwayEnd, south_hallway_end);
-        map.insert(TileBundlePreset::LeftHallwayEnd, west_hallway_end);
-        //endregion
+        for tileset in tile_texture_map.names() {
+            let face_tile = |direction| {
+                let texture = TileTexture::for_face(direction);
+                tile_texture_map.tile(tileset, texture, 0).unwrap().clone()
+            };
+            let presets = TileBundlePreset::all()
+                .map(|preset| (preset, TileBundle::from_preset(preset, face_tile)))
+                .collect();
+            tile_bundle_preset_map
+                .0
+                .insert(tileset.to_string(), presets);
+        }
     }
 
     pub fn setup_player(
         mut commands: Commands,
-        raw_dungeon_data: Res<Assets<RawDungeonData>>,
+        raw_dungeon_floors: Res<Assets<RawDungeonFloors>>,
         dungeon_assets: Res<DungeonAssets>,
+        current_floor: Res<CurrentFloor>,
     ) {
         // player
-        let grid_pos: GridPosition = raw_dungeon_data
-            .get(&dungeon_assets.raw_dungeon_data)
-            .unwrap()
-            .player_start_position
-            .try_into()
-            .unwrap();
-        let start_direction = raw_dungeon_data
-            .get(&dungeon_assets.raw_dungeon_data)
-            .unwrap()
-            .player_start_direction;
-        let player_pos = grid_pos.to_vec3(GridPosType::Player);
-        let target = grid_pos.to_vec3(GridPosType::Player) + 2.0 * Vec3::X;
+        let data = dungeon_assets.current_floor(&raw_dungeon_floors, &current_floor);
+        let grid_pos: GridPosition = data.player_start_position.into();
+        let start_direction = data.player_start_direction;
         commands.spawn(DungeonPlayerBundle {
             dungeon_player: DungeonPlayer,
             animator_transform: Animator::new(Tween::new(
@@ -241,63 +166,169 @@ impl DungeonMode {
             ))
             .with_state(AnimatorState::Paused),
             camera: Camera3dBundle {
-                transform: Transform::from_translation(player_pos).looking_at(target, Vec3::Y),
+                transform: player_transform(grid_pos, start_direction),
                 projection: Projection::Perspective(PerspectiveProjection {
                     fov: PI / 3.0,
                     ..default()
                 }),
                 ..default()
             },
+            pick_camera: RaycastPickCamera::default(),
             grid_pos,
             start_direction,
             movement_state: DungeonPlayerMovementState::Stationary,
             speed_multiplier: SpeedMultiplier(1.0),
+            move_direction: MoveDirection(start_direction),
         });
     }
 
     pub fn spawn_grid(
         dungeon_asset: ResMut<DungeonAssets>,
-        grid_asset: Res<Assets<RawDungeonData>>,
+        grid_asset: Res<Assets<RawDungeonFloors>>,
+        current_floor: Res<CurrentFloor>,
         tile_bundle_map: Res<TileBundlePresetMap>,
+        tile_texture_map: Res<TilesetTextureMap>,
         mut dungeon_tile_lookup: ResMut<DungeonTileLookup>,
         mut commands: Commands,
     ) {
-        let grid_handle = &dungeon_asset.raw_dungeon_data;
-        let raw_dungeon_grid = grid_asset
-            .get(grid_handle)
-            .expect("failed to get raw dungeon grid out of assets");
+        let raw_dungeon_grid = dungeon_asset.current_floor(&grid_asset, &current_floor);
+        let tileset = grid_asset
+            .get(&dungeon_asset.raw_dungeon_data)
+            .and_then(|floors| floors.tileset(current_floor.0))
+            .unwrap_or(tile_texture_map.default_tileset());
 
         // first we need to resize the lookup resource
-        dungeon_tile_lookup.resize(&raw_dungeon_grid.dungeon_grid);
-        let num_rows = raw_dungeon_grid.dungeon_grid.len();
+        dungeon_tile_lookup.resize(raw_dungeon_grid.width(), raw_dungeon_grid.height());
         for (i, row) in raw_dungeon_grid.dungeon_grid.iter().enumerate() {
-            // panic if this isn't a square
-            if num_rows != row.len() {
-                panic!("failed to spawn dungeon because it is not a square");
-            }
-
             for j in 0..row.len() {
                 let preset = raw_dungeon_grid.determine_preset(i as i32, j as i32);
                 let grid_position = GridPosition { row: i, col: j };
-                let cell =
-                    DungeonCell::from_tile_bundle(tile_bundle_map.0.get(&preset).unwrap().clone());
+                if raw_dungeon_grid.cell_exists(i as i32, j as i32) {
+                    dungeon_tile_lookup.set_open(grid_position);
+                }
+                // a random (but seeded) variant of the texture for one of this cell's faces
+                let pick_tile = |tileset: &str, texture: TileTexture, direction: GridDirection| {
+                    let weights = raw_dungeon_grid.variant_weights.get(&texture);
+                    let roll = raw_dungeon_grid.face_roll(grid_position, direction);
+                    tile_texture_map
+                        .pick_tile(tileset, texture, weights.map(Vec::as_slice), roll)
+                        .unwrap()
+                        .clone()
+                };
+
+                let mut tile_bundle = tile_bundle_map.get(tileset, preset).unwrap().clone();
+                for direction in GridDirection::ALL {
+                    if preset.has_face(direction) {
+                        let tile = pick_tile(tileset, TileTexture::for_face(direction), direction);
+                        tile_bundle = tile_bundle.with_tile(direction, tile);
+                    }
+                }
+                if let Some(stair) = raw_dungeon_grid.stair_at(grid_position) {
+                    // mark the ceiling for stairs going up and the floor for stairs going down
+                    let face = if stair.target_floor < current_floor.0 {
+                        GridDirection::Top
+                    } else {
+                        GridDirection::Bottom
+                    };
+                    tile_bundle =
+                        tile_bundle.with_tile(face, pick_tile(tileset, TileTexture::Stairs, face));
+                }
+                if let Some(floor_tile) = raw_dungeon_grid.floor_tile_at(grid_position) {
+                    // pits are just a hole in the floor
+                    let tile = match floor_tile.kind {
+                        FloorKind::Pit => Tile::new_empty(),
+                        _ => tile_bundle.tile(GridDirection::Bottom).clone(),
+                    };
+                    tile_bundle = tile_bundle.with_tile(
+                        GridDirection::Bottom,
+                        tile.with_tile_type(floor_tile.kind.tile_type()),
+                    );
+                }
+                let mut door_directions = vec![];
+                for direction in GridDirection::HORIZONTAL {
+                    let kind = raw_dungeon_grid.edge(grid_position, direction);
+                    if matches!(kind, EdgeKind::Door | EdgeKind::LockedDoor) {
+                        door_directions.push(direction);
+                    }
+                    if matches!(kind, EdgeKind::Open | EdgeKind::Wall) {
+                        // already covered by the preset
+                        continue;
+                    }
+                    let texture = kind.texture().expect("non-open edges have a texture");
+                    let tile = pick_tile(tileset, texture, direction);
+                    tile_bundle =
+                        tile_bundle.with_tile(direction, tile.with_tile_type(kind.tile_type()));
+                }
+                for face_override in raw_dungeon_grid.face_overrides_at(grid_position) {
+                    let override_tileset = face_override.tileset.as_deref().unwrap_or(tileset);
+                    let direction = face_override.direction;
+                    let tile = match face_override.variant {
+                        Some(variant) => tile_texture_map
+                            .tile(override_tileset, face_override.texture, variant)
+                            .unwrap()
+                            .clone(),
+                        None => pick_tile(override_tileset, face_override.texture, direction),
+                    };
+                    // only the looks change, the face keeps behaving like it did
+                    let tile_type = tile_bundle.tile(direction).tile_type.clone();
+                    tile_bundle = tile_bundle.with_tile(direction, tile.with_tile_type(tile_type));
+                }
+                let cell = DungeonCell::from_tile_bundle(tile_bundle);
                 spawn_dungeon_cell(cell, grid_position, &mut commands, &mut dungeon_tile_lookup);
+
+                for direction in door_directions {
+                    let key = raw_dungeon_grid
+                        .edge_key(grid_position, direction)
+                        .map(str::to_string);
+                    let tile_entity = dungeon_tile_lookup
+                        .try_get_tile(grid_position, direction)
+                        .unwrap();
+                    commands.entity(tile_entity).insert(Door::new(
+                        current_floor.0,
+                        grid_position,
+                        direction,
+                        key,
+                    ));
+                }
+
+                for raw_decal in raw_dungeon_grid.decals_at(grid_position) {
+                    let decal = tile_texture_map
+                        .decal(&raw_decal.decal)
+                        .unwrap()
+                        .clone()
+                        .on_face(raw_decal.direction);
+                    let tile_entity = dungeon_tile_lookup
+                        .try_get_tile(grid_position, raw_decal.direction)
+                        .unwrap();
+                    commands.entity(tile_entity).with_children(|tile| {
+                        tile.spawn(decal);
+                    });
+                }
             }
         }
     }
 
-    fn spawn_items(
+    pub fn spawn_items(
         mut commands: Commands,
         dungeon_assets: Res<DungeonAssets>,
-        raw_dungeon_data: Res<Assets<RawDungeonData>>,
+        raw_dungeon_floors: Res<Assets<RawDungeonFloors>>,
+        current_floor: Res<CurrentFloor>,
+        collected_items: Res<CollectedItems>,
+        item_database: Res<ItemDatabase>,
     ) {
-        let data = raw_dungeon_data
-            .get(&dungeon_assets.raw_dungeon_data)
-            .unwrap();
+        let data = dungeon_assets.current_floor(&raw_dungeon_floors, &current_floor);
         for raw_item_data in data.items.iter() {
-            let item_type = raw_item_data.item_type;
             let item_position: GridPosition = raw_item_data.item_position.into();
-            DungeonItem::spawn(&mut commands, item_type, item_position, &dungeon_assets);
+            if collected_items.contains(current_floor.0, item_position) {
+                continue;
+            }
+            DungeonItem::spawn(
+                &mut commands,
+                raw_item_data.item_type.clone(),
+                item_position,
+                raw_item_data.key.clone(),
+                &item_database,
+            );
         }
     }
 
@@ -314,23 +345,37 @@ impl Plugin for DungeonMode {
             LoadingState::new(GameModeState::LoadingDungeon)
                 .continue_to_state(GameModeState::InDungeon),
         )
-        .add_collection_to_loading_state::<_, PurpleTileAssets>(GameModeState::LoadingDungeon)
+        .add_collection_to_loading_state::<_, TilesetAssets>(GameModeState::LoadingDungeon)
         .add_collection_to_loading_state::<_, DungeonAssets>(GameModeState::LoadingDungeon)
-        .init_resource_after_loading_state::<_, PurpleTileTextureMap>(GameModeState::LoadingDungeon)
+        .init_resource_after_loading_state::<_, TilesetTextureMap>(GameModeState::LoadingDungeon)
         .init_resource_after_loading_state::<_, TileBundlePresetMap>(GameModeState::LoadingDungeon)
         .init_resource_after_loading_state::<_, DungeonTileLookup>(GameModeState::LoadingDungeon)
         .add_systems(
             OnExit(GameModeState::LoadingDungeon),
             (
-                DungeonMode::initialize_preset_map,
+                use_generated_dungeon,
+                DungeonMode::validate_dungeon_data,
+                apply_deferred,
                 (
-                    DungeonMode::unlight_all_materials,
-                    DungeonMode::setup_player,
-                    DungeonMode::spawn_grid,
-                    DungeonMode::spawn_items,
+                    DungeonMode::initialize_preset_map,
+                    (
+                        DungeonMode::unlight_all_materials,
+                        DungeonMode::setup_player,
+                        DungeonMode::spawn_grid,
+                        DungeonMode::spawn_items,
+                        spawn_foes,
+                    )
+                        .after(DungeonMode::initialize_preset_map),
                 )
-                    .after(DungeonMode::initialize_preset_map),
-            ),
+                    .run_if(not(resource_exists::<DungeonValidationReport>())),
+            )
+                .chain()
+                // broken characters leave this state before the dungeon has even loaded
+                .run_if(not(resource_exists::<CharacterValidationReport>())),
+        )
+        .add_systems(
+            OnEnter(GameModeState::InvalidDungeon),
+            DungeonMode::show_validation_report,
         );
     }
 }
@@ -339,7 +384,15 @@ impl PluginGroup for DungeonModePlugins {
     fn build(self) -> PluginGroupBuilder {
         PluginGroupBuilder::start::<Self>()
             .add(DungeonMode)
+            .add(DungeonInputPlugin)
             .add(DungeonPlayerPlugin)
+            .add(StairsPlugin)
+            .add(FloorTilesPlugin)
+            .add(DoorsPlugin)
+            .add(ItemPickupPlugin)
+            .add(FoePlugin)
+            .add(EncounterPlugin)
+            .add(AftermathPlugin)
     }
 }
 
@@ -349,25 +402,254 @@ pub mod test_helpers {
     use bevy::prelude::AssetPlugin;
     use bevy_common_assets::json::JsonAssetPlugin;
 
+    use crate::modes::dungeon::foes::DefeatedFoes;
+    use crate::modes::sharedassets::itemdatabase::test_helpers::test_item_database;
+
     pub fn setup_test_dungeon_assets(app: &mut App, raw_dungeon_data: RawDungeonData) {
+        setup_test_dungeon_floors(
+            app,
+            RawDungeonFloors {
+                floors: vec![raw_dungeon_data],
+                ..Default::default()
+            },
+        );
+    }
+
+    pub fn setup_test_dungeon_floors(app: &mut App, raw_dungeon_floors: RawDungeonFloors) {
         app.add_plugins((
             AssetPlugin::default(),
-            JsonAssetPlugin::<RawDungeonData>::new(&["irrelevant.json"]),
+            JsonAssetPlugin::<RawDungeonFloors>::new(&["irrelevant.json"]),
         ));
+        app.insert_resource(CurrentFloor(raw_dungeon_floors.start_floor))
+            .init_resource::<CollectedItems>()
+            .init_resource::<DefeatedFoes>()
+            .insert_resource(test_item_database());
         let mut assets = app
             .world
-            .get_resource_mut::<Assets<RawDungeonData>>()
+            .get_resource_mut::<Assets<RawDungeonFloors>>()
             .unwrap();
 
-        let handle = assets.add(raw_dungeon_data);
+        let handle = assets.add(raw_dungeon_floors);
 
         // use asset server to load raw dungeon data
         let dungeon_assets = DungeonAssets {
             raw_dungeon_data: handle,
-            polaroid: Default::default(),
-            key: Default::default(),
-            maxwell: Default::default(),
         };
         app.insert_resource(dungeon_assets);
     }
 }
+
+#[cfg(test)]
+mod test {
+    use bevy::prelude::*;
+
+    use crate::modes::dungeon::dungeonmode::test_helpers::setup_test_dungeon_floors;
+    use crate::modes::dungeon::dungeonmode::DungeonMode;
+    use crate::modes::dungeon::model::cell::test_helpers::setup_test_tile_preset_map;
+    use crate::modes::dungeon::model::cell::{GridDirection, GridPosition};
+    use crate::modes::dungeon::model::grid::test_helpers::setup_dungeon_tile_lookup;
+    use crate::modes::dungeon::model::grid::{DungeonTileLookup, RawDungeonData, RawDungeonFloors};
+    use crate::modes::dungeon::model::tile::{TileTexture, TileType};
+    use crate::modes::dungeon::model::tileset::test_helpers::{
+        test_decal_material, test_material, OTHER_TEST_TILESET, TEST_DECAL, TEST_TILESET,
+        TEST_VARIANTS,
+    };
+    use crate::modes::dungeon::model::tileset::{RawDecalData, RawFaceOverride};
+
+    fn spawn_floors(raw_dungeon_floors: RawDungeonFloors) -> App {
+        let mut app = App::new();
+        setup_test_tile_preset_map(&mut app);
+        setup_dungeon_tile_lookup(&mut app);
+        setup_test_dungeon_floors(&mut app, raw_dungeon_floors);
+        app.add_systems(
+            Startup,
+            (DungeonMode::initialize_preset_map, DungeonMode::spawn_grid).chain(),
+        );
+        app.update();
+        app
+    }
+
+    fn face(app: &App, position: [u8; 2], direction: GridDirection) -> Entity {
+        app.world
+            .resource::<DungeonTileLookup>()
+            .try_get_tile(GridPosition::from(position), direction)
+            .unwrap()
+    }
+
+    fn make_floor(tileset: Option<&str>) -> RawDungeonData {
+        RawDungeonData {
+            dungeon_grid: vec![vec![1, 1]],
+            player_start_position: [0, 0],
+            tileset: tileset.map(str::to_string),
+            // always use the first variant unless a test says otherwise
+            variant_weights: [TileTexture::Wall, TileTexture::Floor, TileTexture::Ceiling]
+                .into_iter()
+                .map(|texture| (texture, vec![1.0, 0.0]))
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    fn material(app: &App, entity: Entity) -> Handle<StandardMaterial> {
+        app.world
+            .get::<Handle<StandardMaterial>>(entity)
+            .unwrap()
+            .clone()
+    }
+
+    #[test]
+    fn should_spawn_floor_tileset() {
+        let app = spawn_floors(RawDungeonFloors {
+            floors: vec![make_floor(None), make_floor(Some(TEST_TILESET))],
+            tileset: Some(OTHER_TEST_TILESET.to_string()),
+            ..Default::default()
+        });
+        let wall = face(&app, [0, 0], GridDirection::Forward);
+        assert_eq!(
+            app.world.get::<Handle<StandardMaterial>>(wall),
+            Some(&test_material(OTHER_TEST_TILESET, TileTexture::Wall, 0))
+        );
+
+        // the second floor picks its own tileset over the dungeon's
+        let app = spawn_floors(RawDungeonFloors {
+            floors: vec![make_floor(None), make_floor(Some(TEST_TILESET))],
+            start_floor: 1,
+            tileset: Some(OTHER_TEST_TILESET.to_string()),
+            ..Default::default()
+        });
+        let floor = face(&app, [0, 1], GridDirection::Bottom);
+        assert_eq!(
+            app.world.get::<Handle<StandardMaterial>>(floor),
+            Some(&test_material(TEST_TILESET, TileTexture::Floor, 0))
+        );
+    }
+
+    #[test]
+    fn should_override_face_textures() {
+        let mut data = make_floor(None);
+        data.face_overrides = vec![
+            RawFaceOverride {
+                position: [0, 0],
+                direction: GridDirection::Left,
+                texture: TileTexture::Door,
+                variant: Some(1),
+                tileset: None,
+            },
+            RawFaceOverride {
+                position: [0, 1],
+                direction: GridDirection::Left,
+                texture: TileTexture::Wall,
+                variant: Some(0),
+                tileset: Some(OTHER_TEST_TILESET.to_string()),
+            },
+        ];
+        let app = spawn_floors(RawDungeonFloors {
+            floors: vec![data],
+            ..Default::default()
+        });
+
+        let wall = face(&app, [0, 0], GridDirection::Left);
+        assert_eq!(
+            app.world.get::<Handle<StandardMaterial>>(wall),
+            Some(&test_material(TEST_TILESET, TileTexture::Door, 1))
+        );
+        assert_eq!(app.world.get::<TileType>(wall), Some(&TileType::Basic));
+
+        // overriding an open side changes how it looks but it's still open
+        let open = face(&app, [0, 1], GridDirection::Left);
+        assert_eq!(
+            app.world.get::<Handle<StandardMaterial>>(open),
+            Some(&test_material(OTHER_TEST_TILESET, TileTexture::Wall, 0))
+        );
+        assert_eq!(app.world.get::<TileType>(open), Some(&TileType::Empty));
+    }
+
+    fn spawn_single_floor(data: RawDungeonData) -> App {
+        spawn_floors(RawDungeonFloors {
+            floors: vec![data],
+            ..Default::default()
+        })
+    }
+
+    /// The variant every wall face on the floor ended up with, in a stable order.
+    fn wall_variants(app: &App, height: u8, width: u8) -> Vec<usize> {
+        let mut variants = vec![];
+        for row in 0..height {
+            for col in 0..width {
+                for direction in GridDirection::HORIZONTAL {
+                    let tile = face(app, [row, col], direction);
+                    if *app.world.get::<TileType>(tile).unwrap() == TileType::Empty {
+                        continue;
+                    }
+                    let tile_material = material(app, tile);
+                    let variant = (0..TEST_VARIANTS)
+                        .find(|variant| {
+                            tile_material
+                                == test_material(TEST_TILESET, TileTexture::Wall, *variant)
+                        })
+                        .unwrap();
+                    variants.push(variant);
+                }
+            }
+        }
+        variants
+    }
+
+    #[test]
+    fn should_pick_weighted_variants() {
+        let mut data = make_floor(None);
+        data.variant_weights
+            .insert(TileTexture::Wall, vec![0.0, 1.0]);
+        let app = spawn_single_floor(data);
+        assert_eq!(wall_variants(&app, 1, 2), vec![1; 6]);
+    }
+
+    #[test]
+    fn should_pick_the_same_variants_for_the_same_seed() {
+        let spawn_with_seed = |seed| {
+            let mut data = make_floor(None);
+            data.dungeon_grid = vec![vec![1; 6]; 6];
+            data.variant_weights.remove(&TileTexture::Wall);
+            data.seed = seed;
+            wall_variants(&spawn_single_floor(data), 6, 6)
+        };
+        let first = spawn_with_seed(1234);
+        assert_eq!(first, spawn_with_seed(1234));
+        // with even weights and 24 walls, both variants should show up
+        assert!(first.contains(&0) && first.contains(&1));
+        assert_ne!(first, spawn_with_seed(4321));
+    }
+
+    #[test]
+    fn should_spawn_decals_on_faces() {
+        let mut data = make_floor(None);
+        data.decals.push(RawDecalData {
+            position: [0, 1],
+            direction: GridDirection::Right,
+            decal: TEST_DECAL.to_string(),
+        });
+        let app = spawn_single_floor(data);
+
+        let wall = face(&app, [0, 1], GridDirection::Right);
+        let children = app.world.get::<Children>(wall).unwrap();
+        assert_eq!(children.len(), 1);
+        let decal = children[0];
+        assert_eq!(material(&app, decal), test_decal_material());
+        // the decal doesn't change what the wall is, and isn't a tile itself
+        assert_eq!(
+            material(&app, wall),
+            test_material(TEST_TILESET, TileTexture::Wall, 0)
+        );
+        assert_eq!(app.world.get::<TileType>(wall), Some(&TileType::Basic));
+        assert_eq!(app.world.get::<TileType>(decal), None);
+
+        // it sits just inside the cell, in front of the wall
+        let wall_transform = *app.world.get::<Transform>(wall).unwrap();
+        let decal_transform =
+            wall_transform.mul_transform(*app.world.get::<Transform>(decal).unwrap());
+        let wall_x = wall_transform.translation.x;
+        let decal_x = decal_transform.translation.x;
+        assert!(decal_x < wall_x);
+        assert!(wall_x - decal_x < 0.01);
+    }
+}
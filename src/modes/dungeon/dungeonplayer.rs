@@ -2,9 +2,11 @@ use std::f32::consts::PI;
 use std::time::Duration;
 
 use bevy::prelude::*;
+use bevy_mod_picking::prelude::RaycastPickCamera;
 use bevy_tweening::lens::{TransformPositionLens, TransformRotationLens};
 use bevy_tweening::{Animator, AnimatorState, EaseMethod, RepeatStrategy, Tween};
 
+use crate::modes::dungeon::dungeoninput::{DungeonAction, ReadDungeonInput};
 use crate::modes::dungeon::model::cell::{GridDirection, GridPosType, GridPosition};
 use crate::modes::dungeon::model::grid::DungeonTileLookup;
 use crate::modes::dungeon::model::tile::TileType;
@@ -30,21 +32,60 @@ pub struct DungeonPlayer;
 #[derive(Component)]
 pub struct SpeedMultiplier(pub f32);
 
+/// The way the player went on their last walk or run, which isn't always the way they're facing.
+#[derive(Component)]
+pub struct MoveDirection(pub GridDirection);
+
+/// Sent once a walk or run has finished animating and the player is standing on the new cell.
+#[derive(Event)]
+pub struct PlayerMoveCompleted {
+    pub grid_position: GridPosition,
+    pub direction: GridDirection,
+}
+
 #[derive(Bundle)]
 pub struct DungeonPlayerBundle {
     pub dungeon_player: DungeonPlayer,
     pub animator_transform: Animator<Transform>,
     pub camera: Camera3dBundle,
+    pub pick_camera: RaycastPickCamera,
     pub grid_pos: GridPosition,
     pub start_direction: GridDirection,
     pub movement_state: DungeonPlayerMovementState,
     pub speed_multiplier: SpeedMultiplier,
+    pub move_direction: MoveDirection,
+}
+
+/// Where the player's camera should be when standing on `grid_pos` facing `direction`.
+pub fn player_transform(grid_pos: GridPosition, direction: GridDirection) -> Transform {
+    let player_pos = grid_pos.to_vec3(GridPosType::Player);
+    Transform::from_translation(player_pos).looking_at(player_pos + Vec3::from(direction), Vec3::Y)
 }
 
+/// The cell the player ends up on if they move from `grid_pos` in `direction`, or `None` if
+/// something's in the way or there's nothing to stand on.
+pub fn destination(
+    dungeon_tile_lookup: &DungeonTileLookup,
+    tile_type_query: &Query<&TileType>,
+    grid_pos: GridPosition,
+    direction: GridDirection,
+) -> Option<GridPosition> {
+    // the edge of the grid and empty cells are always walls, even if nothing was placed there
+    let destination = grid_pos
+        .try_translated(direction)
+        .filter(|pos| dungeon_tile_lookup.is_open(*pos))?;
+    let collision = dungeon_tile_lookup
+        .try_get_tile(grid_pos, direction)
+        .and_then(|tile_entity| tile_type_query.get(tile_entity).ok())
+        .is_none_or(|tile_type| tile_type.is_solid());
+    (!collision).then_some(destination)
+}
+
+#[allow(clippy::type_complexity)]
 pub fn try_move_player(
-    keyboard_input: Res<Input<KeyCode>>,
+    mut action_reader: EventReader<DungeonAction>,
+    mut move_completed_writer: EventWriter<PlayerMoveCompleted>,
     dungeon_tile_lookup: Res<DungeonTileLookup>,
-    mut next_state: ResMut<NextState<GameModeState>>,
     mut player_query: Query<
         (
             Entity,
@@ -54,12 +95,20 @@ pub fn try_move_player(
             &mut GridDirection,
             &mut DungeonPlayerMovementState,
             &mut SpeedMultiplier,
+            &mut MoveDirection,
         ),
         With<DungeonPlayer>,
     >,
-    tile_type_query: Query<(Entity, &TileType)>,
+    tile_type_query: Query<&TileType>,
 ) {
-    let (
+    // always read every action, so nothing sent while we were busy gets picked up later
+    let action = action_reader
+        .iter()
+        .copied()
+        .find(|action| *action != DungeonAction::Interact);
+
+    // the player won't exist if the dungeon failed validation
+    let Ok((
         _id,
         transform,
         mut animator,
@@ -67,109 +116,93 @@ pub fn try_move_player(
         mut grid_direction,
         mut current_movement_state,
         mut speed_multiplier,
-    ) = player_query.single_mut();
+        mut move_direction,
+    )) = player_query.get_single_mut()
+    else {
+        return;
+    };
 
     if !can_change_state(&animator, *current_movement_state) {
         return;
     }
 
-    let mut translate_player = false;
-    let mut rotate_diff = 0_f32;
-    let mut direction_to_translate = GridDirection::Forward;
-    let mut direction_to_rotate = GridDirection::Right;
-    let shift_pressed = keyboard_input.pressed(KeyCode::ShiftLeft);
-
-    // if shift is pressed, then we can't rotate. the speed multiplier is applied and we always
-    // translate
-    //region keyboard input
-    if keyboard_input.pressed(KeyCode::Left) {
-        if shift_pressed {
-            direction_to_translate = grid_direction.rotated(GridDirection::Left);
-            translate_player = true;
-        } else {
-            rotate_diff = PI / 2.0;
-            direction_to_rotate = GridDirection::Left
+    if matches!(
+        *current_movement_state,
+        DungeonPlayerMovementState::Walking | DungeonPlayerMovementState::Running
+    ) {
+        move_completed_writer.send(PlayerMoveCompleted {
+            grid_position: *grid_pos,
+            direction: move_direction.0,
+        });
+        // the floor gets to move us before we can move again
+        let floor_takes_control = dungeon_tile_lookup
+            .try_get_tile(*grid_pos, GridDirection::Bottom)
+            .and_then(|tile_entity| tile_type_query.get(tile_entity).ok())
+            .is_some_and(|tile_type| tile_type.takes_control());
+        if floor_takes_control {
+            *current_movement_state = DungeonPlayerMovementState::Stationary;
+            return;
         }
-    } else if keyboard_input.pressed(KeyCode::Right) {
-        if shift_pressed {
-            direction_to_translate = grid_direction.rotated(GridDirection::Right);
-            translate_player = true;
-        } else {
-            rotate_diff = -PI / 2.0;
-            direction_to_rotate = GridDirection::Right;
-        }
-    }
-    if keyboard_input.pressed(KeyCode::Up) {
-        direction_to_translate = *grid_direction;
-        translate_player = true;
-    } else if keyboard_input.pressed(KeyCode::Down) {
-        direction_to_translate = grid_direction.get_inverse_direction();
-        translate_player = true;
-    }
-    if keyboard_input.just_pressed(KeyCode::Semicolon) {
-        next_state.set(GameModeState::LoadingBattle);
-        return;
-    }
-    //endregion
-
-    let collision = {
-        let tile_entity = dungeon_tile_lookup.get_tile(*grid_pos, direction_to_translate);
-        let tile_type = tile_type_query
-            .get_component::<TileType>(tile_entity)
-            .unwrap();
-        *tile_type != TileType::Empty
-    };
-    let new_multiplier = if shift_pressed {
-        2.0
-    } else {
-        RUN_SPEED_MULTIPLIER
-    };
-    speed_multiplier.0 = new_multiplier;
-
-    if translate_player {
-        move_or_collide(
-            collision,
-            &mut current_movement_state,
-            &mut animator,
-            &mut grid_pos,
-            direction_to_translate,
-            new_multiplier,
-        );
-        return;
     }
 
-    if rotate_diff.abs() > f32::EPSILON {
-        *current_movement_state = DungeonPlayerMovementState::Rotating;
-        animator.set_tweenable(Tween::new(
-            EaseMethod::Linear,
-            Duration::from_secs_f32(ROTATE_ANIMATION_DURATION),
-            TransformRotationLens {
-                start: transform.rotation,
-                end: transform.rotation * Quat::from_rotation_y(rotate_diff),
-            },
-        ));
-        animator.state = AnimatorState::Playing;
-        // change our direction here
-        *grid_direction = grid_direction.rotated(direction_to_rotate);
-        return;
+    match action {
+        Some(DungeonAction::Move { direction, run }) => {
+            let direction_to_translate = direction.relative_to_absolute(*grid_direction);
+            let new_multiplier = if run { 2.0 } else { RUN_SPEED_MULTIPLIER };
+            speed_multiplier.0 = new_multiplier;
+            move_or_collide(
+                destination(
+                    &dungeon_tile_lookup,
+                    &tile_type_query,
+                    *grid_pos,
+                    direction_to_translate,
+                ),
+                &mut current_movement_state,
+                &mut animator,
+                &mut grid_pos,
+                &mut move_direction,
+                direction_to_translate,
+                new_multiplier,
+            );
+        }
+        Some(DungeonAction::Turn(direction_to_rotate)) => {
+            let rotate_diff = if direction_to_rotate == GridDirection::Left {
+                PI / 2.0
+            } else {
+                -PI / 2.0
+            };
+            *current_movement_state = DungeonPlayerMovementState::Rotating;
+            animator.set_tweenable(Tween::new(
+                EaseMethod::Linear,
+                Duration::from_secs_f32(ROTATE_ANIMATION_DURATION),
+                TransformRotationLens {
+                    start: transform.rotation,
+                    end: transform.rotation * Quat::from_rotation_y(rotate_diff),
+                },
+            ));
+            animator.state = AnimatorState::Playing;
+            // change our direction here
+            *grid_direction = grid_direction.rotated(direction_to_rotate);
+        }
+        // not translating or rotating, so we are now stationary
+        _ => *current_movement_state = DungeonPlayerMovementState::Stationary,
     }
-
-    // not translating or rotating, so we are now stationary
-    *current_movement_state = DungeonPlayerMovementState::Stationary;
 }
 
 // TODO: probably want to separate these out into a struct?
-fn move_or_collide(
-    collision: bool,
+/// `destination` is `None` if we collided with something.
+pub fn move_or_collide(
+    destination: Option<GridPosition>,
     movement_state: &mut Mut<DungeonPlayerMovementState>,
     animator: &mut Mut<Animator<Transform>>,
     grid_pos: &mut Mut<GridPosition>,
+    move_direction: &mut Mut<MoveDirection>,
     direction: GridDirection,
     speed_multiplier: f32,
 ) {
     // check for collision here
-    if collision {
-        let translate_diff: Vec3 = direction.try_into().unwrap();
+    let Some(end_grid_pos) = destination else {
+        let translate_diff: Vec3 = direction.into();
         let start = grid_pos.to_vec3(GridPosType::Player);
         let end = start + 0.18 * translate_diff;
         let collision_tween = Tween::new(
@@ -183,8 +216,7 @@ fn move_or_collide(
         animator.state = AnimatorState::Playing;
         **movement_state = DungeonPlayerMovementState::Colliding;
         return;
-    }
-    let end_grid_pos = grid_pos.translated(direction);
+    };
 
     **movement_state = if speed_multiplier > 1.5 {
         DungeonPlayerMovementState::Running
@@ -203,6 +235,7 @@ fn move_or_collide(
     animator.state = AnimatorState::Playing;
     // move our grid position here
     **grid_pos = end_grid_pos;
+    move_direction.0 = direction;
 }
 
 /// Helper function to check if we can change the player's current movement state.
@@ -210,9 +243,8 @@ fn can_change_state(
     animator: &Animator<Transform>,
     movement_state: DungeonPlayerMovementState,
 ) -> bool {
-    let finished_animation = animator.tweenable().duration().as_secs_f32()
-        - animator.tweenable().elapsed().as_secs_f32()
-        < f32::EPSILON;
+    let finished_animation = animator.tweenable().elapsed().as_secs_f32()
+        > animator.tweenable().duration().as_secs_f32() - f32::EPSILON;
     match movement_state {
         DungeonPlayerMovementState::Stationary => true, // no animation playing
         DungeonPlayerMovementState::Walking
@@ -229,9 +261,11 @@ pub struct DungeonPlayerPlugin;
 
 impl Plugin for DungeonPlayerPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(
+        app.add_event::<PlayerMoveCompleted>().add_systems(
             Update,
-            try_move_player.run_if(in_state(GameModeState::InDungeon)),
+            try_move_player
+                .after(ReadDungeonInput)
+                .run_if(in_state(GameModeState::InDungeon)),
         );
     }
 }
@@ -245,23 +279,23 @@ mod test {
     use bevy_tweening::RepeatStrategy::MirroredRepeat;
     use bevy_tweening::{Animator, AnimatorState, EaseMethod, Tween};
 
+    use crate::modes::dungeon::dungeoninput::test_helpers::setup_keyboard_movement;
     use crate::modes::dungeon::dungeonmode::test_helpers::setup_test_dungeon_assets;
     use crate::modes::dungeon::dungeonmode::DungeonMode;
-    use crate::modes::dungeon::dungeonplayer::{
-        can_change_state, try_move_player, DungeonPlayerMovementState,
-    };
+    use crate::modes::dungeon::dungeonplayer::{can_change_state, DungeonPlayerMovementState};
     use crate::modes::dungeon::model::cell::test_helpers::setup_test_tile_preset_map;
-    use crate::modes::dungeon::model::cell::GridDirection;
+    use crate::modes::dungeon::model::cell::{GridDirection, GridPosition};
+    use crate::modes::dungeon::model::edge::{EdgeKind, RawEdgeData};
     use crate::modes::dungeon::model::grid::test_helpers::setup_dungeon_tile_lookup;
-    use crate::modes::dungeon::model::grid::RawDungeonData;
+    use crate::modes::dungeon::model::grid::{DungeonTileLookup, RawDungeonData};
+    use crate::modes::dungeon::model::tile::TileType;
     use crate::modes::mode_state::GameModeState;
 
     fn setup(raw_dungeon_data: Option<RawDungeonData>) -> App {
         let default_data = RawDungeonData {
             dungeon_grid: vec![vec![1, 1], vec![1, 1]],
             player_start_position: [0, 0],
-            player_start_direction: GridDirection::Forward,
-            items: vec![],
+            ..Default::default()
         };
         let mut app = App::new();
         setup_test_tile_preset_map(&mut app);
@@ -277,8 +311,8 @@ mod test {
                 (DungeonMode::spawn_grid, DungeonMode::setup_player)
                     .after(DungeonMode::initialize_preset_map),
             ),
-        )
-        .add_systems(Update, try_move_player);
+        );
+        setup_keyboard_movement(&mut app);
         app
     }
 
@@ -330,7 +364,7 @@ mod test {
             dungeon_grid: vec![vec![1, 1], vec![1, 1]],
             player_start_position: [0, 0],
             player_start_direction: GridDirection::Right,
-            items: vec![],
+            ..Default::default()
         };
         let mut app = setup(Some(raw_dungeon_data));
         let mut input = app.world.get_resource_mut::<Input<KeyCode>>().unwrap();
@@ -350,7 +384,7 @@ mod test {
             dungeon_grid: vec![vec![1, 1], vec![1, 1]],
             player_start_position: [0, 1],
             player_start_direction: GridDirection::Right,
-            items: vec![],
+            ..Default::default()
         };
         let mut app = setup(Some(raw_dungeon_data));
         let mut input = app.world.get_resource_mut::<Input<KeyCode>>().unwrap();
@@ -370,7 +404,7 @@ mod test {
             dungeon_grid: vec![vec![1, 1], vec![1, 1]],
             player_start_position: [0, 0],
             player_start_direction: GridDirection::Right,
-            items: vec![],
+            ..Default::default()
         };
         let mut app = setup(Some(raw_dungeon_data));
         let mut input = app.world.get_resource_mut::<Input<KeyCode>>().unwrap();
@@ -391,7 +425,7 @@ mod test {
             dungeon_grid: vec![vec![1, 1], vec![1, 1]],
             player_start_position: [1, 0],
             player_start_direction: GridDirection::Right,
-            items: vec![],
+            ..Default::default()
         };
         let mut app = setup(Some(raw_dungeon_data));
         let mut input = app.world.get_resource_mut::<Input<KeyCode>>().unwrap();
@@ -412,7 +446,7 @@ mod test {
             dungeon_grid: vec![vec![1, 1], vec![1, 1]],
             player_start_position: [0, 0],
             player_start_direction: GridDirection::Right,
-            items: vec![],
+            ..Default::default()
         };
         let mut app = setup(Some(raw_dungeon_data));
         let mut input = app.world.get_resource_mut::<Input<KeyCode>>().unwrap();
@@ -427,13 +461,289 @@ mod test {
         assert_eq!(animator.state, AnimatorState::Playing);
     }
 
+    fn get_movement_state(app: &mut App) -> DungeonPlayerMovementState {
+        *app.world
+            .query::<&DungeonPlayerMovementState>()
+            .single(&app.world)
+    }
+
+    #[test]
+    fn should_spawn_rectangular_grids() {
+        let raw_dungeon_data = RawDungeonData {
+            dungeon_grid: vec![vec![1; 40]; 12],
+            player_start_position: [0, 0],
+            player_start_direction: GridDirection::Right,
+            ..Default::default()
+        };
+        let mut app = setup(Some(raw_dungeon_data));
+        app.update();
+        let dungeon_tile_lookup = app.world.get_resource::<DungeonTileLookup>().unwrap();
+        assert!(dungeon_tile_lookup.contains(GridPosition { row: 11, col: 39 }));
+        assert!(!dungeon_tile_lookup.contains(GridPosition { row: 12, col: 0 }));
+        assert!(!dungeon_tile_lookup.contains(GridPosition { row: 0, col: 40 }));
+    }
+
+    #[test]
+    fn should_walk_in_wide_grid() {
+        let raw_dungeon_data = RawDungeonData {
+            dungeon_grid: vec![vec![1; 8]; 3],
+            player_start_position: [1, 6],
+            player_start_direction: GridDirection::Right,
+            ..Default::default()
+        };
+        let mut app = setup(Some(raw_dungeon_data));
+        let mut input = app.world.get_resource_mut::<Input<KeyCode>>().unwrap();
+        input.press(KeyCode::Up);
+        app.update();
+        assert_eq!(
+            get_movement_state(&mut app),
+            DungeonPlayerMovementState::Walking
+        );
+    }
+
+    #[test]
+    fn should_collide_at_end_of_wide_grid() {
+        let raw_dungeon_data = RawDungeonData {
+            dungeon_grid: vec![vec![1; 8]; 3],
+            player_start_position: [1, 7],
+            player_start_direction: GridDirection::Right,
+            ..Default::default()
+        };
+        let mut app = setup(Some(raw_dungeon_data));
+        let mut input = app.world.get_resource_mut::<Input<KeyCode>>().unwrap();
+        input.press(KeyCode::Up);
+        app.update();
+        assert_eq!(
+            get_movement_state(&mut app),
+            DungeonPlayerMovementState::Colliding
+        );
+    }
+
+    #[test]
+    fn should_walk_in_tall_grid() {
+        let raw_dungeon_data = RawDungeonData {
+            dungeon_grid: vec![vec![1; 3]; 8],
+            player_start_position: [6, 1],
+            player_start_direction: GridDirection::Back,
+            ..Default::default()
+        };
+        let mut app = setup(Some(raw_dungeon_data));
+        let mut input = app.world.get_resource_mut::<Input<KeyCode>>().unwrap();
+        input.press(KeyCode::Up);
+        app.update();
+        assert_eq!(
+            get_movement_state(&mut app),
+            DungeonPlayerMovementState::Walking
+        );
+    }
+
+    #[test]
+    fn should_collide_at_end_of_tall_grid() {
+        let raw_dungeon_data = RawDungeonData {
+            dungeon_grid: vec![vec![1; 3]; 8],
+            player_start_position: [7, 1],
+            player_start_direction: GridDirection::Back,
+            ..Default::default()
+        };
+        let mut app = setup(Some(raw_dungeon_data));
+        let mut input = app.world.get_resource_mut::<Input<KeyCode>>().unwrap();
+        input.press(KeyCode::Up);
+        app.update();
+        assert_eq!(
+            get_movement_state(&mut app),
+            DungeonPlayerMovementState::Colliding
+        );
+    }
+
+    #[test]
+    fn should_walk_in_single_row_grid() {
+        let raw_dungeon_data = RawDungeonData {
+            dungeon_grid: vec![vec![1; 5]],
+            player_start_position: [0, 3],
+            player_start_direction: GridDirection::Right,
+            ..Default::default()
+        };
+        let mut app = setup(Some(raw_dungeon_data));
+        let mut input = app.world.get_resource_mut::<Input<KeyCode>>().unwrap();
+        input.press(KeyCode::Up);
+        app.update();
+        assert_eq!(
+            get_movement_state(&mut app),
+            DungeonPlayerMovementState::Walking
+        );
+    }
+
+    #[test]
+    fn should_collide_sideways_in_single_row_grid() {
+        let raw_dungeon_data = RawDungeonData {
+            dungeon_grid: vec![vec![1; 5]],
+            player_start_position: [0, 3],
+            ..Default::default()
+        };
+        let mut app = setup(Some(raw_dungeon_data));
+        let mut input = app.world.get_resource_mut::<Input<KeyCode>>().unwrap();
+        input.press(KeyCode::Up);
+        app.update();
+        assert_eq!(
+            get_movement_state(&mut app),
+            DungeonPlayerMovementState::Colliding
+        );
+    }
+
+    #[test]
+    fn should_walk_in_single_column_grid() {
+        let raw_dungeon_data = RawDungeonData {
+            dungeon_grid: vec![vec![1]; 5],
+            player_start_position: [4, 0],
+            ..Default::default()
+        };
+        let mut app = setup(Some(raw_dungeon_data));
+        let mut input = app.world.get_resource_mut::<Input<KeyCode>>().unwrap();
+        input.press(KeyCode::Up);
+        app.update();
+        assert_eq!(
+            get_movement_state(&mut app),
+            DungeonPlayerMovementState::Walking
+        );
+    }
+
+    #[test]
+    fn should_collide_with_grid_edge() {
+        let mut app = setup(None);
+        app.update();
+
+        // open up the wall on the edge of the map so only the grid bounds stop us
+        let start = GridPosition { row: 0, col: 0 };
+        let edge_tile = app
+            .world
+            .get_resource::<DungeonTileLookup>()
+            .unwrap()
+            .try_get_tile(start, GridDirection::Forward)
+            .unwrap();
+        *app.world.get_mut::<TileType>(edge_tile).unwrap() = TileType::Empty;
+
+        let mut input = app.world.get_resource_mut::<Input<KeyCode>>().unwrap();
+        input.press(KeyCode::Up);
+        app.update();
+        assert_eq!(
+            get_movement_state(&mut app),
+            DungeonPlayerMovementState::Colliding
+        );
+        let grid_pos = app.world.query::<&GridPosition>().single(&app.world);
+        assert_eq!(*grid_pos, start);
+    }
+
+    /// Two open cells side by side with `kind` placed on the right side of the left cell. Walks
+    /// the player across it from `start`, facing `direction`.
+    fn walk_across_edge(
+        kind: EdgeKind,
+        start: [u8; 2],
+        direction: GridDirection,
+    ) -> DungeonPlayerMovementState {
+        let raw_dungeon_data = RawDungeonData {
+            dungeon_grid: vec![vec![1, 1]],
+            player_start_position: start,
+            player_start_direction: direction,
+            edges: vec![RawEdgeData {
+                position: [0, 0],
+                direction: GridDirection::Right,
+                kind,
+                key: None,
+            }],
+            ..Default::default()
+        };
+        let mut app = setup(Some(raw_dungeon_data));
+        let mut input = app.world.get_resource_mut::<Input<KeyCode>>().unwrap();
+        input.press(KeyCode::Up);
+        app.update();
+        get_movement_state(&mut app)
+    }
+
+    #[test]
+    fn should_collide_with_wall_edge() {
+        assert_eq!(
+            walk_across_edge(EdgeKind::Wall, [0, 0], GridDirection::Right),
+            DungeonPlayerMovementState::Colliding
+        );
+        assert_eq!(
+            walk_across_edge(EdgeKind::Wall, [0, 1], GridDirection::Left),
+            DungeonPlayerMovementState::Colliding
+        );
+    }
+
+    #[test]
+    fn should_only_pass_one_way_edge_forwards() {
+        assert_eq!(
+            walk_across_edge(EdgeKind::OneWay, [0, 0], GridDirection::Right),
+            DungeonPlayerMovementState::Walking
+        );
+        assert_eq!(
+            walk_across_edge(EdgeKind::OneWay, [0, 1], GridDirection::Left),
+            DungeonPlayerMovementState::Colliding
+        );
+    }
+
+    #[test]
+    fn should_walk_through_secret_walls() {
+        assert_eq!(
+            walk_across_edge(EdgeKind::SecretWall, [0, 0], GridDirection::Right),
+            DungeonPlayerMovementState::Walking
+        );
+        assert_eq!(
+            walk_across_edge(EdgeKind::SecretWall, [0, 1], GridDirection::Left),
+            DungeonPlayerMovementState::Walking
+        );
+    }
+
+    #[test]
+    fn should_collide_with_empty_cells_behind_open_edges() {
+        // validation rejects this, but nobody should end up in the void if it gets through
+        for kind in [EdgeKind::Open, EdgeKind::SecretWall] {
+            let raw_dungeon_data = RawDungeonData {
+                dungeon_grid: vec![vec![1, 0]],
+                player_start_position: [0, 0],
+                player_start_direction: GridDirection::Right,
+                edges: vec![RawEdgeData {
+                    position: [0, 0],
+                    direction: GridDirection::Right,
+                    kind,
+                    key: None,
+                }],
+                ..Default::default()
+            };
+            let mut app = setup(Some(raw_dungeon_data));
+            let mut input = app.world.get_resource_mut::<Input<KeyCode>>().unwrap();
+            input.press(KeyCode::Up);
+            app.update();
+            assert_eq!(
+                get_movement_state(&mut app),
+                DungeonPlayerMovementState::Colliding
+            );
+        }
+    }
+
+    #[test]
+    fn should_collide_with_closed_doors() {
+        // doors have to be opened first, see doors.rs
+        for kind in [EdgeKind::Door, EdgeKind::LockedDoor] {
+            assert_eq!(
+                walk_across_edge(kind, [0, 0], GridDirection::Right),
+                DungeonPlayerMovementState::Colliding
+            );
+            assert_eq!(
+                walk_across_edge(kind, [0, 1], GridDirection::Left),
+                DungeonPlayerMovementState::Colliding
+            );
+        }
+    }
+
     #[test]
     fn can_change_state_works() {
         let raw_dungeon_data = RawDungeonData {
             dungeon_grid: vec![vec![1, 1], vec![1, 1]],
             player_start_position: [1, 0],
             player_start_direction: GridDirection::Right,
-            items: vec![],
+            ..Default::default()
         };
         let mut app = setup(Some(raw_dungeon_data));
         app.update();
@@ -0,0 +1,249 @@
+use bevy::prelude::*;
+
+use crate::modes::battle::encounter::{BattleInitiator, BattleRequest};
+use crate::modes::dungeon::dungeonmode::{CurrentFloor, DungeonAssets};
+use crate::modes::dungeon::dungeonplayer::{try_move_player, PlayerMoveCompleted};
+use crate::modes::dungeon::foes::move_foes;
+use crate::modes::dungeon::model::cell::GridDirection;
+use crate::modes::dungeon::model::grid::{DungeonTileLookup, RawDungeonFloors};
+use crate::modes::dungeon::model::tile::TileType;
+use crate::modes::mode_state::GameModeState;
+use crate::utils::utilresources::GameRng;
+
+/// How close the player is to getting attacked. It's the chance of a battle starting on the next
+/// step, so 0 right after a battle and 1 when the next step is a battle for sure. It carries over
+/// between floors.
+#[derive(Resource, Default, Debug)]
+pub struct DangerMeter(pub f32);
+
+impl DangerMeter {
+    /// Adds a step's worth of danger and rolls against it. The meter empties again when it goes
+    /// off.
+    pub fn step(&mut self, danger_per_step: f32, rng: &mut fastrand::Rng) -> bool {
+        self.0 = (self.0 + danger_per_step).clamp(0.0, 1.0);
+        let attacked = rng.f32() < self.0;
+        if attacked {
+            self.0 = 0.0;
+        }
+        attacked
+    }
+}
+
+pub struct EncounterPlugin;
+
+impl Plugin for EncounterPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<DangerMeter>().add_systems(
+            Update,
+            roll_encounters
+                .after(try_move_player)
+                .after(move_foes)
+                .run_if(in_state(GameModeState::InDungeon)),
+        );
+    }
+}
+
+/// Every finished step can start a battle, unless the player's about to be taken somewhere by
+/// stairs or the floor. Those get to finish first, and the step that ends up somewhere normal
+/// rolls instead. A FOE catching the player takes priority too.
+fn roll_encounters(
+    mut commands: Commands,
+    mut move_completed_reader: EventReader<PlayerMoveCompleted>,
+    raw_dungeon_floors: Res<Assets<RawDungeonFloors>>,
+    (dungeon_assets, current_floor): (Res<DungeonAssets>, Res<CurrentFloor>),
+    (dungeon_tile_lookup, tile_type_query): (Res<DungeonTileLookup>, Query<&TileType>),
+    (mut danger_meter, mut rng): (ResMut<DangerMeter>, ResMut<GameRng>),
+    mut next_state: ResMut<NextState<GameModeState>>,
+) {
+    if next_state.0.is_some() {
+        return;
+    }
+    let Some(floors) = raw_dungeon_floors.get(&dungeon_assets.raw_dungeon_data) else {
+        return;
+    };
+    for event in move_completed_reader.iter() {
+        let floor_takes_control = dungeon_tile_lookup
+            .try_get_tile(event.grid_position, GridDirection::Bottom)
+            .and_then(|tile_entity| tile_type_query.get(tile_entity).ok())
+            .is_some_and(|tile_type| tile_type.takes_control());
+        let on_stairs = floors
+            .floor(current_floor.0)
+            .stair_at(event.grid_position)
+            .is_some();
+        if floor_takes_control || on_stairs {
+            continue;
+        }
+        let Some(table) = floors.encounter_table(current_floor.0, event.grid_position) else {
+            continue;
+        };
+        let formation = rng.with(|rng| {
+            danger_meter
+                .step(table.danger_per_step, rng)
+                .then(|| table.roll_formation(rng))
+                .flatten()
+        });
+        if let Some(formation) = formation {
+            info!("encountered {} at {:?}", formation, event.grid_position);
+            commands.insert_resource(BattleRequest {
+                formation: formation.clone(),
+                terrain: floors.tileset(current_floor.0).map(str::to_string),
+                initiator: BattleInitiator::RandomEncounter,
+            });
+            next_state.set(GameModeState::LoadingBattle);
+            return;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use bevy::prelude::*;
+    use bevy::utils::HashMap;
+    use bevy_tweening::Animator;
+
+    use crate::modes::battle::encounter::{BattleInitiator, BattleRequest, FormationId};
+    use crate::modes::dungeon::dungeoninput::test_helpers::setup_keyboard_movement;
+    use crate::modes::dungeon::dungeonmode::test_helpers::setup_test_dungeon_floors;
+    use crate::modes::dungeon::dungeonmode::DungeonMode;
+    use crate::modes::dungeon::encounters::{DangerMeter, EncounterPlugin};
+    use crate::modes::dungeon::model::cell::test_helpers::setup_test_tile_preset_map;
+    use crate::modes::dungeon::model::cell::GridDirection;
+    use crate::modes::dungeon::model::encounter::{
+        RawEncounterFormation, RawEncounterRegion, RawEncounterTable,
+    };
+    use crate::modes::dungeon::model::grid::test_helpers::setup_dungeon_tile_lookup;
+    use crate::modes::dungeon::model::grid::{RawDungeonData, RawDungeonFloors};
+    use crate::modes::mode_state::GameModeState;
+    use crate::utils::utilresources::GameRng;
+
+    /// A corridor going right, where every step on the `deadly` table starts a battle.
+    fn setup(encounter_regions: Vec<RawEncounterRegion>) -> App {
+        let data = RawDungeonData {
+            dungeon_grid: vec![vec![1, 1, 1]],
+            player_start_position: [0, 0],
+            player_start_direction: GridDirection::Right,
+            encounter_table: Some("deadly".to_string()),
+            encounter_regions,
+            ..Default::default()
+        };
+        let mut encounter_tables = HashMap::new();
+        encounter_tables.insert(
+            "deadly".to_string(),
+            RawEncounterTable {
+                danger_per_step: 1.0,
+                formations: vec![RawEncounterFormation {
+                    formation: FormationId("slimes".to_string()),
+                    weight: 1,
+                }],
+            },
+        );
+        let mut app = App::new();
+        setup_test_tile_preset_map(&mut app);
+        setup_dungeon_tile_lookup(&mut app);
+        setup_test_dungeon_floors(
+            &mut app,
+            RawDungeonFloors {
+                floors: vec![data],
+                encounter_tables,
+                ..Default::default()
+            },
+        );
+        app.add_state::<GameModeState>()
+            .insert_resource(Input::<KeyCode>::default())
+            .insert_resource(GameRng(0))
+            .add_plugins(EncounterPlugin)
+            .add_systems(
+                Startup,
+                (
+                    DungeonMode::initialize_preset_map,
+                    (DungeonMode::spawn_grid, DungeonMode::setup_player)
+                        .after(DungeonMode::initialize_preset_map),
+                ),
+            );
+        setup_keyboard_movement(&mut app);
+        app.world
+            .resource_mut::<NextState<GameModeState>>()
+            .set(GameModeState::InDungeon);
+        app.update();
+        app
+    }
+
+    fn step_forward(app: &mut App) {
+        app.world
+            .resource_mut::<Input<KeyCode>>()
+            .press(KeyCode::Up);
+        app.update();
+        app.world
+            .resource_mut::<Input<KeyCode>>()
+            .release(KeyCode::Up);
+        app.world
+            .query::<&mut Animator<Transform>>()
+            .single_mut(&mut app.world)
+            .tweenable_mut()
+            .set_elapsed(Duration::from_secs(1));
+        app.update();
+        app.update();
+    }
+
+    fn state(app: &App) -> GameModeState {
+        *app.world.resource::<State<GameModeState>>().get()
+    }
+
+    #[test]
+    fn danger_builds_up_until_a_battle() {
+        let mut rng = fastrand::Rng::with_seed(3);
+        let mut meter = DangerMeter::default();
+        let mut steps = 0;
+        while !meter.step(0.25, &mut rng) {
+            steps += 1;
+            assert!(steps < 4, "the meter should be full after 4 steps");
+        }
+        assert_eq!(meter.0, 0.0);
+        assert!(!meter.step(0.0, &mut rng));
+    }
+
+    #[test]
+    fn same_seed_same_encounters() {
+        let run = |seed| {
+            let mut rng = fastrand::Rng::with_seed(seed);
+            let mut meter = DangerMeter::default();
+            (0..100)
+                .map(|_| meter.step(0.1, &mut rng))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(run(42), run(42));
+        assert!(run(42).contains(&true));
+    }
+
+    #[test]
+    fn walking_starts_a_battle() {
+        let mut app = setup(vec![]);
+        assert!(app.world.get_resource::<BattleRequest>().is_none());
+        step_forward(&mut app);
+        assert_eq!(state(&app), GameModeState::LoadingBattle);
+        assert_eq!(
+            *app.world.resource::<BattleRequest>(),
+            BattleRequest {
+                formation: FormationId("slimes".to_string()),
+                terrain: None,
+                initiator: BattleInitiator::RandomEncounter,
+            }
+        );
+    }
+
+    #[test]
+    fn safe_regions_never_start_battles() {
+        let mut app = setup(vec![RawEncounterRegion {
+            from: [0, 1],
+            to: [0, 2],
+            table: None,
+        }]);
+        step_forward(&mut app);
+        step_forward(&mut app);
+        assert_eq!(state(&app), GameModeState::InDungeon);
+        assert!(app.world.get_resource::<BattleRequest>().is_none());
+        assert_eq!(app.world.resource::<DangerMeter>().0, 0.0);
+    }
+}
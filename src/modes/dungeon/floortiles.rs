@@ -0,0 +1,463 @@
+use bevy::pbr::{FogFalloff, FogSettings};
+use bevy::prelude::*;
+use bevy_tweening::{Animator, AnimatorState};
+
+use crate::modes::dungeon::dungeonmode::CurrentFloor;
+use crate::modes::dungeon::dungeonplayer::{
+    destination, move_or_collide, player_transform, try_move_player, DungeonPlayer,
+    DungeonPlayerMovementState, MoveDirection, PlayerMoveCompleted,
+};
+use crate::modes::dungeon::model::cell::{GridDirection, GridPosition};
+use crate::modes::dungeon::model::grid::DungeonTileLookup;
+use crate::modes::dungeon::model::tile::TileType;
+use crate::modes::dungeon::stairs::FloorChange;
+use crate::modes::mode_state::GameModeState;
+use crate::modes::party::Party;
+
+/// How far the player can see while standing in darkness.
+const DARKNESS_RANGE: f32 = 1.5;
+
+/// Sent every time the player steps onto a damage floor.
+#[derive(Event)]
+pub struct FloorDamage {
+    pub amount: u32,
+}
+
+pub struct FloorTilesPlugin;
+
+impl Plugin for FloorTilesPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<FloorDamage>().add_systems(
+            Update,
+            (
+                trigger_floor_tiles.after(try_move_player),
+                fall_through_pits.after(try_move_player),
+                apply_floor_damage.after(trigger_floor_tiles),
+            )
+                .run_if(in_state(GameModeState::InDungeon)),
+        );
+    }
+}
+
+fn darkness() -> FogSettings {
+    FogSettings {
+        color: Color::BLACK,
+        falloff: FogFalloff::Linear {
+            start: 0.0,
+            end: DARKNESS_RANGE,
+        },
+        ..default()
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn trigger_floor_tiles(
+    mut commands: Commands,
+    mut move_completed_reader: EventReader<PlayerMoveCompleted>,
+    mut damage_writer: EventWriter<FloorDamage>,
+    dungeon_tile_lookup: Res<DungeonTileLookup>,
+    tile_type_query: Query<&TileType>,
+    mut player_query: Query<
+        (
+            Entity,
+            &mut Transform,
+            &mut Animator<Transform>,
+            &mut GridPosition,
+            &mut GridDirection,
+            &mut DungeonPlayerMovementState,
+            &mut MoveDirection,
+        ),
+        With<DungeonPlayer>,
+    >,
+) {
+    for event in move_completed_reader.iter() {
+        let Ok((
+            player,
+            mut transform,
+            mut animator,
+            mut grid_pos,
+            mut grid_direction,
+            mut movement_state,
+            mut move_direction,
+        )) = player_query.get_single_mut()
+        else {
+            return;
+        };
+        let tile_type = dungeon_tile_lookup
+            .try_get_tile(event.grid_position, GridDirection::Bottom)
+            .and_then(|tile_entity| tile_type_query.get(tile_entity).ok());
+
+        // darkness lasts until the player steps onto something that isn't dark
+        if tile_type == Some(&TileType::Darkness) {
+            commands.entity(player).insert(darkness());
+        } else {
+            commands.entity(player).remove::<FogSettings>();
+        }
+
+        // where the player gets put without walking there
+        let mut snap_to = None;
+        let mut slide_direction = None;
+        match tile_type {
+            Some(TileType::Damage(amount)) => {
+                damage_writer.send(FloorDamage { amount: *amount });
+            }
+            Some(TileType::Spinner(turn)) => {
+                let direction = match turn {
+                    GridDirection::Back => grid_direction.get_inverse_direction(),
+                    turn => grid_direction.rotated(*turn),
+                };
+                snap_to = Some((event.grid_position, direction));
+            }
+            Some(TileType::Teleporter { target, direction }) => {
+                snap_to = Some((*target, direction.unwrap_or(*grid_direction)));
+            }
+            Some(TileType::Conveyor(direction)) => slide_direction = Some(*direction),
+            Some(TileType::Ice) => slide_direction = Some(event.direction),
+            _ => {}
+        }
+        if let Some(slide_direction) = slide_direction {
+            // sliding into a wall just stops the player where they are
+            if let Some(end) = destination(
+                &dungeon_tile_lookup,
+                &tile_type_query,
+                event.grid_position,
+                slide_direction,
+            ) {
+                move_or_collide(
+                    Some(end),
+                    &mut movement_state,
+                    &mut animator,
+                    &mut grid_pos,
+                    &mut move_direction,
+                    slide_direction,
+                    1.0,
+                );
+            }
+        }
+        if let Some((position, direction)) = snap_to {
+            animator.state = AnimatorState::Paused;
+            *movement_state = DungeonPlayerMovementState::Stationary;
+            *grid_pos = position;
+            *grid_direction = direction;
+            *transform = player_transform(position, direction);
+        }
+    }
+}
+
+/// Pits work like stairs going down, except they always land on the same cell.
+pub fn fall_through_pits(
+    mut commands: Commands,
+    mut move_completed_reader: EventReader<PlayerMoveCompleted>,
+    dungeon_tile_lookup: Res<DungeonTileLookup>,
+    current_floor: Res<CurrentFloor>,
+    tile_type_query: Query<&TileType>,
+    player_query: Query<&GridDirection, With<DungeonPlayer>>,
+    mut next_state: ResMut<NextState<GameModeState>>,
+) {
+    for event in move_completed_reader.iter() {
+        let is_pit = dungeon_tile_lookup
+            .try_get_tile(event.grid_position, GridDirection::Bottom)
+            .and_then(|tile_entity| tile_type_query.get(tile_entity).ok())
+            .is_some_and(|tile_type| *tile_type == TileType::Pit);
+        let Ok(grid_direction) = player_query.get_single() else {
+            return;
+        };
+        if is_pit {
+            commands.insert_resource(FloorChange {
+                floor: current_floor.0 + 1,
+                grid_position: event.grid_position,
+                direction: *grid_direction,
+            });
+            next_state.set(GameModeState::ChangingFloor);
+        }
+    }
+}
+
+/// Damage floors hurt everyone in the party. It's game over once nobody's left standing, same as
+/// losing a battle.
+pub fn apply_floor_damage(
+    mut damage_reader: EventReader<FloorDamage>,
+    mut party: ResMut<Party>,
+    mut next_state: ResMut<NextState<GameModeState>>,
+) {
+    for damage in damage_reader.iter() {
+        info!("the floor did {} damage", damage.amount);
+        for member in party.members_mut() {
+            member.hp = member.hp.saturating_sub(damage.amount);
+        }
+        if party.is_wiped_out() {
+            next_state.set(GameModeState::GameOver);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use bevy::pbr::FogSettings;
+    use bevy::prelude::*;
+    use bevy_tweening::Animator;
+
+    use crate::modes::dungeon::dungeoninput::test_helpers::setup_keyboard_movement;
+    use crate::modes::dungeon::dungeonmode::test_helpers::setup_test_dungeon_floors;
+    use crate::modes::dungeon::dungeonmode::{CurrentFloor, DungeonMode};
+    use crate::modes::dungeon::dungeonplayer::{player_transform, DungeonPlayerMovementState};
+    use crate::modes::dungeon::floortiles::{FloorDamage, FloorTilesPlugin};
+    use crate::modes::dungeon::model::cell::test_helpers::setup_test_tile_preset_map;
+    use crate::modes::dungeon::model::cell::{GridDirection, GridPosition};
+    use crate::modes::dungeon::model::floor::{FloorKind, RawFloorTileData};
+    use crate::modes::dungeon::model::grid::test_helpers::setup_dungeon_tile_lookup;
+    use crate::modes::dungeon::model::grid::{DungeonTileLookup, RawDungeonData, RawDungeonFloors};
+    use crate::modes::dungeon::model::tile::TileType;
+    use crate::modes::dungeon::stairs::StairsPlugin;
+    use crate::modes::mode_state::GameModeState;
+    use crate::modes::party::test_helpers::test_party;
+    use crate::modes::party::Party;
+
+    /// A single row of `width` open cells with the player on the left end facing right.
+    fn make_row(width: usize, floor_tiles: Vec<(usize, FloorKind)>) -> RawDungeonData {
+        RawDungeonData {
+            dungeon_grid: vec![vec![1; width]],
+            player_start_position: [0, 0],
+            player_start_direction: GridDirection::Right,
+            floor_tiles: floor_tiles
+                .into_iter()
+                .map(|(col, kind)| RawFloorTileData {
+                    position: [0, col as u8],
+                    kind,
+                })
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    fn setup(floors: Vec<RawDungeonData>) -> App {
+        let mut app = App::new();
+        setup_test_tile_preset_map(&mut app);
+        setup_dungeon_tile_lookup(&mut app);
+        setup_test_dungeon_floors(
+            &mut app,
+            RawDungeonFloors {
+                floors,
+                ..Default::default()
+            },
+        );
+        app.add_state::<GameModeState>()
+            .insert_resource(Input::<KeyCode>::default())
+            .insert_resource(test_party())
+            .add_plugins((StairsPlugin, FloorTilesPlugin))
+            .add_systems(
+                Startup,
+                (
+                    DungeonMode::initialize_preset_map,
+                    (DungeonMode::spawn_grid, DungeonMode::setup_player)
+                        .after(DungeonMode::initialize_preset_map),
+                ),
+            );
+        setup_keyboard_movement(&mut app);
+        app.world
+            .resource_mut::<NextState<GameModeState>>()
+            .set(GameModeState::InDungeon);
+        app.update();
+        app
+    }
+
+    /// Fast forwards whatever the player is doing and lets the move finish.
+    fn finish_move(app: &mut App) {
+        app.world
+            .query::<&mut Animator<Transform>>()
+            .single_mut(&mut app.world)
+            .tweenable_mut()
+            .set_elapsed(Duration::from_secs(1));
+        app.update();
+    }
+
+    /// Takes one step forward and lets the floor do its thing.
+    fn step_forward(app: &mut App) {
+        app.world
+            .resource_mut::<Input<KeyCode>>()
+            .press(KeyCode::Up);
+        app.update();
+        app.world
+            .resource_mut::<Input<KeyCode>>()
+            .release(KeyCode::Up);
+        finish_move(app);
+    }
+
+    fn player(app: &mut App) -> (GridPosition, GridDirection, DungeonPlayerMovementState) {
+        let (grid_pos, grid_direction, movement_state) = app
+            .world
+            .query::<(&GridPosition, &GridDirection, &DungeonPlayerMovementState)>()
+            .single(&app.world);
+        (*grid_pos, *grid_direction, *movement_state)
+    }
+
+    #[test]
+    fn should_damage_player() {
+        let mut app = setup(vec![make_row(
+            2,
+            vec![(1, FloorKind::Damage { amount: 5 })],
+        )]);
+        step_forward(&mut app);
+        let events = app.world.resource::<Events<FloorDamage>>();
+        let damage: Vec<u32> = events
+            .get_reader()
+            .iter(events)
+            .map(|damage| damage.amount)
+            .collect();
+        assert_eq!(damage, vec![5]);
+        assert_eq!(app.world.resource::<Party>().members()[0].hp, 55);
+    }
+
+    #[test]
+    fn lethal_damage_is_game_over() {
+        let mut app = setup(vec![make_row(
+            2,
+            vec![(1, FloorKind::Damage { amount: 5 })],
+        )]);
+        app.world.resource_mut::<Party>().members_mut()[0].hp = 4;
+        step_forward(&mut app);
+        app.update();
+        assert!(app.world.resource::<Party>().is_wiped_out());
+        assert_eq!(
+            app.world.resource::<State<GameModeState>>().get(),
+            &GameModeState::GameOver
+        );
+    }
+
+    #[test]
+    fn should_fall_through_pit() {
+        let top = make_row(2, vec![(1, FloorKind::Pit)]);
+        let mut bottom = make_row(2, vec![]);
+        bottom.player_start_position = [0, 1];
+        let mut app = setup(vec![top, bottom]);
+        let pit_floor = app
+            .world
+            .resource::<DungeonTileLookup>()
+            .try_get_tile(GridPosition { row: 0, col: 1 }, GridDirection::Bottom)
+            .unwrap();
+        assert_eq!(app.world.get::<TileType>(pit_floor), Some(&TileType::Pit));
+
+        step_forward(&mut app);
+        // the floor change happens on the next state transition
+        app.update();
+        assert_eq!(app.world.resource::<CurrentFloor>().0, 1);
+        assert_eq!(
+            player(&mut app),
+            (
+                GridPosition { row: 0, col: 1 },
+                GridDirection::Right,
+                DungeonPlayerMovementState::Stationary
+            )
+        );
+    }
+
+    #[test]
+    fn should_spin_player() {
+        let spinner = FloorKind::Spinner {
+            turn: GridDirection::Back,
+        };
+        let mut app = setup(vec![make_row(2, vec![(1, spinner)])]);
+        step_forward(&mut app);
+        let (grid_pos, grid_direction, _) = player(&mut app);
+        assert_eq!(grid_pos, GridPosition { row: 0, col: 1 });
+        assert_eq!(grid_direction, GridDirection::Left);
+        let transform = *app
+            .world
+            .query_filtered::<&Transform, With<GridPosition>>()
+            .iter(&app.world)
+            .find(|transform| transform.translation.y > 0.9)
+            .unwrap();
+        assert_eq!(transform, player_transform(grid_pos, grid_direction));
+    }
+
+    #[test]
+    fn should_ride_conveyor() {
+        let conveyor = |direction| FloorKind::Conveyor { direction };
+        let mut app = setup(vec![make_row(
+            3,
+            vec![
+                (1, conveyor(GridDirection::Right)),
+                (2, conveyor(GridDirection::Right)),
+            ],
+        )]);
+        step_forward(&mut app);
+        assert_eq!(
+            player(&mut app),
+            (
+                GridPosition { row: 0, col: 2 },
+                GridDirection::Right,
+                DungeonPlayerMovementState::Walking
+            )
+        );
+
+        // the second conveyor pushes into the wall at the end of the row, so nothing happens
+        finish_move(&mut app);
+        app.update();
+        assert_eq!(
+            player(&mut app),
+            (
+                GridPosition { row: 0, col: 2 },
+                GridDirection::Right,
+                DungeonPlayerMovementState::Stationary
+            )
+        );
+    }
+
+    #[test]
+    fn should_slide_on_ice_until_off_it() {
+        let mut app = setup(vec![make_row(
+            5,
+            vec![(1, FloorKind::Ice), (2, FloorKind::Ice)],
+        )]);
+        step_forward(&mut app);
+        assert_eq!(player(&mut app).0, GridPosition { row: 0, col: 2 });
+        finish_move(&mut app);
+        assert_eq!(player(&mut app).0, GridPosition { row: 0, col: 3 });
+        finish_move(&mut app);
+        app.update();
+        assert_eq!(
+            player(&mut app),
+            (
+                GridPosition { row: 0, col: 3 },
+                GridDirection::Right,
+                DungeonPlayerMovementState::Stationary
+            )
+        );
+    }
+
+    #[test]
+    fn should_teleport() {
+        let teleporter = FloorKind::Teleporter {
+            target: [0, 3],
+            direction: Some(GridDirection::Left),
+        };
+        let mut app = setup(vec![make_row(4, vec![(1, teleporter)])]);
+        step_forward(&mut app);
+        assert_eq!(
+            player(&mut app),
+            (
+                GridPosition { row: 0, col: 3 },
+                GridDirection::Left,
+                DungeonPlayerMovementState::Stationary
+            )
+        );
+    }
+
+    #[test]
+    fn should_go_dark_in_darkness() {
+        let mut app = setup(vec![make_row(3, vec![(1, FloorKind::Darkness)])]);
+        let is_dark = |app: &mut App| {
+            app.world
+                .query_filtered::<(), (With<GridPosition>, With<FogSettings>)>()
+                .iter(&app.world)
+                .count()
+                == 1
+        };
+        step_forward(&mut app);
+        assert!(is_dark(&mut app));
+        step_forward(&mut app);
+        assert!(!is_dark(&mut app));
+    }
+}
@@ -0,0 +1,429 @@
+use std::time::Duration;
+
+use bevy::prelude::*;
+use bevy::utils::HashSet;
+use bevy_tweening::lens::TransformPositionLens;
+use bevy_tweening::{Animator, EaseMethod, Tween};
+
+use crate::modes::battle::encounter::{BattleInitiator, BattleRequest, FormationId};
+use crate::modes::dungeon::dungeonmode::{CurrentFloor, DungeonAssets, DungeonFloorEntity};
+use crate::modes::dungeon::dungeonplayer::{
+    destination, try_move_player, DungeonPlayer, PlayerMoveCompleted,
+};
+use crate::modes::dungeon::floortiles::{apply_floor_damage, fall_through_pits};
+use crate::modes::dungeon::model::cell::{GridDirection, GridPosType, GridPosition};
+use crate::modes::dungeon::model::foe::FoeBehavior;
+use crate::modes::dungeon::model::grid::{DungeonTileLookup, RawDungeonFloors};
+use crate::modes::dungeon::model::tile::TileType;
+use crate::modes::dungeon::stairs::take_stairs;
+use crate::modes::mode_state::GameModeState;
+use crate::utils::utilresources::GameRng;
+
+const FOE_STEP_DURATION: f32 = 0.3;
+
+/// An enemy walking around the floor. Its place on the grid is in its `GridPosition` and
+/// `GridDirection`, the same as the player's.
+#[derive(Component)]
+pub struct Foe {
+    /// Index into the floor's `foes`.
+    pub index: usize,
+    pub formation: FormationId,
+    pub behavior: FoeBehavior,
+    /// The route cell a patrolling FOE is heading for.
+    pub patrol_index: usize,
+    /// Steps the player gets to take before it moves again. Set when the player runs away from
+    /// its battle, so it doesn't catch them again straight away.
+    pub wait_steps: u32,
+}
+
+/// Every FOE the player has fought, as `(floor, index)`. They're gone for good, even after leaving
+/// the floor and coming back.
+#[derive(Resource, Default)]
+pub struct DefeatedFoes(pub HashSet<(usize, usize)>);
+
+pub struct FoePlugin;
+
+impl Plugin for FoePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<DefeatedFoes>().add_systems(
+            Update,
+            // stairs, pits and deadly floors win over getting caught
+            move_foes
+                .after(try_move_player)
+                .after(take_stairs)
+                .after(fall_through_pits)
+                .after(apply_floor_damage)
+                .run_if(in_state(GameModeState::InDungeon)),
+        );
+    }
+}
+
+fn foe_transform(grid_position: GridPosition, direction: GridDirection) -> Transform {
+    let position = grid_position.to_vec3(GridPosType::Foe);
+    Transform::from_translation(position).looking_at(position + Vec3::from(direction), Vec3::Y)
+}
+
+pub fn spawn_foes(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    dungeon_assets: Res<DungeonAssets>,
+    raw_dungeon_floors: Res<Assets<RawDungeonFloors>>,
+    current_floor: Res<CurrentFloor>,
+    defeated_foes: Res<DefeatedFoes>,
+) {
+    let data = dungeon_assets.current_floor(&raw_dungeon_floors, &current_floor);
+    for (index, raw_foe_data) in data.foes.iter().enumerate() {
+        if defeated_foes.0.contains(&(current_floor.0, index)) {
+            continue;
+        }
+        let grid_position: GridPosition = raw_foe_data.position.into();
+        commands.spawn((
+            SceneBundle {
+                scene: asset_server.load(raw_foe_data.model.as_str()),
+                transform: foe_transform(grid_position, raw_foe_data.direction),
+                ..default()
+            },
+            DungeonFloorEntity,
+            Foe {
+                index,
+                formation: raw_foe_data.formation.clone(),
+                behavior: raw_foe_data.behavior.clone(),
+                patrol_index: 0,
+                wait_steps: 0,
+            },
+            grid_position,
+            raw_foe_data.direction,
+        ));
+    }
+}
+
+/// Every FOE takes a step each time the player finishes one. Whoever ends up on the other's cell
+/// starts the FOE's battle. Nothing moves if the player is already on their way somewhere else.
+pub fn move_foes(
+    mut commands: Commands,
+    mut move_completed_reader: EventReader<PlayerMoveCompleted>,
+    (dungeon_tile_lookup, tile_type_query): (Res<DungeonTileLookup>, Query<&TileType>),
+    (current_floor, dungeon_assets, raw_dungeon_floors): (
+        Res<CurrentFloor>,
+        Res<DungeonAssets>,
+        Res<Assets<RawDungeonFloors>>,
+    ),
+    mut rng: ResMut<GameRng>,
+    mut foe_query: Query<
+        (
+            Entity,
+            &mut Foe,
+            &mut GridPosition,
+            &mut GridDirection,
+            &mut Transform,
+        ),
+        Without<DungeonPlayer>,
+    >,
+    mut next_state: ResMut<NextState<GameModeState>>,
+) {
+    if next_state.0.is_some() {
+        move_completed_reader.clear();
+        return;
+    }
+    let Some(player_position) = move_completed_reader
+        .iter()
+        .last()
+        .map(|event| event.grid_position)
+    else {
+        return;
+    };
+
+    let mut occupied: HashSet<GridPosition> = foe_query
+        .iter()
+        .map(|(_, _, position, ..)| *position)
+        .collect();
+    let mut caught = foe_query
+        .iter()
+        .find(|(_, _, position, ..)| **position == player_position)
+        .map(|(entity, ..)| entity);
+
+    if caught.is_none() {
+        for (entity, mut foe, mut grid_position, mut grid_direction, mut transform) in
+            foe_query.iter_mut()
+        {
+            let can_step = |direction| {
+                destination(
+                    &dungeon_tile_lookup,
+                    &tile_type_query,
+                    *grid_position,
+                    direction,
+                )
+                .is_some_and(|next| !occupied.contains(&next))
+            };
+            let foe = &mut *foe;
+            if foe.wait_steps > 0 {
+                foe.wait_steps -= 1;
+                continue;
+            }
+            let Some(direction) = rng.with(|rng| {
+                foe.behavior.next_step(
+                    *grid_position,
+                    player_position,
+                    &mut foe.patrol_index,
+                    can_step,
+                    rng,
+                )
+            }) else {
+                continue;
+            };
+            let Some(next) = grid_position.try_translated(direction) else {
+                continue;
+            };
+            occupied.remove(&*grid_position);
+            occupied.insert(next);
+            *transform = foe_transform(*grid_position, direction);
+            commands.entity(entity).insert(Animator::new(Tween::new(
+                EaseMethod::Linear,
+                Duration::from_secs_f32(FOE_STEP_DURATION),
+                TransformPositionLens {
+                    start: grid_position.to_vec3(GridPosType::Foe),
+                    end: next.to_vec3(GridPosType::Foe),
+                },
+            )));
+            *grid_position = next;
+            *grid_direction = direction;
+            if next == player_position {
+                caught = Some(entity);
+                break;
+            }
+        }
+    }
+
+    let Some(entity) = caught else {
+        return;
+    };
+    let Ok((_, foe, ..)) = foe_query.get(entity) else {
+        return;
+    };
+    info!("FOE {} caught the player", foe.index);
+    // it stays where it is until the battle's over, see `apply_battle_outcome`
+    commands.insert_resource(BattleRequest {
+        formation: foe.formation.clone(),
+        terrain: raw_dungeon_floors
+            .get(&dungeon_assets.raw_dungeon_data)
+            .and_then(|floors| floors.tileset(current_floor.0))
+            .map(str::to_string),
+        initiator: BattleInitiator::Foe {
+            floor: current_floor.0,
+            index: foe.index,
+        },
+    });
+    next_state.set(GameModeState::LoadingBattle);
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use bevy::prelude::*;
+    use bevy_tweening::Animator;
+
+    use crate::modes::battle::encounter::{BattleInitiator, BattleRequest, FormationId};
+    use crate::modes::dungeon::dungeoninput::test_helpers::setup_keyboard_movement;
+    use crate::modes::dungeon::dungeonmode::test_helpers::setup_test_dungeon_assets;
+    use crate::modes::dungeon::dungeonmode::DungeonMode;
+    use crate::modes::dungeon::dungeonplayer::{
+        try_move_player, DungeonPlayer, PlayerMoveCompleted,
+    };
+    use crate::modes::dungeon::foes::{move_foes, spawn_foes, DefeatedFoes, Foe, FoePlugin};
+    use crate::modes::dungeon::model::cell::test_helpers::setup_test_tile_preset_map;
+    use crate::modes::dungeon::model::cell::{GridDirection, GridPosition};
+    use crate::modes::dungeon::model::edge::{EdgeKind, RawEdgeData};
+    use crate::modes::dungeon::model::foe::{FoeBehavior, RawFoeData};
+    use crate::modes::dungeon::model::grid::test_helpers::setup_dungeon_tile_lookup;
+    use crate::modes::dungeon::model::grid::RawDungeonData;
+    use crate::modes::mode_state::GameModeState;
+    use crate::utils::utilresources::GameRng;
+
+    fn make_foe(position: [u8; 2], behavior: FoeBehavior) -> RawFoeData {
+        RawFoeData {
+            position,
+            direction: GridDirection::Left,
+            formation: FormationId("slime_king".to_string()),
+            model: "irrelevant.glb#Scene0".to_string(),
+            behavior,
+        }
+    }
+
+    /// Two rows of three cells, with the player in the bottom middle facing up.
+    fn setup(foe: RawFoeData, edges: Vec<RawEdgeData>) -> App {
+        let data = RawDungeonData {
+            dungeon_grid: vec![vec![1, 1, 1], vec![1, 1, 1]],
+            player_start_position: [1, 1],
+            edges,
+            foes: vec![foe],
+            ..Default::default()
+        };
+        let mut app = App::new();
+        // the FOE models get loaded for real
+        app.add_plugins(TaskPoolPlugin::default());
+        setup_test_tile_preset_map(&mut app);
+        setup_dungeon_tile_lookup(&mut app);
+        setup_test_dungeon_assets(&mut app, data);
+        app.add_state::<GameModeState>()
+            .insert_resource(Input::<KeyCode>::default())
+            .insert_resource(GameRng(0))
+            .add_plugins(FoePlugin)
+            .add_systems(
+                Startup,
+                (
+                    DungeonMode::initialize_preset_map,
+                    (
+                        DungeonMode::spawn_grid,
+                        DungeonMode::setup_player,
+                        spawn_foes,
+                    )
+                        .after(DungeonMode::initialize_preset_map),
+                ),
+            );
+        setup_keyboard_movement(&mut app);
+        app.world
+            .resource_mut::<NextState<GameModeState>>()
+            .set(GameModeState::InDungeon);
+        app.update();
+        app
+    }
+
+    fn step(app: &mut App, key: KeyCode) {
+        app.world.resource_mut::<Input<KeyCode>>().press(key);
+        app.update();
+        app.world.resource_mut::<Input<KeyCode>>().release(key);
+        app.world
+            .query_filtered::<&mut Animator<Transform>, With<DungeonPlayer>>()
+            .single_mut(&mut app.world)
+            .tweenable_mut()
+            .set_elapsed(Duration::from_secs(1));
+        app.update();
+        app.update();
+    }
+
+    fn foe_position(app: &mut App) -> Option<GridPosition> {
+        app.world
+            .query_filtered::<&GridPosition, With<Foe>>()
+            .get_single(&app.world)
+            .ok()
+            .copied()
+    }
+
+    fn assert_caught(app: &mut App) {
+        assert_eq!(
+            *app.world.resource::<State<GameModeState>>().get(),
+            GameModeState::LoadingBattle
+        );
+        assert_eq!(
+            *app.world.resource::<BattleRequest>(),
+            BattleRequest {
+                formation: FormationId("slime_king".to_string()),
+                terrain: None,
+                initiator: BattleInitiator::Foe { floor: 0, index: 0 },
+            }
+        );
+        // it's only gone once it's been beaten
+        let player_position = *app
+            .world
+            .query_filtered::<&GridPosition, With<DungeonPlayer>>()
+            .single(&app.world);
+        assert_eq!(foe_position(app), Some(player_position));
+        assert!(app.world.resource::<DefeatedFoes>().0.is_empty());
+    }
+
+    #[test]
+    fn foes_catch_the_player() {
+        let mut app = setup(
+            make_foe(
+                [0, 2],
+                FoeBehavior::Patrol {
+                    route: vec![[0, 0]],
+                },
+            ),
+            vec![],
+        );
+        step(&mut app, KeyCode::Up);
+        assert_caught(&mut app);
+    }
+
+    #[test]
+    fn foes_stay_put_if_the_player_is_leaving() {
+        let mut app = setup(
+            make_foe(
+                [0, 2],
+                FoeBehavior::Patrol {
+                    route: vec![[0, 0]],
+                },
+            ),
+            vec![],
+        );
+        // stands in for the stairs taking the player away
+        app.add_systems(
+            Update,
+            (|mut move_completed_reader: EventReader<PlayerMoveCompleted>,
+              mut next_state: ResMut<NextState<GameModeState>>| {
+                if move_completed_reader.iter().count() > 0 {
+                    next_state.set(GameModeState::ChangingFloor);
+                }
+            })
+            .after(try_move_player)
+            .before(move_foes),
+        );
+        step(&mut app, KeyCode::Up);
+        assert_eq!(
+            *app.world.resource::<State<GameModeState>>().get(),
+            GameModeState::ChangingFloor
+        );
+        assert_eq!(
+            foe_position(&mut app),
+            Some(GridPosition { row: 0, col: 2 })
+        );
+    }
+
+    #[test]
+    fn walking_into_a_foe_starts_its_battle() {
+        let mut app = setup(
+            make_foe(
+                [0, 0],
+                FoeBehavior::Patrol {
+                    route: vec![[0, 0]],
+                },
+            ),
+            vec![],
+        );
+        app.world
+            .resource_mut::<Input<KeyCode>>()
+            .press(KeyCode::ShiftLeft);
+        step(&mut app, KeyCode::Left);
+        assert_eq!(
+            foe_position(&mut app),
+            Some(GridPosition { row: 0, col: 0 })
+        );
+        assert_eq!(
+            *app.world.resource::<State<GameModeState>>().get(),
+            GameModeState::InDungeon
+        );
+        step(&mut app, KeyCode::Up);
+        assert_caught(&mut app);
+    }
+
+    #[test]
+    fn foes_follow_the_walls() {
+        let mut app = setup(
+            make_foe([1, 2], FoeBehavior::Chase { sight: 5 }),
+            vec![RawEdgeData {
+                position: [1, 2],
+                direction: GridDirection::Left,
+                kind: EdgeKind::Wall,
+                key: None,
+            }],
+        );
+        step(&mut app, KeyCode::Up);
+        // the wall's in the way, so it goes around
+        assert_eq!(
+            foe_position(&mut app),
+            Some(GridPosition { row: 0, col: 2 })
+        );
+    }
+}
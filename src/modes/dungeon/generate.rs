@@ -0,0 +1,485 @@
+use bevy::asset::Assets;
+use bevy::prelude::{info, Res, ResMut, Resource};
+
+use crate::modes::dungeon::dungeonmode::DungeonAssets;
+use crate::modes::dungeon::model::cell::{GridDirection, GridPosition};
+use crate::modes::dungeon::model::grid::{RawDungeonData, RawDungeonFloors, RawDungeonItemData};
+use crate::modes::dungeon::model::items::ItemId;
+
+/// How many times we try to place each room before giving up on it.
+const ROOM_ATTEMPTS: usize = 10;
+
+/// Everything `generate` can be tuned with.
+#[derive(Clone, Debug)]
+pub struct GeneratorParams {
+    pub width: u8,
+    pub height: u8,
+    /// How many rooms to try for. Fewer can end up being placed if they don't fit.
+    pub room_count: usize,
+    pub min_room_size: u8,
+    pub max_room_size: u8,
+    /// Extra dead end corridors to dig, per 100 cells of grid.
+    pub corridor_density: f32,
+    /// Chance (0 to 1) that each room gets an extra corridor on top of the ones needed to
+    /// connect everything. These are what make loops.
+    pub loop_ratio: f32,
+    /// Number of items to scatter around. Capped by the number of open cells.
+    pub item_budget: usize,
+    /// Ids of the items to pick from when scattering them, all equally likely.
+    pub item_pool: Vec<ItemId>,
+}
+
+impl Default for GeneratorParams {
+    fn default() -> Self {
+        GeneratorParams {
+            width: 24,
+            height: 24,
+            room_count: 6,
+            min_room_size: 2,
+            max_room_size: 5,
+            corridor_density: 0.5,
+            loop_ratio: 0.25,
+            item_budget: 3,
+            item_pool: ["Polaroid", "Key", "Maxwell"]
+                .into_iter()
+                .map(ItemId::new)
+                .collect(),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Room {
+    row: usize,
+    col: usize,
+    height: usize,
+    width: usize,
+}
+
+impl Room {
+    fn center(&self) -> (usize, usize) {
+        (self.row + self.height / 2, self.col + self.width / 2)
+    }
+
+    /// Whether the rooms overlap or touch, we want at least one wall between them.
+    fn touches(&self, other: &Room) -> bool {
+        self.row <= other.row + other.height
+            && other.row <= self.row + self.height
+            && self.col <= other.col + other.width
+            && other.col <= self.col + self.width
+    }
+}
+
+struct Generator {
+    rng: fastrand::Rng,
+    grid: Vec<Vec<u8>>,
+}
+
+impl Generator {
+    fn height(&self) -> usize {
+        self.grid.len()
+    }
+
+    fn width(&self) -> usize {
+        self.grid[0].len()
+    }
+
+    fn carve(&mut self, row: usize, col: usize) {
+        self.grid[row][col] = 1;
+    }
+
+    fn is_open(&self, row: usize, col: usize) -> bool {
+        self.grid
+            .get(row)
+            .and_then(|cells| cells.get(col))
+            .is_some_and(|val| *val > 0)
+    }
+
+    fn open_cells(&self) -> Vec<(usize, usize)> {
+        (0..self.height())
+            .flat_map(|row| (0..self.width()).map(move |col| (row, col)))
+            .filter(|&(row, col)| self.is_open(row, col))
+            .collect()
+    }
+
+    fn place_rooms(&mut self, params: &GeneratorParams) -> Vec<Room> {
+        let max_height = (params.max_room_size as usize).clamp(1, self.height());
+        let max_width = (params.max_room_size as usize).clamp(1, self.width());
+        let min_height = (params.min_room_size as usize).clamp(1, max_height);
+        let min_width = (params.min_room_size as usize).clamp(1, max_width);
+
+        let mut rooms: Vec<Room> = vec![];
+        for _ in 0..params.room_count * ROOM_ATTEMPTS {
+            if rooms.len() == params.room_count {
+                break;
+            }
+            let height = self.rng.usize(min_height..=max_height);
+            let width = self.rng.usize(min_width..=max_width);
+            let room = Room {
+                row: self.rng.usize(0..=self.height() - height),
+                col: self.rng.usize(0..=self.width() - width),
+                height,
+                width,
+            };
+            if rooms.iter().all(|other| !room.touches(other)) {
+                rooms.push(room);
+            }
+        }
+        if rooms.is_empty() {
+            // there has to be somewhere to stand
+            rooms.push(Room {
+                row: self.height() / 2,
+                col: self.width() / 2,
+                height: 1,
+                width: 1,
+            });
+        }
+
+        for room in rooms.iter() {
+            for row in room.row..room.row + room.height {
+                for col in room.col..room.col + room.width {
+                    self.carve(row, col);
+                }
+            }
+        }
+        rooms
+    }
+
+    /// Digs an L shaped corridor between two cells.
+    fn connect(&mut self, from: (usize, usize), to: (usize, usize)) {
+        let corner = if self.rng.bool() {
+            (from.0, to.1)
+        } else {
+            (to.0, from.1)
+        };
+        for (start, end) in [(from, corner), (corner, to)] {
+            for row in start.0.min(end.0)..=start.0.max(end.0) {
+                for col in start.1.min(end.1)..=start.1.max(end.1) {
+                    self.carve(row, col);
+                }
+            }
+        }
+    }
+
+    fn connect_rooms(&mut self, rooms: &[Room], loop_ratio: f32) {
+        // every room links back to one placed before it, so everything ends up connected
+        for i in 1..rooms.len() {
+            let j = self.rng.usize(0..i);
+            self.connect(rooms[i].center(), rooms[j].center());
+        }
+        if rooms.len() < 2 {
+            return;
+        }
+        for i in 0..rooms.len() {
+            if self.rng.f32() < loop_ratio {
+                let mut j = self.rng.usize(0..rooms.len() - 1);
+                if j >= i {
+                    j += 1;
+                }
+                self.connect(rooms[i].center(), rooms[j].center());
+            }
+        }
+    }
+
+    /// Dead ends branch off cells that are already open, so they can't cut anything off.
+    fn dig_dead_ends(&mut self, corridor_density: f32) {
+        let cells = self.width() * self.height();
+        let count = (corridor_density.max(0.0) * cells as f32 / 100.0).round() as usize;
+        for _ in 0..count {
+            let open_cells = self.open_cells();
+            let (row, col) = open_cells[self.rng.usize(0..open_cells.len())];
+            let direction = GridDirection::HORIZONTAL[self.rng.usize(0..4)];
+            let mut position = GridPosition { row, col };
+            for _ in 0..self.rng.usize(2..=6) {
+                match position.try_translated(direction) {
+                    Some(next) if next.row < self.height() && next.col < self.width() => {
+                        position = next;
+                        self.carve(position.row, position.col);
+                    }
+                    _ => break,
+                }
+            }
+        }
+    }
+
+    /// Faces somewhere open if there's anywhere to go, staring at a wall is a bad first
+    /// impression.
+    fn start_direction(&mut self, start: (usize, usize)) -> GridDirection {
+        let position = GridPosition {
+            row: start.0,
+            col: start.1,
+        };
+        let open_directions: Vec<GridDirection> = GridDirection::HORIZONTAL
+            .into_iter()
+            .filter(|direction| {
+                position
+                    .try_translated(*direction)
+                    .is_some_and(|next| self.is_open(next.row, next.col))
+            })
+            .collect();
+        if open_directions.is_empty() {
+            GridDirection::Forward
+        } else {
+            open_directions[self.rng.usize(0..open_directions.len())]
+        }
+    }
+
+    fn scatter_items(
+        &mut self,
+        start: (usize, usize),
+        budget: usize,
+        pool: &[ItemId],
+    ) -> Vec<RawDungeonItemData> {
+        if pool.is_empty() {
+            return vec![];
+        }
+        let mut cells: Vec<(usize, usize)> = self
+            .open_cells()
+            .into_iter()
+            .filter(|cell| *cell != start)
+            .collect();
+        self.rng.shuffle(&mut cells);
+        cells
+            .into_iter()
+            .take(budget)
+            .map(|(row, col)| RawDungeonItemData {
+                item_type: pool[self.rng.usize(0..pool.len())].clone(),
+                item_position: [row as u8, col as u8],
+                key: None,
+            })
+            .collect()
+    }
+}
+
+/// Builds a random floor. The same `seed` and `params` always give the same floor, and every open
+/// cell can be reached from the start, so the result always passes `RawDungeonData::validate`.
+pub fn generate(seed: u64, params: &GeneratorParams) -> RawDungeonData {
+    let height = params.height.max(1) as usize;
+    let width = params.width.max(1) as usize;
+    let mut generator = Generator {
+        rng: fastrand::Rng::with_seed(seed),
+        grid: vec![vec![0; width]; height],
+    };
+
+    let rooms = generator.place_rooms(params);
+    generator.connect_rooms(&rooms, params.loop_ratio);
+    generator.dig_dead_ends(params.corridor_density);
+
+    let start = rooms[0].center();
+    let player_start_direction = generator.start_direction(start);
+    let items = generator.scatter_items(start, params.item_budget, &params.item_pool);
+    RawDungeonData {
+        dungeon_grid: generator.grid,
+        player_start_position: [start.0 as u8, start.1 as u8],
+        player_start_direction,
+        items,
+        seed,
+        ..Default::default()
+    }
+}
+
+/// Set by running the game with `--generate <seed>`. The loaded dungeon is swapped for a single
+/// floor from `generate` with the default params, to try the generator out in game.
+#[derive(Resource, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct GeneratedDungeon(pub u64);
+
+impl GeneratedDungeon {
+    /// Looks for `--generate <seed>` in the command line arguments. A missing or unreadable seed
+    /// is ignored.
+    pub fn from_args(args: impl IntoIterator<Item = String>) -> Option<Self> {
+        let mut args = args.into_iter();
+        args.find(|arg| arg == "--generate")?;
+        args.next()?.parse().ok().map(GeneratedDungeon)
+    }
+}
+
+/// Runs before the dungeon gets validated, so a generated floor goes through the same checks as
+/// a loaded one.
+pub fn use_generated_dungeon(
+    generated_dungeon: Option<Res<GeneratedDungeon>>,
+    dungeon_assets: Res<DungeonAssets>,
+    mut raw_dungeon_floors: ResMut<Assets<RawDungeonFloors>>,
+) {
+    let (Some(generated_dungeon), Some(floors)) = (
+        generated_dungeon,
+        raw_dungeon_floors.get_mut(&dungeon_assets.raw_dungeon_data),
+    ) else {
+        return;
+    };
+    info!("generating a dungeon from seed {}", generated_dungeon.0);
+    floors.floors = vec![generate(generated_dungeon.0, &GeneratorParams::default())];
+    floors.start_floor = 0;
+}
+
+#[cfg(test)]
+mod test {
+    use bevy::prelude::*;
+
+    use crate::modes::dungeon::dungeonmode::test_helpers::setup_test_dungeon_assets;
+    use crate::modes::dungeon::dungeonmode::DungeonMode;
+    use crate::modes::dungeon::generate::{generate, GeneratedDungeon, GeneratorParams};
+    use crate::modes::dungeon::model::cell::test_helpers::setup_test_tile_preset_map;
+    use crate::modes::dungeon::model::cell::{GridDirection, GridPosition};
+    use crate::modes::dungeon::model::grid::test_helpers::setup_dungeon_tile_lookup;
+    use crate::modes::dungeon::model::grid::{DungeonTileLookup, RawDungeonData};
+    use crate::modes::dungeon::model::tile::TileType;
+
+    fn param_sets() -> Vec<GeneratorParams> {
+        vec![
+            GeneratorParams::default(),
+            GeneratorParams {
+                width: 60,
+                height: 12,
+                room_count: 12,
+                corridor_density: 2.0,
+                loop_ratio: 1.0,
+                item_budget: 10,
+                ..default()
+            },
+            GeneratorParams {
+                width: 5,
+                height: 5,
+                room_count: 20,
+                min_room_size: 4,
+                max_room_size: 9,
+                ..default()
+            },
+            GeneratorParams {
+                width: 1,
+                height: 1,
+                room_count: 0,
+                item_budget: 4,
+                ..default()
+            },
+            GeneratorParams {
+                width: 1,
+                height: 30,
+                room_count: 3,
+                min_room_size: 1,
+                max_room_size: 1,
+                corridor_density: 5.0,
+                ..default()
+            },
+            GeneratorParams {
+                width: 255,
+                height: 255,
+                room_count: 40,
+                max_room_size: 12,
+                ..default()
+            },
+        ]
+    }
+
+    /// Everything that makes up the layout, since `RawDungeonData` doesn't compare.
+    #[allow(clippy::type_complexity)]
+    fn summarize(data: &RawDungeonData) -> (Vec<Vec<u8>>, [u8; 2], GridDirection, Vec<[u8; 2]>) {
+        (
+            data.dungeon_grid.clone(),
+            data.player_start_position,
+            data.player_start_direction,
+            data.items.iter().map(|item| item.item_position).collect(),
+        )
+    }
+
+    #[test]
+    fn generated_floors_are_valid() {
+        for params in param_sets() {
+            for seed in 0..50 {
+                let data = generate(seed, &params);
+                assert_eq!(data.height(), params.height.max(1) as usize);
+                assert_eq!(data.width(), params.width.max(1) as usize);
+                assert_eq!(data.validate(), Ok(()), "seed {} with {:?}", seed, params);
+            }
+        }
+    }
+
+    #[test]
+    fn generation_is_deterministic() {
+        let params = GeneratorParams::default();
+        assert_eq!(
+            summarize(&generate(7, &params)),
+            summarize(&generate(7, &params))
+        );
+        assert_ne!(
+            summarize(&generate(7, &params)).0,
+            summarize(&generate(8, &params)).0
+        );
+    }
+
+    #[test]
+    fn items_fill_the_budget() {
+        let params = GeneratorParams {
+            item_budget: 8,
+            ..default()
+        };
+        for seed in 0..20 {
+            let data = generate(seed, &params);
+            assert_eq!(data.items.len(), 8);
+        }
+
+        // a single cell has nowhere to put items that isn't the start
+        let params = GeneratorParams {
+            width: 1,
+            height: 1,
+            item_budget: 3,
+            ..default()
+        };
+        assert!(generate(0, &params).items.is_empty());
+    }
+
+    #[test]
+    fn should_spawn_generated_floors() {
+        for seed in 0..10 {
+            let data = generate(seed, &param_sets()[seed as usize % 3]);
+            let open_cells: Vec<GridPosition> = data
+                .dungeon_grid
+                .iter()
+                .enumerate()
+                .flat_map(|(row, cells)| {
+                    cells
+                        .iter()
+                        .enumerate()
+                        .filter(|(_, val)| **val > 0)
+                        .map(move |(col, _)| GridPosition { row, col })
+                })
+                .collect();
+            let (height, width) = (data.height(), data.width());
+
+            let mut app = App::new();
+            setup_test_tile_preset_map(&mut app);
+            setup_dungeon_tile_lookup(&mut app);
+            setup_test_dungeon_assets(&mut app, data);
+            app.add_systems(
+                Startup,
+                (DungeonMode::initialize_preset_map, DungeonMode::spawn_grid).chain(),
+            );
+            app.update();
+
+            let lookup = app.world.resource::<DungeonTileLookup>();
+            assert!(lookup.contains(GridPosition {
+                row: height - 1,
+                col: width - 1
+            }));
+            for position in open_cells {
+                // every open cell has a floor to stand on
+                let floor = lookup
+                    .try_get_tile(position, GridDirection::Bottom)
+                    .unwrap();
+                assert_eq!(app.world.get::<TileType>(floor), Some(&TileType::Basic));
+            }
+        }
+    }
+
+    #[test]
+    fn seed_comes_from_the_command_line() {
+        let args =
+            |args: &[&str]| GeneratedDungeon::from_args(args.iter().map(|arg| arg.to_string()));
+        assert_eq!(
+            args(&["dark-adapters", "--generate", "42"]),
+            Some(GeneratedDungeon(42))
+        );
+        assert_eq!(args(&["dark-adapters"]), None);
+        assert_eq!(args(&["dark-adapters", "--generate"]), None);
+        assert_eq!(args(&["dark-adapters", "--generate", "lots"]), None);
+    }
+}
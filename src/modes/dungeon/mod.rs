@@ -1,3 +1,12 @@
+pub mod aftermath;
+pub mod doors;
+pub mod dungeoninput;
 pub mod dungeonmode;
 pub mod dungeonplayer;
+pub mod encounters;
+pub mod floortiles;
+pub mod foes;
+pub mod generate;
 pub mod model;
+pub mod pickup;
+pub mod stairs;
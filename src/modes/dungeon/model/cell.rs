@@ -9,32 +9,49 @@ use bevy_mod_picking::prelude::RaycastPickTarget;
 use bevy_mod_picking::PickableBundle;
 use serde::{Deserialize, Serialize};
 
+use crate::modes::dungeon::dungeonmode::DungeonFloorEntity;
 use crate::modes::dungeon::model::grid::DungeonTileLookup;
 use crate::modes::dungeon::model::tile::Tile;
 
+const DECAL_OFFSET: f32 = 0.001;
+
+/// Which of a cell's six faces have a tile on them, one bit per `GridDirection`. Horizontal faces
+/// get walls, `Top` gets the ceiling and `Bottom` gets the floor.
 #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
-pub enum TileBundlePreset {
-    Empty,
-    Open,
-    Closed, // gay baby jail
-    ForwardWall,
-    RightWall,
-    BackWall,
-    LeftWall,
-    ForwardLeftCorner,
-    ForwardRightCorner,
-    BackRightCorner,
-    BackLeftCorner,
-    ForwardBackHallway,
-    LeftRightHallway,
-    ForwardHallwayEnd,
-    RightHallwayEnd,
-    BackHallwayEnd,
-    LeftHallwayEnd,
+pub struct TileBundlePreset(u8);
+
+impl TileBundlePreset {
+    /// Nothing at all, for cells that aren't part of the dungeon.
+    pub const EMPTY: Self = TileBundlePreset(0);
+    /// A floor and a ceiling but no walls.
+    pub const OPEN: Self =
+        TileBundlePreset((1 << GridDirection::Top as u8) | (1 << GridDirection::Bottom as u8));
+    /// Every face is covered.
+    pub const CLOSED: Self = TileBundlePreset(0b11_1111);
+
+    /// Every one of the 64 possible presets.
+    pub fn all() -> impl Iterator<Item = Self> {
+        (0..=Self::CLOSED.0).map(TileBundlePreset)
+    }
+
+    pub fn with_face(self, direction: GridDirection) -> Self {
+        TileBundlePreset(self.0 | (1 << direction as u8))
+    }
+
+    pub fn has_face(self, direction: GridDirection) -> bool {
+        self.0 & (1 << direction as u8) != 0
+    }
 }
 
+/// Every preset, built once for each tileset.
 #[derive(Resource)]
-pub struct TileBundlePresetMap(pub HashMap<TileBundlePreset, TileBundle>);
+pub struct TileBundlePresetMap(pub HashMap<String, HashMap<TileBundlePreset, TileBundle>>);
+
+impl TileBundlePresetMap {
+    pub fn get(&self, tileset: &str, preset: TileBundlePreset) -> Option<&TileBundle> {
+        self.0.get(tileset).and_then(|presets| presets.get(&preset))
+    }
+}
 
 impl Default for TileBundlePresetMap {
     fn default() -> Self {
@@ -42,9 +59,10 @@ impl Default for TileBundlePresetMap {
     }
 }
 
-#[derive(Hash, Clone, Copy, Eq, PartialEq, Serialize, Deserialize, Component, Debug)]
+#[derive(Hash, Clone, Copy, Eq, PartialEq, Serialize, Deserialize, Component, Debug, Default)]
 pub enum GridDirection {
     Left = 0,
+    #[default]
     Forward = 1,
     Right = 2,
     Back = 3,
@@ -53,7 +71,24 @@ pub enum GridDirection {
 }
 
 impl GridDirection {
-    fn get_tile_transform(direction: GridDirection) -> Transform {
+    pub const ALL: [GridDirection; 6] = [
+        GridDirection::Left,
+        GridDirection::Forward,
+        GridDirection::Right,
+        GridDirection::Back,
+        GridDirection::Top,
+        GridDirection::Bottom,
+    ];
+
+    /// The four directions the player can face and move in.
+    pub const HORIZONTAL: [GridDirection; 4] = [
+        GridDirection::Left,
+        GridDirection::Forward,
+        GridDirection::Right,
+        GridDirection::Back,
+    ];
+
+    pub fn get_tile_transform(direction: GridDirection) -> Transform {
         // these tiny offsets are here to prevent z-fighting
         match direction {
             GridDirection::Left => Transform::from_xyz(-0.5000001, 1.0, 0.0)
@@ -70,6 +105,14 @@ impl GridDirection {
                 .with_rotation(Quat::from_rotation_x(-PI / 2.0)),
         }
     }
+    /// Where a decal goes relative to the tile on this face: a hair in front of it, towards the
+    /// middle of the cell.
+    pub fn decal_transform(self) -> Transform {
+        let tile_rotation = GridDirection::get_tile_transform(self).rotation;
+        let inwards = -Vec3::from(self);
+        Transform::from_translation(tile_rotation.inverse() * inwards * DECAL_OFFSET)
+    }
+
     fn set_tile_transform(tile: &mut Tile, transform: Transform) {
         tile.set_tile_transform(transform);
     }
@@ -78,12 +121,12 @@ impl GridDirection {
         match rotate_dir {
             GridDirection::Left => {
                 let num_self = self as i8;
-                let new_dir: GridDirection = ((num_self + 3) % 4).try_into().unwrap();
+                let new_dir: GridDirection = ((num_self + 3) % 4).into();
                 new_dir
             }
             GridDirection::Right => {
                 let num_self = self as i8;
-                let new_dir: GridDirection = ((num_self + 1) % 4).try_into().unwrap();
+                let new_dir: GridDirection = ((num_self + 1) % 4).into();
                 new_dir
             }
             _ => self,
@@ -100,6 +143,23 @@ impl GridDirection {
             GridDirection::Bottom => GridDirection::Top,
         }
     }
+
+    /// Turns a direction relative to the way something is `facing` (`Forward`, `Back`, `Left` or
+    /// `Right`) into one on the grid. `Top` and `Bottom` are the same either way.
+    pub fn relative_to_absolute(self, facing: GridDirection) -> Self {
+        match self {
+            GridDirection::Top | GridDirection::Bottom => self,
+            _ => ((facing as i8 + self as i8 - GridDirection::Forward as i8 + 4) % 4).into(),
+        }
+    }
+
+    /// The opposite of `relative_to_absolute`.
+    pub fn absolute_to_relative(self, facing: GridDirection) -> Self {
+        match self {
+            GridDirection::Top | GridDirection::Bottom => self,
+            _ => ((self as i8 - facing as i8 + GridDirection::Forward as i8 + 4) % 4).into(),
+        }
+    }
 }
 
 impl From<i8> for GridDirection {
@@ -134,7 +194,7 @@ pub enum DungeonCellType {
     Basic,
 }
 
-#[derive(Component, Copy, Clone, Debug)]
+#[derive(Component, Copy, Clone, Debug, PartialEq, Eq, Hash)]
 pub struct GridPosition {
     pub row: usize,
     pub col: usize,
@@ -145,10 +205,11 @@ pub enum GridPosType {
     Player,
     Cell,
     Item,
+    /// Standing on the floor.
+    Foe,
 }
 
 impl GridPosition {
-    ///
     pub fn to_transform(self, grid_pos_type: GridPosType) -> Transform {
         match grid_pos_type {
             GridPosType::Player => Transform::from_translation(self.to_vec3(grid_pos_type)),
@@ -156,6 +217,7 @@ impl GridPosition {
             GridPosType::Item => Transform::from_translation(self.to_vec3(grid_pos_type))
                 .with_scale(Vec3::splat(0.3))
                 .with_rotation(Quat::from_rotation_z(PI / 7.0)),
+            GridPosType::Foe => Transform::from_translation(self.to_vec3(grid_pos_type)),
         }
     }
 
@@ -164,29 +226,31 @@ impl GridPosition {
             GridPosType::Player => Vec3::new(self.col as f32, 1.0, self.row as f32),
             GridPosType::Cell => Vec3::new(self.col as f32, 0.0, self.row as f32),
             GridPosType::Item => Vec3::new(self.col as f32, 0.75, self.row as f32),
+            GridPosType::Foe => Vec3::new(self.col as f32, 0.5, self.row as f32),
         }
     }
 
-    /// this can panic! make sure to only use this when you know a collision won't occur
-    pub fn translated(self, direction: GridDirection) -> Self {
+    /// Returns `None` if moving in `direction` would go below row or column 0. This doesn't know
+    /// how big the grid is, so check the result against `DungeonTileLookup::contains` as well.
+    pub fn try_translated(self, direction: GridDirection) -> Option<Self> {
         match direction {
-            GridDirection::Left => GridPosition {
+            GridDirection::Left => Some(GridPosition {
                 row: self.row,
-                col: self.col - 1,
-            },
-            GridDirection::Forward => GridPosition {
-                row: self.row - 1,
+                col: self.col.checked_sub(1)?,
+            }),
+            GridDirection::Forward => Some(GridPosition {
+                row: self.row.checked_sub(1)?,
                 col: self.col,
-            },
-            GridDirection::Right => GridPosition {
+            }),
+            GridDirection::Right => Some(GridPosition {
                 row: self.row,
-                col: self.col + 1,
-            },
-            GridDirection::Back => GridPosition {
-                row: self.row + 1,
+                col: self.col.checked_add(1)?,
+            }),
+            GridDirection::Back => Some(GridPosition {
+                row: self.row.checked_add(1)?,
                 col: self.col,
-            },
-            _ => self,
+            }),
+            _ => Some(self),
         }
     }
 }
@@ -289,6 +353,53 @@ impl TileBundle {
             bottom,
         }
     }
+
+    /// Builds the bundle for `preset`, asking `face_tile` for the tile on every face it covers and
+    /// leaving the rest empty.
+    pub fn from_preset(
+        preset: TileBundlePreset,
+        face_tile: impl Fn(GridDirection) -> Tile,
+    ) -> Self {
+        let empty = TileBundle::new(
+            Tile::new_empty(),
+            Tile::new_empty(),
+            Tile::new_empty(),
+            Tile::new_empty(),
+            Tile::new_empty(),
+            Tile::new_empty(),
+        );
+        GridDirection::ALL
+            .into_iter()
+            .filter(|direction| preset.has_face(*direction))
+            .fold(empty, |bundle, direction| {
+                bundle.with_tile(direction, face_tile(direction))
+            })
+    }
+
+    pub fn tile(&self, direction: GridDirection) -> &Tile {
+        match direction {
+            GridDirection::Left => &self.left,
+            GridDirection::Forward => &self.forward,
+            GridDirection::Right => &self.right,
+            GridDirection::Back => &self.back,
+            GridDirection::Top => &self.top,
+            GridDirection::Bottom => &self.bottom,
+        }
+    }
+
+    /// Replaces the tile on one face of the bundle.
+    pub fn with_tile(mut self, direction: GridDirection, mut tile: Tile) -> Self {
+        GridDirection::set_tile_transform(&mut tile, GridDirection::get_tile_transform(direction));
+        match direction {
+            GridDirection::Left => self.left = tile,
+            GridDirection::Forward => self.forward = tile,
+            GridDirection::Right => self.right = tile,
+            GridDirection::Back => self.back = tile,
+            GridDirection::Top => self.top = tile,
+            GridDirection::Bottom => self.bottom = tile,
+        }
+        self
+    }
 }
 
 pub fn spawn_dungeon_cell(
@@ -360,6 +471,7 @@ pub fn spawn_dungeon_cell(
         .spawn((
             cell.cell_type,
             cell.spatial_bundle,
+            DungeonFloorEntity,
             PickableBundle::default(),
             RaycastPickTarget::default(),
         ))
@@ -372,7 +484,7 @@ pub fn spawn_dungeon_cell(
 #[cfg(test)]
 pub mod test_helpers {
     use super::*;
-    use crate::modes::dungeon::model::tile::test_helpers::setup_test_texture_map;
+    use crate::modes::dungeon::model::tileset::test_helpers::setup_test_texture_map;
     use bevy::prelude::App;
 
     pub fn setup_test_tile_preset_map(app: &mut App) {
@@ -381,3 +493,101 @@ pub mod test_helpers {
         app.insert_resource(tile_preset_map);
     }
 }
+
+#[cfg(test)]
+mod test {
+    use bevy::utils::HashSet;
+
+    use crate::modes::dungeon::model::cell::{
+        GridDirection, GridPosition, TileBundle, TileBundlePreset,
+    };
+    use crate::modes::dungeon::model::tile::{Tile, TileType};
+
+    #[test]
+    fn presets_cover_every_face_combination() {
+        let presets: HashSet<TileBundlePreset> = TileBundlePreset::all().collect();
+        assert_eq!(presets.len(), 64);
+        assert!(presets.contains(&TileBundlePreset::EMPTY));
+        assert!(presets.contains(&TileBundlePreset::CLOSED));
+        assert_eq!(
+            GridDirection::ALL
+                .into_iter()
+                .fold(TileBundlePreset::EMPTY, TileBundlePreset::with_face),
+            TileBundlePreset::CLOSED
+        );
+        assert!(!TileBundlePreset::OPEN.has_face(GridDirection::Left));
+        assert!(TileBundlePreset::OPEN.has_face(GridDirection::Top));
+        assert!(TileBundlePreset::OPEN.has_face(GridDirection::Bottom));
+    }
+
+    #[test]
+    fn bundles_match_their_preset() {
+        let face_tile = |_| Tile::new_empty().with_tile_type(TileType::Basic);
+        for preset in TileBundlePreset::all() {
+            let bundle = TileBundle::from_preset(preset, face_tile);
+            let faces = [
+                (GridDirection::Left, &bundle.left),
+                (GridDirection::Forward, &bundle.forward),
+                (GridDirection::Right, &bundle.right),
+                (GridDirection::Back, &bundle.back),
+                (GridDirection::Top, &bundle.top),
+                (GridDirection::Bottom, &bundle.bottom),
+            ];
+            for (direction, tile) in faces {
+                let expected = if preset.has_face(direction) {
+                    TileType::Basic
+                } else {
+                    TileType::Empty
+                };
+                assert_eq!(tile.tile_type, expected, "{:?} {:?}", preset, direction);
+            }
+        }
+    }
+
+    #[test]
+    fn try_translated_stops_at_zero() {
+        let origin = GridPosition { row: 0, col: 0 };
+        assert_eq!(origin.try_translated(GridDirection::Left), None);
+        assert_eq!(origin.try_translated(GridDirection::Forward), None);
+        assert_eq!(
+            origin.try_translated(GridDirection::Right),
+            Some(GridPosition { row: 0, col: 1 })
+        );
+        assert_eq!(
+            origin.try_translated(GridDirection::Back),
+            Some(GridPosition { row: 1, col: 0 })
+        );
+        assert_eq!(origin.try_translated(GridDirection::Top), Some(origin));
+    }
+
+    #[test]
+    fn relative_directions_follow_facing() {
+        let facing = GridDirection::Right;
+        assert_eq!(
+            GridDirection::Forward.relative_to_absolute(facing),
+            GridDirection::Right
+        );
+        assert_eq!(
+            GridDirection::Left.relative_to_absolute(facing),
+            GridDirection::Forward
+        );
+        assert_eq!(
+            GridDirection::Back.relative_to_absolute(facing),
+            GridDirection::Left
+        );
+        assert_eq!(
+            GridDirection::Bottom.relative_to_absolute(facing),
+            GridDirection::Bottom
+        );
+        for facing in GridDirection::ALL.into_iter().take(4) {
+            for direction in GridDirection::ALL {
+                assert_eq!(
+                    direction
+                        .relative_to_absolute(facing)
+                        .absolute_to_relative(facing),
+                    direction
+                );
+            }
+        }
+    }
+}
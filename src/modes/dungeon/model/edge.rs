@@ -0,0 +1,65 @@
+use serde::Deserialize;
+
+use crate::modes::dungeon::model::cell::GridDirection;
+use crate::modes::dungeon::model::tile::{TileTexture, TileType};
+
+/// What sits on the edge between two cells. Edges that aren't listed in a floor's `edges` are
+/// inferred from the grid: open between two open cells, a wall everywhere else.
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum EdgeKind {
+    Open,
+    Wall,
+    Door,
+    LockedDoor,
+    /// Can be walked through from the cell it's placed on, but is a wall from the other side.
+    OneWay,
+    /// Looks like a wall, but can be walked through.
+    SecretWall,
+}
+
+impl EdgeKind {
+    /// What this edge is from the cell on the other side of it.
+    pub fn mirrored(self) -> Self {
+        match self {
+            EdgeKind::OneWay => EdgeKind::Wall,
+            kind => kind,
+        }
+    }
+
+    /// Whether the player can ever get through this edge. Doors count even though they start
+    /// closed, and so do locked ones since the key is somewhere in the dungeon.
+    pub fn is_traversable(self) -> bool {
+        self != EdgeKind::Wall
+    }
+
+    pub fn tile_type(self) -> TileType {
+        match self {
+            EdgeKind::Open => TileType::Empty,
+            EdgeKind::Wall => TileType::Basic,
+            EdgeKind::Door => TileType::Door,
+            EdgeKind::LockedDoor => TileType::LockedDoor,
+            EdgeKind::OneWay => TileType::OneWay,
+            EdgeKind::SecretWall => TileType::SecretWall,
+        }
+    }
+
+    pub fn texture(self) -> Option<TileTexture> {
+        match self {
+            EdgeKind::Open => None,
+            EdgeKind::Wall | EdgeKind::OneWay | EdgeKind::SecretWall => Some(TileTexture::Wall),
+            EdgeKind::Door => Some(TileTexture::Door),
+            EdgeKind::LockedDoor => Some(TileTexture::LockedDoor),
+        }
+    }
+}
+
+/// Overrides the edge on the `direction` side of the cell at `position`.
+#[derive(Deserialize, Clone, Debug)]
+pub struct RawEdgeData {
+    pub position: [u8; 2],
+    pub direction: GridDirection,
+    pub kind: EdgeKind,
+    /// Id of the key that opens a `LockedDoor`. Any key will do if it's missing.
+    #[serde(default)]
+    pub key: Option<String>,
+}
@@ -0,0 +1,165 @@
+use serde::Deserialize;
+
+use crate::modes::battle::encounter::FormationId;
+use crate::modes::dungeon::model::cell::GridPosition;
+
+const DEFAULT_DANGER_PER_STEP: f32 = 0.02;
+
+fn default_danger_per_step() -> f32 {
+    DEFAULT_DANGER_PER_STEP
+}
+
+fn default_weight() -> u32 {
+    1
+}
+
+/// One of the formations an encounter table can roll.
+#[derive(Deserialize, Clone, Debug)]
+pub struct RawEncounterFormation {
+    pub formation: FormationId,
+    /// How likely this formation is compared to the others in the table.
+    #[serde(default = "default_weight")]
+    pub weight: u32,
+}
+
+/// What the player can run into while walking somewhere, for example:
+///
+/// ```json
+/// "encounter_tables": {
+///     "halls": {
+///         "danger_per_step": 0.03,
+///         "formations": [
+///             { "formation": "two_slimes", "weight": 3 },
+///             { "formation": "bat_swarm" }
+///         ]
+///     }
+/// }
+/// ```
+///
+/// Every step adds `danger_per_step` to the danger meter, and the meter is the chance of a battle
+/// starting on that step, so the longer the player goes without a fight the likelier one gets.
+#[derive(Deserialize, Clone, Debug)]
+pub struct RawEncounterTable {
+    #[serde(default = "default_danger_per_step")]
+    pub danger_per_step: f32,
+    pub formations: Vec<RawEncounterFormation>,
+}
+
+impl RawEncounterTable {
+    /// Stops at `u32::MAX` rather than overflowing on huge weights.
+    fn total_weight(&self) -> u32 {
+        self.formations
+            .iter()
+            .fold(0, |total, entry| total.saturating_add(entry.weight))
+    }
+
+    /// Whether there's anything to roll. Tables without any weight can never start a battle.
+    pub fn is_empty(&self) -> bool {
+        self.total_weight() == 0
+    }
+
+    /// Picks one of the formations, taking their weights into account.
+    pub fn roll_formation(&self, rng: &mut fastrand::Rng) -> Option<&FormationId> {
+        let total_weight = self.total_weight();
+        if total_weight == 0 {
+            return None;
+        }
+        let mut roll = rng.u32(0..total_weight);
+        self.formations
+            .iter()
+            .find(|entry| {
+                if roll < entry.weight {
+                    return true;
+                }
+                roll -= entry.weight;
+                false
+            })
+            .map(|entry| &entry.formation)
+    }
+}
+
+/// Uses a different encounter table for the rectangle of cells from `from` to `to`, corners
+/// included. A region without a `table` is safe, nothing attacks the player there.
+#[derive(Deserialize, Clone, Debug)]
+pub struct RawEncounterRegion {
+    pub from: [u8; 2],
+    pub to: [u8; 2],
+    #[serde(default)]
+    pub table: Option<String>,
+}
+
+impl RawEncounterRegion {
+    pub fn contains(&self, grid_position: GridPosition) -> bool {
+        let rows = self.from[0].min(self.to[0]) as usize..=self.from[0].max(self.to[0]) as usize;
+        let cols = self.from[1].min(self.to[1]) as usize..=self.from[1].max(self.to[1]) as usize;
+        rows.contains(&grid_position.row) && cols.contains(&grid_position.col)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::modes::battle::encounter::FormationId;
+    use crate::modes::dungeon::model::cell::GridPosition;
+    use crate::modes::dungeon::model::encounter::{
+        RawEncounterFormation, RawEncounterRegion, RawEncounterTable,
+    };
+
+    fn make_table(weights: &[(&str, u32)]) -> RawEncounterTable {
+        RawEncounterTable {
+            danger_per_step: 0.1,
+            formations: weights
+                .iter()
+                .map(|(formation, weight)| RawEncounterFormation {
+                    formation: FormationId(formation.to_string()),
+                    weight: *weight,
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn formations_are_rolled_by_weight() {
+        let table = make_table(&[("slimes", 3), ("never", 0), ("bats", 1)]);
+        let mut rng = fastrand::Rng::with_seed(7);
+        let rolls: Vec<_> = (0..400)
+            .map(|_| table.roll_formation(&mut rng).unwrap().0.clone())
+            .collect();
+        let slimes = rolls.iter().filter(|id| *id == "slimes").count();
+        let bats = rolls.iter().filter(|id| *id == "bats").count();
+        assert_eq!(slimes + bats, 400);
+        assert!(slimes > bats * 2, "{} slimes, {} bats", slimes, bats);
+    }
+
+    #[test]
+    fn empty_tables_roll_nothing() {
+        let mut rng = fastrand::Rng::with_seed(7);
+        assert!(make_table(&[]).is_empty());
+        assert!(make_table(&[("never", 0)]).is_empty());
+        assert_eq!(make_table(&[("never", 0)]).roll_formation(&mut rng), None);
+    }
+
+    #[test]
+    fn huge_weights_dont_overflow() {
+        let table = make_table(&[("slimes", u32::MAX), ("bats", u32::MAX)]);
+        let mut rng = fastrand::Rng::with_seed(7);
+        assert!(!table.is_empty());
+        for _ in 0..100 {
+            assert!(table.roll_formation(&mut rng).is_some());
+        }
+    }
+
+    #[test]
+    fn regions_include_their_corners() {
+        let region = RawEncounterRegion {
+            from: [3, 1],
+            to: [1, 2],
+            table: None,
+        };
+        let contains = |row, col| region.contains(GridPosition { row, col });
+        assert!(contains(1, 1));
+        assert!(contains(3, 2));
+        assert!(contains(2, 2));
+        assert!(!contains(0, 1));
+        assert!(!contains(2, 3));
+    }
+}
@@ -0,0 +1,54 @@
+use serde::Deserialize;
+
+use crate::modes::dungeon::model::cell::GridDirection;
+use crate::modes::dungeon::model::tile::TileType;
+
+/// Something on a cell's floor that does something to the player once they've walked onto it.
+/// Cells that aren't listed in a floor's `floor_tiles` are just plain floor.
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FloorKind {
+    /// Hurts the player every time they step on it.
+    Damage { amount: u32 },
+    /// Drops the player onto the same cell of the next floor down.
+    Pit,
+    /// Turns the player. `turn` is `Left`, `Right` or `Back`.
+    Spinner { turn: GridDirection },
+    /// Pushes the player one cell in `direction`, unless something is in the way.
+    Conveyor { direction: GridDirection },
+    /// The player keeps sliding the way they were going until they hit something or get off the
+    /// ice.
+    Ice,
+    /// Moves the player to `target` on the same floor. They keep facing the same way unless
+    /// `direction` is set.
+    Teleporter {
+        target: [u8; 2],
+        #[serde(default)]
+        direction: Option<GridDirection>,
+    },
+    /// Everything goes dark while the player is standing on it.
+    Darkness,
+}
+
+impl FloorKind {
+    pub fn tile_type(self) -> TileType {
+        match self {
+            FloorKind::Damage { amount } => TileType::Damage(amount),
+            FloorKind::Pit => TileType::Pit,
+            FloorKind::Spinner { turn } => TileType::Spinner(turn),
+            FloorKind::Conveyor { direction } => TileType::Conveyor(direction),
+            FloorKind::Ice => TileType::Ice,
+            FloorKind::Teleporter { target, direction } => TileType::Teleporter {
+                target: target.into(),
+                direction,
+            },
+            FloorKind::Darkness => TileType::Darkness,
+        }
+    }
+}
+
+/// Puts `kind` on the floor of the cell at `position`.
+#[derive(Deserialize, Clone, Copy, Debug)]
+pub struct RawFloorTileData {
+    pub position: [u8; 2],
+    pub kind: FloorKind,
+}
@@ -0,0 +1,185 @@
+use serde::Deserialize;
+
+use crate::modes::battle::encounter::FormationId;
+use crate::modes::dungeon::model::cell::{GridDirection, GridPosition};
+
+/// How a FOE decides where to go. FOEs only ever move when the player does, one cell per step.
+#[derive(Deserialize, Clone, Debug, PartialEq)]
+pub enum FoeBehavior {
+    /// Walks to each cell in `route` in turn, starting over after the last one.
+    Patrol { route: Vec<[u8; 2]> },
+    /// Heads for the player once they're within `sight` steps, and stands still otherwise.
+    Chase { sight: u8 },
+    /// Takes a step in a random direction, or stays put.
+    Wander,
+}
+
+/// An enemy that can be seen walking around the floor, for example:
+///
+/// ```json
+/// {
+///     "position": [4, 2],
+///     "direction": "Left",
+///     "formation": "slime_king",
+///     "model": "models/foes/slime.glb#Scene0",
+///     "behavior": { "Chase": { "sight": 3 } }
+/// }
+/// ```
+///
+/// Running into it, or letting it run into the player, starts a battle against `formation`.
+#[derive(Deserialize, Clone, Debug)]
+pub struct RawFoeData {
+    pub position: [u8; 2],
+    pub direction: GridDirection,
+    pub formation: FormationId,
+    /// Path to the scene the FOE is drawn with.
+    pub model: String,
+    pub behavior: FoeBehavior,
+}
+
+fn distance(from: GridPosition, to: GridPosition) -> usize {
+    from.row.abs_diff(to.row) + from.col.abs_diff(to.col)
+}
+
+/// The way to step from `from` to get closer to `to`, trying the axis that's furthest off first.
+/// `can_step` says whether a direction is free.
+pub fn step_towards(
+    from: GridPosition,
+    to: GridPosition,
+    can_step: impl Fn(GridDirection) -> bool,
+) -> Option<GridDirection> {
+    let vertical = match to.row.cmp(&from.row) {
+        std::cmp::Ordering::Less => Some(GridDirection::Forward),
+        std::cmp::Ordering::Equal => None,
+        std::cmp::Ordering::Greater => Some(GridDirection::Back),
+    };
+    let horizontal = match to.col.cmp(&from.col) {
+        std::cmp::Ordering::Less => Some(GridDirection::Left),
+        std::cmp::Ordering::Equal => None,
+        std::cmp::Ordering::Greater => Some(GridDirection::Right),
+    };
+    let candidates = if from.row.abs_diff(to.row) >= from.col.abs_diff(to.col) {
+        [vertical, horizontal]
+    } else {
+        [horizontal, vertical]
+    };
+    candidates
+        .into_iter()
+        .flatten()
+        .find(|direction| can_step(*direction))
+}
+
+impl FoeBehavior {
+    /// Where a FOE standing on `position` goes next, or `None` to stay put. `patrol_index` is the
+    /// route cell a patrolling FOE is heading for, and gets moved on once the FOE reaches it.
+    pub fn next_step(
+        &self,
+        position: GridPosition,
+        player_position: GridPosition,
+        patrol_index: &mut usize,
+        can_step: impl Fn(GridDirection) -> bool,
+        rng: &mut fastrand::Rng,
+    ) -> Option<GridDirection> {
+        match self {
+            FoeBehavior::Patrol { route } => {
+                if route.is_empty() {
+                    return None;
+                }
+                let mut target = GridPosition::from(route[*patrol_index % route.len()]);
+                if target == position {
+                    *patrol_index = (*patrol_index + 1) % route.len();
+                    target = route[*patrol_index].into();
+                }
+                step_towards(position, target, can_step)
+            }
+            FoeBehavior::Chase { sight } => (distance(position, player_position)
+                <= *sight as usize)
+                .then(|| step_towards(position, player_position, can_step))
+                .flatten(),
+            FoeBehavior::Wander => {
+                let free: Vec<_> = GridDirection::HORIZONTAL
+                    .into_iter()
+                    .filter(|direction| can_step(*direction))
+                    .collect();
+                // one extra roll for standing still
+                free.get(rng.usize(0..=free.len())).copied()
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::modes::dungeon::model::cell::{GridDirection, GridPosition};
+    use crate::modes::dungeon::model::foe::{step_towards, FoeBehavior};
+
+    fn pos(row: usize, col: usize) -> GridPosition {
+        GridPosition { row, col }
+    }
+
+    #[test]
+    fn steps_along_the_longest_axis_first() {
+        assert_eq!(
+            step_towards(pos(2, 2), pos(2, 5), |_| true),
+            Some(GridDirection::Right)
+        );
+        assert_eq!(
+            step_towards(pos(2, 2), pos(0, 1), |_| true),
+            Some(GridDirection::Forward)
+        );
+        // blocked, so it goes around
+        assert_eq!(
+            step_towards(pos(2, 2), pos(0, 1), |direction| direction
+                != GridDirection::Forward),
+            Some(GridDirection::Left)
+        );
+        assert_eq!(step_towards(pos(2, 2), pos(2, 2), |_| true), None);
+        assert_eq!(step_towards(pos(2, 2), pos(2, 0), |_| false), None);
+    }
+
+    #[test]
+    fn patrols_loop_through_their_route() {
+        let patrol = FoeBehavior::Patrol {
+            route: vec![[0, 0], [0, 2]],
+        };
+        let mut rng = fastrand::Rng::with_seed(0);
+        let mut index = 0;
+        let mut position = pos(0, 1);
+        let mut visited = vec![];
+        for _ in 0..6 {
+            let step = patrol
+                .next_step(position, pos(5, 5), &mut index, |_| true, &mut rng)
+                .unwrap();
+            position = position.try_translated(step).unwrap();
+            visited.push(position.col);
+        }
+        assert_eq!(visited, vec![0, 1, 2, 1, 0, 1]);
+    }
+
+    #[test]
+    fn chasers_only_chase_what_they_can_see() {
+        let chase = FoeBehavior::Chase { sight: 2 };
+        let mut rng = fastrand::Rng::with_seed(0);
+        let mut index = 0;
+        let mut step = |player| chase.next_step(pos(2, 2), player, &mut index, |_| true, &mut rng);
+        assert_eq!(step(pos(2, 4)), Some(GridDirection::Right));
+        assert_eq!(step(pos(1, 1)), Some(GridDirection::Forward));
+        assert_eq!(step(pos(2, 5)), None);
+    }
+
+    #[test]
+    fn wanderers_only_go_where_they_can() {
+        let mut rng = fastrand::Rng::with_seed(0);
+        let mut index = 0;
+        for _ in 0..20 {
+            let step = FoeBehavior::Wander.next_step(
+                pos(2, 2),
+                pos(0, 0),
+                &mut index,
+                |direction| direction == GridDirection::Back,
+                &mut rng,
+            );
+            assert!(matches!(step, None | Some(GridDirection::Back)));
+        }
+    }
+}
@@ -4,58 +4,240 @@ use bevy::utils::HashMap;
 use serde::Deserialize;
 
 use crate::modes::dungeon::model::cell::{GridDirection, GridPosition, TileBundlePreset};
-use crate::modes::dungeon::model::items::ItemType;
+use crate::modes::dungeon::model::edge::{EdgeKind, RawEdgeData};
+use crate::modes::dungeon::model::encounter::{RawEncounterRegion, RawEncounterTable};
+use crate::modes::dungeon::model::floor::RawFloorTileData;
+use crate::modes::dungeon::model::foe::RawFoeData;
+use crate::modes::dungeon::model::items::ItemId;
+use crate::modes::dungeon::model::tile::TileTexture;
+use crate::modes::dungeon::model::tileset::{RawDecalData, RawFaceOverride};
 
 #[derive(Deserialize)]
 pub struct RawDungeonItemData {
-    pub item_type: ItemType,
+    pub item_type: ItemId,
     pub item_position: [u8; 2],
+    /// Id of the lock this opens if the item `opens_locks`, see `RawEdgeData::key`.
+    #[serde(default)]
+    pub key: Option<String>,
 }
 
-#[derive(Deserialize, TypePath, TypeUuid)]
-#[uuid = "ad582585-3550-465f-a2cc-8be5ed4c540a"]
+/// A staircase (or ladder) cell. Stepping onto it takes the player to `target_position` on
+/// `target_floor`, facing `target_direction`. That's usually the matching staircase on the other
+/// floor so the player can walk straight back.
+#[derive(Deserialize, Clone, Copy, Debug)]
+pub struct RawStairData {
+    pub position: [u8; 2],
+    pub target_floor: usize,
+    pub target_position: [u8; 2],
+    pub target_direction: GridDirection,
+}
+
+/// One floor of a dungeon. `player_start_position` is where the player starts if this is the
+/// dungeon's start floor, and where they land if they arrive here without using stairs.
+#[derive(Deserialize, Default)]
 pub struct RawDungeonData {
     pub dungeon_grid: Vec<Vec<u8>>,
     pub player_start_position: [u8; 2],
     pub player_start_direction: GridDirection,
     pub items: Vec<RawDungeonItemData>,
+    #[serde(default)]
+    pub stairs: Vec<RawStairData>,
+    #[serde(default)]
+    pub edges: Vec<RawEdgeData>,
+    /// Overrides the dungeon's tileset for this floor.
+    #[serde(default)]
+    pub tileset: Option<String>,
+    #[serde(default)]
+    pub face_overrides: Vec<RawFaceOverride>,
+    #[serde(default)]
+    pub decals: Vec<RawDecalData>,
+    #[serde(default)]
+    pub floor_tiles: Vec<RawFloorTileData>,
+    /// Seeds the random texture variants, so the floor looks the same every time it's loaded.
+    #[serde(default)]
+    pub seed: u64,
+    /// Replaces the tileset's variant weights for this floor. The weights are in the same order
+    /// as the variants in the tileset.
+    #[serde(default)]
+    pub variant_weights: HashMap<TileTexture, Vec<f32>>,
+    /// Name of the dungeon's encounter table the whole floor uses. Floors without one are safe.
+    #[serde(default)]
+    pub encounter_table: Option<String>,
+    /// Parts of the floor that use a different table than the rest. The first region containing a
+    /// cell wins.
+    #[serde(default)]
+    pub encounter_regions: Vec<RawEncounterRegion>,
+    #[serde(default)]
+    pub foes: Vec<RawFoeData>,
+}
+
+/// A whole `.dungeon.json` file. Floors are ordered from the top down, so going down a floor means
+/// going to the next index.
+#[derive(Deserialize, TypePath, TypeUuid, Default)]
+#[uuid = "ad582585-3550-465f-a2cc-8be5ed4c540a"]
+pub struct RawDungeonFloors {
+    pub floors: Vec<RawDungeonData>,
+    #[serde(default)]
+    pub start_floor: usize,
+    /// Name of a tileset in the `TilesetManifest`. Uses the manifest's default if it's missing.
+    #[serde(default)]
+    pub tileset: Option<String>,
+    /// Every encounter table the floors can use, by name.
+    #[serde(default)]
+    pub encounter_tables: HashMap<String, RawEncounterTable>,
+}
+
+impl RawDungeonFloors {
+    pub fn floor(&self, index: usize) -> &RawDungeonData {
+        &self.floors[index]
+    }
+
+    /// The tileset a floor asked for, either its own or the whole dungeon's.
+    pub fn tileset(&self, index: usize) -> Option<&str> {
+        self.floor(index)
+            .tileset
+            .as_deref()
+            .or(self.tileset.as_deref())
+    }
+
+    /// The encounter table used on `grid_position` of floor `index`, if anything attacks there.
+    pub fn encounter_table(
+        &self,
+        index: usize,
+        grid_position: GridPosition,
+    ) -> Option<&RawEncounterTable> {
+        self.floor(index)
+            .encounter_table_at(grid_position)
+            .and_then(|name| self.encounter_tables.get(name))
+    }
 }
 
 impl RawDungeonData {
+    /// Number of columns. Validation guarantees every row is this long.
+    pub fn width(&self) -> usize {
+        self.dungeon_grid.first().map_or(0, |row| row.len())
+    }
+
+    /// Number of rows.
+    pub fn height(&self) -> usize {
+        self.dungeon_grid.len()
+    }
+
+    pub fn stair_at(&self, grid_position: GridPosition) -> Option<&RawStairData> {
+        self.stairs.iter().find(|stair| {
+            let stair_position: GridPosition = stair.position.into();
+            stair_position == grid_position
+        })
+    }
+
+    pub fn floor_tile_at(&self, grid_position: GridPosition) -> Option<&RawFloorTileData> {
+        self.floor_tiles.iter().find(|floor_tile| {
+            let floor_tile_position: GridPosition = floor_tile.position.into();
+            floor_tile_position == grid_position
+        })
+    }
+
+    /// Name of the encounter table used on `grid_position`.
+    pub fn encounter_table_at(&self, grid_position: GridPosition) -> Option<&str> {
+        match self
+            .encounter_regions
+            .iter()
+            .find(|region| region.contains(grid_position))
+        {
+            Some(region) => region.table.as_deref(),
+            None => self.encounter_table.as_deref(),
+        }
+    }
+
+    pub fn face_overrides_at(
+        &self,
+        grid_position: GridPosition,
+    ) -> impl Iterator<Item = &RawFaceOverride> {
+        self.face_overrides.iter().filter(move |face_override| {
+            let override_position: GridPosition = face_override.position.into();
+            override_position == grid_position
+        })
+    }
+
+    pub fn decals_at(&self, grid_position: GridPosition) -> impl Iterator<Item = &RawDecalData> {
+        self.decals.iter().filter(move |decal| {
+            let decal_position: GridPosition = decal.position.into();
+            decal_position == grid_position
+        })
+    }
+
+    /// A random number in `0.0..1.0` for one face of one cell. It only depends on `seed`, so it's
+    /// the same every time the floor is loaded.
+    pub fn face_roll(&self, grid_position: GridPosition, direction: GridDirection) -> f32 {
+        let face = ((grid_position.row as u64) << 32)
+            | ((grid_position.col as u64) << 8)
+            | direction as u64;
+        fastrand::Rng::with_seed(self.seed ^ face).f32()
+    }
+
+    fn explicit_edge(
+        &self,
+        grid_position: GridPosition,
+        direction: GridDirection,
+    ) -> Option<&RawEdgeData> {
+        self.edges.iter().find(|edge| {
+            GridPosition::from(edge.position) == grid_position && edge.direction == direction
+        })
+    }
+
+    /// The kind of edge on the `direction` side of the cell at `grid_position`. An edge can be set
+    /// from either of the cells it separates; if neither sets it, it's open if both cells are.
+    pub fn edge(&self, grid_position: GridPosition, direction: GridDirection) -> EdgeKind {
+        if let Some(edge) = self.explicit_edge(grid_position, direction) {
+            return edge.kind;
+        }
+        let Some(neighbor) = grid_position.try_translated(direction) else {
+            return EdgeKind::Wall;
+        };
+        if let Some(edge) = self.explicit_edge(neighbor, direction.get_inverse_direction()) {
+            return edge.kind.mirrored();
+        }
+        if self.cell_exists(neighbor.row as i32, neighbor.col as i32) {
+            EdgeKind::Open
+        } else {
+            EdgeKind::Wall
+        }
+    }
+
+    /// The key needed to open the edge on the `direction` side of the cell at `grid_position`,
+    /// from whichever side it was set on.
+    pub fn edge_key(&self, grid_position: GridPosition, direction: GridDirection) -> Option<&str> {
+        let neighbor_edge = grid_position
+            .try_translated(direction)
+            .and_then(|neighbor| self.explicit_edge(neighbor, direction.get_inverse_direction()));
+        self.explicit_edge(grid_position, direction)
+            .or(neighbor_edge)
+            .and_then(|edge| edge.key.as_deref())
+    }
+
     pub fn determine_preset(&self, i: i32, j: i32) -> TileBundlePreset {
-        // We can determine which preset to use by examining the tiles in each cardinal direction.
+        // We can determine which preset to use by examining the edges in each cardinal direction.
+        // Anything that isn't open gets a wall, doors and such are put on top of it later.
         // Right    -> +X
         // Left     -> -X
         // Forward  -> -Z
         // Back     -> +Z
         if !self.cell_exists(i, j) {
-            return TileBundlePreset::Empty;
-        }
-        let right = self.cell_exists(i, j + 1);
-        let left = self.cell_exists(i, j - 1);
-        let forward = self.cell_exists(i - 1, j);
-        let back = self.cell_exists(i + 1, j);
-        match (right, left, forward, back) {
-            (true, true, true, true) => TileBundlePreset::Open,
-            (true, true, false, true) => TileBundlePreset::ForwardWall,
-            (false, true, true, true) => TileBundlePreset::RightWall,
-            (true, true, true, false) => TileBundlePreset::BackWall,
-            (true, false, true, true) => TileBundlePreset::LeftWall,
-            (true, false, false, true) => TileBundlePreset::ForwardLeftCorner,
-            (false, true, false, true) => TileBundlePreset::ForwardRightCorner,
-            (false, true, true, false) => TileBundlePreset::BackRightCorner,
-            (true, false, true, false) => TileBundlePreset::BackLeftCorner,
-            (false, false, true, true) => TileBundlePreset::ForwardBackHallway,
-            (true, true, false, false) => TileBundlePreset::LeftRightHallway,
-            (false, false, false, true) => TileBundlePreset::ForwardHallwayEnd,
-            (false, true, false, false) => TileBundlePreset::RightHallwayEnd,
-            (false, false, true, false) => TileBundlePreset::BackHallwayEnd,
-            (true, false, false, false) => TileBundlePreset::LeftHallwayEnd,
-            (false, false, false, false) => TileBundlePreset::Closed, // should never be hit
+            return TileBundlePreset::EMPTY;
         }
+        let position = GridPosition {
+            row: i as usize,
+            col: j as usize,
+        };
+        GridDirection::HORIZONTAL
+            .into_iter()
+            .filter(|direction| self.edge(position, *direction) != EdgeKind::Open)
+            .fold(TileBundlePreset::OPEN, |preset, direction| {
+                preset.with_face(direction)
+            })
     }
 
-    fn cell_exists(&self, i: i32, j: i32) -> bool {
+    pub fn cell_exists(&self, i: i32, j: i32) -> bool {
         if i < 0 || j < 0 {
             return false;
         }
@@ -64,18 +246,57 @@ impl RawDungeonData {
             .dungeon_grid
             .get(i as usize)
             .and_then(|row| row.get(j as usize));
-        cell_option.map_or(false, |val| *val > 0u8)
+        cell_option.is_some_and(|val| *val > 0u8)
     }
 }
 
-#[derive(Resource)]
-pub struct DungeonTileLookup(Vec<Vec<HashMap<GridDirection, Entity>>>);
+/// Maps every face of every cell in the grid to its tile entity. Cells are stored row-major, so
+/// the lookup has to be resized to the grid's width and height before anything is inserted.
+/// Empty (0) cells still get tiles, so the lookup also keeps track of which cells are open.
+#[derive(Resource, Default)]
+pub struct DungeonTileLookup {
+    width: usize,
+    height: usize,
+    tiles: Vec<[Option<Entity>; 6]>,
+    open: Vec<bool>,
+}
 
 impl DungeonTileLookup {
-    pub fn get_tile(&self, grid_position: GridPosition, direction: GridDirection) -> Entity {
-        let (row, col) = (grid_position.row, grid_position.col);
-        // this only works because of copy which is fine
-        *self.0[row][col].get(&direction).unwrap()
+    pub fn contains(&self, grid_position: GridPosition) -> bool {
+        grid_position.row < self.height && grid_position.col < self.width
+    }
+
+    /// Whether the cell at `grid_position` is inside the grid and part of the dungeon.
+    pub fn is_open(&self, grid_position: GridPosition) -> bool {
+        self.contains(grid_position) && self.open[self.index(grid_position)]
+    }
+
+    pub fn set_open(&mut self, grid_position: GridPosition) {
+        let index = self.index(grid_position);
+        self.open[index] = true;
+    }
+
+    fn index(&self, grid_position: GridPosition) -> usize {
+        debug_assert!(
+            self.contains(grid_position),
+            "{:?} is outside the {}x{} dungeon",
+            grid_position,
+            self.width,
+            self.height
+        );
+        grid_position.row * self.width + grid_position.col
+    }
+
+    /// Returns `None` if `grid_position` is outside the grid or nothing was spawned there.
+    pub fn try_get_tile(
+        &self,
+        grid_position: GridPosition,
+        direction: GridDirection,
+    ) -> Option<Entity> {
+        if !self.contains(grid_position) {
+            return None;
+        }
+        self.tiles[self.index(grid_position)][direction as usize]
     }
 
     pub fn insert_tile(
@@ -84,31 +305,31 @@ impl DungeonTileLookup {
         direction: GridDirection,
         entity: Entity,
     ) {
-        let (row, col) = (grid_position.row, grid_position.col);
-        self.0[row][col].insert(direction, entity);
+        let index = self.index(grid_position);
+        self.tiles[index][direction as usize] = Some(entity);
     }
 
-    pub fn resize(&mut self, dungeon_grid: &Vec<Vec<u8>>) {
-        let table = &mut self.0;
-        let num_rows = dungeon_grid.len();
-        let num_cols = dungeon_grid[0].len();
-        // this is a little janky, but the 0th row will only have one hashmap if we don't do this
-        for _ in 0..num_cols - 1 {
-            table[0].push(HashMap::new());
-        }
-        table.resize_with(num_rows, || {
-            let mut row = vec![];
-            for _ in 0..num_cols {
-                row.push(HashMap::new());
-            }
-            row
-        });
+    /// Where the tile `entity` is, if it's in the lookup. This goes through every tile, so it's
+    /// only meant for things that don't happen every frame, like clicks.
+    pub fn find_tile(&self, entity: Entity) -> Option<(GridPosition, GridDirection)> {
+        self.tiles.iter().enumerate().find_map(|(index, faces)| {
+            let direction = faces.iter().position(|face| *face == Some(entity))?;
+            let grid_position = GridPosition {
+                row: index / self.width,
+                col: index % self.width,
+            };
+            Some((grid_position, (direction as i8).into()))
+        })
     }
-}
 
-impl Default for DungeonTileLookup {
-    fn default() -> Self {
-        DungeonTileLookup(vec![vec![HashMap::new()]])
+    /// Clears the lookup and makes room for a `width` x `height` grid, with every cell empty.
+    pub fn resize(&mut self, width: usize, height: usize) {
+        self.width = width;
+        self.height = height;
+        self.tiles.clear();
+        self.tiles.resize(width * height, [None; 6]);
+        self.open.clear();
+        self.open.resize(width * height, false);
     }
 }
 
@@ -121,3 +342,132 @@ pub mod test_helpers {
         app.insert_resource(DungeonTileLookup::default());
     }
 }
+
+#[cfg(test)]
+mod test {
+    use bevy::prelude::Entity;
+
+    use crate::modes::dungeon::model::cell::{GridDirection, GridPosition, TileBundlePreset};
+    use crate::modes::dungeon::model::edge::{EdgeKind, RawEdgeData};
+    use crate::modes::dungeon::model::grid::{DungeonTileLookup, RawDungeonData};
+
+    /// A plus shape centered on [1, 1], with each arm only present if its bit in `neighbors` is
+    /// set. Bits follow `GridDirection::HORIZONTAL`.
+    fn make_plus(neighbors: u8) -> RawDungeonData {
+        let mut dungeon_grid = vec![vec![0, 0, 0], vec![0, 1, 0], vec![0, 0, 0]];
+        let arms = [(1, 0), (0, 1), (1, 2), (2, 1)];
+        for (bit, (row, col)) in arms.into_iter().enumerate() {
+            if neighbors & (1 << bit) != 0 {
+                dungeon_grid[row][col] = 1;
+            }
+        }
+        RawDungeonData {
+            dungeon_grid,
+            player_start_position: [1, 1],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn determine_preset_covers_every_neighbor_combination() {
+        for neighbors in 0..16u8 {
+            let data = make_plus(neighbors);
+            // missing neighbors become walls, and every cell gets a floor and a ceiling
+            let expected = GridDirection::HORIZONTAL
+                .into_iter()
+                .enumerate()
+                .filter(|(bit, _)| neighbors & (1 << bit) == 0)
+                .fold(TileBundlePreset::OPEN, |preset, (_, direction)| {
+                    preset.with_face(direction)
+                });
+            assert_eq!(data.determine_preset(1, 1), expected, "{:04b}", neighbors);
+        }
+    }
+
+    #[test]
+    fn determine_preset_handles_edges() {
+        let mut data = make_plus(0b1111);
+        assert_eq!(data.determine_preset(1, 1), TileBundlePreset::OPEN);
+        assert_eq!(
+            data.determine_preset(1, 0),
+            TileBundlePreset::OPEN
+                .with_face(GridDirection::Left)
+                .with_face(GridDirection::Forward)
+                .with_face(GridDirection::Back)
+        );
+
+        // anything that isn't open is built as a wall, on both sides
+        data.edges.push(RawEdgeData {
+            position: [1, 1],
+            direction: GridDirection::Forward,
+            kind: EdgeKind::Door,
+            key: None,
+        });
+        assert_eq!(
+            data.determine_preset(1, 1),
+            TileBundlePreset::OPEN.with_face(GridDirection::Forward)
+        );
+        assert!(data.determine_preset(0, 1).has_face(GridDirection::Back));
+    }
+
+    #[test]
+    fn determine_preset_is_empty_outside_the_dungeon() {
+        let data = make_plus(0);
+        assert_eq!(data.determine_preset(0, 0), TileBundlePreset::EMPTY);
+        assert_eq!(data.determine_preset(-1, 1), TileBundlePreset::EMPTY);
+        assert_eq!(data.determine_preset(1, 3), TileBundlePreset::EMPTY);
+    }
+
+    #[test]
+    fn only_open_cells_can_be_stood_on() {
+        let mut lookup = DungeonTileLookup::default();
+        lookup.resize(2, 1);
+        let open = GridPosition { row: 0, col: 0 };
+        lookup.set_open(open);
+        assert!(lookup.is_open(open));
+        assert!(!lookup.is_open(GridPosition { row: 0, col: 1 }));
+        assert!(!lookup.is_open(GridPosition { row: 1, col: 0 }));
+
+        lookup.resize(2, 1);
+        assert!(!lookup.is_open(open));
+    }
+
+    #[test]
+    fn tiles_can_be_found_by_entity() {
+        let mut lookup = DungeonTileLookup::default();
+        lookup.resize(3, 2);
+        let position = GridPosition { row: 1, col: 2 };
+        lookup.insert_tile(position, GridDirection::Bottom, Entity::from_raw(7));
+        assert_eq!(
+            lookup.find_tile(Entity::from_raw(7)),
+            Some((position, GridDirection::Bottom))
+        );
+        assert_eq!(lookup.find_tile(Entity::from_raw(8)), None);
+    }
+
+    #[test]
+    fn face_rolls_are_seeded() {
+        let mut data = make_plus(0b1111);
+        let rolls = |data: &RawDungeonData| -> Vec<f32> {
+            (0..3)
+                .flat_map(|row| (0..3).map(move |col| GridPosition { row, col }))
+                .flat_map(|position| {
+                    GridDirection::ALL
+                        .into_iter()
+                        .map(move |direction| data.face_roll(position, direction))
+                })
+                .collect()
+        };
+        let first = rolls(&data);
+        assert!(first.iter().all(|roll| (0.0..1.0).contains(roll)));
+        assert_eq!(first, rolls(&data));
+        // every face gets its own roll
+        let mut unique = first.clone();
+        unique.sort_by(f32::total_cmp);
+        unique.dedup();
+        assert_eq!(unique.len(), first.len());
+
+        data.seed = 99;
+        assert_ne!(first, rolls(&data));
+    }
+}
@@ -1,8 +1,9 @@
-use crate::utils::tweenutils::PreserveQuatRotateYLens;
-use crate::modes::dungeon::dungeonmode::DungeonAssets;
+use crate::modes::dungeon::dungeonmode::DungeonFloorEntity;
 use crate::modes::dungeon::model::cell::{GridPosType, GridPosition};
+use crate::modes::sharedassets::itemdatabase::{IdleAnimation, ItemDatabase};
+use crate::utils::tweenutils::PreserveQuatRotateYLens;
 use bevy::math::Vec3;
-use bevy::prelude::{default, Commands, Component, Res, SceneBundle};
+use bevy::prelude::{default, warn, Commands, Component, SceneBundle, Transform};
 use bevy_mod_picking::PickableBundle;
 use bevy_tweening::lens::TransformPositionLens;
 use bevy_tweening::{
@@ -10,67 +11,101 @@ use bevy_tweening::{
 };
 use serde::Deserialize;
 use std::f32::consts::TAU;
+use std::fmt::{Display, Formatter};
 use std::time::Duration;
 
-#[derive(Component, Deserialize, Clone, Copy, PartialEq)]
-pub enum ItemType {
-    Polaroid,
-    Key,
-    Maxwell,
+/// Id of an item in the `ItemDatabase`.
+#[derive(Component, Deserialize, Clone, PartialEq, Eq, Hash, Debug)]
+#[serde(transparent)]
+pub struct ItemId(pub String);
+
+impl ItemId {
+    pub fn new(id: &str) -> Self {
+        ItemId(id.to_string())
+    }
+}
+
+impl Display for ItemId {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
 }
 
-pub struct DungeonItem;
+/// An item that's lying around waiting to be picked up. It's removed once the item is collected.
+#[derive(Component)]
+pub struct DungeonItem {
+    /// Id of the lock this opens, for keys.
+    pub key: Option<String>,
+}
 
 impl DungeonItem {
     pub fn spawn(
         commands: &mut Commands,
-        item_type: ItemType,
+        item_id: ItemId,
         grid_pos: GridPosition,
-        dungeon_assets: &Res<DungeonAssets>,
+        key: Option<String>,
+        item_database: &ItemDatabase,
     ) {
-        let scene_handle = match item_type {
-            ItemType::Polaroid => dungeon_assets.polaroid.clone(),
-            ItemType::Key => dungeon_assets.key.clone(),
-            ItemType::Maxwell => dungeon_assets.maxwell.clone(),
-        };
-        let transform = if item_type == ItemType::Maxwell {
-            grid_pos
-                .to_transform(GridPosType::Item)
-                .with_scale(Vec3::splat(0.01))
-        } else {
-            grid_pos.to_transform(GridPosType::Item)
+        let Some(info) = item_database.get(&item_id) else {
+            warn!("{} at {:?} isn't in the item database", item_id, grid_pos);
+            return;
         };
+        let mut transform = grid_pos.to_transform(GridPosType::Item);
+        transform.translation += Vec3::from_array(info.model.offset);
+        transform.scale = Vec3::splat(info.model.scale);
 
         let scene_bundle = SceneBundle {
-            scene: scene_handle,
+            scene: item_database.model(&item_id),
             transform,
             ..default()
         };
-        let rotation_tween = Tween::new(
-            EaseMethod::Linear,
-            Duration::from_secs_f32(2.0),
-            PreserveQuatRotateYLens {
-                start_quat: transform.rotation,
-                start: 0.0,
-                end: TAU,
-            },
-        )
-        .with_repeat_count(RepeatCount::Infinite);
-        let bounce_tween = Tween::new(
-            EaseMethod::EaseFunction(EaseFunction::QuadraticInOut),
-            Duration::from_secs_f32(0.8),
-            TransformPositionLens {
-                start: transform.translation,
-                end: transform.translation + 0.1 * Vec3::Y,
-            },
-        )
-        .with_repeat_strategy(RepeatStrategy::MirroredRepeat)
-        .with_repeat_count(RepeatCount::Infinite);
-        let track = Tracks::new([rotation_tween, bounce_tween]);
-        commands.spawn((
+        let mut entity = commands.spawn((
             scene_bundle,
-            Animator::new(track),
             PickableBundle::default(),
+            DungeonFloorEntity,
+            DungeonItem { key },
+            item_id,
+            grid_pos,
         ));
+        if let Some(tracks) = idle_tracks(transform, info.idle_animation) {
+            entity.insert(Animator::new(tracks));
+        }
+    }
+}
+
+fn idle_tracks(transform: Transform, idle_animation: IdleAnimation) -> Option<Tracks<Transform>> {
+    let mut tweens = vec![];
+    if idle_animation.spin_period > 0.0 {
+        tweens.push(
+            Tween::new(
+                EaseMethod::Linear,
+                Duration::from_secs_f32(idle_animation.spin_period),
+                PreserveQuatRotateYLens {
+                    start_quat: transform.rotation,
+                    start: 0.0,
+                    end: TAU,
+                },
+            )
+            .with_repeat_count(RepeatCount::Infinite),
+        );
+    }
+    if idle_animation.bounce_period > 0.0 && idle_animation.bounce_height != 0.0 {
+        tweens.push(
+            Tween::new(
+                EaseMethod::EaseFunction(EaseFunction::QuadraticInOut),
+                Duration::from_secs_f32(idle_animation.bounce_period),
+                TransformPositionLens {
+                    start: transform.translation,
+                    end: transform.translation + idle_animation.bounce_height * Vec3::Y,
+                },
+            )
+            .with_repeat_strategy(RepeatStrategy::MirroredRepeat)
+            .with_repeat_count(RepeatCount::Infinite),
+        );
+    }
+    if tweens.is_empty() {
+        None
+    } else {
+        Some(Tracks::new(tweens))
     }
 }
@@ -1,4 +1,10 @@
 pub mod cell;
+pub mod edge;
+pub mod encounter;
+pub mod floor;
+pub mod foe;
 pub mod grid;
 pub mod items;
 pub mod tile;
+pub mod tileset;
+pub mod validation;
@@ -1,97 +1,78 @@
 use std::f32::consts::PI;
 
 use bevy::prelude::*;
-use bevy::utils::HashMap;
-use bevy_asset_loader::prelude::AssetCollection;
 use bevy_inspector_egui::prelude::*;
+use serde::Deserialize;
 
-const QUAD_WIDTH: f32 = 1.0;
+use crate::modes::dungeon::model::cell::{GridDirection, GridPosition};
 
-#[derive(Reflect, Hash, PartialEq, Eq, Copy, Clone, Debug, Component, InspectorOptions)]
+#[derive(
+    Reflect, Hash, PartialEq, Eq, Copy, Clone, Debug, Component, InspectorOptions, Deserialize,
+)]
 #[reflect(InspectorOptions)]
 pub enum TileTexture {
     Wall,
     Floor,
     Ceiling,
+    Stairs,
+    Door,
+    LockedDoor,
 }
 
-#[derive(AssetCollection, Resource)]
-pub struct PurpleTileAssets {
-    #[asset(standard_material)]
-    #[asset(path = "img/dun/wall1.png")]
-    pub wall: Handle<StandardMaterial>,
-    #[asset(standard_material)]
-    #[asset(path = "img/dun/floor.png")]
-    pub floor: Handle<StandardMaterial>,
-    #[asset(standard_material)]
-    #[asset(path = "img/dun/plainCeiling.png")]
-    pub ceiling: Handle<StandardMaterial>,
-}
-
-#[derive(Resource)]
-pub struct PurpleTileTextureMap(pub HashMap<TileTexture, Tile>);
-
-impl FromWorld for PurpleTileTextureMap {
-    fn from_world(world: &mut World) -> Self {
-        let cell = world.cell();
-        let tile_material_handles = cell
-            .get_resource_mut::<PurpleTileAssets>()
-            .expect("failed to get purple tile asset handles");
-        let mut meshes = cell
-            .get_resource_mut::<Assets<Mesh>>()
-            .expect("failed to get meshes");
-        let mesh_handle = meshes.add(Mesh::from(shape::Box::new(
-            QUAD_WIDTH,
-            QUAD_WIDTH,
-            f32::EPSILON,
-        )));
-        let wall_handle = &tile_material_handles.wall;
-        let floor_handle = &tile_material_handles.floor;
-        let ceiling_handle = &tile_material_handles.ceiling;
-        let wall = Tile {
-            tile_type: TileType::Basic,
-            pbr_bundle: PbrBundle {
-                mesh: mesh_handle.clone(),
-                material: wall_handle.clone(),
-                transform: Transform::from_xyz(0.0, 0.0, 0.0)
-                    .with_rotation(Quat::from_rotation_x(-PI / 2.0)),
-                ..default()
-            },
-        };
-        let floor = Tile {
-            tile_type: TileType::Basic,
-            pbr_bundle: PbrBundle {
-                mesh: mesh_handle.clone(),
-                material: floor_handle.clone(),
-                transform: Transform::from_xyz(0.0, 0.0, 0.0)
-                    .with_rotation(Quat::from_rotation_x(-PI / 2.0)),
-                ..default()
-            },
-        };
-        let ceiling = Tile {
-            tile_type: TileType::Basic,
-            pbr_bundle: PbrBundle {
-                mesh: mesh_handle.clone(),
-                material: ceiling_handle.clone(),
-                transform: Transform::from_xyz(0.0, 0.0, 0.0)
-                    .with_rotation(Quat::from_rotation_x(-PI / 2.0)),
-                ..default()
-            },
-        };
-
-        let map = HashMap::from([
-            (TileTexture::Wall, wall),
-            (TileTexture::Floor, floor),
-            (TileTexture::Ceiling, ceiling),
-        ]);
-        PurpleTileTextureMap(map)
+impl TileTexture {
+    /// What a plain cell has on this face.
+    pub fn for_face(direction: GridDirection) -> Self {
+        match direction {
+            GridDirection::Top => TileTexture::Ceiling,
+            GridDirection::Bottom => TileTexture::Floor,
+            _ => TileTexture::Wall,
+        }
     }
 }
 
 #[derive(Component, PartialEq, Clone, Debug)]
 pub enum TileType {
-    Empty, // nothing
-    Basic, // just a texture. solid, collideable
+    Empty,      // nothing
+    Basic,      // just a texture. solid, collideable
+    Door,       // a closed door. solid until it's opened
+    OpenDoor,   // can be walked through
+    LockedDoor, // solid until it's unlocked
+    OneWay,     // can be walked through from this side, the other side is Basic
+    SecretWall, // looks like Basic but can be walked through
+    // the rest only go on floors, see FloorKind
+    Damage(u32),
+    Pit,
+    Spinner(GridDirection),
+    Conveyor(GridDirection),
+    Ice,
+    Teleporter {
+        target: GridPosition,
+        direction: Option<GridDirection>,
+    },
+    Darkness,
+}
+
+impl TileType {
+    /// Whether the player collides with this tile when trying to move through it.
+    pub fn is_solid(&self) -> bool {
+        matches!(
+            self,
+            TileType::Basic | TileType::Door | TileType::LockedDoor
+        )
+    }
+
+    /// Whether stepping onto this floor can move or turn the player, in which case they don't
+    /// get to move again until it's done.
+    pub fn takes_control(&self) -> bool {
+        matches!(
+            self,
+            TileType::Pit
+                | TileType::Spinner(_)
+                | TileType::Conveyor(_)
+                | TileType::Ice
+                | TileType::Teleporter { .. }
+        )
+    }
 }
 
 #[derive(Bundle, Clone)]
@@ -101,6 +82,20 @@ pub struct Tile {
 }
 
 impl Tile {
+    /// A solid tile showing `material`. Its transform gets set once it's placed in a `TileBundle`.
+    pub fn new(mesh: Handle<Mesh>, material: Handle<StandardMaterial>) -> Self {
+        Tile {
+            tile_type: TileType::Basic,
+            pbr_bundle: PbrBundle {
+                mesh,
+                material,
+                transform: Transform::from_xyz(0.0, 0.0, 0.0)
+                    .with_rotation(Quat::from_rotation_x(-PI / 2.0)),
+                ..default()
+            },
+        }
+    }
+
     pub fn new_empty() -> Self {
         Tile {
             tile_type: TileType::Empty,
@@ -108,28 +103,38 @@ impl Tile {
         }
     }
 
+    pub fn with_tile_type(mut self, tile_type: TileType) -> Self {
+        self.tile_type = tile_type;
+        self
+    }
+
     pub fn set_tile_transform(&mut self, transform: Transform) {
         self.pbr_bundle.transform = transform;
     }
 }
 
-#[cfg(test)]
-pub mod test_helpers {
-    use super::*;
-    use bevy::prelude::App;
+/// A quad drawn just in front of a face. Decals are only for looks and don't have a `TileType`, so
+/// whatever they're on keeps behaving the same.
+#[derive(Bundle, Clone)]
+pub struct Decal {
+    pbr_bundle: PbrBundle,
+}
 
-    fn make_basic_tile() -> Tile {
-        Tile {
-            tile_type: TileType::Basic,
-            pbr_bundle: Default::default(),
+impl Decal {
+    pub fn new(mesh: Handle<Mesh>, material: Handle<StandardMaterial>) -> Self {
+        Decal {
+            pbr_bundle: PbrBundle {
+                mesh,
+                material,
+                ..default()
+            },
         }
     }
-    pub fn setup_test_texture_map(app: &mut App) {
-        let mut map: HashMap<TileTexture, Tile> = HashMap::new();
-        map.insert(TileTexture::Wall, make_basic_tile());
-        map.insert(TileTexture::Floor, make_basic_tile());
-        map.insert(TileTexture::Ceiling, make_basic_tile());
-        let tile_texture_map = PurpleTileTextureMap(map);
-        app.insert_resource(tile_texture_map);
+
+    /// Decals are spawned as children of the tile they're on, this puts them on the side of it
+    /// that faces into the cell.
+    pub fn on_face(mut self, direction: GridDirection) -> Self {
+        self.pbr_bundle.transform = direction.decal_transform();
+        self
     }
 }
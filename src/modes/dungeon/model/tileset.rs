@@ -0,0 +1,428 @@
+use bevy::prelude::*;
+use bevy::reflect::{TypePath, TypeUuid};
+use bevy::utils::HashMap;
+use bevy_asset_loader::prelude::AssetCollection;
+use serde::Deserialize;
+
+use crate::modes::dungeon::model::cell::GridDirection;
+use crate::modes::dungeon::model::tile::{Decal, Tile, TileTexture};
+
+const QUAD_WIDTH: f32 = 1.0;
+
+/// One way a `TileTexture` can look. `image` is a path under `img/dun`, and `tint` gets multiplied
+/// into it, which is handy for things we don't have art for yet. Variants get picked at random in
+/// proportion to their `weight`.
+#[derive(Deserialize, Clone, Debug)]
+pub struct RawTileVariant {
+    pub image: String,
+    #[serde(default)]
+    pub tint: Option<[f32; 3]>,
+    #[serde(default = "default_weight")]
+    pub weight: f32,
+}
+
+fn default_weight() -> f32 {
+    1.0
+}
+
+/// Something painted over a face, like a sign, a crack or a switch. The image should be mostly
+/// transparent.
+#[derive(Deserialize, Clone, Debug)]
+pub struct RawDecal {
+    pub image: String,
+    #[serde(default)]
+    pub tint: Option<[f32; 3]>,
+}
+
+/// Every tileset (theme) the dungeons can use, from `tilesets.tileset.json`. Each tileset lists
+/// the weighted variants for each `TileTexture`. Tilesets don't have to list every texture,
+/// anything missing comes from `default_tileset`. Decals are shared by every tileset.
+///
+/// ```json
+/// {
+///     "default_tileset": "purple",
+///     "tilesets": {
+///         "purple": {
+///             "Wall": [
+///                 { "image": "img/dun/wall1.png", "weight": 4.0 },
+///                 { "image": "img/dun/wall2.png" }
+///             ],
+///             "Floor": [{ "image": "img/dun/floor.png" }],
+///             "Ceiling": [{ "image": "img/dun/plainCeiling.png" }],
+///             "Stairs": [{ "image": "img/dun/floor.png", "tint": [1.0, 0.8, 0.3] }],
+///             "Door": [{ "image": "img/dun/wall1.png", "tint": [0.6, 0.4, 0.2] }],
+///             "LockedDoor": [{ "image": "img/dun/wall1.png", "tint": [0.6, 0.2, 0.2] }]
+///         }
+///     },
+///     "decals": {
+///         "crack": { "image": "img/dun/decals/crack.png" }
+///     }
+/// }
+/// ```
+#[derive(Deserialize, TypePath, TypeUuid)]
+#[uuid = "e320ffa6-705e-4619-b952-c878d385bdfe"]
+pub struct TilesetManifest {
+    pub default_tileset: String,
+    pub tilesets: HashMap<String, HashMap<TileTexture, Vec<RawTileVariant>>>,
+    #[serde(default)]
+    pub decals: HashMap<String, RawDecal>,
+}
+
+/// Swaps the texture on one face of a cell. This only changes how the face looks, a wall stays a
+/// wall and an open side stays open.
+#[derive(Deserialize, Clone, Debug)]
+pub struct RawFaceOverride {
+    pub position: [u8; 2],
+    pub direction: GridDirection,
+    pub texture: TileTexture,
+    /// Picked at random like any other face if it's missing.
+    #[serde(default)]
+    pub variant: Option<usize>,
+    /// Borrow the texture from another tileset instead of the floor's.
+    #[serde(default)]
+    pub tileset: Option<String>,
+}
+
+/// Puts the decal called `decal` in the manifest on one face of a cell.
+#[derive(Deserialize, Clone, Debug)]
+pub struct RawDecalData {
+    pub position: [u8; 2],
+    pub direction: GridDirection,
+    pub decal: String,
+}
+
+#[derive(AssetCollection, Resource)]
+pub struct TilesetAssets {
+    #[asset(path = "tilesets/tilesets.tileset.json")]
+    pub manifest: Handle<TilesetManifest>,
+    #[asset(path = "img/dun", collection(typed, mapped))]
+    pub images: HashMap<String, Handle<Image>>,
+}
+
+#[derive(Clone)]
+pub struct TileVariant {
+    pub tile: Tile,
+    pub weight: f32,
+}
+
+/// Picks an index with a chance proportional to its weight, where `roll` is uniform in
+/// `0.0..1.0`. Negative weights count as zero, and if nothing has any weight the first index wins.
+pub fn pick_weighted(weights: impl Iterator<Item = f32> + Clone, roll: f32) -> usize {
+    let total: f32 = weights.clone().map(|weight| weight.max(0.0)).sum();
+    if total <= 0.0 {
+        return 0;
+    }
+    let mut remaining = roll * total;
+    let mut last_picked = 0;
+    for (index, weight) in weights.map(|weight| weight.max(0.0)).enumerate() {
+        if weight <= 0.0 {
+            continue;
+        }
+        if remaining < weight {
+            return index;
+        }
+        remaining -= weight;
+        last_picked = index;
+    }
+    // floating point error can leave a tiny bit left over at the end
+    last_picked
+}
+
+/// The tiles for every variant of every texture in every tileset, plus the decals, built from the
+/// manifest once everything has loaded.
+#[derive(Resource)]
+pub struct TilesetTextureMap {
+    default_tileset: String,
+    tilesets: HashMap<String, HashMap<TileTexture, Vec<TileVariant>>>,
+    decals: HashMap<String, Decal>,
+}
+
+impl TilesetTextureMap {
+    pub fn default_tileset(&self) -> &str {
+        &self.default_tileset
+    }
+
+    pub fn contains_tileset(&self, tileset: &str) -> bool {
+        self.tilesets.contains_key(tileset)
+    }
+
+    pub fn contains_decal(&self, decal: &str) -> bool {
+        self.decals.contains_key(decal)
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.tilesets.keys().map(|name| name.as_str())
+    }
+
+    /// Falls back to the default tileset if `tileset` doesn't have `texture`.
+    pub fn variants(&self, tileset: &str, texture: TileTexture) -> Option<&[TileVariant]> {
+        self.tilesets
+            .get(tileset)
+            .and_then(|textures| textures.get(&texture))
+            .or_else(|| {
+                self.tilesets
+                    .get(&self.default_tileset)
+                    .and_then(|textures| textures.get(&texture))
+            })
+            .map(Vec::as_slice)
+    }
+
+    /// Falls back to the first variant if `variant` doesn't exist.
+    pub fn tile(&self, tileset: &str, texture: TileTexture, variant: usize) -> Option<&Tile> {
+        let variants = self.variants(tileset, texture)?;
+        variants
+            .get(variant)
+            .or_else(|| variants.first())
+            .map(|variant| &variant.tile)
+    }
+
+    /// Picks a variant at random using `roll` (see `pick_weighted`). `weights` replaces the
+    /// manifest's weights when it's given.
+    pub fn pick_tile(
+        &self,
+        tileset: &str,
+        texture: TileTexture,
+        weights: Option<&[f32]>,
+        roll: f32,
+    ) -> Option<&Tile> {
+        let variants = self.variants(tileset, texture)?;
+        let variant = match weights {
+            Some(weights) => pick_weighted(weights.iter().copied(), roll),
+            None => pick_weighted(variants.iter().map(|variant| variant.weight), roll),
+        };
+        self.tile(tileset, texture, variant)
+    }
+
+    pub fn decal(&self, decal: &str) -> Option<&Decal> {
+        self.decals.get(decal)
+    }
+}
+
+impl FromWorld for TilesetTextureMap {
+    fn from_world(world: &mut World) -> Self {
+        let cell = world.cell();
+        let tileset_assets = cell
+            .get_resource::<TilesetAssets>()
+            .expect("failed to get tileset asset handles");
+        let manifests = cell
+            .get_resource::<Assets<TilesetManifest>>()
+            .expect("failed to get tileset manifests");
+        let manifest = manifests
+            .get(&tileset_assets.manifest)
+            .expect("failed to get tileset manifest out of assets");
+        let mut meshes = cell
+            .get_resource_mut::<Assets<Mesh>>()
+            .expect("failed to get meshes");
+        let mut materials = cell
+            .get_resource_mut::<Assets<StandardMaterial>>()
+            .expect("failed to get materials");
+        let mesh_handle = meshes.add(Mesh::from(shape::Box::new(
+            QUAD_WIDTH,
+            QUAD_WIDTH,
+            f32::EPSILON,
+        )));
+
+        let mut make_material = |image_path: &str, tint: Option<[f32; 3]>, alpha_mode| {
+            let image = tileset_assets.images.get(image_path).cloned();
+            if image.is_none() {
+                warn!("tileset image {} wasn't loaded", image_path);
+            }
+            materials.add(StandardMaterial {
+                base_color: tint.map_or(Color::WHITE, |[r, g, b]| Color::rgb(r, g, b)),
+                base_color_texture: image,
+                alpha_mode,
+                ..default()
+            })
+        };
+
+        let mut tilesets = HashMap::new();
+        for (name, raw_textures) in manifest.tilesets.iter() {
+            let mut textures = HashMap::new();
+            for (texture, raw_variants) in raw_textures.iter() {
+                let variants = raw_variants
+                    .iter()
+                    .map(|variant| TileVariant {
+                        tile: Tile::new(
+                            mesh_handle.clone(),
+                            make_material(&variant.image, variant.tint, AlphaMode::Opaque),
+                        ),
+                        weight: variant.weight,
+                    })
+                    .collect();
+                textures.insert(*texture, variants);
+            }
+            tilesets.insert(name.clone(), textures);
+        }
+        let decals = manifest
+            .decals
+            .iter()
+            .map(|(name, decal)| {
+                let material = make_material(&decal.image, decal.tint, AlphaMode::Blend);
+                (name.clone(), Decal::new(mesh_handle.clone(), material))
+            })
+            .collect();
+        TilesetTextureMap {
+            default_tileset: manifest.default_tileset.clone(),
+            tilesets,
+            decals,
+        }
+    }
+}
+
+#[cfg(test)]
+pub mod test_helpers {
+    use super::*;
+    use bevy::asset::HandleId;
+    use bevy::prelude::App;
+
+    pub const TEST_TILESET: &str = "test";
+    pub const OTHER_TEST_TILESET: &str = "other";
+    pub const TEST_VARIANTS: usize = 2;
+    pub const TEST_DECAL: &str = "crack";
+
+    /// Every tile in the test tilesets gets its own material handle, so tests can tell which
+    /// tileset and variant a spawned tile came from.
+    pub fn test_material(
+        tileset: &str,
+        texture: TileTexture,
+        variant: usize,
+    ) -> Handle<StandardMaterial> {
+        let tileset_index = if tileset == TEST_TILESET { 0 } else { 1 };
+        let id = tileset_index * 100 + texture as u64 * 10 + variant as u64;
+        Handle::weak(HandleId::Id(StandardMaterial::TYPE_UUID, id))
+    }
+
+    pub fn test_decal_material() -> Handle<StandardMaterial> {
+        Handle::weak(HandleId::Id(StandardMaterial::TYPE_UUID, 1000))
+    }
+
+    /// Every variant has the same weight.
+    pub fn make_tileset(tileset: &str) -> HashMap<TileTexture, Vec<TileVariant>> {
+        let textures = [
+            TileTexture::Wall,
+            TileTexture::Floor,
+            TileTexture::Ceiling,
+            TileTexture::Stairs,
+            TileTexture::Door,
+            TileTexture::LockedDoor,
+        ];
+        textures
+            .into_iter()
+            .map(|texture| {
+                let variants = (0..TEST_VARIANTS)
+                    .map(|variant| TileVariant {
+                        tile: Tile::new(
+                            Handle::default(),
+                            test_material(tileset, texture, variant),
+                        ),
+                        weight: 1.0,
+                    })
+                    .collect();
+                (texture, variants)
+            })
+            .collect()
+    }
+
+    pub fn make_test_texture_map() -> TilesetTextureMap {
+        TilesetTextureMap {
+            default_tileset: TEST_TILESET.to_string(),
+            tilesets: HashMap::from([
+                (TEST_TILESET.to_string(), make_tileset(TEST_TILESET)),
+                (
+                    OTHER_TEST_TILESET.to_string(),
+                    make_tileset(OTHER_TEST_TILESET),
+                ),
+            ]),
+            decals: HashMap::from([(
+                TEST_DECAL.to_string(),
+                Decal::new(Handle::default(), test_decal_material()),
+            )]),
+        }
+    }
+
+    pub fn setup_test_texture_map(app: &mut App) {
+        app.insert_resource(make_test_texture_map());
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use bevy::utils::HashMap;
+
+    use crate::modes::dungeon::model::tile::TileTexture;
+    use crate::modes::dungeon::model::tileset::test_helpers::{
+        make_test_texture_map, make_tileset, TEST_TILESET,
+    };
+    use crate::modes::dungeon::model::tileset::{pick_weighted, TilesetTextureMap};
+
+    #[test]
+    fn tile_lookup_falls_back() {
+        let mut partial = make_tileset("partial");
+        partial.retain(|texture, _| *texture == TileTexture::Wall);
+        let map = TilesetTextureMap {
+            default_tileset: TEST_TILESET.to_string(),
+            tilesets: HashMap::from([
+                (TEST_TILESET.to_string(), make_tileset(TEST_TILESET)),
+                ("partial".to_string(), partial),
+            ]),
+            decals: HashMap::new(),
+        };
+        let tile = |tileset, texture, variant| map.tile(tileset, texture, variant).unwrap();
+        let variant = |tileset: &str, texture: TileTexture, variant: usize| {
+            &map.tilesets[tileset][&texture][variant].tile
+        };
+
+        assert!(std::ptr::eq(
+            tile("partial", TileTexture::Wall, 1),
+            variant("partial", TileTexture::Wall, 1)
+        ));
+        // missing variants use the first one
+        assert!(std::ptr::eq(
+            tile("partial", TileTexture::Wall, 7),
+            variant("partial", TileTexture::Wall, 0)
+        ));
+        // missing textures and tilesets come from the default tileset
+        assert!(std::ptr::eq(
+            tile("partial", TileTexture::Floor, 1),
+            variant(TEST_TILESET, TileTexture::Floor, 1)
+        ));
+        assert!(std::ptr::eq(
+            tile("missing", TileTexture::Door, 0),
+            variant(TEST_TILESET, TileTexture::Door, 0)
+        ));
+    }
+
+    #[test]
+    fn pick_weighted_respects_weights() {
+        let weights = [1.0, 0.0, 3.0];
+        assert_eq!(pick_weighted(weights.into_iter(), 0.0), 0);
+        assert_eq!(pick_weighted(weights.into_iter(), 0.24), 0);
+        assert_eq!(pick_weighted(weights.into_iter(), 0.25), 2);
+        assert_eq!(pick_weighted(weights.into_iter(), 0.999_999), 2);
+        // zero weight variants never come up
+        assert_eq!(pick_weighted([0.0, 1.0].into_iter(), 0.0), 1);
+        assert_eq!(pick_weighted([1.0, 0.0].into_iter(), 0.999_999), 0);
+        // nothing to pick from
+        assert_eq!(pick_weighted([0.0, -2.0].into_iter(), 0.5), 0);
+        assert_eq!(pick_weighted([].into_iter(), 0.5), 0);
+    }
+
+    #[test]
+    fn pick_tile_uses_given_weights() {
+        let map = make_test_texture_map();
+        let variant = |index| map.tile(TEST_TILESET, TileTexture::Wall, index).unwrap();
+        let pick = |weights, roll| {
+            map.pick_tile(TEST_TILESET, TileTexture::Wall, weights, roll)
+                .unwrap()
+        };
+
+        // the manifest weights are even
+        assert!(std::ptr::eq(pick(None, 0.4), variant(0)));
+        assert!(std::ptr::eq(pick(None, 0.6), variant(1)));
+        let only_second = [0.0, 1.0];
+        assert!(std::ptr::eq(pick(Some(&only_second), 0.0), variant(1)));
+        // weights for variants that don't exist fall back to the first one
+        let missing = [0.0, 0.0, 1.0];
+        assert!(std::ptr::eq(pick(Some(&missing), 0.5), variant(0)));
+    }
+}
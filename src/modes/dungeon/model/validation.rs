@@ -0,0 +1,1576 @@
+use std::collections::VecDeque;
+use std::fmt::{Display, Formatter};
+
+use bevy::utils::HashMap;
+
+use crate::modes::dungeon::model::cell::{GridDirection, GridPosition};
+use crate::modes::dungeon::model::edge::EdgeKind;
+use crate::modes::dungeon::model::floor::FloorKind;
+use crate::modes::dungeon::model::foe::FoeBehavior;
+use crate::modes::dungeon::model::grid::{RawDungeonData, RawDungeonFloors};
+use crate::modes::dungeon::model::items::ItemId;
+use crate::modes::dungeon::model::tileset::TilesetTextureMap;
+use crate::modes::sharedassets::itemdatabase::ItemDatabase;
+
+/// Everything that can be wrong with a `RawDungeonData` before we try to spawn it.
+/// Positions are always `[row, col]`, the same as in the `.dungeon.json` files.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DungeonValidationError {
+    NoFloors,
+    StartFloorOutOfRange {
+        start_floor: usize,
+        num_floors: usize,
+    },
+    OnFloor {
+        floor: usize,
+        error: Box<DungeonValidationError>,
+    },
+    EmptyGrid,
+    NonRectangularRow {
+        row: usize,
+        expected_len: usize,
+        found_len: usize,
+    },
+    StartPositionOutOfBounds {
+        position: [u8; 2],
+    },
+    StartPositionOnEmptyCell {
+        position: [u8; 2],
+    },
+    ItemOutOfBounds {
+        item_type: ItemId,
+        position: [u8; 2],
+    },
+    ItemInEmptyCell {
+        item_type: ItemId,
+        position: [u8; 2],
+    },
+    UnknownItem {
+        item_type: ItemId,
+        position: [u8; 2],
+    },
+    KeyOnNonKeyItem {
+        item_type: ItemId,
+        position: [u8; 2],
+    },
+    DuplicateItems {
+        position: [u8; 2],
+        count: usize,
+    },
+    UnreachableRegion {
+        first_cell: [usize; 2],
+        size: usize,
+    },
+    StairOutOfBounds {
+        position: [u8; 2],
+    },
+    StairInEmptyCell {
+        position: [u8; 2],
+    },
+    StairTargetFloorMissing {
+        position: [u8; 2],
+        target_floor: usize,
+    },
+    StairTargetNotOpen {
+        position: [u8; 2],
+        target_floor: usize,
+        target_position: [u8; 2],
+    },
+    EdgeOutOfBounds {
+        position: [u8; 2],
+    },
+    EdgeInEmptyCell {
+        position: [u8; 2],
+    },
+    EdgeNotHorizontal {
+        position: [u8; 2],
+        direction: GridDirection,
+    },
+    KeyOnUnlockedEdge {
+        position: [u8; 2],
+        direction: GridDirection,
+    },
+    /// Something other than a wall faces an empty cell or the outside of the grid.
+    EdgeLeadsNowhere {
+        position: [u8; 2],
+        direction: GridDirection,
+    },
+    /// The edges set on both sides of the same boundary don't agree.
+    ConflictingEdges {
+        position: [u8; 2],
+        direction: GridDirection,
+    },
+    FaceOverrideOutOfBounds {
+        position: [u8; 2],
+    },
+    FaceOverrideInEmptyCell {
+        position: [u8; 2],
+    },
+    DecalOutOfBounds {
+        position: [u8; 2],
+    },
+    DecalInEmptyCell {
+        position: [u8; 2],
+    },
+    FloorTileOutOfBounds {
+        position: [u8; 2],
+    },
+    FloorTileInEmptyCell {
+        position: [u8; 2],
+    },
+    ConveyorNotHorizontal {
+        position: [u8; 2],
+        direction: GridDirection,
+    },
+    TeleporterTargetNotOpen {
+        position: [u8; 2],
+        target: [u8; 2],
+    },
+    PitOnBottomFloor {
+        position: [u8; 2],
+    },
+    PitLandingNotOpen {
+        position: [u8; 2],
+    },
+    UnknownTileset {
+        name: String,
+    },
+    UnknownDecal {
+        name: String,
+    },
+    UnknownEncounterTable {
+        name: String,
+    },
+    EmptyEncounterTable {
+        name: String,
+    },
+    EncounterRegionOutOfBounds {
+        from: [u8; 2],
+        to: [u8; 2],
+    },
+    FoeOutOfBounds {
+        position: [u8; 2],
+    },
+    FoeInEmptyCell {
+        position: [u8; 2],
+    },
+    PatrolRouteNotOpen {
+        position: [u8; 2],
+        route_cell: [u8; 2],
+    },
+}
+
+impl Display for DungeonValidationError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DungeonValidationError::NoFloors => write!(f, "dungeon has no floors"),
+            DungeonValidationError::StartFloorOutOfRange {
+                start_floor,
+                num_floors,
+            } => write!(
+                f,
+                "start floor {} doesn't exist, there are only {} floor(s)",
+                start_floor, num_floors
+            ),
+            DungeonValidationError::OnFloor { floor, error } => {
+                write!(f, "floor {}: {}", floor, error)
+            }
+            DungeonValidationError::EmptyGrid => write!(f, "dungeon grid has no cells"),
+            DungeonValidationError::NonRectangularRow {
+                row,
+                expected_len,
+                found_len,
+            } => write!(
+                f,
+                "row {} has {} cells but row 0 has {}",
+                row, found_len, expected_len
+            ),
+            DungeonValidationError::StartPositionOutOfBounds { position } => write!(
+                f,
+                "player start position {:?} is outside the grid",
+                position
+            ),
+            DungeonValidationError::StartPositionOnEmptyCell { position } => write!(
+                f,
+                "player start position {:?} is on an empty (0) cell",
+                position
+            ),
+            DungeonValidationError::ItemOutOfBounds {
+                item_type,
+                position,
+            } => write!(f, "{} at {:?} is outside the grid", item_type, position),
+            DungeonValidationError::ItemInEmptyCell {
+                item_type,
+                position,
+            } => write!(
+                f,
+                "{} at {:?} is placed in an empty (0) cell",
+                item_type, position
+            ),
+            DungeonValidationError::UnknownItem {
+                item_type,
+                position,
+            } => write!(
+                f,
+                "{} at {:?} isn't in the item database",
+                item_type, position
+            ),
+            DungeonValidationError::KeyOnNonKeyItem {
+                item_type,
+                position,
+            } => write!(
+                f,
+                "{} at {:?} has a key id, but it can't open locks",
+                item_type, position
+            ),
+            DungeonValidationError::DuplicateItems { position, count } => {
+                write!(f, "{} items are placed on cell {:?}", count, position)
+            }
+            DungeonValidationError::UnreachableRegion { first_cell, size } => write!(
+                f,
+                "{} cell(s) starting at {:?} can't be reached from the player start position or any stairs",
+                size, first_cell
+            ),
+            DungeonValidationError::StairOutOfBounds { position } => {
+                write!(f, "stairs at {:?} are outside the grid", position)
+            }
+            DungeonValidationError::StairInEmptyCell { position } => {
+                write!(f, "stairs at {:?} are placed in an empty (0) cell", position)
+            }
+            DungeonValidationError::StairTargetFloorMissing {
+                position,
+                target_floor,
+            } => write!(
+                f,
+                "stairs at {:?} lead to floor {}, which doesn't exist",
+                position, target_floor
+            ),
+            DungeonValidationError::StairTargetNotOpen {
+                position,
+                target_floor,
+                target_position,
+            } => write!(
+                f,
+                "stairs at {:?} lead to {:?} on floor {}, which isn't an open cell",
+                position, target_position, target_floor
+            ),
+            DungeonValidationError::EdgeOutOfBounds { position } => {
+                write!(f, "edge at {:?} is outside the grid", position)
+            }
+            DungeonValidationError::EdgeInEmptyCell { position } => {
+                write!(f, "edge at {:?} is on an empty (0) cell", position)
+            }
+            DungeonValidationError::EdgeNotHorizontal {
+                position,
+                direction,
+            } => write!(
+                f,
+                "edge at {:?} faces {:?}, only Left, Forward, Right and Back have edges",
+                position, direction
+            ),
+            DungeonValidationError::KeyOnUnlockedEdge {
+                position,
+                direction,
+            } => write!(
+                f,
+                "edge at {:?} facing {:?} needs a key but isn't a LockedDoor",
+                position, direction
+            ),
+            DungeonValidationError::EdgeLeadsNowhere {
+                position,
+                direction,
+            } => write!(
+                f,
+                "edge at {:?} facing {:?} isn't a Wall but leads to an empty (0) cell or out of \
+                 the grid",
+                position, direction
+            ),
+            DungeonValidationError::ConflictingEdges {
+                position,
+                direction,
+            } => write!(
+                f,
+                "edge at {:?} facing {:?} doesn't match the edge set on the other side",
+                position, direction
+            ),
+            DungeonValidationError::FaceOverrideOutOfBounds { position } => {
+                write!(f, "face override at {:?} is outside the grid", position)
+            }
+            DungeonValidationError::FaceOverrideInEmptyCell { position } => {
+                write!(f, "face override at {:?} is on an empty (0) cell", position)
+            }
+            DungeonValidationError::DecalOutOfBounds { position } => {
+                write!(f, "decal at {:?} is outside the grid", position)
+            }
+            DungeonValidationError::DecalInEmptyCell { position } => {
+                write!(f, "decal at {:?} is on an empty (0) cell", position)
+            }
+            DungeonValidationError::FloorTileOutOfBounds { position } => {
+                write!(f, "floor tile at {:?} is outside the grid", position)
+            }
+            DungeonValidationError::FloorTileInEmptyCell { position } => {
+                write!(f, "floor tile at {:?} is on an empty (0) cell", position)
+            }
+            DungeonValidationError::ConveyorNotHorizontal {
+                position,
+                direction,
+            } => write!(
+                f,
+                "conveyor at {:?} pushes {:?}, it can only push Left, Forward, Right or Back",
+                position, direction
+            ),
+            DungeonValidationError::TeleporterTargetNotOpen { position, target } => write!(
+                f,
+                "teleporter at {:?} leads to {:?}, which isn't an open cell",
+                position, target
+            ),
+            DungeonValidationError::PitOnBottomFloor { position } => write!(
+                f,
+                "pit at {:?} is on the bottom floor, there's nowhere to fall to",
+                position
+            ),
+            DungeonValidationError::PitLandingNotOpen { position } => write!(
+                f,
+                "pit at {:?} drops onto a cell that isn't open on the floor below",
+                position
+            ),
+            DungeonValidationError::UnknownTileset { name } => {
+                write!(f, "tileset {:?} isn't in the tileset manifest", name)
+            }
+            DungeonValidationError::UnknownDecal { name } => {
+                write!(f, "decal {:?} isn't in the tileset manifest", name)
+            }
+            DungeonValidationError::UnknownEncounterTable { name } => {
+                write!(f, "encounter table {:?} isn't in the dungeon", name)
+            }
+            DungeonValidationError::EmptyEncounterTable { name } => {
+                write!(f, "encounter table {:?} has no formations to roll", name)
+            }
+            DungeonValidationError::EncounterRegionOutOfBounds { from, to } => write!(
+                f,
+                "encounter region from {:?} to {:?} doesn't fit in the grid",
+                from, to
+            ),
+            DungeonValidationError::FoeOutOfBounds { position } => {
+                write!(f, "FOE at {:?} is outside the grid", position)
+            }
+            DungeonValidationError::FoeInEmptyCell { position } => {
+                write!(f, "FOE at {:?} is placed in an empty (0) cell", position)
+            }
+            DungeonValidationError::PatrolRouteNotOpen {
+                position,
+                route_cell,
+            } => write!(
+                f,
+                "FOE at {:?} patrols to {:?}, which isn't an open cell",
+                position, route_cell
+            ),
+        }
+    }
+}
+
+impl std::error::Error for DungeonValidationError {}
+
+impl RawDungeonFloors {
+    /// Validates every floor, plus the stairs linking them. Errors found on a single floor are
+    /// wrapped in `DungeonValidationError::OnFloor`.
+    pub fn validate(&self) -> Result<(), Vec<DungeonValidationError>> {
+        if self.floors.is_empty() {
+            return Err(vec![DungeonValidationError::NoFloors]);
+        }
+
+        let mut errors = vec![];
+        if self.start_floor >= self.floors.len() {
+            errors.push(DungeonValidationError::StartFloorOutOfRange {
+                start_floor: self.start_floor,
+                num_floors: self.floors.len(),
+            });
+        }
+
+        // keep the report order stable, the hashmap iteration order isn't
+        let mut empty_tables: Vec<_> = self
+            .encounter_tables
+            .iter()
+            .filter(|(_, table)| table.is_empty())
+            .map(|(name, _)| name.clone())
+            .collect();
+        empty_tables.sort();
+        errors.extend(
+            empty_tables
+                .into_iter()
+                .map(|name| DungeonValidationError::EmptyEncounterTable { name }),
+        );
+
+        for (floor, data) in self.floors.iter().enumerate() {
+            let mut floor_errors = data.validate().err().unwrap_or_default();
+            let region_tables = data
+                .encounter_regions
+                .iter()
+                .filter_map(|region| region.table.as_deref());
+            for name in data
+                .encounter_table
+                .as_deref()
+                .into_iter()
+                .chain(region_tables)
+            {
+                if !self.encounter_tables.contains_key(name) {
+                    floor_errors.push(DungeonValidationError::UnknownEncounterTable {
+                        name: name.to_string(),
+                    });
+                }
+            }
+            for stair in data.stairs.iter() {
+                let target_is_open = self.floors.get(stair.target_floor).map(|target| {
+                    target
+                        .raw_cell(stair.target_position)
+                        .is_some_and(|val| val > 0u8)
+                });
+                match target_is_open {
+                    None => floor_errors.push(DungeonValidationError::StairTargetFloorMissing {
+                        position: stair.position,
+                        target_floor: stair.target_floor,
+                    }),
+                    Some(false) => floor_errors.push(DungeonValidationError::StairTargetNotOpen {
+                        position: stair.position,
+                        target_floor: stair.target_floor,
+                        target_position: stair.target_position,
+                    }),
+                    Some(true) => {}
+                }
+            }
+            let pits = data
+                .floor_tiles
+                .iter()
+                .filter(|floor_tile| floor_tile.kind == FloorKind::Pit);
+            for pit in pits {
+                // pits always drop onto the same cell of the next floor down
+                let landing_is_open = self
+                    .floors
+                    .get(floor + 1)
+                    .map(|below| below.raw_cell(pit.position).is_some_and(|val| val > 0u8));
+                match landing_is_open {
+                    None => floor_errors.push(DungeonValidationError::PitOnBottomFloor {
+                        position: pit.position,
+                    }),
+                    Some(false) => floor_errors.push(DungeonValidationError::PitLandingNotOpen {
+                        position: pit.position,
+                    }),
+                    Some(true) => {}
+                }
+            }
+            errors.extend(
+                floor_errors
+                    .into_iter()
+                    .map(|error| DungeonValidationError::OnFloor {
+                        floor,
+                        error: Box::new(error),
+                    }),
+            );
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
+    /// Checks every tileset and decal the dungeon names against the ones that were actually
+    /// loaded. This is separate from `validate` since those live in their own asset.
+    pub fn validate_textures(
+        &self,
+        textures: &TilesetTextureMap,
+    ) -> Result<(), Vec<DungeonValidationError>> {
+        let unknown_tileset = |name: &str| DungeonValidationError::UnknownTileset {
+            name: name.to_string(),
+        };
+        let mut errors = vec![];
+        if let Some(name) = self
+            .tileset
+            .as_deref()
+            .filter(|name| !textures.contains_tileset(name))
+        {
+            errors.push(unknown_tileset(name));
+        }
+        for (floor, data) in self.floors.iter().enumerate() {
+            let face_override_tilesets = data
+                .face_overrides
+                .iter()
+                .filter_map(|face_override| face_override.tileset.as_deref());
+            let unknown_tilesets = data
+                .tileset
+                .as_deref()
+                .into_iter()
+                .chain(face_override_tilesets)
+                .filter(|name| !textures.contains_tileset(name))
+                .map(unknown_tileset);
+            let unknown_decals = data
+                .decals
+                .iter()
+                .filter(|decal| !textures.contains_decal(&decal.decal))
+                .map(|decal| DungeonValidationError::UnknownDecal {
+                    name: decal.decal.clone(),
+                });
+            errors.extend(unknown_tilesets.chain(unknown_decals).map(|error| {
+                DungeonValidationError::OnFloor {
+                    floor,
+                    error: Box::new(error),
+                }
+            }));
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
+    /// Checks every item the dungeon places against the item database, which is its own asset
+    /// like the tilesets.
+    pub fn validate_items(
+        &self,
+        item_database: &ItemDatabase,
+    ) -> Result<(), Vec<DungeonValidationError>> {
+        let mut errors = vec![];
+        for (floor, data) in self.floors.iter().enumerate() {
+            for item in data.items.iter() {
+                let item_type = item.item_type.clone();
+                let position = item.item_position;
+                let error = if !item_database.contains(&item.item_type) {
+                    DungeonValidationError::UnknownItem {
+                        item_type,
+                        position,
+                    }
+                } else if item.key.is_some() && !item_database.opens_locks(&item.item_type) {
+                    DungeonValidationError::KeyOnNonKeyItem {
+                        item_type,
+                        position,
+                    }
+                } else {
+                    continue;
+                };
+                errors.push(DungeonValidationError::OnFloor {
+                    floor,
+                    error: Box::new(error),
+                });
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+impl RawDungeonData {
+    /// Checks the raw data for anything that would make spawning the dungeon fail or leave it
+    /// unplayable. Every problem is collected instead of stopping at the first one so designers
+    /// can fix a map in one pass.
+    pub fn validate(&self) -> Result<(), Vec<DungeonValidationError>> {
+        let mut errors = vec![];
+
+        let num_cols = self.width();
+        if self.height() == 0 || num_cols == 0 {
+            return Err(vec![DungeonValidationError::EmptyGrid]);
+        }
+
+        for (row, cells) in self.dungeon_grid.iter().enumerate() {
+            if cells.len() != num_cols {
+                errors.push(DungeonValidationError::NonRectangularRow {
+                    row,
+                    expected_len: num_cols,
+                    found_len: cells.len(),
+                });
+            }
+        }
+
+        let start = self.player_start_position;
+        let start_is_valid = match self.raw_cell(start) {
+            None => {
+                errors.push(DungeonValidationError::StartPositionOutOfBounds { position: start });
+                false
+            }
+            Some(0) => {
+                errors.push(DungeonValidationError::StartPositionOnEmptyCell { position: start });
+                false
+            }
+            Some(_) => true,
+        };
+
+        let mut item_counts: HashMap<[u8; 2], usize> = HashMap::new();
+        for item in self.items.iter() {
+            let position = item.item_position;
+            match self.raw_cell(position) {
+                None => errors.push(DungeonValidationError::ItemOutOfBounds {
+                    item_type: item.item_type.clone(),
+                    position,
+                }),
+                Some(0) => errors.push(DungeonValidationError::ItemInEmptyCell {
+                    item_type: item.item_type.clone(),
+                    position,
+                }),
+                Some(_) => {}
+            }
+            *item_counts.entry(position).or_insert(0) += 1;
+        }
+        // keep the report order stable, the hashmap iteration order isn't
+        let mut duplicates: Vec<_> = item_counts
+            .into_iter()
+            .filter(|(_, count)| *count > 1)
+            .collect();
+        duplicates.sort();
+        for (position, count) in duplicates {
+            errors.push(DungeonValidationError::DuplicateItems { position, count });
+        }
+
+        let mut entrances = vec![];
+        if start_is_valid {
+            entrances.push(start);
+        }
+        for stair in self.stairs.iter() {
+            match self.raw_cell(stair.position) {
+                None => errors.push(DungeonValidationError::StairOutOfBounds {
+                    position: stair.position,
+                }),
+                Some(0) => errors.push(DungeonValidationError::StairInEmptyCell {
+                    position: stair.position,
+                }),
+                Some(_) => entrances.push(stair.position),
+            }
+        }
+
+        for (i, edge) in self.edges.iter().enumerate() {
+            let horizontal = !matches!(edge.direction, GridDirection::Top | GridDirection::Bottom);
+            let neighbor = GridPosition::from(edge.position).try_translated(edge.direction);
+            if self.raw_cell(edge.position).is_some_and(|cell| cell > 0)
+                && horizontal
+                && edge.kind.is_traversable()
+                && !neighbor.is_some_and(|neighbor| self.is_open(neighbor.row, neighbor.col))
+            {
+                errors.push(DungeonValidationError::EdgeLeadsNowhere {
+                    position: edge.position,
+                    direction: edge.direction,
+                });
+            }
+            // each pair of sides only gets reported once, from the first one listed
+            let other_side = neighbor.and_then(|neighbor| {
+                self.edges[i + 1..].iter().find(|other| {
+                    GridPosition::from(other.position) == neighbor
+                        && other.direction == edge.direction.get_inverse_direction()
+                })
+            });
+            if let Some(other) = other_side {
+                let kinds_agree =
+                    other.kind == edge.kind.mirrored() || edge.kind == other.kind.mirrored();
+                let keys_agree = edge.key.is_none() || other.key.is_none() || edge.key == other.key;
+                if !(kinds_agree && keys_agree) {
+                    errors.push(DungeonValidationError::ConflictingEdges {
+                        position: edge.position,
+                        direction: edge.direction,
+                    });
+                }
+            }
+            match self.raw_cell(edge.position) {
+                None => errors.push(DungeonValidationError::EdgeOutOfBounds {
+                    position: edge.position,
+                }),
+                Some(0) => errors.push(DungeonValidationError::EdgeInEmptyCell {
+                    position: edge.position,
+                }),
+                Some(_) => {}
+            }
+            if !horizontal {
+                errors.push(DungeonValidationError::EdgeNotHorizontal {
+                    position: edge.position,
+                    direction: edge.direction,
+                });
+            }
+            if edge.key.is_some() && edge.kind != EdgeKind::LockedDoor {
+                errors.push(DungeonValidationError::KeyOnUnlockedEdge {
+                    position: edge.position,
+                    direction: edge.direction,
+                });
+            }
+        }
+
+        for face_override in self.face_overrides.iter() {
+            match self.raw_cell(face_override.position) {
+                None => errors.push(DungeonValidationError::FaceOverrideOutOfBounds {
+                    position: face_override.position,
+                }),
+                Some(0) => errors.push(DungeonValidationError::FaceOverrideInEmptyCell {
+                    position: face_override.position,
+                }),
+                Some(_) => {}
+            }
+        }
+
+        for decal in self.decals.iter() {
+            match self.raw_cell(decal.position) {
+                None => errors.push(DungeonValidationError::DecalOutOfBounds {
+                    position: decal.position,
+                }),
+                Some(0) => errors.push(DungeonValidationError::DecalInEmptyCell {
+                    position: decal.position,
+                }),
+                Some(_) => {}
+            }
+        }
+
+        for floor_tile in self.floor_tiles.iter() {
+            let position = floor_tile.position;
+            match self.raw_cell(position) {
+                None => errors.push(DungeonValidationError::FloorTileOutOfBounds { position }),
+                Some(0) => errors.push(DungeonValidationError::FloorTileInEmptyCell { position }),
+                Some(_) => {}
+            }
+            match floor_tile.kind {
+                FloorKind::Conveyor { direction }
+                    if !GridDirection::HORIZONTAL.contains(&direction) =>
+                {
+                    errors.push(DungeonValidationError::ConveyorNotHorizontal {
+                        position,
+                        direction,
+                    });
+                }
+                FloorKind::Teleporter { target, .. }
+                    if self.raw_cell(target).is_none_or(|val| val == 0u8) =>
+                {
+                    errors
+                        .push(DungeonValidationError::TeleporterTargetNotOpen { position, target });
+                }
+                _ => {}
+            }
+        }
+
+        for region in self.encounter_regions.iter() {
+            if self.raw_cell(region.from).is_none() || self.raw_cell(region.to).is_none() {
+                errors.push(DungeonValidationError::EncounterRegionOutOfBounds {
+                    from: region.from,
+                    to: region.to,
+                });
+            }
+        }
+
+        for foe in self.foes.iter() {
+            let position = foe.position;
+            match self.raw_cell(position) {
+                None => errors.push(DungeonValidationError::FoeOutOfBounds { position }),
+                Some(0) => errors.push(DungeonValidationError::FoeInEmptyCell { position }),
+                Some(_) => {}
+            }
+            if let FoeBehavior::Patrol { route } = &foe.behavior {
+                for route_cell in route.iter().copied() {
+                    if self.raw_cell(route_cell).is_none_or(|val| val == 0u8) {
+                        errors.push(DungeonValidationError::PatrolRouteNotOpen {
+                            position,
+                            route_cell,
+                        });
+                    }
+                }
+            }
+        }
+
+        // reachability only makes sense if we know where the player actually comes in
+        if !entrances.is_empty() {
+            errors.extend(self.find_unreachable_regions(&entrances));
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
+    fn raw_cell(&self, position: [u8; 2]) -> Option<u8> {
+        self.dungeon_grid
+            .get(position[0] as usize)
+            .and_then(|row| row.get(position[1] as usize))
+            .copied()
+    }
+
+    fn is_open(&self, row: usize, col: usize) -> bool {
+        self.dungeon_grid
+            .get(row)
+            .and_then(|cells| cells.get(col))
+            .is_some_and(|val| *val > 0u8)
+    }
+
+    /// Open cells the player can get to in one step, taking walls, one-way edges and teleporters
+    /// into account.
+    fn open_neighbors(&self, row: usize, col: usize) -> Vec<(usize, usize)> {
+        let position = GridPosition { row, col };
+        let teleport_target = self
+            .floor_tile_at(position)
+            .and_then(|floor_tile| match floor_tile.kind {
+                FloorKind::Teleporter { target, .. } => Some(GridPosition::from(target)),
+                _ => None,
+            });
+        GridDirection::HORIZONTAL
+            .into_iter()
+            .filter(|direction| self.edge(position, *direction).is_traversable())
+            .filter_map(|direction| position.try_translated(direction))
+            .chain(teleport_target)
+            .map(|neighbor| (neighbor.row, neighbor.col))
+            .filter(|&(r, c)| self.is_open(r, c))
+            .collect()
+    }
+
+    /// Flood fills open cells outwards from `start`, marking everything it reaches in `visited`.
+    /// Returns the size of the region.
+    fn flood_fill(&self, visited: &mut [Vec<bool>], start: (usize, usize)) -> usize {
+        let mut size = 0;
+        let mut queue = VecDeque::from([start]);
+        visited[start.0][start.1] = true;
+        while let Some((row, col)) = queue.pop_front() {
+            size += 1;
+            for (r, c) in self.open_neighbors(row, col) {
+                if !visited[r][c] {
+                    visited[r][c] = true;
+                    queue.push_back((r, c));
+                }
+            }
+        }
+        size
+    }
+
+    /// Finds every region that can't be walked to from any of `entrances`.
+    fn find_unreachable_regions(&self, entrances: &[[u8; 2]]) -> Vec<DungeonValidationError> {
+        let mut visited: Vec<Vec<bool>> = self
+            .dungeon_grid
+            .iter()
+            .map(|row| vec![false; row.len()])
+            .collect();
+        for entrance in entrances {
+            let (row, col) = (entrance[0] as usize, entrance[1] as usize);
+            if !visited[row][col] {
+                self.flood_fill(&mut visited, (row, col));
+            }
+        }
+
+        let mut errors = vec![];
+        for (row, cells) in self.dungeon_grid.iter().enumerate() {
+            for col in 0..cells.len() {
+                if self.is_open(row, col) && !visited[row][col] {
+                    let size = self.flood_fill(&mut visited, (row, col));
+                    errors.push(DungeonValidationError::UnreachableRegion {
+                        first_cell: [row, col],
+                        size,
+                    });
+                }
+            }
+        }
+        errors
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use crate::modes::battle::encounter::FormationId;
+    use crate::modes::dungeon::model::cell::GridDirection;
+    use crate::modes::dungeon::model::edge::{EdgeKind, RawEdgeData};
+    use crate::modes::dungeon::model::encounter::{
+        RawEncounterFormation, RawEncounterRegion, RawEncounterTable,
+    };
+    use crate::modes::dungeon::model::floor::{FloorKind, RawFloorTileData};
+    use crate::modes::dungeon::model::foe::{FoeBehavior, RawFoeData};
+    use crate::modes::dungeon::model::grid::{
+        RawDungeonData, RawDungeonFloors, RawDungeonItemData, RawStairData,
+    };
+    use crate::modes::dungeon::model::items::ItemId;
+    use crate::modes::dungeon::model::tile::TileTexture;
+    use crate::modes::dungeon::model::tileset::test_helpers::{
+        make_test_texture_map, OTHER_TEST_TILESET, TEST_DECAL, TEST_TILESET,
+    };
+    use crate::modes::dungeon::model::tileset::{RawDecalData, RawFaceOverride};
+    use crate::modes::dungeon::model::validation::DungeonValidationError;
+    use crate::modes::sharedassets::itemdatabase::test_helpers::test_item_database;
+
+    fn make_data(dungeon_grid: Vec<Vec<u8>>, player_start_position: [u8; 2]) -> RawDungeonData {
+        RawDungeonData {
+            dungeon_grid,
+            player_start_position,
+            ..Default::default()
+        }
+    }
+
+    fn make_stair(
+        position: [u8; 2],
+        target_floor: usize,
+        target_position: [u8; 2],
+    ) -> RawStairData {
+        RawStairData {
+            position,
+            target_floor,
+            target_position,
+            target_direction: GridDirection::Forward,
+        }
+    }
+
+    #[test]
+    fn valid_dungeon_passes() {
+        let mut data = make_data(vec![vec![1, 1, 0], vec![0, 1, 1]], [0, 0]);
+        data.items.push(RawDungeonItemData {
+            item_type: ItemId::new("Key"),
+            item_position: [1, 2],
+            key: Some("red".to_string()),
+        });
+        assert_eq!(data.validate(), Ok(()));
+    }
+
+    #[test]
+    fn empty_grid_is_reported() {
+        let data = make_data(vec![], [0, 0]);
+        assert_eq!(
+            data.validate(),
+            Err(vec![DungeonValidationError::EmptyGrid])
+        );
+    }
+
+    #[test]
+    fn non_rectangular_rows_are_reported() {
+        let data = make_data(vec![vec![1, 1], vec![1], vec![1, 1, 1]], [0, 0]);
+        let errors = data.validate().unwrap_err();
+        assert!(errors.contains(&DungeonValidationError::NonRectangularRow {
+            row: 1,
+            expected_len: 2,
+            found_len: 1,
+        }));
+        assert!(errors.contains(&DungeonValidationError::NonRectangularRow {
+            row: 2,
+            expected_len: 2,
+            found_len: 3,
+        }));
+    }
+
+    #[test]
+    fn bad_start_positions_are_reported() {
+        let data = make_data(vec![vec![1, 1], vec![1, 1]], [2, 0]);
+        assert_eq!(
+            data.validate(),
+            Err(vec![DungeonValidationError::StartPositionOutOfBounds {
+                position: [2, 0]
+            }])
+        );
+
+        let data = make_data(vec![vec![0, 1], vec![1, 1]], [0, 0]);
+        assert_eq!(
+            data.validate(),
+            Err(vec![DungeonValidationError::StartPositionOnEmptyCell {
+                position: [0, 0]
+            }])
+        );
+    }
+
+    #[test]
+    fn bad_items_are_reported() {
+        let mut data = make_data(vec![vec![1, 0], vec![1, 1]], [0, 0]);
+        data.items = vec![
+            RawDungeonItemData {
+                item_type: ItemId::new("Polaroid"),
+                item_position: [0, 1],
+                key: None,
+            },
+            RawDungeonItemData {
+                item_type: ItemId::new("Maxwell"),
+                item_position: [5, 5],
+                key: None,
+            },
+            RawDungeonItemData {
+                item_type: ItemId::new("Key"),
+                item_position: [1, 1],
+                key: None,
+            },
+            RawDungeonItemData {
+                item_type: ItemId::new("Key"),
+                item_position: [1, 1],
+                key: None,
+            },
+        ];
+        assert_eq!(
+            data.validate(),
+            Err(vec![
+                DungeonValidationError::ItemInEmptyCell {
+                    item_type: ItemId::new("Polaroid"),
+                    position: [0, 1],
+                },
+                DungeonValidationError::ItemOutOfBounds {
+                    item_type: ItemId::new("Maxwell"),
+                    position: [5, 5],
+                },
+                DungeonValidationError::DuplicateItems {
+                    position: [1, 1],
+                    count: 2,
+                },
+            ])
+        );
+    }
+
+    #[test]
+    fn unknown_items_are_reported() {
+        let item_database = test_item_database();
+        let mut floor = make_data(vec![vec![1, 1, 1]], [0, 0]);
+        floor.items = vec![
+            RawDungeonItemData {
+                item_type: ItemId::new("Key"),
+                item_position: [0, 0],
+                key: Some("red".to_string()),
+            },
+            RawDungeonItemData {
+                item_type: ItemId::new("Polaroid"),
+                item_position: [0, 1],
+                key: Some("red".to_string()),
+            },
+            RawDungeonItemData {
+                item_type: ItemId::new("Dingus"),
+                item_position: [0, 2],
+                key: None,
+            },
+        ];
+        let floors = RawDungeonFloors {
+            floors: vec![make_data(vec![vec![1]], [0, 0]), floor],
+            ..Default::default()
+        };
+        let on_floor_one = |error| DungeonValidationError::OnFloor {
+            floor: 1,
+            error: Box::new(error),
+        };
+        assert_eq!(
+            floors.validate_items(&item_database),
+            Err(vec![
+                on_floor_one(DungeonValidationError::KeyOnNonKeyItem {
+                    item_type: ItemId::new("Polaroid"),
+                    position: [0, 1],
+                }),
+                on_floor_one(DungeonValidationError::UnknownItem {
+                    item_type: ItemId::new("Dingus"),
+                    position: [0, 2],
+                }),
+            ])
+        );
+    }
+
+    #[test]
+    fn unreachable_regions_are_reported() {
+        let data = make_data(
+            vec![vec![1, 1, 0, 1], vec![0, 0, 0, 1], vec![1, 0, 0, 0]],
+            [0, 0],
+        );
+        assert_eq!(
+            data.validate(),
+            Err(vec![
+                DungeonValidationError::UnreachableRegion {
+                    first_cell: [0, 3],
+                    size: 2,
+                },
+                DungeonValidationError::UnreachableRegion {
+                    first_cell: [2, 0],
+                    size: 1,
+                },
+            ])
+        );
+    }
+
+    #[test]
+    fn stairs_count_as_entrances() {
+        // the right half is only reachable by the stairs at [0, 2]
+        let mut data = make_data(vec![vec![1, 0, 1]], [0, 0]);
+        data.stairs.push(make_stair([0, 2], 0, [0, 0]));
+        assert_eq!(data.validate(), Ok(()));
+    }
+
+    fn make_edge(position: [u8; 2], direction: GridDirection, kind: EdgeKind) -> RawEdgeData {
+        RawEdgeData {
+            position,
+            direction,
+            kind,
+            key: None,
+        }
+    }
+
+    #[test]
+    fn edges_affect_reachability() {
+        // a wall cuts off the right cell
+        let mut data = make_data(vec![vec![1, 1]], [0, 0]);
+        data.edges
+            .push(make_edge([0, 0], GridDirection::Right, EdgeKind::Wall));
+        assert_eq!(
+            data.validate(),
+            Err(vec![DungeonValidationError::UnreachableRegion {
+                first_cell: [0, 1],
+                size: 1,
+            }])
+        );
+
+        // a one-way edge only lets us through from the side it's placed on
+        data.edges = vec![make_edge([0, 0], GridDirection::Right, EdgeKind::OneWay)];
+        assert_eq!(data.validate(), Ok(()));
+        data.player_start_position = [0, 1];
+        assert_eq!(
+            data.validate(),
+            Err(vec![DungeonValidationError::UnreachableRegion {
+                first_cell: [0, 0],
+                size: 1,
+            }])
+        );
+
+        // doors don't block anything, even locked ones
+        data.dungeon_grid = vec![vec![1, 1, 1]];
+        data.player_start_position = [0, 0];
+        data.edges = vec![
+            make_edge([0, 0], GridDirection::Right, EdgeKind::LockedDoor),
+            make_edge([0, 1], GridDirection::Right, EdgeKind::Door),
+        ];
+        assert_eq!(data.validate(), Ok(()));
+    }
+
+    #[test]
+    fn bad_edges_are_reported() {
+        let mut data = make_data(vec![vec![1, 0]], [0, 0]);
+        data.edges = vec![
+            make_edge([1, 0], GridDirection::Left, EdgeKind::Door),
+            make_edge([0, 1], GridDirection::Left, EdgeKind::Door),
+            make_edge([0, 0], GridDirection::Top, EdgeKind::SecretWall),
+            RawEdgeData {
+                key: Some("red".to_string()),
+                ..make_edge([0, 0], GridDirection::Back, EdgeKind::Wall)
+            },
+        ];
+        assert_eq!(
+            data.validate(),
+            Err(vec![
+                DungeonValidationError::EdgeOutOfBounds { position: [1, 0] },
+                DungeonValidationError::EdgeInEmptyCell { position: [0, 1] },
+                DungeonValidationError::EdgeNotHorizontal {
+                    position: [0, 0],
+                    direction: GridDirection::Top,
+                },
+                DungeonValidationError::KeyOnUnlockedEdge {
+                    position: [0, 0],
+                    direction: GridDirection::Back,
+                },
+            ])
+        );
+    }
+
+    #[test]
+    fn edges_must_lead_somewhere() {
+        let mut data = make_data(vec![vec![1, 1, 0]], [0, 0]);
+        // walls can face anything, since that's what's there anyway
+        data.edges = vec![
+            make_edge([0, 0], GridDirection::Forward, EdgeKind::Wall),
+            make_edge([0, 1], GridDirection::Right, EdgeKind::Wall),
+        ];
+        assert_eq!(data.validate(), Ok(()));
+
+        data.edges = vec![
+            make_edge([0, 0], GridDirection::Forward, EdgeKind::Open),
+            make_edge([0, 0], GridDirection::Left, EdgeKind::SecretWall),
+            make_edge([0, 1], GridDirection::Right, EdgeKind::OneWay),
+            make_edge([0, 1], GridDirection::Back, EdgeKind::Door),
+        ];
+        assert_eq!(
+            data.validate(),
+            Err(vec![
+                DungeonValidationError::EdgeLeadsNowhere {
+                    position: [0, 0],
+                    direction: GridDirection::Forward,
+                },
+                DungeonValidationError::EdgeLeadsNowhere {
+                    position: [0, 0],
+                    direction: GridDirection::Left,
+                },
+                DungeonValidationError::EdgeLeadsNowhere {
+                    position: [0, 1],
+                    direction: GridDirection::Right,
+                },
+                DungeonValidationError::EdgeLeadsNowhere {
+                    position: [0, 1],
+                    direction: GridDirection::Back,
+                },
+            ])
+        );
+    }
+
+    #[test]
+    fn both_sides_of_an_edge_must_agree() {
+        let mut data = make_data(vec![vec![1, 1]], [0, 0]);
+        for (kind, other_kind) in [
+            (EdgeKind::Door, EdgeKind::Door),
+            (EdgeKind::OneWay, EdgeKind::Wall),
+            (EdgeKind::SecretWall, EdgeKind::SecretWall),
+        ] {
+            data.edges = vec![
+                make_edge([0, 0], GridDirection::Right, kind),
+                make_edge([0, 1], GridDirection::Left, other_kind),
+            ];
+            assert_eq!(data.validate(), Ok(()), "{:?} and {:?}", kind, other_kind);
+        }
+
+        for (kind, other_kind) in [
+            (EdgeKind::Open, EdgeKind::Wall),
+            (EdgeKind::Door, EdgeKind::SecretWall),
+            (EdgeKind::OneWay, EdgeKind::OneWay),
+        ] {
+            data.edges = vec![
+                make_edge([0, 0], GridDirection::Right, kind),
+                make_edge([0, 1], GridDirection::Left, other_kind),
+            ];
+            assert_eq!(
+                data.validate(),
+                Err(vec![DungeonValidationError::ConflictingEdges {
+                    position: [0, 0],
+                    direction: GridDirection::Right,
+                }]),
+                "{:?} and {:?}",
+                kind,
+                other_kind
+            );
+        }
+
+        // locked from both sides, but with different keys
+        data.edges = vec![
+            RawEdgeData {
+                key: Some("red".to_string()),
+                ..make_edge([0, 0], GridDirection::Right, EdgeKind::LockedDoor)
+            },
+            RawEdgeData {
+                key: Some("blue".to_string()),
+                ..make_edge([0, 1], GridDirection::Left, EdgeKind::LockedDoor)
+            },
+        ];
+        assert_eq!(
+            data.validate(),
+            Err(vec![DungeonValidationError::ConflictingEdges {
+                position: [0, 0],
+                direction: GridDirection::Right,
+            }])
+        );
+    }
+
+    #[test]
+    fn bad_face_overrides_are_reported() {
+        let mut data = make_data(vec![vec![1, 0]], [0, 0]);
+        let make_override = |position| RawFaceOverride {
+            position,
+            direction: GridDirection::Top,
+            texture: TileTexture::Ceiling,
+            variant: None,
+            tileset: None,
+        };
+        data.face_overrides = vec![
+            make_override([0, 0]),
+            make_override([0, 1]),
+            make_override([3, 0]),
+        ];
+        assert_eq!(
+            data.validate(),
+            Err(vec![
+                DungeonValidationError::FaceOverrideInEmptyCell { position: [0, 1] },
+                DungeonValidationError::FaceOverrideOutOfBounds { position: [3, 0] },
+            ])
+        );
+    }
+
+    #[test]
+    fn bad_decals_are_reported() {
+        let mut data = make_data(vec![vec![1, 0]], [0, 0]);
+        let make_decal = |position| RawDecalData {
+            position,
+            direction: GridDirection::Forward,
+            decal: TEST_DECAL.to_string(),
+        };
+        data.decals = vec![make_decal([0, 0]), make_decal([0, 1]), make_decal([0, 2])];
+        assert_eq!(
+            data.validate(),
+            Err(vec![
+                DungeonValidationError::DecalInEmptyCell { position: [0, 1] },
+                DungeonValidationError::DecalOutOfBounds { position: [0, 2] },
+            ])
+        );
+    }
+
+    #[test]
+    fn bad_floor_tiles_are_reported() {
+        let mut data = make_data(vec![vec![1, 0]], [0, 0]);
+        let make_floor_tile = |position, kind| RawFloorTileData { position, kind };
+        data.floor_tiles = vec![
+            make_floor_tile([0, 1], FloorKind::Ice),
+            make_floor_tile([2, 0], FloorKind::Darkness),
+            make_floor_tile(
+                [0, 0],
+                FloorKind::Conveyor {
+                    direction: GridDirection::Bottom,
+                },
+            ),
+            make_floor_tile(
+                [0, 0],
+                FloorKind::Teleporter {
+                    target: [0, 1],
+                    direction: None,
+                },
+            ),
+        ];
+        assert_eq!(
+            data.validate(),
+            Err(vec![
+                DungeonValidationError::FloorTileInEmptyCell { position: [0, 1] },
+                DungeonValidationError::FloorTileOutOfBounds { position: [2, 0] },
+                DungeonValidationError::ConveyorNotHorizontal {
+                    position: [0, 0],
+                    direction: GridDirection::Bottom,
+                },
+                DungeonValidationError::TeleporterTargetNotOpen {
+                    position: [0, 0],
+                    target: [0, 1],
+                },
+            ])
+        );
+    }
+
+    #[test]
+    fn teleporters_affect_reachability() {
+        let mut data = make_data(vec![vec![1, 0, 1]], [0, 0]);
+        data.floor_tiles.push(RawFloorTileData {
+            position: [0, 0],
+            kind: FloorKind::Teleporter {
+                target: [0, 2],
+                direction: None,
+            },
+        });
+        assert_eq!(data.validate(), Ok(()));
+    }
+
+    #[test]
+    fn bad_pits_are_reported() {
+        let pit = |position| RawFloorTileData {
+            position,
+            kind: FloorKind::Pit,
+        };
+        let mut top = make_data(vec![vec![1, 1]], [0, 0]);
+        top.floor_tiles = vec![pit([0, 0]), pit([0, 1])];
+        let mut bottom = make_data(vec![vec![1, 0]], [0, 0]);
+        bottom.floor_tiles = vec![pit([0, 0])];
+        let floors = RawDungeonFloors {
+            floors: vec![top, bottom],
+            ..Default::default()
+        };
+        assert_eq!(
+            floors.validate(),
+            Err(vec![
+                DungeonValidationError::OnFloor {
+                    floor: 0,
+                    error: Box::new(DungeonValidationError::PitLandingNotOpen { position: [0, 1] }),
+                },
+                DungeonValidationError::OnFloor {
+                    floor: 1,
+                    error: Box::new(DungeonValidationError::PitOnBottomFloor { position: [0, 0] }),
+                },
+            ])
+        );
+    }
+
+    #[test]
+    fn unknown_textures_are_reported() {
+        let textures = make_test_texture_map();
+        let mut floor = make_data(vec![vec![1]], [0, 0]);
+        floor.tileset = Some(OTHER_TEST_TILESET.to_string());
+        floor.face_overrides.push(RawFaceOverride {
+            position: [0, 0],
+            direction: GridDirection::Left,
+            texture: TileTexture::Wall,
+            variant: None,
+            tileset: Some(TEST_TILESET.to_string()),
+        });
+        floor.decals.push(RawDecalData {
+            position: [0, 0],
+            direction: GridDirection::Left,
+            decal: TEST_DECAL.to_string(),
+        });
+        let mut floors = RawDungeonFloors {
+            floors: vec![floor],
+            tileset: Some(TEST_TILESET.to_string()),
+            ..Default::default()
+        };
+        assert_eq!(floors.validate_textures(&textures), Ok(()));
+
+        floors.tileset = Some("sewer".to_string());
+        floors.floors[0].tileset = Some("moon".to_string());
+        floors.floors[0].decals[0].decal = "graffiti".to_string();
+        let on_floor_zero = |error| DungeonValidationError::OnFloor {
+            floor: 0,
+            error: Box::new(error),
+        };
+        assert_eq!(
+            floors.validate_textures(&textures),
+            Err(vec![
+                DungeonValidationError::UnknownTileset {
+                    name: "sewer".to_string()
+                },
+                on_floor_zero(DungeonValidationError::UnknownTileset {
+                    name: "moon".to_string()
+                }),
+                on_floor_zero(DungeonValidationError::UnknownDecal {
+                    name: "graffiti".to_string()
+                }),
+            ])
+        );
+    }
+
+    #[test]
+    fn multi_floor_dungeon_passes() {
+        let mut top = make_data(vec![vec![1, 1]], [0, 0]);
+        top.stairs.push(make_stair([0, 1], 1, [0, 0]));
+        let mut bottom = make_data(vec![vec![1], vec![1]], [1, 0]);
+        bottom.stairs.push(make_stair([0, 0], 0, [0, 1]));
+        let floors = RawDungeonFloors {
+            floors: vec![top, bottom],
+            ..Default::default()
+        };
+        assert_eq!(floors.validate(), Ok(()));
+    }
+
+    #[test]
+    fn bad_floors_are_reported() {
+        let floors = RawDungeonFloors {
+            ..Default::default()
+        };
+        assert_eq!(
+            floors.validate(),
+            Err(vec![DungeonValidationError::NoFloors])
+        );
+
+        let mut top = make_data(vec![vec![1, 1]], [0, 0]);
+        top.stairs = vec![
+            make_stair([0, 1], 2, [0, 0]),
+            make_stair([0, 0], 1, [0, 1]),
+            make_stair([0, 2], 1, [0, 0]),
+        ];
+        let bottom = make_data(vec![vec![1, 0]], [0, 0]);
+        let floors = RawDungeonFloors {
+            floors: vec![top, bottom],
+            start_floor: 3,
+            ..Default::default()
+        };
+        let on_floor_zero = |error| DungeonValidationError::OnFloor {
+            floor: 0,
+            error: Box::new(error),
+        };
+        assert_eq!(
+            floors.validate(),
+            Err(vec![
+                DungeonValidationError::StartFloorOutOfRange {
+                    start_floor: 3,
+                    num_floors: 2,
+                },
+                on_floor_zero(DungeonValidationError::StairOutOfBounds { position: [0, 2] }),
+                on_floor_zero(DungeonValidationError::StairTargetFloorMissing {
+                    position: [0, 1],
+                    target_floor: 2,
+                }),
+                on_floor_zero(DungeonValidationError::StairTargetNotOpen {
+                    position: [0, 0],
+                    target_floor: 1,
+                    target_position: [0, 1],
+                }),
+            ])
+        );
+    }
+
+    #[test]
+    fn bad_encounters_are_reported() {
+        let make_table = |formations| RawEncounterTable {
+            danger_per_step: 0.1,
+            formations,
+        };
+        let mut floor = make_data(vec![vec![1, 1], vec![1, 1]], [0, 0]);
+        floor.encounter_table = Some("halls".to_string());
+        floor.encounter_regions = vec![
+            RawEncounterRegion {
+                from: [0, 0],
+                to: [1, 0],
+                table: Some("lair".to_string()),
+            },
+            RawEncounterRegion {
+                from: [0, 1],
+                to: [2, 1],
+                table: None,
+            },
+        ];
+        let mut floors = RawDungeonFloors {
+            floors: vec![floor],
+            ..Default::default()
+        };
+        floors.encounter_tables.insert(
+            "halls".to_string(),
+            make_table(vec![RawEncounterFormation {
+                formation: FormationId("slimes".to_string()),
+                weight: 1,
+            }]),
+        );
+        floors
+            .encounter_tables
+            .insert("empty".to_string(), make_table(vec![]));
+        let on_floor_zero = |error| DungeonValidationError::OnFloor {
+            floor: 0,
+            error: Box::new(error),
+        };
+        assert_eq!(
+            floors.validate(),
+            Err(vec![
+                DungeonValidationError::EmptyEncounterTable {
+                    name: "empty".to_string()
+                },
+                on_floor_zero(DungeonValidationError::EncounterRegionOutOfBounds {
+                    from: [0, 1],
+                    to: [2, 1],
+                }),
+                on_floor_zero(DungeonValidationError::UnknownEncounterTable {
+                    name: "lair".to_string()
+                }),
+            ])
+        );
+    }
+
+    #[test]
+    fn bad_foes_are_reported() {
+        let make_foe = |position, behavior| RawFoeData {
+            position,
+            direction: GridDirection::Forward,
+            formation: FormationId("slimes".to_string()),
+            model: "slime.glb#Scene0".to_string(),
+            behavior,
+        };
+        let mut data = make_data(vec![vec![1, 1, 0]], [0, 0]);
+        data.foes = vec![
+            make_foe(
+                [0, 1],
+                FoeBehavior::Patrol {
+                    route: vec![[0, 0], [0, 2], [1, 0]],
+                },
+            ),
+            make_foe([0, 2], FoeBehavior::Wander),
+            make_foe([3, 0], FoeBehavior::Chase { sight: 2 }),
+        ];
+        assert_eq!(
+            data.validate(),
+            Err(vec![
+                DungeonValidationError::PatrolRouteNotOpen {
+                    position: [0, 1],
+                    route_cell: [0, 2],
+                },
+                DungeonValidationError::PatrolRouteNotOpen {
+                    position: [0, 1],
+                    route_cell: [1, 0],
+                },
+                DungeonValidationError::FoeInEmptyCell { position: [0, 2] },
+                DungeonValidationError::FoeOutOfBounds { position: [3, 0] },
+            ])
+        );
+    }
+}
@@ -0,0 +1,335 @@
+use std::time::Duration;
+
+use bevy::prelude::*;
+use bevy::utils::HashSet;
+use bevy_tweening::lens::{TransformPositionLens, TransformScaleLens};
+use bevy_tweening::{Animator, EaseFunction, EaseMethod, Tracks, Tween, TweenCompleted};
+
+use crate::modes::dungeon::dungeonmode::CurrentFloor;
+use crate::modes::dungeon::dungeonplayer::{try_move_player, PlayerMoveCompleted};
+use crate::modes::dungeon::model::cell::GridPosition;
+use crate::modes::dungeon::model::items::{DungeonItem, ItemId};
+use crate::modes::inventory::Inventory;
+use crate::modes::mode_state::GameModeState;
+use crate::modes::sharedassets::itemdatabase::ItemDatabase;
+
+const COLLECT_ANIMATION_DURATION: f32 = 0.4;
+/// `TweenCompleted::user_data` for the collect animation.
+const COLLECT_TWEEN_COMPLETED: u64 = 0x17E3;
+
+/// Sent when the player picks an item up.
+#[derive(Event)]
+pub struct ItemCollected {
+    pub item: ItemId,
+    pub grid_position: GridPosition,
+    pub key: Option<String>,
+}
+
+/// An item that's been picked up and is playing its collect animation. It's despawned once that's
+/// done.
+#[derive(Component)]
+pub struct CollectingItem;
+
+/// Every item that's been picked up, by floor and position, so they aren't spawned again when the
+/// player comes back to a floor.
+#[derive(Resource, Default)]
+pub struct CollectedItems(HashSet<(usize, GridPosition)>);
+
+impl CollectedItems {
+    pub fn contains(&self, floor: usize, grid_position: GridPosition) -> bool {
+        self.0.contains(&(floor, grid_position))
+    }
+}
+
+pub struct ItemPickupPlugin;
+
+impl Plugin for ItemPickupPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Inventory>()
+            .init_resource::<CollectedItems>()
+            .add_event::<ItemCollected>()
+            .add_systems(
+                Update,
+                (
+                    pick_up_items.after(try_move_player),
+                    store_collected_items.after(pick_up_items),
+                )
+                    .run_if(in_state(GameModeState::InDungeon)),
+            )
+            .add_systems(Update, despawn_collected_items);
+    }
+}
+
+/// Floats the item up while shrinking it away.
+fn collect_tween(transform: &Transform) -> Tracks<Transform> {
+    let duration = Duration::from_secs_f32(COLLECT_ANIMATION_DURATION);
+    let ease = EaseMethod::EaseFunction(EaseFunction::QuadraticIn);
+    Tracks::new([
+        Tween::new(
+            ease,
+            duration,
+            TransformPositionLens {
+                start: transform.translation,
+                end: transform.translation + 0.5 * Vec3::Y,
+            },
+        )
+        .with_completed_event(COLLECT_TWEEN_COMPLETED),
+        Tween::new(
+            ease,
+            duration,
+            TransformScaleLens {
+                start: transform.scale,
+                end: Vec3::ZERO,
+            },
+        ),
+    ])
+}
+
+/// Picks up whatever's on the cell the player just stepped onto, as long as there's room for it.
+fn pick_up_items(
+    mut commands: Commands,
+    mut move_completed_reader: EventReader<PlayerMoveCompleted>,
+    mut item_collected_writer: EventWriter<ItemCollected>,
+    item_query: Query<(Entity, &DungeonItem, &ItemId, &GridPosition, &Transform)>,
+    inventory: Res<Inventory>,
+    item_database: Res<ItemDatabase>,
+) {
+    for event in move_completed_reader.iter() {
+        for (entity, dungeon_item, item, grid_pos, transform) in item_query.iter() {
+            if *grid_pos != event.grid_position {
+                continue;
+            }
+            if !inventory.has_room_for(item, &item_database) {
+                info!("no room for {}", item_database.name(item));
+                continue;
+            }
+            item_collected_writer.send(ItemCollected {
+                item: item.clone(),
+                grid_position: *grid_pos,
+                key: dungeon_item.key.clone(),
+            });
+            commands
+                .entity(entity)
+                .remove::<DungeonItem>()
+                .insert((CollectingItem, Animator::new(collect_tween(transform))));
+        }
+    }
+}
+
+fn store_collected_items(
+    mut item_collected_reader: EventReader<ItemCollected>,
+    current_floor: Res<CurrentFloor>,
+    mut inventory: ResMut<Inventory>,
+    mut collected_items: ResMut<CollectedItems>,
+    item_database: Res<ItemDatabase>,
+) {
+    for event in item_collected_reader.iter() {
+        let added = inventory.add(&event.item, event.key.as_deref(), &item_database);
+        match added {
+            Ok(()) => info!(
+                "picked up {}, carrying {}",
+                item_database.name(&event.item),
+                inventory.count(&event.item)
+            ),
+            // two items on the same cell can both pass the check in pick_up_items
+            Err(_) => warn!("no room left for {}, it's lost", event.item),
+        }
+        collected_items
+            .0
+            .insert((current_floor.0, event.grid_position));
+    }
+}
+
+fn despawn_collected_items(
+    mut commands: Commands,
+    mut tween_completed_reader: EventReader<TweenCompleted>,
+    collecting_query: Query<(), With<CollectingItem>>,
+) {
+    for event in tween_completed_reader.iter() {
+        if event.user_data == COLLECT_TWEEN_COMPLETED && collecting_query.contains(event.entity) {
+            commands.entity(event.entity).despawn_recursive();
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use bevy::prelude::*;
+    use bevy_tweening::{Animator, TweenCompleted};
+
+    use crate::modes::dungeon::dungeoninput::test_helpers::setup_keyboard_movement;
+    use crate::modes::dungeon::dungeonmode::test_helpers::setup_test_dungeon_assets;
+    use crate::modes::dungeon::dungeonmode::DungeonMode;
+    use crate::modes::dungeon::dungeonplayer::DungeonPlayer;
+    use crate::modes::dungeon::model::cell::test_helpers::setup_test_tile_preset_map;
+    use crate::modes::dungeon::model::cell::{GridDirection, GridPosition};
+    use crate::modes::dungeon::model::grid::test_helpers::setup_dungeon_tile_lookup;
+    use crate::modes::dungeon::model::grid::{RawDungeonData, RawDungeonItemData};
+    use crate::modes::dungeon::model::items::{DungeonItem, ItemId};
+    use crate::modes::dungeon::pickup::{
+        CollectedItems, CollectingItem, ItemCollected, ItemPickupPlugin, COLLECT_TWEEN_COMPLETED,
+    };
+    use crate::modes::dungeon::stairs::{FloorChange, StairsPlugin};
+    use crate::modes::inventory::Inventory;
+    use crate::modes::mode_state::GameModeState;
+    use crate::modes::sharedassets::itemdatabase::ItemDatabase;
+
+    /// A row of three cells with the player on the left, a key in the middle and a polaroid on the
+    /// right.
+    fn setup() -> App {
+        let data = RawDungeonData {
+            dungeon_grid: vec![vec![1, 1, 1]],
+            player_start_position: [0, 0],
+            player_start_direction: GridDirection::Right,
+            items: vec![
+                RawDungeonItemData {
+                    item_type: ItemId::new("Key"),
+                    item_position: [0, 1],
+                    key: Some("red".to_string()),
+                },
+                RawDungeonItemData {
+                    item_type: ItemId::new("Polaroid"),
+                    item_position: [0, 2],
+                    key: None,
+                },
+            ],
+            ..Default::default()
+        };
+        let mut app = App::new();
+        setup_test_tile_preset_map(&mut app);
+        setup_dungeon_tile_lookup(&mut app);
+        setup_test_dungeon_assets(&mut app, data);
+        app.add_state::<GameModeState>()
+            .add_event::<TweenCompleted>()
+            .insert_resource(Input::<KeyCode>::default())
+            .add_plugins((StairsPlugin, ItemPickupPlugin))
+            .add_systems(
+                Startup,
+                (
+                    DungeonMode::initialize_preset_map,
+                    (
+                        DungeonMode::spawn_grid,
+                        DungeonMode::spawn_items,
+                        DungeonMode::setup_player,
+                    )
+                        .after(DungeonMode::initialize_preset_map),
+                ),
+            );
+        setup_keyboard_movement(&mut app);
+        app.world
+            .resource_mut::<NextState<GameModeState>>()
+            .set(GameModeState::InDungeon);
+        app.update();
+        app
+    }
+
+    fn step_forward(app: &mut App) {
+        app.world
+            .resource_mut::<Input<KeyCode>>()
+            .press(KeyCode::Up);
+        app.update();
+        app.world
+            .resource_mut::<Input<KeyCode>>()
+            .release(KeyCode::Up);
+        app.world
+            .query_filtered::<&mut Animator<Transform>, With<DungeonPlayer>>()
+            .single_mut(&mut app.world)
+            .tweenable_mut()
+            .set_elapsed(Duration::from_secs(1));
+        app.update();
+    }
+
+    fn items_on_floor(app: &mut App) -> Vec<(ItemId, GridPosition)> {
+        let mut items: Vec<(ItemId, GridPosition)> = app
+            .world
+            .query_filtered::<(&ItemId, &GridPosition), With<DungeonItem>>()
+            .iter(&app.world)
+            .map(|(item, grid_pos)| (item.clone(), *grid_pos))
+            .collect();
+        items.sort_by_key(|(_, grid_pos)| grid_pos.col);
+        items
+    }
+
+    #[test]
+    fn items_know_where_they_are() {
+        let mut app = setup();
+        assert_eq!(
+            items_on_floor(&mut app),
+            vec![
+                (ItemId::new("Key"), GridPosition { row: 0, col: 1 }),
+                (ItemId::new("Polaroid"), GridPosition { row: 0, col: 2 }),
+            ]
+        );
+    }
+
+    #[test]
+    fn should_pick_up_items_when_stepped_on() {
+        let mut app = setup();
+        step_forward(&mut app);
+
+        let events = app.world.resource::<Events<ItemCollected>>();
+        let collected: Vec<(ItemId, Option<String>)> = events
+            .get_reader()
+            .iter(events)
+            .map(|event| (event.item.clone(), event.key.clone()))
+            .collect();
+        assert_eq!(
+            collected,
+            vec![(ItemId::new("Key"), Some("red".to_string()))]
+        );
+
+        let inventory = app.world.resource::<Inventory>();
+        let item_database = app.world.resource::<ItemDatabase>();
+        assert_eq!(inventory.count(&ItemId::new("Key")), 1);
+        assert!(inventory.can_unlock(Some("red"), item_database));
+        assert_eq!(
+            items_on_floor(&mut app),
+            vec![(ItemId::new("Polaroid"), GridPosition { row: 0, col: 2 })]
+        );
+    }
+
+    #[test]
+    fn should_despawn_items_once_collected() {
+        let mut app = setup();
+        step_forward(&mut app);
+        let collecting = app
+            .world
+            .query_filtered::<Entity, With<CollectingItem>>()
+            .single(&app.world);
+
+        // the tweening plugin isn't running, so pretend the animation finished
+        app.world.send_event(TweenCompleted {
+            entity: collecting,
+            user_data: COLLECT_TWEEN_COMPLETED,
+        });
+        app.update();
+        assert!(app.world.get_entity(collecting).is_none());
+    }
+
+    #[test]
+    fn should_not_respawn_collected_items() {
+        let mut app = setup();
+        step_forward(&mut app);
+        assert!(app
+            .world
+            .resource::<CollectedItems>()
+            .contains(0, GridPosition { row: 0, col: 1 }));
+
+        // come back to the same floor
+        app.world.insert_resource(FloorChange {
+            floor: 0,
+            grid_position: GridPosition { row: 0, col: 0 },
+            direction: GridDirection::Right,
+        });
+        app.world
+            .resource_mut::<NextState<GameModeState>>()
+            .set(GameModeState::ChangingFloor);
+        app.update();
+        assert_eq!(
+            items_on_floor(&mut app),
+            vec![(ItemId::new("Polaroid"), GridPosition { row: 0, col: 2 })]
+        );
+    }
+}
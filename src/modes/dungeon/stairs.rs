@@ -0,0 +1,215 @@
+use bevy::prelude::*;
+use bevy_tweening::{Animator, AnimatorState};
+
+use crate::modes::dungeon::dungeonmode::{
+    CurrentFloor, DungeonAssets, DungeonFloorEntity, DungeonMode,
+};
+use crate::modes::dungeon::dungeonplayer::{
+    player_transform, try_move_player, DungeonPlayer, DungeonPlayerMovementState,
+    PlayerMoveCompleted,
+};
+use crate::modes::dungeon::foes::spawn_foes;
+use crate::modes::dungeon::model::cell::{GridDirection, GridPosition};
+use crate::modes::dungeon::model::grid::RawDungeonFloors;
+use crate::modes::mode_state::GameModeState;
+use crate::utils::utilsystems::cleanup_system;
+
+/// Where the player ends up once `GameModeState::ChangingFloor` is entered.
+#[derive(Resource, Clone, Copy, Debug)]
+pub struct FloorChange {
+    pub floor: usize,
+    pub grid_position: GridPosition,
+    pub direction: GridDirection,
+}
+
+pub struct StairsPlugin;
+
+impl Plugin for StairsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            take_stairs
+                .after(try_move_player)
+                .run_if(in_state(GameModeState::InDungeon)),
+        )
+        .add_systems(
+            OnEnter(GameModeState::ChangingFloor),
+            (
+                set_current_floor,
+                cleanup_system::<DungeonFloorEntity>,
+                DungeonMode::spawn_grid,
+                DungeonMode::spawn_items,
+                spawn_foes,
+                place_player,
+                finish_floor_change,
+            )
+                .chain(),
+        );
+    }
+}
+
+pub fn take_stairs(
+    mut commands: Commands,
+    mut move_completed_reader: EventReader<PlayerMoveCompleted>,
+    raw_dungeon_floors: Res<Assets<RawDungeonFloors>>,
+    dungeon_assets: Res<DungeonAssets>,
+    current_floor: Res<CurrentFloor>,
+    mut next_state: ResMut<NextState<GameModeState>>,
+) {
+    let data = dungeon_assets.current_floor(&raw_dungeon_floors, &current_floor);
+    for event in move_completed_reader.iter() {
+        if let Some(stair) = data.stair_at(event.grid_position) {
+            commands.insert_resource(FloorChange {
+                floor: stair.target_floor,
+                grid_position: stair.target_position.into(),
+                direction: stair.target_direction,
+            });
+            next_state.set(GameModeState::ChangingFloor);
+        }
+    }
+}
+
+fn set_current_floor(floor_change: Res<FloorChange>, mut current_floor: ResMut<CurrentFloor>) {
+    current_floor.0 = floor_change.floor;
+}
+
+#[allow(clippy::type_complexity)]
+fn place_player(
+    floor_change: Res<FloorChange>,
+    mut player_query: Query<
+        (
+            &mut Transform,
+            &mut Animator<Transform>,
+            &mut GridPosition,
+            &mut GridDirection,
+            &mut DungeonPlayerMovementState,
+        ),
+        With<DungeonPlayer>,
+    >,
+) {
+    let (mut transform, mut animator, mut grid_pos, mut grid_direction, mut movement_state) =
+        player_query.single_mut();
+    // whatever move we were in the middle of doesn't matter anymore
+    animator.state = AnimatorState::Paused;
+    *movement_state = DungeonPlayerMovementState::Stationary;
+    *grid_pos = floor_change.grid_position;
+    *grid_direction = floor_change.direction;
+    *transform = player_transform(floor_change.grid_position, floor_change.direction);
+}
+
+fn finish_floor_change(mut commands: Commands, mut next_state: ResMut<NextState<GameModeState>>) {
+    commands.remove_resource::<FloorChange>();
+    next_state.set(GameModeState::InDungeon);
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use bevy::prelude::*;
+    use bevy_tweening::Animator;
+
+    use crate::modes::dungeon::dungeoninput::test_helpers::setup_keyboard_movement;
+    use crate::modes::dungeon::dungeonmode::test_helpers::setup_test_dungeon_floors;
+    use crate::modes::dungeon::dungeonmode::{CurrentFloor, DungeonMode};
+    use crate::modes::dungeon::model::cell::test_helpers::setup_test_tile_preset_map;
+    use crate::modes::dungeon::model::cell::{GridDirection, GridPosition};
+    use crate::modes::dungeon::model::grid::test_helpers::setup_dungeon_tile_lookup;
+    use crate::modes::dungeon::model::grid::{RawDungeonData, RawDungeonFloors, RawStairData};
+    use crate::modes::dungeon::stairs::StairsPlugin;
+    use crate::modes::mode_state::GameModeState;
+
+    fn setup() -> App {
+        let top = RawDungeonData {
+            dungeon_grid: vec![vec![1, 1]],
+            player_start_position: [0, 0],
+            player_start_direction: GridDirection::Right,
+            stairs: vec![RawStairData {
+                position: [0, 1],
+                target_floor: 1,
+                target_position: [2, 0],
+                target_direction: GridDirection::Forward,
+            }],
+            ..Default::default()
+        };
+        let bottom = RawDungeonData {
+            dungeon_grid: vec![vec![1], vec![1], vec![1]],
+            player_start_position: [0, 0],
+            player_start_direction: GridDirection::Back,
+            stairs: vec![RawStairData {
+                position: [2, 0],
+                target_floor: 0,
+                target_position: [0, 1],
+                target_direction: GridDirection::Left,
+            }],
+            ..Default::default()
+        };
+        let mut app = App::new();
+        setup_test_tile_preset_map(&mut app);
+        setup_dungeon_tile_lookup(&mut app);
+        setup_test_dungeon_floors(
+            &mut app,
+            RawDungeonFloors {
+                floors: vec![top, bottom],
+                ..Default::default()
+            },
+        );
+        app.add_state::<GameModeState>()
+            .insert_resource(Input::<KeyCode>::default())
+            .add_plugins(StairsPlugin)
+            .add_systems(
+                Startup,
+                (
+                    DungeonMode::initialize_preset_map,
+                    (DungeonMode::spawn_grid, DungeonMode::setup_player)
+                        .after(DungeonMode::initialize_preset_map),
+                ),
+            );
+        setup_keyboard_movement(&mut app);
+        app.world
+            .resource_mut::<NextState<GameModeState>>()
+            .set(GameModeState::InDungeon);
+        app.update();
+        app
+    }
+
+    #[test]
+    fn should_take_stairs_to_linked_floor() {
+        let mut app = setup();
+        app.world
+            .resource_mut::<Input<KeyCode>>()
+            .press(KeyCode::Up);
+        app.update();
+
+        // finish walking onto the stairs
+        app.world
+            .resource_mut::<Input<KeyCode>>()
+            .release(KeyCode::Up);
+        app.world
+            .query::<&mut Animator<Transform>>()
+            .single_mut(&mut app.world)
+            .tweenable_mut()
+            .set_elapsed(Duration::from_secs(1));
+        app.update();
+        assert_eq!(
+            *app.world.resource::<State<GameModeState>>().get(),
+            GameModeState::InDungeon
+        );
+
+        // the floor change happens on the next state transition
+        app.update();
+        assert_eq!(app.world.resource::<CurrentFloor>().0, 1);
+        let (grid_pos, grid_direction) = app
+            .world
+            .query::<(&GridPosition, &GridDirection)>()
+            .single(&app.world);
+        assert_eq!(*grid_pos, GridPosition { row: 2, col: 0 });
+        assert_eq!(*grid_direction, GridDirection::Forward);
+
+        app.update();
+        assert_eq!(
+            *app.world.resource::<State<GameModeState>>().get(),
+            GameModeState::InDungeon
+        );
+    }
+}
@@ -0,0 +1,391 @@
+use bevy::app::AppExit;
+use bevy::prelude::*;
+
+use crate::modes::battle::battlemode::BattleModeEntity;
+use crate::modes::battle::encounter::BattleRequest;
+use crate::modes::battle::outcome::{BattleOutcome, BattleResult};
+use crate::modes::dungeon::doors::DoorStates;
+use crate::modes::dungeon::dungeonmode::DungeonFloorEntity;
+use crate::modes::dungeon::dungeonplayer::DungeonPlayer;
+use crate::modes::dungeon::encounters::DangerMeter;
+use crate::modes::dungeon::foes::DefeatedFoes;
+use crate::modes::dungeon::model::grid::DungeonTileLookup;
+use crate::modes::dungeon::pickup::CollectedItems;
+use crate::modes::inventory::Inventory;
+use crate::modes::mode_state::GameModeState;
+use crate::modes::party::Party;
+use crate::modes::sharedassets::characterdatabase::CharacterDatabase;
+use crate::modes::sharedassets::shared::FontAssets;
+use crate::utils::utilsystems::cleanup_system;
+
+/// Everything on the game over screen.
+#[derive(Component)]
+pub struct GameOverEntity;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GameOverChoice {
+    RetryBattle,
+    Restart,
+    Quit,
+}
+
+/// The inventory as it was when the battle started. Retrying puts it back, so items used up in
+/// the lost battle can be used again.
+#[derive(Resource)]
+pub struct InventorySnapshot(pub Inventory);
+
+/// Shows the game over screen once the party is wiped out, either by losing a battle or on a
+/// damage floor.
+pub struct GameOverPlugin;
+
+impl Plugin for GameOverPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            end_game_on_defeat
+                .run_if(in_state(GameModeState::InBattle))
+                .run_if(resource_exists::<BattleOutcome>()),
+        )
+        .add_systems(
+            OnEnter(GameModeState::GameOver),
+            (cleanup_system::<BattleModeEntity>, show_game_over_screen),
+        )
+        .add_systems(
+            Update,
+            choose_option.run_if(in_state(GameModeState::GameOver)),
+        )
+        .add_systems(
+            OnExit(GameModeState::GameOver),
+            cleanup_system::<GameOverEntity>,
+        )
+        .add_systems(OnEnter(GameModeState::InBattle), snapshot_inventory);
+    }
+}
+
+/// R retries the battle (only if the party died in one), L restarts from the beginning and Q
+/// quits.
+pub fn choice_from_keys(
+    keyboard_input: &Input<KeyCode>,
+    can_retry: bool,
+) -> Option<GameOverChoice> {
+    if can_retry && keyboard_input.just_pressed(KeyCode::R) {
+        Some(GameOverChoice::RetryBattle)
+    } else if keyboard_input.just_pressed(KeyCode::L) {
+        Some(GameOverChoice::Restart)
+    } else if keyboard_input.just_pressed(KeyCode::Q) {
+        Some(GameOverChoice::Quit)
+    } else {
+        None
+    }
+}
+
+fn snapshot_inventory(mut commands: Commands, inventory: Res<Inventory>) {
+    commands.insert_resource(InventorySnapshot(inventory.clone()));
+}
+
+fn end_game_on_defeat(
+    battle_outcome: Res<BattleOutcome>,
+    mut next_state: ResMut<NextState<GameModeState>>,
+) {
+    if battle_outcome.result == BattleResult::Defeat {
+        next_state.set(GameModeState::GameOver);
+    }
+}
+
+fn show_game_over_screen(
+    mut commands: Commands,
+    battle_request: Option<Res<BattleRequest>>,
+    font_assets: Res<FontAssets>,
+) {
+    commands.spawn((
+        GameOverEntity,
+        Camera2dBundle {
+            camera: Camera {
+                order: 2,
+                ..default()
+            },
+            ..default()
+        },
+    ));
+    let root = NodeBundle {
+        style: Style {
+            width: Val::Percent(100.0),
+            height: Val::Percent(100.0),
+            flex_direction: FlexDirection::Column,
+            align_items: AlignItems::Center,
+            justify_content: JustifyContent::Center,
+            row_gap: Val::Px(10.0),
+            ..default()
+        },
+        background_color: Color::BLACK.into(),
+        ..default()
+    };
+    let text_style = TextStyle {
+        font: font_assets.ui_font.clone(),
+        font_size: 20.0,
+        color: Color::WHITE,
+    };
+    let mut options = vec![];
+    if battle_request.is_some() {
+        options.push("R - Retry the battle");
+    }
+    options.extend(["L - Restart from the beginning", "Q - Quit"]);
+    commands
+        .spawn((GameOverEntity, root))
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section(
+                "The party was wiped out",
+                TextStyle {
+                    font_size: 40.0,
+                    color: Color::RED,
+                    ..text_style.clone()
+                },
+            ));
+            for option in options {
+                parent.spawn(TextBundle::from_section(option, text_style.clone()));
+            }
+        });
+}
+
+#[allow(clippy::type_complexity)]
+fn choose_option(
+    mut commands: Commands,
+    keyboard_input: Res<Input<KeyCode>>,
+    (battle_request, inventory_snapshot): (
+        Option<Res<BattleRequest>>,
+        Option<Res<InventorySnapshot>>,
+    ),
+    dungeon_query: Query<Entity, Or<(With<DungeonFloorEntity>, With<DungeonPlayer>)>>,
+    mut next_state: ResMut<NextState<GameModeState>>,
+    mut exit_writer: EventWriter<AppExit>,
+    character_database: Res<CharacterDatabase>,
+) {
+    let Some(choice) = choice_from_keys(&keyboard_input, battle_request.is_some()) else {
+        return;
+    };
+    match choice {
+        // the request is still around, so the same formation gets spawned again
+        GameOverChoice::RetryBattle => {
+            commands.remove_resource::<BattleOutcome>();
+            if let Some(inventory_snapshot) = inventory_snapshot {
+                commands.insert_resource(inventory_snapshot.0.clone());
+            }
+            next_state.set(GameModeState::LoadingBattle);
+        }
+        // there's no saving yet, so everything starts over: back to the dungeon entrance with
+        // the party as it was at the start
+        GameOverChoice::Restart => {
+            for entity in dungeon_query.iter() {
+                commands.entity(entity).despawn_recursive();
+            }
+            reset_progress(&mut commands, &character_database);
+            next_state.set(GameModeState::LoadingDungeon);
+        }
+        GameOverChoice::Quit => exit_writer.send(AppExit),
+    }
+}
+
+/// Forgets everything that happened since the game started, so loading the dungeon again starts
+/// from a clean world.
+fn reset_progress(commands: &mut Commands, character_database: &CharacterDatabase) {
+    commands.remove_resource::<BattleOutcome>();
+    commands.remove_resource::<BattleRequest>();
+    commands.remove_resource::<InventorySnapshot>();
+    commands.remove_resource::<DungeonTileLookup>();
+    commands.insert_resource(Inventory::default());
+    commands.insert_resource(CollectedItems::default());
+    commands.insert_resource(DefeatedFoes::default());
+    commands.insert_resource(DoorStates::default());
+    commands.insert_resource(DangerMeter::default());
+    commands.insert_resource(Party::new(character_database));
+}
+
+#[cfg(test)]
+mod test {
+    use bevy::app::AppExit;
+    use bevy::prelude::*;
+
+    use crate::modes::battle::battlemode::BattleModeEntity;
+    use crate::modes::battle::encounter::{BattleInitiator, BattleRequest, FormationId};
+    use crate::modes::battle::outcome::{BattleOutcome, BattleResult};
+    use crate::modes::dungeon::dungeonmode::DungeonFloorEntity;
+    use crate::modes::dungeon::dungeonplayer::DungeonPlayer;
+    use crate::modes::dungeon::foes::DefeatedFoes;
+    use crate::modes::dungeon::model::grid::DungeonTileLookup;
+    use crate::modes::dungeon::model::items::ItemId;
+    use crate::modes::gameover::gameovermode::{
+        choice_from_keys, GameOverChoice, GameOverEntity, GameOverPlugin, InventorySnapshot,
+    };
+    use crate::modes::inventory::Inventory;
+    use crate::modes::mode_state::GameModeState;
+    use crate::modes::party::test_helpers::test_party;
+    use crate::modes::party::Party;
+    use crate::modes::sharedassets::characterdatabase::test_helpers::test_character_database;
+    use crate::modes::sharedassets::itemdatabase::test_helpers::test_item_database;
+    use crate::modes::sharedassets::shared::FontAssets;
+
+    #[test]
+    fn retrying_needs_a_battle() {
+        let mut keyboard_input = Input::<KeyCode>::default();
+        keyboard_input.press(KeyCode::R);
+        assert_eq!(
+            choice_from_keys(&keyboard_input, true),
+            Some(GameOverChoice::RetryBattle)
+        );
+        assert_eq!(choice_from_keys(&keyboard_input, false), None);
+        keyboard_input.press(KeyCode::Q);
+        assert_eq!(
+            choice_from_keys(&keyboard_input, false),
+            Some(GameOverChoice::Quit)
+        );
+        keyboard_input.press(KeyCode::L);
+        assert_eq!(
+            choice_from_keys(&keyboard_input, false),
+            Some(GameOverChoice::Restart)
+        );
+    }
+
+    /// Loses a battle against the slimes with a floor and a battle already spawned. The party
+    /// goes in with two tonics and uses one up.
+    fn lose_battle() -> App {
+        let item_database = test_item_database();
+        let mut inventory = Inventory::default();
+        for _ in 0..2 {
+            inventory
+                .add(&ItemId::new("Tonic"), None, &item_database)
+                .unwrap();
+        }
+        let mut app = App::new();
+        app.add_state::<GameModeState>()
+            .add_event::<AppExit>()
+            .add_plugins(GameOverPlugin)
+            .insert_resource(Input::<KeyCode>::default())
+            .insert_resource(FontAssets {
+                ui_font: Handle::default(),
+            })
+            .insert_resource(DungeonTileLookup::default())
+            .insert_resource(DefeatedFoes([(0, 1)].into_iter().collect()))
+            .insert_resource(test_character_database())
+            .insert_resource(test_party())
+            .insert_resource(inventory)
+            .insert_resource(BattleRequest {
+                formation: FormationId("slimes".to_string()),
+                terrain: None,
+                initiator: BattleInitiator::RandomEncounter,
+            });
+        app.world.spawn(DungeonFloorEntity);
+        app.world.spawn(DungeonPlayer);
+        app.world.spawn(BattleModeEntity);
+        app.world.resource_mut::<Party>().members_mut()[0].hp = 7;
+        app.world
+            .resource_mut::<NextState<GameModeState>>()
+            .set(GameModeState::InBattle);
+        app.update();
+        assert!(app
+            .world
+            .resource_mut::<Inventory>()
+            .remove(&ItemId::new("Tonic")));
+        app.world.insert_resource(BattleOutcome {
+            result: BattleResult::Defeat,
+            xp: 0,
+            drops: vec![],
+            hp_changes: vec![],
+        });
+        app.update();
+        app.update();
+        app
+    }
+
+    fn game_mode_state(app: &App) -> GameModeState {
+        *app.world.resource::<State<GameModeState>>().get()
+    }
+
+    fn count<T: Component>(app: &mut App) -> usize {
+        app.world
+            .query_filtered::<(), With<T>>()
+            .iter(&app.world)
+            .count()
+    }
+
+    fn press(app: &mut App, key_code: KeyCode) {
+        app.world.resource_mut::<Input<KeyCode>>().press(key_code);
+        app.update();
+        app.update();
+    }
+
+    #[test]
+    fn losing_a_battle_is_game_over() {
+        let mut app = lose_battle();
+        assert_eq!(game_mode_state(&app), GameModeState::GameOver);
+        assert_eq!(count::<BattleModeEntity>(&mut app), 0);
+        // camera, root and the text
+        assert!(count::<GameOverEntity>(&mut app) >= 2);
+        // the dungeon sticks around in case the battle is retried
+        assert_eq!(count::<DungeonFloorEntity>(&mut app), 1);
+    }
+
+    #[test]
+    fn winning_a_battle_is_not() {
+        let mut app = App::new();
+        app.add_state::<GameModeState>()
+            .add_plugins(GameOverPlugin)
+            .init_resource::<Inventory>()
+            .insert_resource(BattleOutcome {
+                result: BattleResult::Victory,
+                xp: 0,
+                drops: vec![],
+                hp_changes: vec![],
+            });
+        app.world
+            .resource_mut::<NextState<GameModeState>>()
+            .set(GameModeState::InBattle);
+        app.update();
+        app.update();
+        assert_eq!(game_mode_state(&app), GameModeState::InBattle);
+    }
+
+    #[test]
+    fn retrying_fights_the_same_battle() {
+        let mut app = lose_battle();
+        press(&mut app, KeyCode::R);
+        assert_eq!(game_mode_state(&app), GameModeState::LoadingBattle);
+        assert!(app.world.get_resource::<BattleOutcome>().is_none());
+        assert_eq!(
+            app.world.resource::<BattleRequest>().formation,
+            FormationId("slimes".to_string())
+        );
+        assert_eq!(count::<GameOverEntity>(&mut app), 0);
+        assert_eq!(count::<DungeonFloorEntity>(&mut app), 1);
+        // the tonic used in the lost battle is back
+        assert_eq!(
+            app.world
+                .resource::<Inventory>()
+                .count(&ItemId::new("Tonic")),
+            2
+        );
+    }
+
+    #[test]
+    fn restarting_starts_from_a_clean_world() {
+        let mut app = lose_battle();
+        press(&mut app, KeyCode::L);
+        assert_eq!(game_mode_state(&app), GameModeState::LoadingDungeon);
+        assert_eq!(count::<DungeonFloorEntity>(&mut app), 0);
+        assert_eq!(count::<DungeonPlayer>(&mut app), 0);
+        assert_eq!(count::<GameOverEntity>(&mut app), 0);
+        assert!(app.world.get_resource::<DungeonTileLookup>().is_none());
+        assert!(app.world.get_resource::<BattleRequest>().is_none());
+        assert!(app.world.get_resource::<InventorySnapshot>().is_none());
+        assert!(app.world.resource::<Inventory>().stacks().is_empty());
+        assert!(app.world.resource::<DefeatedFoes>().0.is_empty());
+        let party = app.world.resource::<Party>();
+        assert_eq!(party.members()[0].hp, 60);
+    }
+
+    #[test]
+    fn quitting_exits() {
+        let mut app = lose_battle();
+        press(&mut app, KeyCode::Q);
+        assert!(!app.world.resource::<Events<AppExit>>().is_empty());
+    }
+}
@@ -0,0 +1,208 @@
+use bevy::prelude::Resource;
+use bevy::utils::HashSet;
+
+use crate::modes::dungeon::model::items::ItemId;
+use crate::modes::sharedassets::itemdatabase::ItemDatabase;
+
+/// One inventory slot.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ItemStack {
+    pub item: ItemId,
+    pub count: u32,
+}
+
+/// Returned when there's no room left for an item.
+#[derive(Debug, PartialEq, Eq)]
+pub struct InventoryFull;
+
+/// Everything the party has picked up, in slots of up to `ItemInfo::max_stack`. The party gets
+/// `ItemDatabase::inventory_capacity` slots.
+#[derive(Resource, Clone, Default)]
+pub struct Inventory {
+    stacks: Vec<ItemStack>,
+    /// Ids of every key picked up, keys without an id only show up in `stacks`.
+    keys: HashSet<String>,
+}
+
+impl Inventory {
+    pub fn add(
+        &mut self,
+        item: &ItemId,
+        key: Option<&str>,
+        item_database: &ItemDatabase,
+    ) -> Result<(), InventoryFull> {
+        let max_stack = item_database.max_stack(item);
+        if let Some(stack) = self
+            .stacks
+            .iter_mut()
+            .find(|stack| stack.item == *item && stack.count < max_stack)
+        {
+            stack.count += 1;
+        } else if self.stacks.len() < item_database.inventory_capacity() {
+            self.stacks.push(ItemStack {
+                item: item.clone(),
+                count: 1,
+            });
+        } else {
+            return Err(InventoryFull);
+        }
+        if let Some(key) = key {
+            self.keys.insert(key.to_string());
+        }
+        Ok(())
+    }
+
+    /// Whether `add` would succeed.
+    pub fn has_room_for(&self, item: &ItemId, item_database: &ItemDatabase) -> bool {
+        let max_stack = item_database.max_stack(item);
+        self.stacks.len() < item_database.inventory_capacity()
+            || self
+                .stacks
+                .iter()
+                .any(|stack| stack.item == *item && stack.count < max_stack)
+    }
+
+    /// Takes one `item` out of the inventory. Returns `false` if there weren't any.
+    pub fn remove(&mut self, item: &ItemId) -> bool {
+        let Some(index) = self.stacks.iter().rposition(|stack| stack.item == *item) else {
+            return false;
+        };
+        self.stacks[index].count -= 1;
+        if self.stacks[index].count == 0 {
+            self.stacks.remove(index);
+        }
+        true
+    }
+
+    pub fn count(&self, item: &ItemId) -> u32 {
+        self.stacks
+            .iter()
+            .filter(|stack| stack.item == *item)
+            .map(|stack| stack.count)
+            .sum()
+    }
+
+    /// Every slot that's in use, in the order they were filled.
+    pub fn stacks(&self) -> &[ItemStack] {
+        &self.stacks
+    }
+
+    /// Whether the party can open a lock needing `key`. Locks without a key id can be opened with
+    /// any item that `opens_locks`.
+    pub fn can_unlock(&self, key: Option<&str>, item_database: &ItemDatabase) -> bool {
+        match key {
+            Some(key) => self.keys.contains(key),
+            None => self
+                .stacks
+                .iter()
+                .any(|stack| item_database.opens_locks(&stack.item)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::modes::dungeon::model::items::ItemId;
+    use crate::modes::inventory::{Inventory, InventoryFull, ItemStack};
+    use crate::modes::sharedassets::itemdatabase::test_helpers::test_item_database;
+
+    #[test]
+    fn items_are_counted() {
+        let item_database = test_item_database();
+        let key = ItemId::new("Key");
+        let polaroid = ItemId::new("Polaroid");
+        let mut inventory = Inventory::default();
+        assert_eq!(inventory.count(&key), 0);
+        inventory.add(&key, None, &item_database).unwrap();
+        inventory.add(&key, None, &item_database).unwrap();
+        inventory.add(&polaroid, None, &item_database).unwrap();
+        assert_eq!(inventory.count(&key), 2);
+        assert_eq!(inventory.count(&polaroid), 1);
+        assert_eq!(inventory.count(&ItemId::new("Maxwell")), 0);
+    }
+
+    #[test]
+    fn items_stack_until_full() {
+        let item_database = test_item_database();
+        let key = ItemId::new("Key");
+        let polaroid = ItemId::new("Polaroid");
+        let mut inventory = Inventory::default();
+        for _ in 0..4 {
+            inventory.add(&key, None, &item_database).unwrap();
+        }
+        inventory.add(&polaroid, None, &item_database).unwrap();
+        assert_eq!(
+            inventory.stacks(),
+            &[
+                ItemStack {
+                    item: key.clone(),
+                    count: 3
+                },
+                ItemStack {
+                    item: key.clone(),
+                    count: 1
+                },
+                ItemStack {
+                    item: polaroid.clone(),
+                    count: 1
+                },
+            ]
+        );
+
+        // every slot is used, but there's still room on the second stack of keys
+        assert!(!inventory.has_room_for(&polaroid, &item_database));
+        assert_eq!(
+            inventory.add(&polaroid, None, &item_database),
+            Err(InventoryFull)
+        );
+        assert!(inventory.has_room_for(&key, &item_database));
+        inventory.add(&key, None, &item_database).unwrap();
+        inventory.add(&key, None, &item_database).unwrap();
+        assert_eq!(
+            inventory.add(&key, None, &item_database),
+            Err(InventoryFull)
+        );
+        assert_eq!(inventory.count(&key), 6);
+    }
+
+    #[test]
+    fn keys_unlock_their_locks() {
+        let item_database = test_item_database();
+        let mut inventory = Inventory::default();
+        inventory
+            .add(&ItemId::new("Polaroid"), None, &item_database)
+            .unwrap();
+        assert!(!inventory.can_unlock(None, &item_database));
+        assert!(!inventory.can_unlock(Some("red"), &item_database));
+
+        inventory
+            .add(&ItemId::new("Key"), Some("red"), &item_database)
+            .unwrap();
+        assert!(inventory.can_unlock(None, &item_database));
+        assert!(inventory.can_unlock(Some("red"), &item_database));
+        assert!(!inventory.can_unlock(Some("blue"), &item_database));
+    }
+
+    #[test]
+    fn used_items_leave_the_inventory() {
+        let item_database = test_item_database();
+        let tonic = ItemId::new("Tonic");
+        let mut inventory = Inventory::default();
+        for _ in 0..4 {
+            inventory.add(&tonic, None, &item_database).unwrap();
+        }
+        assert!(inventory.remove(&tonic));
+        assert_eq!(
+            inventory.stacks(),
+            &[ItemStack {
+                item: tonic.clone(),
+                count: 3
+            }]
+        );
+        for _ in 0..3 {
+            assert!(inventory.remove(&tonic));
+        }
+        assert!(!inventory.remove(&tonic));
+        assert!(inventory.stacks().is_empty());
+    }
+}
@@ -1,5 +1,8 @@
 pub mod battle;
 pub mod dungeon;
+pub mod gameover;
+pub mod inventory;
 pub mod mode_state;
+pub mod party;
 pub mod pause;
 pub mod sharedassets;
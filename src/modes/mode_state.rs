@@ -4,19 +4,20 @@ use bevy::prelude::{States, SystemSet};
 pub enum GameModeState {
     #[default]
     LoadingSharedAssets,
+    InvalidCharacters, // the character database failed validation, see CharacterValidationReport
     LoadingDungeon,
+    InvalidDungeon, // the dungeon data failed validation, see DungeonValidationReport
     InDungeon,
+    ChangingFloor, // despawns the current floor and spawns the one in FloorChange
     LoadingBattle,
     InBattle,
     ExitingBattle, // used for tile transition. there might be a better way to do this
     Paused,
+    GameOver, // the party was wiped out, see GameOverPlugin
 }
 
 impl GameModeState {
     pub fn can_pause(&self) -> bool {
-        match self {
-            GameModeState::InDungeon => true,
-            _ => false,
-        }
+        matches!(self, GameModeState::InDungeon)
     }
 }
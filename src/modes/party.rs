@@ -0,0 +1,278 @@
+use bevy::prelude::{warn, Commands, Res, Resource};
+
+use crate::modes::battle::affinity::Affinities;
+use crate::modes::battle::combatant::{Combatant, Side, Stats};
+use crate::modes::battle::skilldatabase::SkillId;
+use crate::modes::sharedassets::characterdatabase::{
+    CharacterDatabase, CharacterId, CharacterInfo, Equipment,
+};
+use crate::modes::sharedassets::itemdatabase::ItemDatabase;
+
+pub const MAX_PARTY_SIZE: usize = 4;
+
+/// Each level takes this much more XP than the one before it.
+pub const XP_PER_LEVEL: u32 = 50;
+
+/// Total XP needed to reach `level`, so 0 for level 1, 50 for level 2, 150 for level 3 and so on.
+pub fn xp_for_level(level: u32) -> u32 {
+    XP_PER_LEVEL * level * level.saturating_sub(1) / 2
+}
+
+/// Returned when there's no room left in the party.
+#[derive(Debug, PartialEq, Eq)]
+pub struct PartyFull;
+
+/// One character in the party, and everything about them that changes as the game goes on.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PartyMember {
+    pub character: CharacterId,
+    pub name: String,
+    pub level: u32,
+    /// Everything earned so far, not just since the last level up.
+    pub xp: u32,
+    pub hp: u32,
+    pub mp: u32,
+    pub max_hp: u32,
+    pub max_mp: u32,
+    /// Without the equipment.
+    pub base_stats: Stats,
+    pub equipment: Equipment,
+    pub skills: Vec<SkillId>,
+    pub affinities: Affinities,
+}
+
+impl PartyMember {
+    /// Level 1 with full HP and MP.
+    pub fn new(character: &CharacterId, info: &CharacterInfo) -> Self {
+        PartyMember {
+            character: character.clone(),
+            name: info.name.clone(),
+            level: 1,
+            xp: 0,
+            hp: info.max_hp,
+            mp: info.max_mp,
+            max_hp: info.max_hp,
+            max_mp: info.max_mp,
+            base_stats: info.stats,
+            equipment: info.equipment.clone(),
+            skills: info.skills_at(1),
+            affinities: info.affinities.clone(),
+        }
+    }
+
+    pub fn is_alive(&self) -> bool {
+        self.hp > 0
+    }
+
+    /// The base stats plus whatever the equipment adds.
+    pub fn stats(&self, item_database: &ItemDatabase) -> Stats {
+        self.equipment.items().fold(self.base_stats, |stats, item| {
+            stats + item_database.bonus(item)
+        })
+    }
+
+    /// The member as they are right now, ready to fight.
+    pub fn combatant(&self, item_database: &ItemDatabase) -> Combatant {
+        Combatant {
+            hp: self.hp,
+            mp: self.mp,
+            ..Combatant::new(
+                &self.name,
+                Side::Party,
+                self.max_hp,
+                self.max_mp,
+                self.stats(item_database),
+            )
+            .with_affinities(self.affinities.clone())
+            .with_skills(self.skills.clone())
+        }
+    }
+
+    /// Returns how many levels were gained. Every level adds the character's growth, on top of
+    /// HP and MP as well as their maximum, and teaches whatever skills come with it.
+    pub fn gain_xp(&mut self, xp: u32, info: &CharacterInfo) -> u32 {
+        self.xp += xp;
+        let mut levels = 0;
+        while self.xp >= xp_for_level(self.level + 1) {
+            self.level += 1;
+            levels += 1;
+            self.max_hp += info.growth.max_hp;
+            self.hp += info.growth.max_hp;
+            self.max_mp += info.growth.max_mp;
+            self.mp += info.growth.max_mp;
+            self.base_stats = self.base_stats + info.growth.stats;
+        }
+        for skill in info.skills_at(self.level) {
+            if !self.skills.contains(&skill) {
+                self.skills.push(skill);
+            }
+        }
+        levels
+    }
+}
+
+/// Everyone travelling together, up to `MAX_PARTY_SIZE` of them. This is what battles spawn the
+/// party's combatants from, and where their HP and MP go once a battle's over.
+#[derive(Resource, Default, Debug)]
+pub struct Party {
+    members: Vec<PartyMember>,
+}
+
+impl Party {
+    /// The database's starting party at level 1. Characters missing from the database are left
+    /// out.
+    pub fn new(character_database: &CharacterDatabase) -> Self {
+        let mut party = Party::default();
+        for id in character_database.starting_party() {
+            let Some(info) = character_database.get(id) else {
+                warn!("starting party member {} isn't in the database", id);
+                continue;
+            };
+            if party.add(PartyMember::new(id, info)).is_err() {
+                warn!("no room in the party for {}", id);
+            }
+        }
+        party
+    }
+
+    pub fn add(&mut self, member: PartyMember) -> Result<(), PartyFull> {
+        if self.members.len() >= MAX_PARTY_SIZE {
+            return Err(PartyFull);
+        }
+        self.members.push(member);
+        Ok(())
+    }
+
+    pub fn members(&self) -> &[PartyMember] {
+        &self.members
+    }
+
+    pub fn members_mut(&mut self) -> &mut [PartyMember] {
+        &mut self.members
+    }
+
+    pub fn is_wiped_out(&self) -> bool {
+        !self.members.iter().any(PartyMember::is_alive)
+    }
+}
+
+/// A fresh party, for when the game starts or starts over.
+pub fn start_new_party(mut commands: Commands, character_database: Res<CharacterDatabase>) {
+    commands.insert_resource(Party::new(&character_database));
+}
+
+#[cfg(test)]
+pub mod test_helpers {
+    use crate::modes::party::Party;
+    use crate::modes::sharedassets::characterdatabase::test_helpers::test_character_database;
+
+    /// Just the hero from `test_character_database`, at level 1.
+    pub fn test_party() -> Party {
+        Party::new(&test_character_database())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::modes::battle::combatant::{Side, Stats};
+    use crate::modes::battle::skilldatabase::SkillId;
+    use crate::modes::party::test_helpers::test_party;
+    use crate::modes::party::{xp_for_level, PartyFull, PartyMember, MAX_PARTY_SIZE};
+    use crate::modes::sharedassets::characterdatabase::test_helpers::test_character_database;
+    use crate::modes::sharedassets::characterdatabase::CharacterId;
+    use crate::modes::sharedassets::itemdatabase::test_helpers::test_item_database;
+
+    #[test]
+    fn starts_with_the_starting_party() {
+        let party = test_party();
+        assert_eq!(party.members().len(), 1);
+        let hero = &party.members()[0];
+        assert_eq!(hero.name, "Hero");
+        assert_eq!(hero.level, 1);
+        assert_eq!((hero.hp, hero.max_hp), (60, 60));
+        assert_eq!((hero.mp, hero.max_mp), (20, 20));
+        assert_eq!(hero.skills, [SkillId("Zap".to_string())]);
+    }
+
+    #[test]
+    fn equipment_adds_to_stats() {
+        let party = test_party();
+        let hero = &party.members()[0];
+        assert_eq!(
+            hero.stats(&test_item_database()),
+            Stats {
+                attack: 12,
+                defense: 8 + 5,
+                magic: 10,
+                speed: 10,
+            }
+        );
+    }
+
+    #[test]
+    fn combatant_keeps_hp_and_mp() {
+        let mut party = test_party();
+        party.members_mut()[0].hp = 25;
+        party.members_mut()[0].mp = 3;
+        let combatant = party.members()[0].combatant(&test_item_database());
+        assert_eq!(combatant.side, Side::Party);
+        assert_eq!((combatant.hp, combatant.max_hp), (25, 60));
+        assert_eq!((combatant.mp, combatant.max_mp), (3, 20));
+        assert_eq!(combatant.stats.defense, 13);
+        assert_eq!(combatant.skills, [SkillId("Zap".to_string())]);
+    }
+
+    #[test]
+    fn xp_needed_grows_every_level() {
+        assert_eq!(xp_for_level(1), 0);
+        assert_eq!(xp_for_level(2), 50);
+        assert_eq!(xp_for_level(3), 150);
+        assert_eq!(xp_for_level(4), 300);
+    }
+
+    #[test]
+    fn levelling_up_grows_the_character() {
+        let character_database = test_character_database();
+        let info = character_database
+            .get(&CharacterId("Hero".to_string()))
+            .unwrap();
+        let mut party = test_party();
+        let hero = &mut party.members_mut()[0];
+        hero.hp = 10;
+
+        assert_eq!(hero.gain_xp(49, info), 0);
+        assert_eq!(hero.level, 1);
+        // straight past level 2 and into 3
+        assert_eq!(hero.gain_xp(101, info), 2);
+        assert_eq!(hero.level, 3);
+        assert_eq!(hero.xp, 150);
+        assert_eq!((hero.hp, hero.max_hp), (10 + 16, 76));
+        assert_eq!((hero.mp, hero.max_mp), (26, 26));
+        assert_eq!(hero.base_stats.attack, 16);
+        assert_eq!(
+            hero.skills,
+            [SkillId("Zap".to_string()), SkillId("Blaze".to_string())]
+        );
+    }
+
+    #[test]
+    fn party_has_a_size_limit() {
+        let character_database = test_character_database();
+        let id = CharacterId("Sidekick".to_string());
+        let sidekick = PartyMember::new(&id, character_database.get(&id).unwrap());
+        let mut party = test_party();
+        for _ in 1..MAX_PARTY_SIZE {
+            assert_eq!(party.add(sidekick.clone()), Ok(()));
+        }
+        assert_eq!(party.add(sidekick), Err(PartyFull));
+        assert_eq!(party.members().len(), MAX_PARTY_SIZE);
+    }
+
+    #[test]
+    fn wiped_out_once_everyone_is_down() {
+        let mut party = test_party();
+        assert!(!party.is_wiped_out());
+        party.members_mut()[0].hp = 0;
+        assert!(party.is_wiped_out());
+    }
+}
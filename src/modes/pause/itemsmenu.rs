@@ -0,0 +1,209 @@
+use bevy::app::App;
+use bevy::prelude::{
+    default, in_state, AlignItems, BuildChildren, ButtonBundle, Changed, Color, Commands,
+    Component, DespawnRecursiveExt, Entity, FlexDirection, ImageBundle, Input, IntoSystemConfigs,
+    JustifyContent, KeyCode, NextState, NodeBundle, OnEnter, Plugin, Query, Res, ResMut, Style,
+    Text, TextBundle, TextStyle, UiImage, UiRect, Update, Val, With,
+};
+use bevy_ui_navigation::components::FocusableButtonBundle;
+use bevy_ui_navigation::prelude::*;
+
+use crate::modes::dungeon::model::items::ItemId;
+use crate::modes::inventory::Inventory;
+use crate::modes::mode_state::GameModeState;
+use crate::modes::pause::optionsmenu::option_button_hover;
+use crate::modes::pause::pausemode::{PauseMenuState, PauseModeEntity};
+use crate::modes::sharedassets::itemdatabase::{ItemAssets, ItemDatabase};
+use crate::modes::sharedassets::shared::FontAssets;
+
+#[derive(Component)]
+struct ItemsMenuRoot;
+
+/// One inventory slot in the list.
+#[derive(Component)]
+struct ItemButton(ItemId);
+
+/// Shows the name, description and uses of the focused item.
+#[derive(Component)]
+struct ItemDetailsText;
+
+fn item_details(item: &ItemId, item_database: &ItemDatabase) -> String {
+    let Some(info) = item_database.get(item) else {
+        return item_database.name(item);
+    };
+    let usable = match (info.usable_in_dungeon, info.usable_in_battle) {
+        (true, true) => "Usable anywhere",
+        (true, false) => "Usable in the dungeon",
+        (false, true) => "Usable in battle",
+        (false, false) => "Can't be used",
+    };
+    format!("{}\n\n{}\n\n{}", info.name, info.description, usable)
+}
+
+fn spawn_items_menu(
+    mut commands: Commands,
+    font_assets: Res<FontAssets>,
+    item_assets: Res<ItemAssets>,
+    inventory: Res<Inventory>,
+    item_database: Res<ItemDatabase>,
+) {
+    let text_style = TextStyle {
+        font: font_assets.ui_font.clone(),
+        font_size: 15.0,
+        color: Color::WHITE,
+    };
+    let root = NodeBundle {
+        style: Style {
+            width: Val::Percent(100.0),
+            height: Val::Percent(100.0),
+            justify_content: JustifyContent::Center,
+            align_items: AlignItems::Center,
+            ..default()
+        },
+        background_color: Color::NONE.into(),
+        ..default()
+    };
+    let items_menu_bg = NodeBundle {
+        style: Style {
+            width: Val::Percent(95.0),
+            height: Val::Percent(75.0),
+            justify_content: JustifyContent::SpaceEvenly,
+            align_items: AlignItems::Center,
+            ..default()
+        },
+        background_color: Color::hex("#B9C6D8").unwrap().into(),
+        ..default()
+    };
+    let item_list = NodeBundle {
+        style: Style {
+            width: Val::Percent(45.0),
+            height: Val::Percent(90.0),
+            flex_direction: FlexDirection::Column,
+            justify_content: JustifyContent::FlexStart,
+            ..default()
+        },
+        ..default()
+    };
+    let details_panel = NodeBundle {
+        style: Style {
+            width: Val::Percent(45.0),
+            height: Val::Percent(90.0),
+            padding: UiRect::all(Val::Px(10.0)),
+            ..default()
+        },
+        background_color: Color::GRAY.into(),
+        ..default()
+    };
+
+    commands
+        .spawn((root, PauseModeEntity, ItemsMenuRoot))
+        .with_children(|parent| {
+            parent.spawn(items_menu_bg).with_children(|bg| {
+                bg.spawn(item_list).with_children(|list| {
+                    if inventory.stacks().is_empty() {
+                        list.spawn(TextBundle::from_section("No items", text_style.clone()));
+                    }
+                    for stack in inventory.stacks() {
+                        let icon = item_database
+                            .get(&stack.item)
+                            .and_then(|info| item_assets.icons.get(&info.icon))
+                            .cloned()
+                            .unwrap_or_default();
+                        list.spawn((
+                            FocusableButtonBundle {
+                                button_bundle: ButtonBundle {
+                                    style: Style {
+                                        width: Val::Percent(100.0),
+                                        height: Val::Px(40.0),
+                                        border: UiRect::all(Val::Px(4.0)),
+                                        margin: UiRect::bottom(Val::Px(4.0)),
+                                        padding: UiRect::horizontal(Val::Px(8.0)),
+                                        justify_content: JustifyContent::SpaceBetween,
+                                        align_items: AlignItems::Center,
+                                        ..default()
+                                    },
+                                    background_color: Color::GRAY.into(),
+                                    border_color: Color::BLACK.into(),
+                                    ..default()
+                                },
+                                ..default()
+                            },
+                            ItemButton(stack.item.clone()),
+                        ))
+                        .with_children(|button| {
+                            button.spawn(ImageBundle {
+                                style: Style {
+                                    width: Val::Px(24.0),
+                                    height: Val::Px(24.0),
+                                    ..default()
+                                },
+                                image: UiImage::new(icon),
+                                ..default()
+                            });
+                            button.spawn(TextBundle::from_section(
+                                item_database.name(&stack.item),
+                                text_style.clone(),
+                            ));
+                            button.spawn(TextBundle::from_section(
+                                format!("x{}", stack.count),
+                                text_style.clone(),
+                            ));
+                        });
+                    }
+                });
+                bg.spawn(details_panel).with_children(|panel| {
+                    panel.spawn((
+                        TextBundle::from_section("", text_style.clone()),
+                        ItemDetailsText,
+                    ));
+                });
+            });
+        });
+}
+
+fn show_focused_item_details(
+    item_button_query: Query<(&Focusable, &ItemButton), Changed<Focusable>>,
+    mut details_text_query: Query<&mut Text, With<ItemDetailsText>>,
+    item_database: Res<ItemDatabase>,
+) {
+    for (focus, item_button) in item_button_query.iter() {
+        if focus.state() != FocusState::Focused {
+            continue;
+        }
+        for mut text in details_text_query.iter_mut() {
+            text.sections[0].value = item_details(&item_button.0, &item_database);
+        }
+    }
+}
+
+fn close_items_menu(
+    items_menu_root_query: Query<Entity, With<ItemsMenuRoot>>,
+    mut next_state: ResMut<NextState<PauseMenuState>>,
+    mut commands: Commands,
+    input: Res<Input<KeyCode>>,
+) {
+    if input.just_pressed(KeyCode::X) {
+        next_state.set(PauseMenuState::Stationary);
+        commands
+            .entity(items_menu_root_query.single())
+            .despawn_recursive();
+    }
+}
+
+pub struct ItemsMenuPlugin;
+
+impl Plugin for ItemsMenuPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(OnEnter(PauseMenuState::InItemsMenu), spawn_items_menu)
+            .add_systems(
+                Update,
+                ((
+                    option_button_hover,
+                    show_focused_item_details,
+                    close_items_menu,
+                )
+                    .run_if(in_state(PauseMenuState::InItemsMenu)))
+                .run_if(in_state(GameModeState::Paused)),
+            );
+    }
+}
@@ -1,4 +1,6 @@
+pub mod itemsmenu;
 pub mod optionsmenu;
+pub mod partymenu;
 pub mod pausemenucard;
 pub mod pausemenucardtracker;
 pub mod pausemode;
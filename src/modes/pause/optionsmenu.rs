@@ -11,9 +11,6 @@ use crate::modes::mode_state::GameModeState;
 use crate::modes::pause::pausemode::{OptionsMenuRoot, PauseMenuState};
 use crate::utils::utilsystems::{BASE_WINDOW_HEIGHT, BASE_WINDOW_WIDTH};
 
-#[derive(Component)]
-struct OptionsMenu;
-
 #[derive(Component)]
 pub enum ResolutionOptions {
     Small,
@@ -22,7 +19,7 @@ pub enum ResolutionOptions {
     Fullscreen,
 }
 
-fn option_button_hover(
+pub fn option_button_hover(
     mut interaction_query: Query<(&Focusable, &mut BorderColor), Changed<Focusable>>,
 ) {
     for (focus, mut border_color) in interaction_query.iter_mut() {
@@ -35,7 +32,7 @@ fn option_button_hover(
 }
 
 fn handle_option_menu_nav_events(
-    mut res_button_query: Query<&ResolutionOptions>,
+    res_button_query: Query<&ResolutionOptions>,
     mut windows: Query<&mut Window>,
     options_menu_root_query: Query<Entity, With<OptionsMenuRoot>>,
     mut events: EventReader<NavEvent>,
@@ -52,7 +49,7 @@ fn handle_option_menu_nav_events(
     }
 
     let mut window = windows.single_mut();
-    for res_option in events.nav_iter().activated_in_query(&mut res_button_query) {
+    for res_option in events.nav_iter().activated_in_query(&res_button_query) {
         let mut mode = WindowMode::Windowed;
         match res_option {
             ResolutionOptions::Small => {
@@ -0,0 +1,150 @@
+use bevy::app::App;
+use bevy::prelude::{
+    default, in_state, AlignItems, BuildChildren, Color, Commands, Component, DespawnRecursiveExt,
+    Entity, FlexDirection, Input, IntoSystemConfigs, JustifyContent, KeyCode, NextState,
+    NodeBundle, OnEnter, Plugin, Query, Res, ResMut, Style, TextBundle, TextStyle, UiRect, Update,
+    Val, With,
+};
+
+use crate::modes::mode_state::GameModeState;
+use crate::modes::party::{xp_for_level, Party, PartyMember};
+use crate::modes::pause::pausemode::{PauseMenuState, PauseModeEntity};
+use crate::modes::sharedassets::characterdatabase::EquipmentSlot;
+use crate::modes::sharedassets::itemdatabase::ItemDatabase;
+use crate::modes::sharedassets::shared::FontAssets;
+
+#[derive(Component)]
+struct PartyMenuRoot;
+
+fn member_details(member: &PartyMember, item_database: &ItemDatabase) -> String {
+    let stats = member.stats(item_database);
+    let equipment: Vec<String> = EquipmentSlot::ALL
+        .into_iter()
+        .map(|slot| {
+            let item = member
+                .equipment
+                .get(slot)
+                .map_or_else(|| "-".to_string(), |item| item_database.name(item));
+            format!("{:?}: {}", slot, item)
+        })
+        .collect();
+    let skills = if member.skills.is_empty() {
+        "-".to_string()
+    } else {
+        let skills: Vec<String> = member.skills.iter().map(ToString::to_string).collect();
+        skills.join(", ")
+    };
+    format!(
+        "{}  Lv {}\n\nHP {}/{}\nMP {}/{}\nXP {} (next at {})\n\n\
+         ATK {}  DEF {}\nMAG {}  SPD {}\n\n{}\n\nSkills: {}",
+        member.name,
+        member.level,
+        member.hp,
+        member.max_hp,
+        member.mp,
+        member.max_mp,
+        member.xp,
+        xp_for_level(member.level + 1),
+        stats.attack,
+        stats.defense,
+        stats.magic,
+        stats.speed,
+        equipment.join("\n"),
+        skills,
+    )
+}
+
+fn spawn_party_menu(
+    mut commands: Commands,
+    font_assets: Res<FontAssets>,
+    party: Res<Party>,
+    item_database: Res<ItemDatabase>,
+) {
+    let text_style = TextStyle {
+        font: font_assets.ui_font.clone(),
+        font_size: 15.0,
+        color: Color::WHITE,
+    };
+    let root = NodeBundle {
+        style: Style {
+            width: Val::Percent(100.0),
+            height: Val::Percent(100.0),
+            justify_content: JustifyContent::Center,
+            align_items: AlignItems::Center,
+            ..default()
+        },
+        background_color: Color::NONE.into(),
+        ..default()
+    };
+    let party_menu_bg = NodeBundle {
+        style: Style {
+            width: Val::Percent(95.0),
+            height: Val::Percent(75.0),
+            justify_content: JustifyContent::SpaceEvenly,
+            align_items: AlignItems::Center,
+            ..default()
+        },
+        background_color: Color::hex("#B9C6D8").unwrap().into(),
+        ..default()
+    };
+    let member_panel = NodeBundle {
+        style: Style {
+            width: Val::Percent(22.0),
+            height: Val::Percent(90.0),
+            flex_direction: FlexDirection::Column,
+            padding: UiRect::all(Val::Px(10.0)),
+            ..default()
+        },
+        background_color: Color::GRAY.into(),
+        ..default()
+    };
+
+    commands
+        .spawn((root, PauseModeEntity, PartyMenuRoot))
+        .with_children(|parent| {
+            parent.spawn(party_menu_bg).with_children(|bg| {
+                if party.members().is_empty() {
+                    bg.spawn(TextBundle::from_section(
+                        "Nobody's here",
+                        text_style.clone(),
+                    ));
+                }
+                for member in party.members() {
+                    bg.spawn(member_panel.clone()).with_children(|panel| {
+                        panel.spawn(TextBundle::from_section(
+                            member_details(member, &item_database),
+                            text_style.clone(),
+                        ));
+                    });
+                }
+            });
+        });
+}
+
+fn close_party_menu(
+    party_menu_root_query: Query<Entity, With<PartyMenuRoot>>,
+    mut next_state: ResMut<NextState<PauseMenuState>>,
+    mut commands: Commands,
+    input: Res<Input<KeyCode>>,
+) {
+    if input.just_pressed(KeyCode::X) {
+        next_state.set(PauseMenuState::Stationary);
+        commands
+            .entity(party_menu_root_query.single())
+            .despawn_recursive();
+    }
+}
+
+pub struct PartyMenuPlugin;
+
+impl Plugin for PartyMenuPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(OnEnter(PauseMenuState::InPartyMenu), spawn_party_menu)
+            .add_systems(
+                Update,
+                close_party_menu
+                    .run_if(in_state(PauseMenuState::InPartyMenu))
+                    .run_if(in_state(GameModeState::Paused)),
+            );
+    }
+}
@@ -21,6 +21,8 @@ pub enum PauseMenuCardType {
     Resume,
     Exit,
     Options,
+    Items,
+    Party,
 }
 
 #[derive(Component, Default)]
@@ -104,6 +106,8 @@ fn spawn_card(
         PauseMenuCardType::Resume => "Resume",
         PauseMenuCardType::Exit => "Exit",
         PauseMenuCardType::Options => "Options",
+        PauseMenuCardType::Items => "Items",
+        PauseMenuCardType::Party => "Party",
     };
     let mut text_entity = Entity::from_raw(7777777);
     let card_entity = menu_anchor
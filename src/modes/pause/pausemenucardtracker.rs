@@ -92,6 +92,7 @@ impl FromWorld for PauseMenuCardTracker {
 }
 
 impl PauseMenuCardTracker {
+    #[allow(clippy::type_complexity)]
     pub fn rotate(
         &mut self,
         rotation_direction: RotationDirection,
@@ -19,8 +19,10 @@ use bevy_ui_navigation::systems::InputMapping;
 
 use crate::modes::dungeon::dungeonplayer::{DungeonPlayer, DungeonPlayerMovementState};
 use crate::modes::mode_state::GameModeState;
+use crate::modes::pause::itemsmenu::ItemsMenuPlugin;
 use crate::modes::pause::optionsmenu::ResolutionOptions::Fullscreen;
 use crate::modes::pause::optionsmenu::{OptionsMenuPlugin, ResolutionOptions};
+use crate::modes::pause::partymenu::PartyMenuPlugin;
 use crate::modes::pause::pausemenucard::{
     spawn_cards, CardTween, PauseMenuCardType, PauseMenuText,
 };
@@ -37,7 +39,7 @@ use crate::utils::utilsystems::{cleanup_system, ScalableSpriteComponent};
 struct PreviousState(GameModeState);
 
 #[derive(Component)]
-struct PauseModeEntity;
+pub struct PauseModeEntity;
 
 struct PauseMode;
 
@@ -58,6 +60,8 @@ pub enum PauseMenuState {
     Stationary,
     RotatingCard,
     InOptionsMenu,
+    InItemsMenu,
+    InPartyMenu,
 }
 
 impl PauseMode {
@@ -165,10 +169,10 @@ impl PauseMode {
 
         // initial card order
         let card_types = [
-            PauseMenuCardType::Resume,
+            PauseMenuCardType::Party,
             PauseMenuCardType::Options,
             PauseMenuCardType::Resume,
-            PauseMenuCardType::Resume,
+            PauseMenuCardType::Items,
             PauseMenuCardType::Exit,
         ];
         // let current_index = 2;
@@ -216,6 +220,7 @@ impl PauseMode {
             next_game_state.set(prev_state.0);
         }
     }
+    #[allow(clippy::type_complexity)]
     fn handle_menu_input(
         mut card_query: Query<
             (
@@ -282,6 +287,8 @@ impl PauseMode {
                 PauseMode::spawn_options_menu(&mut commands, &font_assets);
                 next_pause_state.set(PauseMenuState::InOptionsMenu);
             }
+            PauseMenuCardType::Items => next_pause_state.set(PauseMenuState::InItemsMenu),
+            PauseMenuCardType::Party => next_pause_state.set(PauseMenuState::InPartyMenu),
         }
     }
 
@@ -423,5 +430,7 @@ impl PluginGroup for PauseModePlugins {
         PluginGroupBuilder::start::<Self>()
             .add(PauseMode)
             .add(OptionsMenuPlugin)
+            .add(ItemsMenuPlugin)
+            .add(PartyMenuPlugin)
     }
 }
@@ -0,0 +1,460 @@
+use std::fmt::{Display, Formatter};
+
+use bevy::prelude::{AssetServer, Assets, FromWorld, Handle, Resource, World};
+use bevy::reflect::{TypePath, TypeUuid};
+use bevy::utils::HashMap;
+use bevy_asset_loader::prelude::AssetCollection;
+use serde::Deserialize;
+
+use crate::modes::battle::affinity::Affinities;
+use crate::modes::battle::combatant::Stats;
+use crate::modes::battle::skilldatabase::{SkillDatabase, SkillId};
+use crate::modes::dungeon::model::items::ItemId;
+use crate::modes::sharedassets::itemdatabase::ItemDatabase;
+
+/// Id of a character in the `CharacterDatabase`.
+#[derive(Deserialize, Clone, PartialEq, Eq, Hash, Debug)]
+#[serde(transparent)]
+pub struct CharacterId(pub String);
+
+impl Display for CharacterId {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EquipmentSlot {
+    Weapon,
+    Armor,
+    Accessory,
+}
+
+impl EquipmentSlot {
+    pub const ALL: [EquipmentSlot; 3] = [
+        EquipmentSlot::Weapon,
+        EquipmentSlot::Armor,
+        EquipmentSlot::Accessory,
+    ];
+}
+
+/// What a character has equipped, one item per slot.
+#[derive(Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+#[serde(default)]
+pub struct Equipment {
+    pub weapon: Option<ItemId>,
+    pub armor: Option<ItemId>,
+    pub accessory: Option<ItemId>,
+}
+
+impl Equipment {
+    pub fn get(&self, slot: EquipmentSlot) -> Option<&ItemId> {
+        match slot {
+            EquipmentSlot::Weapon => self.weapon.as_ref(),
+            EquipmentSlot::Armor => self.armor.as_ref(),
+            EquipmentSlot::Accessory => self.accessory.as_ref(),
+        }
+    }
+
+    pub fn items(&self) -> impl Iterator<Item = &ItemId> {
+        EquipmentSlot::ALL
+            .into_iter()
+            .filter_map(|slot| self.get(slot))
+    }
+}
+
+/// What a character gains on every level up.
+#[derive(Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(default)]
+pub struct Growth {
+    pub max_hp: u32,
+    pub max_mp: u32,
+    pub stats: Stats,
+}
+
+/// A skill a character picks up once they reach `level`.
+#[derive(Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct LearnedSkill {
+    pub level: u32,
+    pub skill: SkillId,
+}
+
+/// What a character starts out as at level 1.
+#[derive(Deserialize, Clone, Debug)]
+pub struct CharacterInfo {
+    pub name: String,
+    pub max_hp: u32,
+    pub max_mp: u32,
+    pub stats: Stats,
+    #[serde(default)]
+    pub growth: Growth,
+    #[serde(default)]
+    pub skills: Vec<LearnedSkill>,
+    #[serde(default)]
+    pub equipment: Equipment,
+    #[serde(default)]
+    pub affinities: Affinities,
+}
+
+impl CharacterInfo {
+    /// Every skill the character knows by `level`.
+    pub fn skills_at(&self, level: u32) -> Vec<SkillId> {
+        self.skills
+            .iter()
+            .filter(|learned| learned.level <= level)
+            .map(|learned| learned.skill.clone())
+            .collect()
+    }
+}
+
+/// Every playable character by id, and who's in the party when the game starts, from
+/// `characters.characters.json`.
+///
+/// ```json
+/// {
+///     "party": ["Hero"],
+///     "characters": {
+///         "Hero": {
+///             "name": "Hero",
+///             "max_hp": 60,
+///             "max_mp": 20,
+///             "stats": { "attack": 12, "defense": 8, "magic": 10, "speed": 10 },
+///             "growth": {
+///                 "max_hp": 8,
+///                 "max_mp": 3,
+///                 "stats": { "attack": 2, "defense": 2, "magic": 1, "speed": 1 }
+///             },
+///             "skills": [{ "level": 1, "skill": "Zap" }],
+///             "equipment": { "weapon": "Stick" },
+///             "affinities": { "Elec": "Resist" }
+///         }
+///     }
+/// }
+/// ```
+#[derive(Deserialize, TypePath, TypeUuid)]
+#[uuid = "5e0b8d4a-7c1f-4f36-9a2e-3b6d9c1e8f47"]
+pub struct RawCharacterDatabase {
+    pub party: Vec<CharacterId>,
+    pub characters: HashMap<CharacterId, CharacterInfo>,
+}
+
+#[derive(AssetCollection, Resource)]
+pub struct CharacterAssets {
+    #[asset(path = "characters/characters.characters.json")]
+    pub database: Handle<RawCharacterDatabase>,
+}
+
+/// Everything that can be wrong with the character database. The game can't start without a
+/// party, so these stop it the same way a broken dungeon does.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CharacterValidationError {
+    EmptyParty,
+    UnknownPartyMember {
+        character: CharacterId,
+    },
+    UnknownEquipment {
+        character: CharacterId,
+        item: ItemId,
+    },
+    UnknownSkill {
+        character: CharacterId,
+        skill: SkillId,
+    },
+}
+
+impl Display for CharacterValidationError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CharacterValidationError::EmptyParty => write!(f, "the starting party is empty"),
+            CharacterValidationError::UnknownPartyMember { character } => write!(
+                f,
+                "starting party member {} isn't in the character database",
+                character
+            ),
+            CharacterValidationError::UnknownEquipment { character, item } => write!(
+                f,
+                "{} has {} equipped, which isn't in the item database",
+                character, item
+            ),
+            CharacterValidationError::UnknownSkill { character, skill } => write!(
+                f,
+                "{} learns {}, which isn't in the skill database",
+                character, skill
+            ),
+        }
+    }
+}
+
+impl std::error::Error for CharacterValidationError {}
+
+#[derive(Resource)]
+pub struct CharacterDatabase {
+    starting_party: Vec<CharacterId>,
+    characters: HashMap<CharacterId, CharacterInfo>,
+}
+
+impl CharacterDatabase {
+    pub fn new(raw_character_database: &RawCharacterDatabase) -> Self {
+        CharacterDatabase {
+            starting_party: raw_character_database.party.clone(),
+            characters: raw_character_database.characters.clone(),
+        }
+    }
+
+    pub fn get(&self, id: &CharacterId) -> Option<&CharacterInfo> {
+        self.characters.get(id)
+    }
+
+    pub fn starting_party(&self) -> &[CharacterId] {
+        &self.starting_party
+    }
+
+    /// Checks the starting party and every character's equipment and skills against the other
+    /// databases.
+    pub fn validate(
+        &self,
+        item_database: &ItemDatabase,
+        skill_database: &SkillDatabase,
+    ) -> Result<(), Vec<CharacterValidationError>> {
+        let mut errors = vec![];
+        if self.starting_party.is_empty() {
+            errors.push(CharacterValidationError::EmptyParty);
+        }
+        errors.extend(
+            self.starting_party
+                .iter()
+                .filter(|id| !self.characters.contains_key(*id))
+                .map(|id| CharacterValidationError::UnknownPartyMember {
+                    character: id.clone(),
+                }),
+        );
+        // sorted so the errors come out in the same order every time
+        let mut characters: Vec<_> = self.characters.iter().collect();
+        characters.sort_by(|(a, _), (b, _)| a.0.cmp(&b.0));
+        for (id, info) in characters {
+            let unknown_equipment = info
+                .equipment
+                .items()
+                .filter(|item| !item_database.contains(item))
+                .map(|item| CharacterValidationError::UnknownEquipment {
+                    character: id.clone(),
+                    item: item.clone(),
+                });
+            let unknown_skills = info
+                .skills
+                .iter()
+                .filter(|learned| skill_database.get(&learned.skill).is_none())
+                .map(|learned| CharacterValidationError::UnknownSkill {
+                    character: id.clone(),
+                    skill: learned.skill.clone(),
+                });
+            errors.extend(unknown_equipment.chain(unknown_skills));
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+impl FromWorld for CharacterDatabase {
+    fn from_world(world: &mut World) -> Self {
+        let character_assets = world
+            .get_resource::<CharacterAssets>()
+            .expect("failed to get character asset handles");
+        let raw_character_databases = world
+            .get_resource::<Assets<RawCharacterDatabase>>()
+            .expect("failed to get character databases");
+        let raw_character_database = raw_character_databases
+            .get(&character_assets.database)
+            .expect("failed to get character database out of assets");
+        CharacterDatabase::new(raw_character_database)
+    }
+}
+
+#[cfg(test)]
+pub mod test_helpers {
+    use bevy::utils::HashMap;
+
+    use crate::modes::battle::affinity::Affinities;
+    use crate::modes::battle::combatant::Stats;
+    use crate::modes::battle::skilldatabase::SkillId;
+    use crate::modes::dungeon::model::items::ItemId;
+    use crate::modes::sharedassets::characterdatabase::{
+        CharacterDatabase, CharacterId, CharacterInfo, Equipment, Growth, LearnedSkill,
+        RawCharacterDatabase,
+    };
+
+    /// The hero starts out alone with 60 HP and 20 MP, a Maxwell equipped and Zap learned. Every
+    /// level adds 8 HP, 3 MP and 2 attack, and Blaze comes at level 3. The sidekick is left out
+    /// of the party.
+    pub fn test_character_database() -> CharacterDatabase {
+        let stats = Stats {
+            attack: 12,
+            defense: 8,
+            magic: 10,
+            speed: 10,
+        };
+        let hero = CharacterInfo {
+            name: "Hero".to_string(),
+            max_hp: 60,
+            max_mp: 20,
+            stats,
+            growth: Growth {
+                max_hp: 8,
+                max_mp: 3,
+                stats: Stats {
+                    attack: 2,
+                    ..Stats::default()
+                },
+            },
+            skills: vec![
+                LearnedSkill {
+                    level: 1,
+                    skill: SkillId("Zap".to_string()),
+                },
+                LearnedSkill {
+                    level: 3,
+                    skill: SkillId("Blaze".to_string()),
+                },
+            ],
+            equipment: Equipment {
+                accessory: Some(ItemId::new("Maxwell")),
+                ..Equipment::default()
+            },
+            affinities: Affinities::default(),
+        };
+        let sidekick = CharacterInfo {
+            name: "Sidekick".to_string(),
+            max_hp: 40,
+            max_mp: 30,
+            stats,
+            growth: Growth::default(),
+            skills: vec![],
+            equipment: Equipment::default(),
+            affinities: Affinities::default(),
+        };
+        CharacterDatabase::new(&RawCharacterDatabase {
+            party: vec![CharacterId("Hero".to_string())],
+            characters: HashMap::from_iter([
+                (CharacterId("Hero".to_string()), hero),
+                (CharacterId("Sidekick".to_string()), sidekick),
+            ]),
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use bevy::utils::HashMap;
+
+    use crate::modes::battle::affinity::{Affinity, Element};
+    use crate::modes::battle::skilldatabase::test_helpers::test_skill_database;
+    use crate::modes::battle::skilldatabase::SkillId;
+    use crate::modes::dungeon::model::items::ItemId;
+    use crate::modes::sharedassets::characterdatabase::test_helpers::test_character_database;
+    use crate::modes::sharedassets::characterdatabase::{
+        CharacterDatabase, CharacterId, CharacterValidationError, EquipmentSlot, LearnedSkill,
+        RawCharacterDatabase,
+    };
+    use crate::modes::sharedassets::itemdatabase::test_helpers::test_item_database;
+
+    #[test]
+    fn should_parse_character_database() {
+        let raw_character_database: RawCharacterDatabase = serde_json::from_str(
+            r#"{
+                "party": ["Hero"],
+                "characters": {
+                    "Hero": {
+                        "name": "Hero",
+                        "max_hp": 60,
+                        "max_mp": 20,
+                        "stats": { "attack": 12, "defense": 8, "magic": 10, "speed": 10 },
+                        "growth": {
+                            "max_hp": 8,
+                            "stats": { "attack": 2, "defense": 0, "magic": 0, "speed": 0 }
+                        },
+                        "skills": [{ "level": 3, "skill": "Zap" }],
+                        "equipment": { "weapon": "Stick" },
+                        "affinities": { "Elec": "Resist" }
+                    }
+                }
+            }"#,
+        )
+        .unwrap();
+        let character_database = CharacterDatabase::new(&raw_character_database);
+        assert_eq!(
+            character_database.starting_party(),
+            [CharacterId("Hero".to_string())]
+        );
+
+        let hero = character_database
+            .get(&CharacterId("Hero".to_string()))
+            .unwrap();
+        assert_eq!(hero.growth.max_hp, 8);
+        assert_eq!(hero.growth.max_mp, 0);
+        assert_eq!(hero.growth.stats.attack, 2);
+        assert!(hero.skills_at(2).is_empty());
+        assert_eq!(hero.skills_at(3), [SkillId("Zap".to_string())]);
+        assert_eq!(hero.affinities.get(Element::Elec), Affinity::Resist);
+        assert_eq!(
+            hero.equipment.get(EquipmentSlot::Weapon),
+            Some(&ItemId::new("Stick"))
+        );
+        assert_eq!(hero.equipment.get(EquipmentSlot::Armor), None);
+    }
+
+    #[test]
+    fn test_database_is_valid() {
+        assert_eq!(
+            test_character_database().validate(&test_item_database(), &test_skill_database()),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn bad_characters_are_reported() {
+        let test_database = test_character_database();
+        let hero_id = CharacterId("Hero".to_string());
+        let mut hero = test_database.get(&hero_id).unwrap().clone();
+        hero.equipment.weapon = Some(ItemId::new("Dingus"));
+        hero.skills.push(LearnedSkill {
+            level: 2,
+            skill: SkillId("Fizzle".to_string()),
+        });
+        let character_database = CharacterDatabase::new(&RawCharacterDatabase {
+            party: vec![hero_id.clone(), CharacterId("Nobody".to_string())],
+            characters: HashMap::from_iter([(hero_id.clone(), hero)]),
+        });
+        assert_eq!(
+            character_database.validate(&test_item_database(), &test_skill_database()),
+            Err(vec![
+                CharacterValidationError::UnknownPartyMember {
+                    character: CharacterId("Nobody".to_string()),
+                },
+                CharacterValidationError::UnknownEquipment {
+                    character: hero_id.clone(),
+                    item: ItemId::new("Dingus"),
+                },
+                CharacterValidationError::UnknownSkill {
+                    character: hero_id,
+                    skill: SkillId("Fizzle".to_string()),
+                },
+            ])
+        );
+    }
+
+    #[test]
+    fn the_party_cant_be_empty() {
+        let character_database = CharacterDatabase::new(&RawCharacterDatabase {
+            party: vec![],
+            characters: HashMap::new(),
+        });
+        assert_eq!(
+            character_database.validate(&test_item_database(), &test_skill_database()),
+            Err(vec![CharacterValidationError::EmptyParty])
+        );
+    }
+}
@@ -0,0 +1,325 @@
+use bevy::prelude::*;
+use bevy::reflect::{TypePath, TypeUuid};
+use bevy::utils::HashMap;
+use bevy_asset_loader::prelude::AssetCollection;
+use serde::Deserialize;
+
+use crate::modes::battle::combatant::Stats;
+use crate::modes::dungeon::model::items::ItemId;
+
+/// The scene shown for an item lying around in the dungeon, and where it sits in its cell.
+#[derive(Deserialize, Clone, Debug)]
+pub struct ItemModel {
+    /// Path to the scene, like `model/key.gltf#Scene0`.
+    pub path: String,
+    /// Added to the item's usual spot in the middle of the cell.
+    #[serde(default)]
+    pub offset: [f32; 3],
+    #[serde(default = "default_scale")]
+    pub scale: f32,
+}
+
+fn default_scale() -> f32 {
+    1.0
+}
+
+/// How an item moves while it waits to be picked up. A period of 0 turns that part off.
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(default)]
+pub struct IdleAnimation {
+    /// Seconds for one full turn.
+    pub spin_period: f32,
+    pub bounce_height: f32,
+    /// Seconds to go up, the same again to come back down.
+    pub bounce_period: f32,
+}
+
+impl Default for IdleAnimation {
+    fn default() -> Self {
+        IdleAnimation {
+            spin_period: 2.0,
+            bounce_height: 0.1,
+            bounce_period: 0.8,
+        }
+    }
+}
+
+/// What the game knows about an item. `icon` is a path under `img/items`.
+#[derive(Deserialize, Clone, Debug)]
+pub struct ItemInfo {
+    pub name: String,
+    pub description: String,
+    pub icon: String,
+    pub model: ItemModel,
+    #[serde(default)]
+    pub idle_animation: IdleAnimation,
+    /// How many fit in one inventory slot.
+    #[serde(default = "default_max_stack")]
+    pub max_stack: u32,
+    #[serde(default)]
+    pub usable_in_dungeon: bool,
+    #[serde(default)]
+    pub usable_in_battle: bool,
+    /// Whether this is a key. Keys can be given a lock id in the dungeon data, and any of them
+    /// opens locks that don't need a particular key.
+    #[serde(default)]
+    pub opens_locks: bool,
+    /// HP it gives back when it's used on someone in battle.
+    #[serde(default)]
+    pub heal: u32,
+    /// Added to the stats of whoever has it equipped.
+    #[serde(default)]
+    pub bonus: Stats,
+}
+
+fn default_max_stack() -> u32 {
+    1
+}
+
+/// Every item in the game by id, from `items.items.json`. The ids are what dungeons use in
+/// `item_type`.
+///
+/// ```json
+/// {
+///     "inventory_capacity": 20,
+///     "items": {
+///         "Key": {
+///             "name": "Key",
+///             "description": "Opens a locked door somewhere.",
+///             "icon": "img/items/key.png",
+///             "model": { "path": "model/key.gltf#Scene0" },
+///             "max_stack": 9,
+///             "usable_in_dungeon": true,
+///             "opens_locks": true
+///         },
+///         "Maxwell": {
+///             "name": "Maxwell",
+///             "description": "A cat.",
+///             "icon": "img/items/maxwell.png",
+///             "model": { "path": "model/maxwell_the_cat_dingus.glb#Scene0", "scale": 0.01 },
+///             "idle_animation": { "spin_period": 4.0, "bounce_height": 0.0 }
+///         }
+///     }
+/// }
+/// ```
+#[derive(Deserialize, TypePath, TypeUuid)]
+#[uuid = "9b1c6f0e-3d2a-4c57-8e41-5a7d0f2b6c93"]
+pub struct RawItemDatabase {
+    /// How many slots the party's inventory has.
+    pub inventory_capacity: usize,
+    pub items: HashMap<ItemId, ItemInfo>,
+}
+
+#[derive(AssetCollection, Resource)]
+pub struct ItemAssets {
+    #[asset(path = "items/items.items.json")]
+    pub database: Handle<RawItemDatabase>,
+    #[asset(path = "img/items", collection(typed, mapped))]
+    pub icons: HashMap<String, Handle<Image>>,
+}
+
+/// `RawItemDatabase` plus the item models, built once the database has loaded.
+#[derive(Resource)]
+pub struct ItemDatabase {
+    inventory_capacity: usize,
+    items: HashMap<ItemId, ItemInfo>,
+    models: HashMap<ItemId, Handle<Scene>>,
+}
+
+impl ItemDatabase {
+    /// Doesn't load any models, `model` gives the default handle for everything.
+    pub fn new(raw_item_database: &RawItemDatabase) -> Self {
+        ItemDatabase {
+            inventory_capacity: raw_item_database.inventory_capacity,
+            items: raw_item_database.items.clone(),
+            models: HashMap::new(),
+        }
+    }
+
+    pub fn inventory_capacity(&self) -> usize {
+        self.inventory_capacity
+    }
+
+    pub fn get(&self, id: &ItemId) -> Option<&ItemInfo> {
+        self.items.get(id)
+    }
+
+    pub fn contains(&self, id: &ItemId) -> bool {
+        self.items.contains_key(id)
+    }
+
+    /// Items missing from the database don't stack.
+    pub fn max_stack(&self, id: &ItemId) -> u32 {
+        self.get(id).map_or(1, |info| info.max_stack)
+    }
+
+    pub fn opens_locks(&self, id: &ItemId) -> bool {
+        self.get(id).is_some_and(|info| info.opens_locks)
+    }
+
+    /// The item's name, or its id if it's missing from the database.
+    pub fn name(&self, id: &ItemId) -> String {
+        self.get(id)
+            .map_or_else(|| id.to_string(), |info| info.name.clone())
+    }
+
+    pub fn usable_in_battle(&self, id: &ItemId) -> bool {
+        self.get(id).is_some_and(|info| info.usable_in_battle)
+    }
+
+    pub fn heal(&self, id: &ItemId) -> u32 {
+        self.get(id).map_or(0, |info| info.heal)
+    }
+
+    pub fn bonus(&self, id: &ItemId) -> Stats {
+        self.get(id).map_or(Stats::default(), |info| info.bonus)
+    }
+
+    pub fn model(&self, id: &ItemId) -> Handle<Scene> {
+        self.models.get(id).cloned().unwrap_or_default()
+    }
+}
+
+impl FromWorld for ItemDatabase {
+    fn from_world(world: &mut World) -> Self {
+        let item_assets = world
+            .get_resource::<ItemAssets>()
+            .expect("failed to get item asset handles");
+        let raw_item_databases = world
+            .get_resource::<Assets<RawItemDatabase>>()
+            .expect("failed to get item databases");
+        let raw_item_database = raw_item_databases
+            .get(&item_assets.database)
+            .expect("failed to get item database out of assets");
+        let asset_server = world
+            .get_resource::<AssetServer>()
+            .expect("failed to get asset server");
+        let mut item_database = ItemDatabase::new(raw_item_database);
+        for (id, info) in raw_item_database.items.iter() {
+            if !item_assets.icons.contains_key(&info.icon) {
+                warn!("icon {} for {} wasn't loaded", info.icon, id);
+            }
+            // the models only show up once the player is in the dungeon, they can finish loading
+            // in the background until then
+            item_database
+                .models
+                .insert(id.clone(), asset_server.load(&info.model.path));
+        }
+        item_database
+    }
+}
+
+#[cfg(test)]
+pub mod test_helpers {
+    use bevy::utils::HashMap;
+
+    use crate::modes::battle::combatant::Stats;
+    use crate::modes::dungeon::model::items::ItemId;
+    use crate::modes::sharedassets::itemdatabase::{
+        IdleAnimation, ItemDatabase, ItemInfo, ItemModel, RawItemDatabase,
+    };
+
+    /// Three slots. Keys and tonics stack up to 3, polaroids and Maxwell don't stack. Tonics heal
+    /// 30 HP in battle, and Maxwell gives 5 defense to whoever has him equipped.
+    pub fn test_item_database() -> ItemDatabase {
+        let item = |id: &str, max_stack, opens_locks| {
+            let info = ItemInfo {
+                name: id.to_string(),
+                description: String::new(),
+                icon: String::new(),
+                model: ItemModel {
+                    path: String::new(),
+                    offset: [0.0; 3],
+                    scale: 1.0,
+                },
+                idle_animation: IdleAnimation::default(),
+                max_stack,
+                usable_in_dungeon: false,
+                usable_in_battle: false,
+                opens_locks,
+                heal: 0,
+                bonus: Stats::default(),
+            };
+            (ItemId::new(id), info)
+        };
+        let (tonic, mut tonic_info) = item("Tonic", 3, false);
+        tonic_info.usable_in_battle = true;
+        tonic_info.heal = 30;
+        let (maxwell, mut maxwell_info) = item("Maxwell", 1, false);
+        maxwell_info.bonus.defense = 5;
+        ItemDatabase::new(&RawItemDatabase {
+            inventory_capacity: 3,
+            items: HashMap::from_iter([
+                item("Key", 3, true),
+                item("Polaroid", 1, false),
+                (maxwell, maxwell_info),
+                (tonic, tonic_info),
+            ]),
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::modes::dungeon::model::items::ItemId;
+    use crate::modes::sharedassets::itemdatabase::{IdleAnimation, ItemDatabase, RawItemDatabase};
+
+    #[test]
+    fn should_parse_item_database() {
+        let raw_item_database: RawItemDatabase = serde_json::from_str(
+            r#"{
+                "inventory_capacity": 20,
+                "items": {
+                    "Key": {
+                        "name": "Rusty Key",
+                        "description": "Opens a locked door somewhere.",
+                        "icon": "img/items/key.png",
+                        "model": { "path": "model/key.gltf#Scene0" },
+                        "max_stack": 9,
+                        "usable_in_dungeon": true,
+                        "opens_locks": true
+                    },
+                    "Maxwell": {
+                        "name": "Maxwell",
+                        "description": "A cat.",
+                        "icon": "img/items/maxwell.png",
+                        "model": { "path": "model/maxwell.glb#Scene0", "scale": 0.01 },
+                        "idle_animation": { "spin_period": 4.0, "bounce_height": 0.0 }
+                    }
+                }
+            }"#,
+        )
+        .unwrap();
+        let item_database = ItemDatabase::new(&raw_item_database);
+        let key = ItemId::new("Key");
+        let maxwell = ItemId::new("Maxwell");
+        let dingus = ItemId::new("Dingus");
+
+        assert_eq!(item_database.inventory_capacity(), 20);
+        let key_info = item_database.get(&key).unwrap();
+        assert_eq!(key_info.name, "Rusty Key");
+        assert!(key_info.usable_in_dungeon);
+        assert!(!key_info.usable_in_battle);
+        assert_eq!(key_info.model.scale, 1.0);
+        assert_eq!(key_info.idle_animation, IdleAnimation::default());
+        assert!(item_database.opens_locks(&key));
+        assert_eq!(item_database.max_stack(&key), 9);
+
+        let maxwell_info = item_database.get(&maxwell).unwrap();
+        assert_eq!(maxwell_info.model.scale, 0.01);
+        assert_eq!(
+            maxwell_info.idle_animation,
+            IdleAnimation {
+                spin_period: 4.0,
+                bounce_height: 0.0,
+                bounce_period: 0.8,
+            }
+        );
+        assert!(!item_database.opens_locks(&maxwell));
+        assert_eq!(item_database.max_stack(&maxwell), 1);
+
+        assert!(!item_database.contains(&dingus));
+        assert_eq!(item_database.max_stack(&dingus), 1);
+        assert_eq!(item_database.name(&dingus), "Dingus");
+    }
+}
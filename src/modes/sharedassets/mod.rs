@@ -1 +1,3 @@
+pub mod characterdatabase;
+pub mod itemdatabase;
 pub mod shared;
@@ -1,8 +1,20 @@
+use crate::modes::battle::skilldatabase::{SkillAssets, SkillDatabase};
 use crate::modes::mode_state::GameModeState;
+use crate::modes::party::start_new_party;
+use crate::modes::sharedassets::characterdatabase::{
+    CharacterAssets, CharacterDatabase, CharacterValidationError,
+};
+use crate::modes::sharedassets::itemdatabase::{ItemAssets, ItemDatabase};
 use bevy::app::App;
-use bevy::prelude::{AssetServer, Font, Handle, Plugin, Resource};
+use bevy::prelude::{
+    apply_deferred, default, error, not, resource_exists, AlignItems, AssetServer, BuildChildren,
+    Camera2dBundle, Color, Commands, FlexDirection, Font, Handle, IntoSystemConfigs, NextState,
+    NodeBundle, OnEnter, OnExit, Plugin, Res, ResMut, Resource, Style, TextBundle, TextStyle,
+    UiRect, Val,
+};
 use bevy_asset_loader::asset_collection::AssetCollection;
 use bevy_asset_loader::prelude::{LoadingState, LoadingStateAppExt};
+use std::fmt::Display;
 
 #[derive(Resource, AssetCollection)]
 pub struct FontAssets {
@@ -10,14 +22,166 @@ pub struct FontAssets {
     pub ui_font: Handle<Font>,
 }
 
+/// Only exists if the character database failed validation. The dungeon doesn't get spawned
+/// while it's around.
+#[derive(Resource)]
+pub struct CharacterValidationReport(pub Vec<CharacterValidationError>);
+
 pub struct SharedAssetsPlugin;
 
+fn validate_character_database(
+    mut commands: Commands,
+    character_database: Res<CharacterDatabase>,
+    item_database: Res<ItemDatabase>,
+    skill_database: Res<SkillDatabase>,
+    mut next_state: ResMut<NextState<GameModeState>>,
+) {
+    let Err(errors) = character_database.validate(&item_database, &skill_database) else {
+        return;
+    };
+    for e in errors.iter() {
+        error!("invalid character database: {}", e);
+    }
+    commands.insert_resource(CharacterValidationReport(errors));
+    next_state.set(GameModeState::InvalidCharacters);
+}
+
+fn show_character_validation_report(
+    mut commands: Commands,
+    report: Res<CharacterValidationReport>,
+    font_assets: Res<FontAssets>,
+) {
+    spawn_validation_report(
+        &mut commands,
+        &font_assets,
+        "Failed to load characters:",
+        &report.0,
+    );
+}
+
+/// A black screen listing everything that failed validation, for when the game can't start.
+pub fn spawn_validation_report(
+    commands: &mut Commands,
+    font_assets: &FontAssets,
+    heading: &str,
+    errors: &[impl Display],
+) {
+    commands.spawn(Camera2dBundle::default());
+    let root = NodeBundle {
+        style: Style {
+            width: Val::Percent(100.0),
+            height: Val::Percent(100.0),
+            flex_direction: FlexDirection::Column,
+            align_items: AlignItems::FlexStart,
+            padding: UiRect::all(Val::Px(20.0)),
+            ..default()
+        },
+        background_color: Color::BLACK.into(),
+        ..default()
+    };
+    let text_style = TextStyle {
+        font: font_assets.ui_font.clone(),
+        font_size: 15.0,
+        color: Color::WHITE,
+    };
+    commands.spawn(root).with_children(|parent| {
+        parent.spawn(TextBundle::from_section(
+            heading,
+            TextStyle {
+                color: Color::RED,
+                ..text_style.clone()
+            },
+        ));
+        for e in errors.iter() {
+            parent.spawn(TextBundle::from_section(
+                format!("- {}", e),
+                text_style.clone(),
+            ));
+        }
+    });
+}
+
 impl Plugin for SharedAssetsPlugin {
     fn build(&self, app: &mut App) {
         app.add_loading_state(
             LoadingState::new(GameModeState::LoadingSharedAssets)
                 .continue_to_state(GameModeState::LoadingDungeon),
         )
-        .add_collection_to_loading_state::<_, FontAssets>(GameModeState::LoadingSharedAssets);
+        .add_collection_to_loading_state::<_, FontAssets>(GameModeState::LoadingSharedAssets)
+        .add_collection_to_loading_state::<_, ItemAssets>(GameModeState::LoadingSharedAssets)
+        .init_resource_after_loading_state::<_, ItemDatabase>(GameModeState::LoadingSharedAssets)
+        .add_collection_to_loading_state::<_, CharacterAssets>(GameModeState::LoadingSharedAssets)
+        .init_resource_after_loading_state::<_, CharacterDatabase>(
+            GameModeState::LoadingSharedAssets,
+        )
+        .add_collection_to_loading_state::<_, SkillAssets>(GameModeState::LoadingSharedAssets)
+        .init_resource_after_loading_state::<_, SkillDatabase>(GameModeState::LoadingSharedAssets)
+        .add_systems(
+            OnExit(GameModeState::LoadingSharedAssets),
+            (
+                validate_character_database,
+                apply_deferred,
+                start_new_party.run_if(not(resource_exists::<CharacterValidationReport>())),
+            )
+                .chain(),
+        )
+        .add_systems(
+            OnEnter(GameModeState::InvalidCharacters),
+            show_character_validation_report,
+        );
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use bevy::prelude::{App, NextState, Update};
+    use bevy::utils::HashMap;
+
+    use crate::modes::battle::skilldatabase::test_helpers::test_skill_database;
+    use crate::modes::mode_state::GameModeState;
+    use crate::modes::sharedassets::characterdatabase::test_helpers::test_character_database;
+    use crate::modes::sharedassets::characterdatabase::{
+        CharacterDatabase, CharacterValidationError, RawCharacterDatabase,
+    };
+    use crate::modes::sharedassets::itemdatabase::test_helpers::test_item_database;
+    use crate::modes::sharedassets::shared::{
+        validate_character_database, CharacterValidationReport,
+    };
+
+    fn validate(character_database: CharacterDatabase) -> App {
+        let mut app = App::new();
+        app.add_state::<GameModeState>()
+            .insert_resource(character_database)
+            .insert_resource(test_item_database())
+            .insert_resource(test_skill_database())
+            .add_systems(Update, validate_character_database);
+        app.update();
+        app
+    }
+
+    #[test]
+    fn valid_characters_carry_on() {
+        let app = validate(test_character_database());
+        assert!(app
+            .world
+            .get_resource::<CharacterValidationReport>()
+            .is_none());
+        assert_eq!(app.world.resource::<NextState<GameModeState>>().0, None);
+    }
+
+    #[test]
+    fn invalid_characters_stop_the_game() {
+        let app = validate(CharacterDatabase::new(&RawCharacterDatabase {
+            party: vec![],
+            characters: HashMap::new(),
+        }));
+        assert_eq!(
+            app.world.resource::<CharacterValidationReport>().0,
+            [CharacterValidationError::EmptyParty]
+        );
+        assert_eq!(
+            app.world.resource::<NextState<GameModeState>>().0,
+            Some(GameModeState::InvalidCharacters)
+        );
     }
 }
@@ -2,3 +2,27 @@ use bevy::prelude::Resource;
 
 #[derive(Resource)]
 pub struct WindowScaleFactor(pub f32);
+
+/// Where everything random in the gameplay comes from, so tests can seed it and get the same
+/// results every time. Purely cosmetic randomness doesn't need to use it.
+///
+/// `fastrand::Rng` can't be shared between threads, so this only keeps its state around. Setting
+/// the state is the same as seeding it.
+#[derive(Resource)]
+pub struct GameRng(pub u64);
+
+impl GameRng {
+    /// Runs `f` with the generator, carrying on from where the last call left it.
+    pub fn with<T>(&mut self, f: impl FnOnce(&mut fastrand::Rng) -> T) -> T {
+        let mut rng = fastrand::Rng::with_seed(self.0);
+        let result = f(&mut rng);
+        self.0 = rng.get_seed();
+        result
+    }
+}
+
+impl Default for GameRng {
+    fn default() -> Self {
+        GameRng(fastrand::u64(..))
+    }
+}
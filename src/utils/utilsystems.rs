@@ -11,7 +11,6 @@ use bevy::window::WindowResized;
 pub const BASE_WINDOW_WIDTH: f32 = 640.;
 pub const BASE_WINDOW_HEIGHT: f32 = 360.;
 
-pub const TARGET_RESOLUTION: f32 = 16. / 9.;
 pub fn cleanup_system<T: Component>(mut commands: Commands, q: Query<Entity, With<T>>) {
     for e in q.iter() {
         commands.entity(e).despawn_recursive();